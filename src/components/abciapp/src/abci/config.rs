@@ -114,6 +114,7 @@ pub(crate) mod global_cfg {
         pub tendermint_node_self_addr: Option<String>,
         pub tendermint_node_key_config_path: Option<String>,
         pub ledger_dir: String,
+        pub replay_from: Option<String>,
         #[cfg(target_os = "linux")]
         pub btmcfg: BtmCfg,
     }
@@ -142,6 +143,7 @@ pub(crate) mod global_cfg {
                 .arg_from_usage("--tendermint-node-self-addr=[Address] 'the address of your tendermint node, in upper-hex format'")
                 .arg_from_usage("--tendermint-node-key-config-path=[Path] 'such as: ${HOME}/.tendermint/config/priv_validator_key.json'")
                 .arg_from_usage("-d, --ledger-dir=[Path]")
+                .arg_from_usage("--replay-from=[File] 'rebuild a fresh ledger from an exported stream of blocks(JSON lines), then exit'")
 
                 .arg_from_usage("--enable-snapshot 'global switch for enabling snapshot functions'")
                 .arg_from_usage("--snapshot-list 'list all available snapshots in the form of block height'")
@@ -217,6 +219,7 @@ pub(crate) mod global_cfg {
                     format!("{}/.tendermint/__findora__", pnk!(env::var("HOME")))
                 })
             });
+        let rf = m.value_of("replay-from").map(|v| v.to_owned());
 
         let res = Config {
             abci_host: ah,
@@ -229,6 +232,7 @@ pub(crate) mod global_cfg {
             tendermint_node_self_addr: tnsa,
             tendermint_node_key_config_path: tnkcp,
             ledger_dir: ld,
+            replay_from: rf,
             #[cfg(target_os = "linux")]
             btmcfg: parse_btmcfg(&m).c(d!())?,
         };
@@ -6,6 +6,7 @@
 #![allow(clippy::needless_borrow)]
 
 mod config;
+pub mod replay;
 mod server;
 pub mod staking;
 
@@ -49,6 +50,10 @@ pub fn run() -> Result<()> {
         env::set_var("FINDORAD_KEEP_HIST", "1");
     }
 
+    // exit early if `FINDORAD_NETWORK_PROFILE` is invalid
+    let profile = ledger::upgrade::check_profile().c(d!())?;
+    log::info!("Network profile: {}", profile);
+
     let app = server::ABCISubmissionServer::new(
         basedir,
         format!("{}:{}", config.tendermint_host, config.tendermint_port),
@@ -86,3 +91,22 @@ pub fn run() -> Result<()> {
 
     Ok(())
 }
+
+/// Check if `--replay-from` is specified.
+#[inline(always)]
+pub fn is_replay_mode() -> bool {
+    CFG.replay_from.is_some()
+}
+
+/// Rebuild a fresh ledger from the blocks specified by `--replay-from`.
+pub fn replay() -> Result<()> {
+    let path = CFG.replay_from.as_deref().c(d!("no blocks to replay"))?;
+
+    env::set_var("BNC_DATA_DIR", format!("{}/__bnc__", &CFG.ledger_dir));
+
+    // exit early if `FINDORAD_NETWORK_PROFILE` is invalid
+    let profile = ledger::upgrade::check_profile().c(d!())?;
+    log::info!("Network profile: {}", profile);
+
+    replay::run(path, &CFG.ledger_dir).c(d!())
+}
@@ -0,0 +1,319 @@
+//!
+//! # Ledger Replay
+//!
+//! Rebuild a fresh ledger from an exported stream of tendermint blocks,
+//! and verify the app hash of every height against the recorded one.
+//!
+//! Every line of the stream is a JSON object of [ReplayBlock](self::ReplayBlock),
+//! the blocks will be driven through the same callbacks used by tendermint.
+//!
+
+use {
+    crate::abci::server::{callback, ABCISubmissionServer},
+    abci::{
+        Evidence, Header, LastCommitInfo, RequestBeginBlock, RequestCommit,
+        RequestDeliverTx, RequestEndBlock, RequestInfo, Validator, VoteInfo,
+    },
+    ledger::data_model::StateCommitmentData,
+    protobuf::RepeatedField,
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    serde_json::Value,
+    std::{
+        fs::{self, File},
+        io::{BufRead, BufReader},
+    },
+};
+
+/// The recorded data of a block.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReplayBlock {
+    /// block height
+    pub height: i64,
+    /// tendermint address of the proposer, in hex format
+    #[serde(default)]
+    pub proposer_address: String,
+    /// votes of the last block, `None` if no `LastCommitInfo` in `BeginBlock`
+    #[serde(default)]
+    pub last_commit_votes: Option<Vec<ReplayVote>>,
+    /// evidences of byzantine validators
+    #[serde(default)]
+    pub byzantine_validators: Vec<ReplayEvidence>,
+    /// raw transactions in `DeliverTx`, in base64 format
+    #[serde(default)]
+    pub txs: Vec<String>,
+    /// the app hash returned by `Commit`, in hex format
+    pub app_hash: String,
+    /// the recorded `StateCommitmentData`, it will be used to print a diff if present
+    #[serde(default)]
+    pub state_commitment: Option<StateCommitmentData>,
+}
+
+/// A vote in `LastCommitInfo`.
+#[allow(missing_docs)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReplayVote {
+    /// tendermint address, in hex format
+    pub address: String,
+    pub power: i64,
+    pub signed_last_block: bool,
+}
+
+/// An evidence of `BeginBlock`.
+#[allow(missing_docs)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReplayEvidence {
+    /// eg. "duplicate/vote"
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// tendermint address, in hex format
+    pub address: String,
+    pub power: i64,
+    pub height: i64,
+}
+
+impl ReplayBlock {
+    fn gen_begin_block(&self) -> Result<RequestBeginBlock> {
+        let mut header = Header::new();
+        header.set_height(self.height);
+        header.set_proposer_address(hex::decode(&self.proposer_address).c(d!())?);
+
+        let mut req = RequestBeginBlock::new();
+        req.set_header(header);
+
+        if let Some(votes) = self.last_commit_votes.as_ref() {
+            let votes = votes
+                .iter()
+                .map(|v| {
+                    let mut vote = VoteInfo::new();
+                    vote.set_validator(gen_validator(&v.address, v.power).c(d!())?);
+                    vote.set_signed_last_block(v.signed_last_block);
+                    Ok(vote)
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let mut lci = LastCommitInfo::new();
+            lci.set_votes(RepeatedField::from_vec(votes));
+            req.set_last_commit_info(lci);
+        }
+
+        let evs = self
+            .byzantine_validators
+            .iter()
+            .map(|e| {
+                let mut ev = Evidence::new();
+                ev.set_field_type(e.kind.clone());
+                ev.set_validator(gen_validator(&e.address, e.power).c(d!())?);
+                ev.set_height(e.height);
+                Ok(ev)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        req.set_byzantine_validators(RepeatedField::from_vec(evs));
+
+        Ok(req)
+    }
+}
+
+fn gen_validator(addr: &str, power: i64) -> Result<Validator> {
+    let mut v = Validator::new();
+    v.set_address(hex::decode(addr).c(d!())?);
+    v.set_power(power);
+    Ok(v)
+}
+
+/// Replay all the blocks in `path` against a fresh ledger in `ledger_dir`,
+/// stop at the first mismatch of app hashes.
+pub fn run(path: &str, ledger_dir: &str) -> Result<()> {
+    if fs::read_dir(ledger_dir)
+        .map(|mut d| d.next().is_some())
+        .unwrap_or(false)
+    {
+        return Err(eg!(format!("{} is not empty", ledger_dir)));
+    }
+    fs::create_dir_all(ledger_dir).c(d!())?;
+
+    let mut app = ABCISubmissionServer::new(Some(ledger_dir), String::new()).c(d!())?;
+    callback::info(&mut app, &RequestInfo::new());
+
+    let reader = BufReader::new(File::open(path).c(d!())?);
+    let cnt = replay_stream(&mut app, reader).c(d!())?;
+
+    println!("{} blocks replayed, all app hashes matched", cnt);
+
+    Ok(())
+}
+
+// replay the blocks one by one, return the number of replayed blocks
+fn replay_stream(app: &mut ABCISubmissionServer, reader: impl BufRead) -> Result<u64> {
+    let mut cnt = 0;
+    for (idx, line) in reader.lines().enumerate() {
+        let line = line.c(d!())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let block = serde_json::from_str::<ReplayBlock>(&line)
+            .c(d!(format!("invalid block at line {}", 1 + idx)))?;
+
+        let app_hash = replay_block(app, &block).c(d!())?;
+        if hex::encode(&app_hash) != block.app_hash.to_lowercase() {
+            let local = app
+                .la
+                .read()
+                .get_committed_state()
+                .read()
+                .get_state_commitment_data()
+                .cloned();
+            print_mismatch(&block, &app_hash, local.as_ref());
+            return Err(eg!(format!("app hash mismatch at height {}", block.height)));
+        }
+
+        cnt += 1;
+    }
+
+    Ok(cnt)
+}
+
+fn replay_block(app: &mut ABCISubmissionServer, block: &ReplayBlock) -> Result<Vec<u8>> {
+    callback::begin_block(app, &block.gen_begin_block().c(d!())?);
+
+    for (i, tx) in block.txs.iter().enumerate() {
+        let mut req = RequestDeliverTx::new();
+        req.set_tx(base64::decode(tx).c(d!())?);
+        let resp = callback::deliver_tx(app, &req);
+        if 0 != resp.code {
+            println!(
+                "height {}, tx {}: failed to deliver, {}",
+                block.height, i, resp.log
+            );
+        }
+    }
+
+    let mut req = RequestEndBlock::new();
+    req.set_height(block.height);
+    callback::end_block(app, &req);
+
+    Ok(callback::commit(app, &RequestCommit::new()).data)
+}
+
+fn print_mismatch(
+    block: &ReplayBlock,
+    app_hash: &[u8],
+    local: Option<&StateCommitmentData>,
+) {
+    println!("App hash mismatch at height {}:", block.height);
+    println!("    recorded: {}", block.app_hash);
+    println!("    replayed: {}", hex::encode(app_hash));
+
+    let to_json = |scd: Option<&StateCommitmentData>| {
+        scd.and_then(|i| serde_json::to_value(i).ok())
+            .unwrap_or(Value::Null)
+    };
+    let local = to_json(local);
+
+    if let Some(recorded) = block.state_commitment.as_ref() {
+        let recorded = to_json(Some(recorded));
+        println!("StateCommitmentData diff(recorded => replayed):");
+        for (k, v) in diff_fields(&recorded, &local) {
+            println!("    {}: {} => {}", k, v.0, v.1);
+        }
+    } else {
+        println!(
+            "StateCommitmentData(replayed):\n{}",
+            serde_json::to_string_pretty(&local).unwrap_or_default()
+        );
+    }
+}
+
+// compare two json objects field by field
+fn diff_fields(a: &Value, b: &Value) -> Vec<(String, (Value, Value))> {
+    let empty = serde_json::Map::new();
+    let a = a.as_object().unwrap_or(&empty);
+    let b = b.as_object().unwrap_or(&empty);
+
+    let mut keys = a.keys().chain(b.keys()).collect::<Vec<_>>();
+    keys.sort();
+    keys.dedup();
+
+    keys.into_iter()
+        .filter_map(|k| {
+            let va = a.get(k).cloned().unwrap_or(Value::Null);
+            let vb = b.get(k).cloned().unwrap_or(Value::Null);
+            alt!(va == vb, None, Some((k.clone(), (va, vb))))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn gen_block(height: i64) -> ReplayBlock {
+        ReplayBlock {
+            height,
+            proposer_address: String::new(),
+            last_commit_votes: None,
+            byzantine_validators: vec![],
+            txs: vec![],
+            app_hash: String::new(),
+            state_commitment: None,
+        }
+    }
+
+    fn to_stream(blocks: &[ReplayBlock]) -> String {
+        blocks
+            .iter()
+            .map(|b| pnk!(serde_json::to_string(b)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn replay_match_and_mismatch() {
+        // record the blocks from a temporary ledger
+        let mut recorder = pnk!(ABCISubmissionServer::new(None, String::new()));
+        let blocks = (1..=3)
+            .map(|h| {
+                let mut b = gen_block(h);
+                b.app_hash = hex::encode(pnk!(replay_block(&mut recorder, &b)));
+                b.state_commitment = recorder
+                    .la
+                    .read()
+                    .get_committed_state()
+                    .read()
+                    .get_state_commitment_data()
+                    .cloned();
+                b
+            })
+            .collect::<Vec<_>>();
+
+        let dir = globutils::fresh_tmp_dir().to_string_lossy().into_owned();
+        let mut app = pnk!(ABCISubmissionServer::new(Some(&dir), String::new()));
+        callback::info(&mut app, &RequestInfo::new());
+
+        let stream = to_stream(&blocks[..2]);
+        assert_eq!(2, pnk!(replay_stream(&mut app, stream.as_bytes())));
+
+        let mut bad = blocks[2].clone();
+        bad.app_hash = hex::encode([0_u8; 32]);
+        let stream = to_stream(&[bad]);
+        assert!(replay_stream(&mut app, stream.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn replay_diff_fields() {
+        let a = serde_json::json!({"a": 1, "b": [1, 2], "c": "x"});
+        let b = serde_json::json!({"a": 1, "b": [1, 3], "d": null, "e": true});
+        assert_eq!(
+            diff_fields(&a, &b),
+            vec![
+                (
+                    "b".to_owned(),
+                    (serde_json::json!([1, 2]), serde_json::json!([1, 3]))
+                ),
+                ("c".to_owned(), (serde_json::json!("x"), Value::Null)),
+                ("e".to_owned(), (Value::Null, serde_json::json!(true))),
+            ]
+        );
+        assert!(diff_fields(&a, &a).is_empty());
+        assert!(diff_fields(&Value::Null, &Value::Null).is_empty());
+    }
+}
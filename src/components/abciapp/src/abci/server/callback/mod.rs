@@ -79,17 +79,25 @@ pub fn info(s: &mut ABCISubmissionServer, _req: &RequestInfo) -> ResponseInfo {
 }
 
 /// any new tx will trigger this callback before it can enter the mem-pool of tendermint
-pub fn check_tx(_s: &mut ABCISubmissionServer, req: &RequestCheckTx) -> ResponseCheckTx {
+pub fn check_tx(s: &mut ABCISubmissionServer, req: &RequestCheckTx) -> ResponseCheckTx {
     let mut resp = ResponseCheckTx::new();
 
     if matches!(req.field_type, CheckTxType::New) {
         if let Ok(tx) = convert_tx(req.get_tx()) {
-            if !tx.valid_in_abci() {
+            if !tx.valid_in_abci(get_tx_fee_min(s)) {
                 resp.log = "Should not appear in ABCI".to_owned();
                 resp.code = 1;
             } else if TX_HISTORY.read().contains_key(&tx.hash_tm_rawbytes()) {
                 resp.log = "Historical transaction".to_owned();
                 resp.code = 1;
+            } else {
+                // `ResponseCheckTx` of tendermint v0.33(rust-abci v0.7.x) has no
+                // `priority` field, it was added in v0.35 along with the priority
+                // mempool, the mempool of v0.33 is FIFO and can not be reordered
+                // by the app, so the priority is only reported through `info`
+                // to let clients see it, see also the `fee_estimate` route
+                let priority = tx.get_priority(req.get_tx().len());
+                resp.info = format!("priority: {}", priority);
             }
         } else {
             resp.log = "Invalid format".to_owned();
@@ -100,6 +108,13 @@ pub fn check_tx(_s: &mut ABCISubmissionServer, req: &RequestCheckTx) -> Response
     resp
 }
 
+// the minimum fee defined by current chain parameters,
+// of the same staking state which the txns are applied to
+#[inline(always)]
+fn get_tx_fee_min(s: &ABCISubmissionServer) -> u64 {
+    s.la.read().get_tx_fee_min()
+}
+
 pub fn begin_block(
     s: &mut ABCISubmissionServer,
     req: &RequestBeginBlock,
@@ -161,7 +176,7 @@ pub fn deliver_tx(
             TX_HISTORY.write().set_value(txhash, Default::default());
         });
 
-        if tx.valid_in_abci() {
+        if tx.valid_in_abci(get_tx_fee_min(s)) {
             if *KEEP_HIST {
                 // set attr(tags) if any, only needed on a fullnode
                 let attr = utils::gen_tendermint_attr(&tx);
@@ -240,6 +255,11 @@ pub fn commit(s: &mut ABCISubmissionServer, _req: &RequestCommit) -> ResponseCom
 
     // cache last block for QueryServer
     pnk!(api_cache::update_api_cache(&mut state));
+    // the tendermint key is reused for the light headers,
+    // their signed bytes have a domain tag of their own
+    if let Some(key) = staking::TD_NODE_KEY.as_ref() {
+        info_omit!(api_cache::sign_light_header(&mut state, &key.priv_key));
+    }
 
     // snapshot them finally
     let path = format!("{}/{}", &CFG.ledger_dir, &state.get_status().snapshot_file);
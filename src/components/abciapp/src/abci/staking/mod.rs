@@ -12,6 +12,7 @@ mod test;
 use {
     crate::abci::server::callback::TENDERMINT_BLOCK_HEIGHT,
     abci::{Evidence, Header, LastCommitInfo, PubKey, ValidatorUpdate},
+    finutils::common::utils::ValidatorKey,
     lazy_static::lazy_static,
     ledger::{
         data_model::{Operation, Transaction, ASSET_TYPE_FRA},
@@ -23,6 +24,7 @@ use {
             td_addr_to_string, Staking, VALIDATOR_UPDATE_BLOCK_ITV,
         },
         store::LedgerState,
+        upgrade::{self, Feature},
     },
     ruc::*,
     serde::Serialize,
@@ -33,13 +35,13 @@ use {
     },
 };
 
-// The top 50~ candidate validators
-// will become official validators.
-const VALIDATOR_LIMIT: usize = 58;
-
 lazy_static! {
     /// Tendermint node address, sha256(pubkey)[:20]
     pub static ref TD_NODE_SELF_ADDR: Vec<u8> = pnk!(whoami::get_self_addr());
+
+    /// Tendermint key of this node, used to sign the light headers,
+    /// `None` if it is not available.
+    pub static ref TD_NODE_KEY: Option<ValidatorKey> = whoami::get_self_key().ok();
 }
 
 /// Get the effective validators at current block height.
@@ -96,22 +98,24 @@ pub fn get_validators(
         .c(d!())?
         .body
         .values()
-        .filter(|v| {
+        // jailed validators will be removed from tendermint
+        .map(|v| (v, alt!(v.is_jailed(), 0, v.td_power)))
+        .filter(|(v, td_power)| {
             if let Some(power) = cur_entries.get(&v.td_addr) {
                 // - new power > 0: change existing entries
                 // - new power = 0: remove existing entries
                 // - the power returned by `LastCommitInfo` is impossible
                 // to be zero in the context of tendermint
-                *power as u64 != v.td_power
+                *power as u64 != *td_power
             } else {
                 // add new validator
                 //
                 // try to remove non-existing entries is not allowed
-                0 < v.td_power
+                0 < *td_power
             }
         })
         // this conversion is safe in the context of tendermint
-        .map(|v| (&v.td_pubkey, v.td_power as i64))
+        .map(|(v, td_power)| (&v.td_pubkey, td_power as i64))
         .collect::<Vec<_>>();
 
     if vs.is_empty() {
@@ -123,7 +127,8 @@ pub fn get_validators(
 
     // set the power of every extra validators to zero,
     // then tendermint can remove them from consensus logic.
-    vs.iter_mut().skip(VALIDATOR_LIMIT).for_each(|(k, power)| {
+    let validator_limit = staking.chain_params().validator_limit as usize;
+    vs.iter_mut().skip(validator_limit).for_each(|(k, power)| {
         alt!(cur_entries.contains_key(k), *power = 0, *power = -1);
     });
 
@@ -184,6 +189,15 @@ pub fn system_ops(
             .filter(|v| v.signed_last_block)
             .flat_map(|info| info.validator.as_ref().map(|v| &v.address))
             .collect::<BTreeSet<_>>();
+        let voter_list = lci
+            .votes
+            .iter()
+            .flat_map(|info| info.validator.as_ref().map(|v| &v.address))
+            .collect::<BTreeSet<_>>();
+
+        // the missed blocks are counted only when jailing is active
+        let jail_active =
+            upgrade::is_active(Feature::ValidatorJail, la.get_staking().cur_height());
 
         // mark if a validator is online at last block
         if let Ok(vd) = ruc::info!(la.get_staking_mut().validator_get_current_mut()) {
@@ -191,8 +205,15 @@ pub fn system_ops(
                 if online_list.contains(&v.td_addr) {
                     v.signed_last_block = true;
                     v.signed_cnt += 1;
+                    if jail_active {
+                        v.missed_blocks_in_row = 0;
+                    }
                 } else {
                     v.signed_last_block = false;
+                    // only count the validators in the consensus set
+                    if jail_active && voter_list.contains(&v.td_addr) {
+                        v.missed_blocks_in_row += 1;
+                    }
                 }
             });
         }
@@ -329,10 +350,12 @@ fn gen_offline_punish_list(
     let mut vs = validators
         .body
         .values()
-        .map(|v| (&v.td_addr, v.td_power))
+        // jailed validators have been punished
+        .map(|v| (&v.td_addr, alt!(v.is_jailed(), 0, v.td_power)))
         .collect::<Vec<_>>();
     vs.sort_by(|a, b| b.1.cmp(&a.1));
-    vs.iter_mut().skip(VALIDATOR_LIMIT).for_each(|(_, power)| {
+    let validator_limit = staking.chain_params().validator_limit as usize;
+    vs.iter_mut().skip(validator_limit).for_each(|(_, power)| {
         *power = 0;
     });
 
@@ -5,14 +5,36 @@
 //!
 
 use {
-    crate::abci::config::global_cfg::CFG, lazy_static::lazy_static,
-    ledger::staking::td_addr_to_bytes, ruc::*, serde::Deserialize, std::fs,
+    crate::abci::config::global_cfg::CFG,
+    finutils::common::utils::{parse_td_validator_keys, ValidatorKey},
+    lazy_static::lazy_static,
+    ledger::staking::td_addr_to_bytes,
+    ruc::*,
+    serde::Deserialize,
+    std::fs,
 };
 
+// the config path in the abci container
+const CFG_PATH_FF: &str = "/root/.tendermint/config/priv_validator_key.json";
+
+lazy_static! {
+    static ref CFG_PATH: &'static str = CFG
+        .tendermint_node_key_config_path
+        .as_deref()
+        .unwrap_or(CFG_PATH_FF);
+}
+
 pub fn get_self_addr() -> Result<Vec<u8>> {
     from_env().c(d!()).or_else(|_| from_config_file().c(d!()))
 }
 
+/// The tendermint key of this node, it's only available in the config file.
+pub fn get_self_key() -> Result<ValidatorKey> {
+    fs::read_to_string(&*CFG_PATH)
+        .c(d!())
+        .and_then(|cfg| parse_td_validator_keys(&cfg).c(d!()))
+}
+
 fn from_env() -> Result<Vec<u8>> {
     CFG.tendermint_node_self_addr
         .as_ref()
@@ -21,15 +43,6 @@ fn from_env() -> Result<Vec<u8>> {
 }
 
 fn from_config_file() -> Result<Vec<u8>> {
-    // the config path in the abci container
-    const CFG_PATH_FF: &str = "/root/.tendermint/config/priv_validator_key.json";
-    lazy_static! {
-        static ref CFG_PATH: &'static str = CFG
-            .tendermint_node_key_config_path
-            .as_deref()
-            .unwrap_or(CFG_PATH_FF);
-    }
-
     fs::read_to_string(&*CFG_PATH)
         .c(d!())
         .and_then(|cfg| serde_json::from_str::<SelfAddr>(&cfg).c(d!()))
@@ -3,22 +3,28 @@
 //!
 
 use {
-    super::server::QueryServer,
+    super::server::{Cursor, FeeEstimate, Page, QueryServer},
     actix_web::{error, web},
+    cryptohash::HashValue,
     finutils::api::{
-        DelegationInfo, DelegatorInfo, DelegatorList, NetworkRoute, Validator,
-        ValidatorDetail, ValidatorList,
+        BlockDetail, BlockTxn, DelegationInfo, DelegatorInfo, DelegatorList,
+        NetworkRoute, Validator, ValidatorDetail, ValidatorList, MAX_BLOCKS_PER_QUERY,
     },
     globutils::HashOf,
     ledger::{
         data_model::{
-            AssetType, AssetTypeCode, AuthenticatedUtxo, StateCommitmentData, TxnSID,
-            TxoSID, UnAuthenticatedUtxo, Utxo,
+            AssetType, AssetTypeCode, AuthenticatedTransaction, AuthenticatedUtxo,
+            MerkleConsistencyProof, NoReplayWindow, Operation, StateCommitmentData,
+            TxnSID, TxoRef, TxoSID, UnAuthenticatedUtxo, Utxo,
         },
+        light_client::SignedLightHeader,
         staking::{
-            DelegationRwdDetail, DelegationState, Staking, TendermintAddr,
-            TendermintAddrRef, UNBOND_BLOCK_CNT,
+            chain_params::ChainParamsInfo, ops::governance::RuleSet, BlockHeight,
+            DelegationRwdDetail, DelegationState, JailEvent, Staking, TendermintAddr,
+            TendermintAddrRef,
         },
+        store::api_cache::{StateCommitmentDiff, StateCommitmentInfo},
+        upgrade::{self, NetworkProfile, UpgradeStatus},
     },
     parking_lot::RwLock,
     ruc::*,
@@ -192,6 +198,54 @@ pub async fn query_txn_light(
     }
 }
 
+#[allow(missing_docs)]
+#[derive(Debug, Deserialize)]
+pub struct TxnAtParams {
+    sid: usize,
+    version: u64,
+}
+
+/// query tx according to `TxnSID`, along with its proof data against
+/// the global state version(`global_state_version`) of a block height
+pub async fn query_txn_at(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    web::Query(info): web::Query<TxnAtParams>,
+) -> actix_web::Result<web::Json<AuthenticatedTransaction>> {
+    let qs = data.read();
+    let mut txn = qs
+        .ledger_cloned
+        .get_transaction_at(TxnSID(info.sid), info.version)
+        .map_err(|e| error::ErrorNotFound(e.to_string()))?;
+    txn.finalized_txn.set_txo_id();
+    Ok(web::Json(txn))
+}
+
+/// query the root hashes of the transaction merkle tree and the block merkle tree
+/// at the global state version of a block height
+pub async fn query_merkle_roots(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    version: web::Path<u64>,
+) -> actix_web::Result<web::Json<(HashValue, HashValue)>> {
+    data.read()
+        .ledger_cloned
+        .get_merkle_roots_at(*version)
+        .map(web::Json)
+        .map_err(|e| error::ErrorNotFound(e.to_string()))
+}
+
+/// query the proof that the merkle trees at the global state version `from`
+/// are prefixes of the ones at the global state version `to`
+pub async fn query_merkle_consistency(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    web::Query(info): web::Query<BlockRangeParams>,
+) -> actix_web::Result<web::Json<MerkleConsistencyProof>> {
+    data.read()
+        .ledger_cloned
+        .get_merkle_consistency_proof(info.from, info.to)
+        .map(web::Json)
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))
+}
+
 /// query global state, return (apphash, block count, apphash and block count signatures)
 #[allow(clippy::type_complexity)]
 pub async fn query_global_state(
@@ -253,11 +307,16 @@ pub async fn query_validators(
             .collect();
         return Ok(web::Json(ValidatorList::new(
             staking.cur_height() as u64,
+            staking.chain_params().max_power_percent_per_validator,
             validators_list,
         )));
     };
 
-    Ok(web::Json(ValidatorList::new(0, vec![])))
+    Ok(web::Json(ValidatorList::new(
+        0,
+        staking.chain_params().max_power_percent_per_validator,
+        vec![],
+    )))
 }
 
 #[allow(missing_docs)]
@@ -432,6 +491,41 @@ pub async fn get_validator_delegation_history(
     Ok(web::Json(res))
 }
 
+/// Default number of entries in a page.
+pub const DEFAULT_PAGE_LIMIT: usize = 100;
+
+#[allow(missing_docs)]
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderOption {
+    Desc,
+    Asc,
+}
+
+/// Cursor-based paging parameters, shared by all the history routes,
+/// pass the `next` field of the last page as `start` to get the next page.
+#[derive(Deserialize, Debug, Default)]
+pub struct CursorParams {
+    /// the sid, height or index to start from, inclusive
+    pub start: Option<u64>,
+    /// default to `DEFAULT_PAGE_LIMIT`, at most `MAX_PAGE_LIMIT`
+    pub limit: Option<usize>,
+    /// default to `asc`
+    pub order: Option<OrderOption>,
+}
+
+impl CursorParams {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn cursor(&self) -> Cursor {
+        Cursor::new(
+            self.start,
+            self.limit.unwrap_or(DEFAULT_PAGE_LIMIT),
+            self.order == Some(OrderOption::Desc),
+        )
+    }
+}
+
 #[allow(missing_docs)]
 #[derive(Deserialize, Debug)]
 pub struct DelegatorQueryParams {
@@ -441,13 +535,6 @@ pub struct DelegatorQueryParams {
     order: OrderOption,
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
-#[serde(rename_all = "snake_case")]
-enum OrderOption {
-    Desc,
-    Asc,
-}
-
 /// paging Query delegators according to `DelegatorQueryParams`
 pub async fn get_delegators_with_params(
     data: web::Data<Arc<RwLock<QueryServer>>>,
@@ -507,6 +594,45 @@ pub async fn query_delegator_list(
     Ok(web::Json(DelegatorList::new(list)))
 }
 
+#[allow(missing_docs)]
+#[derive(Deserialize, Debug)]
+pub struct DelegatorPagedQueryParams {
+    address: String,
+}
+
+/// paging Query delegators according to `DelegatorPagedQueryParams` and `CursorParams`,
+/// delegators are indexed in the order of delegation
+pub async fn get_delegators_with_params_paged(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    web::Query(info): web::Query<DelegatorPagedQueryParams>,
+    web::Query(cp): web::Query<CursorParams>,
+) -> actix_web::Result<web::Json<Page<DelegatorInfo>>> {
+    let qs = data.read();
+    let ledger = &qs.ledger_cloned;
+    let staking = ledger.get_staking();
+
+    validator_get_delegator_page(staking, info.address.as_ref(), &cp.cursor())
+        .c(d!())
+        .map_err(error::ErrorNotFound)
+        .map(web::Json)
+}
+
+/// query delegator list according to `TendermintAddr`, paged by the delegation order
+pub async fn query_delegator_list_paged(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    addr: web::Path<TendermintAddr>,
+    web::Query(cp): web::Query<CursorParams>,
+) -> actix_web::Result<web::Json<Page<DelegatorInfo>>> {
+    let qs = data.read();
+    let ledger = &qs.ledger_cloned;
+    let staking = ledger.get_staking();
+
+    validator_get_delegator_page(staking, addr.as_ref(), &cp.cursor())
+        .c(d!())
+        .map_err(error::ErrorNotFound)
+        .map(web::Json)
+}
+
 /// query validator detail according to `TendermintAddr`
 pub async fn query_validator_detail(
     data: web::Data<Arc<RwLock<QueryServer>>>,
@@ -580,6 +706,9 @@ pub async fn query_validator_detail(
                 expected_annualization,
                 kind: v.kind(),
                 delegator_cnt: v.delegators.len() as u64,
+                jail: v.jail.clone(),
+                commission_limit: v.get_commission_limit(),
+                commission_schedule: v.commission_schedule.clone(),
             };
             return Ok(web::Json(resp));
         }
@@ -614,6 +743,7 @@ pub async fn query_delegation_info(
         end_height,
         delegation_rwd_cnt,
         proposer_rwd_cnt,
+        auto_compound,
     ) = staking
         .delegation_get(&pk)
         .map(|d| {
@@ -638,7 +768,8 @@ pub async fn query_delegation_info(
                 }
                 DelegationState::Bond => {
                     if staking.cur_height()
-                        > d.end_height().saturating_sub(UNBOND_BLOCK_CNT)
+                        > d.end_height()
+                            .saturating_sub(staking.chain_params().unbond_block_cnt)
                     {
                         mem::swap(&mut bond_amount, &mut unbond_amount);
                     }
@@ -656,9 +787,10 @@ pub async fn query_delegation_info(
                 d.end_height(),
                 d.delegation_rwd_cnt,
                 d.proposer_rwd_cnt,
+                d.auto_compound,
             )
         })
-        .unwrap_or((0, vec![], 0, 0, 0, 0, 0, 0));
+        .unwrap_or((0, vec![], 0, 0, 0, 0, 0, 0, false));
 
     let mut resp = DelegationInfo::new(
         bond_amount,
@@ -674,10 +806,296 @@ pub async fn query_delegation_info(
     resp.end_height = end_height;
     resp.delegation_rwd_cnt = delegation_rwd_cnt;
     resp.proposer_rwd_cnt = proposer_rwd_cnt;
+    resp.auto_compound = auto_compound;
 
     Ok(web::Json(resp))
 }
 
+/// query the penalty rules of governance,
+/// the first entry is the one in effect, others are scheduled ones
+pub async fn query_governance_rules(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+) -> actix_web::Result<web::Json<BTreeMap<BlockHeight, RuleSet>>> {
+    let qs = data.read();
+    let ledger = &qs.ledger_cloned;
+    Ok(web::Json(ledger.get_staking().governance_get_rules_hist()))
+}
+
+/// query the chain parameters, including
+/// the activated ones, the scheduled ones and the pending proposals
+pub async fn query_chain_params(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+) -> actix_web::Result<web::Json<ChainParamsInfo>> {
+    let qs = data.read();
+    let ledger = &qs.ledger_cloned;
+    Ok(web::Json(ledger.get_staking().chain_params_info().clone()))
+}
+
+/// Protocol upgrades of the current network profile
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UpgradeInfo {
+    /// current network profile
+    pub profile: NetworkProfile,
+    /// current tendermint height
+    pub height: BlockHeight,
+    /// all the upgrades, active or scheduled
+    pub upgrades: Vec<UpgradeStatus>,
+}
+
+/// query the jail history of a validator, in ascending order of height
+pub async fn query_validator_jail_hist(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    addr: web::Path<TendermintAddr>,
+) -> actix_web::Result<web::Json<Vec<(BlockHeight, JailEvent)>>> {
+    let qs = data.read();
+    let ledger = &qs.ledger_cloned;
+
+    let v_id = info!(ledger
+        .get_staking()
+        .validator_td_addr_to_app_pk(addr.as_ref()))
+    .map_err(error::ErrorBadRequest)?;
+
+    Ok(web::Json(
+        ledger
+            .api_cache
+            .as_ref()
+            .unwrap()
+            .staking_jail_hist
+            .get(&v_id)
+            .map(|hist| {
+                hist.iter()
+                    .flat_map(|(h, evs)| evs.into_iter().map(move |ev| (h, ev)))
+                    .collect()
+            })
+            .unwrap_or_default(),
+    ))
+}
+
+/// query every component of the state commitment at a height,
+/// only the latest heights are available(`FINDORAD_STATE_COMMITMENT_HIST_CNT`)
+pub async fn query_state_commitment(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    height: web::Path<BlockHeight>,
+) -> actix_web::Result<web::Json<StateCommitmentInfo>> {
+    let qs = data.read();
+    qs.ledger_cloned
+        .api_cache
+        .as_ref()
+        .unwrap()
+        .state_commitment_hist
+        .get(&height.into_inner())
+        .map(web::Json)
+        .ok_or_else(|| error::ErrorNotFound("nonexistent height"))
+}
+
+/// list the components of the state commitment
+/// which changed between two heights
+pub async fn query_state_commitment_diff(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    web::Query(info): web::Query<BlockRangeParams>,
+) -> actix_web::Result<web::Json<StateCommitmentDiff>> {
+    let qs = data.read();
+    let hist = &qs
+        .ledger_cloned
+        .api_cache
+        .as_ref()
+        .unwrap()
+        .state_commitment_hist;
+    let get = |h| {
+        hist.get(&h)
+            .ok_or_else(|| error::ErrorNotFound(format!("nonexistent height {}", h)))
+    };
+
+    Ok(web::Json(get(info.from)?.diff(&get(info.to)?)))
+}
+
+/// query the light header at a height, only signed by this node,
+/// the signatures of other validators can be merged by the light clients
+pub async fn query_light_header(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    height: web::Path<BlockHeight>,
+) -> actix_web::Result<web::Json<SignedLightHeader>> {
+    let qs = data.read();
+    qs.ledger_cloned
+        .api_cache
+        .as_ref()
+        .unwrap()
+        .light_headers
+        .get(&height.into_inner())
+        .map(web::Json)
+        .ok_or_else(|| error::ErrorNotFound("nonexistent height"))
+}
+
+/// query the protocol upgrades, including
+/// the activated ones and the scheduled ones
+pub async fn query_upgrades(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+) -> actix_web::Result<web::Json<UpgradeInfo>> {
+    let qs = data.read();
+    let height = qs.ledger_cloned.get_tendermint_height();
+    Ok(web::Json(UpgradeInfo {
+        profile: *upgrade::PROFILE,
+        height,
+        upgrades: upgrade::get_upgrades(height),
+    }))
+}
+
+fn get_block_detail(
+    qs: &QueryServer,
+    height: BlockHeight,
+) -> Result<Option<BlockDetail>> {
+    let (block_sid, txn_sids) = if let Some(b) = qs.get_block_by_height(height) {
+        b
+    } else {
+        return Ok(None);
+    };
+
+    let ledger = &qs.ledger_cloned;
+    let txns = txn_sids
+        .into_iter()
+        .map(|sid| {
+            let ft = ledger.get_transaction_light(sid).c(d!())?;
+
+            // same as `get_utxos_with_memo`, the position of a txo
+            // is also the index of its owner memo
+            let memos = ft.txn.get_owner_memos_ref();
+            let outputs = ft
+                .txo_ids
+                .iter()
+                .enumerate()
+                .map(|(idx, txo_sid)| {
+                    ledger
+                        .get_utxo_light(*txo_sid)
+                        .or_else(|| ledger.get_spent_utxo_light(*txo_sid))
+                        .map(|au| {
+                            (
+                                *txo_sid,
+                                au.utxo,
+                                memos.get(idx).copied().flatten().cloned(),
+                            )
+                        })
+                        .c(d!())
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let inputs = ft
+                .txn
+                .body
+                .operations
+                .iter()
+                .filter_map(|op| {
+                    if let Operation::TransferAsset(xfr) = op {
+                        Some(xfr.body.inputs.iter().filter_map(|i| {
+                            if let TxoRef::Absolute(txo_sid) = i {
+                                Some(*txo_sid)
+                            } else {
+                                None
+                            }
+                        }))
+                    } else {
+                        None
+                    }
+                })
+                .flatten()
+                .collect();
+
+            Ok(BlockTxn {
+                sid,
+                hash: ft.txn.hash_tm().hex().to_uppercase(),
+                fee: ft.txn.get_fee(),
+                operations: ft.txn.body.operations,
+                outputs,
+                inputs,
+            })
+        })
+        .collect::<Result<Vec<_>>>()
+        .c(d!())?;
+
+    Ok(Some(BlockDetail {
+        height,
+        block_sid,
+        fee_total: txns.iter().map(|t| t.fee).sum(),
+        txns,
+        // the index of a commitment is the block count
+        state_commitment: ledger
+            .get_state_commitment_at_block_height(1 + block_sid.0 as u64),
+    }))
+}
+
+/// query a block and its txns according to the tendermint height
+pub async fn query_block(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    height: web::Path<BlockHeight>,
+) -> actix_web::Result<web::Json<BlockDetail>> {
+    let qs = data.read();
+    get_block_detail(&qs, *height)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .map(web::Json)
+        .ok_or_else(|| error::ErrorNotFound("empty or nonexistent block"))
+}
+
+#[allow(missing_docs)]
+#[derive(Debug, Deserialize)]
+pub struct BlockRangeParams {
+    from: BlockHeight,
+    to: BlockHeight,
+}
+
+/// query the latest height committed by the query server,
+/// blocks above it can not be queried yet
+pub async fn query_committed_height(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+) -> web::Json<BlockHeight> {
+    web::Json(data.read().ledger_cloned.get_tendermint_height())
+}
+
+/// query non-empty blocks in the range of `[from, to]`, all of them must have been committed,
+/// at most `MAX_BLOCKS_PER_QUERY` heights can be queried at a time
+pub async fn query_blocks(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    web::Query(info): web::Query<BlockRangeParams>,
+) -> actix_web::Result<web::Json<Vec<BlockDetail>>> {
+    if info.from > info.to {
+        return Err(error::ErrorBadRequest("`from` is bigger than `to`"));
+    }
+    if info.to - info.from >= MAX_BLOCKS_PER_QUERY {
+        return Err(error::ErrorBadRequest(format!(
+            "at most {} blocks can be queried at a time",
+            MAX_BLOCKS_PER_QUERY
+        )));
+    }
+
+    let qs = data.read();
+    // so an empty result means there are no txns in these blocks,
+    // rather than the query server has not caught up
+    if info.to > qs.ledger_cloned.get_tendermint_height() {
+        return Err(error::ErrorBadRequest(
+            "`to` is bigger than the latest committed height",
+        ));
+    }
+
+    (info.from..=info.to)
+        .filter_map(|h| get_block_detail(&qs, h).transpose())
+        .collect::<Result<Vec<_>>>()
+        .map(web::Json)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))
+}
+
+/// query the range of `seq_id` that can be used by the `NoReplayToken` of a new txn,
+/// offline signers should use the `max_seq_id` to get the longest lifetime
+pub async fn query_no_replay_window(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+) -> web::Json<NoReplayWindow> {
+    web::Json(data.read().ledger_cloned.get_no_replay_window())
+}
+
+/// estimate the priority(fee per 1000 bytes) of a new txn
+/// according to the txns of recent blocks
+pub async fn query_fee_estimate(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+) -> actix_web::Result<web::Json<FeeEstimate>> {
+    Ok(web::Json(data.read().get_fee_estimate()))
+}
+
 /// query utxos according `public_key`
 pub async fn query_owned_utxos(
     data: web::Data<Arc<RwLock<QueryServer>>>,
@@ -691,6 +1109,37 @@ pub async fn query_owned_utxos(
         .map(|pk| web::Json(pnk!(ledger.get_owned_utxos(&pk))))
 }
 
+/// query utxos according `public_key`, paged by `TxoSID`
+#[allow(clippy::type_complexity)]
+pub async fn query_owned_utxos_paged(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    owner: web::Path<String>,
+    web::Query(cp): web::Query<CursorParams>,
+) -> actix_web::Result<web::Json<Page<(TxoSID, (Utxo, Option<OwnerMemo>))>>> {
+    let qs = data.read();
+    let ledger = &qs.ledger_cloned;
+    let pk = globutils::wallet::public_key_from_base64(owner.as_str())
+        .c(d!())
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    let sids = ledger.get_owned_utxo_sids(&pk);
+    let page = cp.cursor().collect_sorted(&sids, |sid| sid.0, |sid| sid);
+    let utxos = ledger
+        .get_utxos_with_memo(page.items)
+        .c(d!())
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // keep the order of the cursor
+    let mut items = utxos.into_iter().collect::<Vec<_>>();
+    alt!(cp.cursor().desc, items.reverse());
+
+    Ok(web::Json(Page {
+        total: page.total,
+        next: page.next,
+        items,
+    }))
+}
+
 #[allow(missing_docs)]
 pub enum ApiRoutes {
     UtxoSid,
@@ -701,12 +1150,29 @@ pub enum ApiRoutes {
     GlobalState,
     TxnSid,
     TxnSidLight,
+    TxnSidAt,
     GlobalStateVersion,
     OwnedUtxos,
+    OwnedUtxosPaged,
     ValidatorList,
     DelegationInfo,
     DelegatorList,
+    DelegatorListPaged,
     ValidatorDetail,
+    GovernanceRules,
+    ChainParams,
+    Block,
+    Blocks,
+    CommittedHeight,
+    FeeEstimate,
+    NoReplayWindow,
+    Upgrades,
+    ValidatorJailHist,
+    StateCommitment,
+    StateCommitmentDiff,
+    LightHeader,
+    MerkleRoots,
+    MerkleConsistency,
 }
 
 impl NetworkRoute for ApiRoutes {
@@ -720,12 +1186,29 @@ impl NetworkRoute for ApiRoutes {
             ApiRoutes::GlobalState => "global_state",
             ApiRoutes::TxnSid => "txn_sid",
             ApiRoutes::TxnSidLight => "txn_sid_light",
+            ApiRoutes::TxnSidAt => "txn_sid_at",
             ApiRoutes::GlobalStateVersion => "global_state_version",
             ApiRoutes::OwnedUtxos => "owned_utxos",
+            ApiRoutes::OwnedUtxosPaged => "owned_utxos_paged",
             ApiRoutes::ValidatorList => "validator_list",
             ApiRoutes::DelegationInfo => "delegation_info",
             ApiRoutes::DelegatorList => "delegator_list",
+            ApiRoutes::DelegatorListPaged => "delegator_list_paged",
             ApiRoutes::ValidatorDetail => "validator_detail",
+            ApiRoutes::GovernanceRules => "governance_rules",
+            ApiRoutes::ChainParams => "chain_params",
+            ApiRoutes::Block => "block",
+            ApiRoutes::Blocks => "blocks",
+            ApiRoutes::CommittedHeight => "committed_height",
+            ApiRoutes::FeeEstimate => "fee_estimate",
+            ApiRoutes::NoReplayWindow => "no_replay_window",
+            ApiRoutes::Upgrades => "upgrades",
+            ApiRoutes::ValidatorJailHist => "validator_jail_hist",
+            ApiRoutes::StateCommitment => "state_commitment",
+            ApiRoutes::StateCommitmentDiff => "state_commitment_diff",
+            ApiRoutes::LightHeader => "light_header",
+            ApiRoutes::MerkleRoots => "merkle_roots",
+            ApiRoutes::MerkleConsistency => "merkle_consistency",
         };
         "/".to_owned() + endpoint
     }
@@ -755,3 +1238,30 @@ pub fn validator_get_delegator_list<'a>(
         Err(eg!("Not a validator or non-existing node address"))
     }
 }
+
+#[allow(missing_docs)]
+pub fn validator_get_delegator_page(
+    s: &Staking,
+    validator: TendermintAddrRef,
+    cursor: &Cursor,
+) -> Result<Page<DelegatorInfo>> {
+    let validator = s.validator_td_addr_to_app_pk(validator).c(d!())?;
+
+    if let Some(v) = s.validator_get_current_one_by_id(&validator) {
+        let len = v.delegators.len() as u64;
+        Ok(cursor.collect(len, |idx, desc| {
+            let idx = alt!(desc, min!(idx, len.checked_sub(1)?), idx);
+            v.delegators.get_index(idx as usize).map(|(key, am)| {
+                (
+                    idx,
+                    DelegatorInfo::new(
+                        globutils::wallet::public_key_to_base64(key),
+                        *am,
+                    ),
+                )
+            })
+        }))
+    } else {
+        Err(eg!("Not a validator or non-existing node address"))
+    }
+}
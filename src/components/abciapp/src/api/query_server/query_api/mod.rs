@@ -7,6 +7,7 @@ pub mod ledger_api;
 
 pub mod server;
 pub mod service;
+pub mod subscription;
 
 use {
     actix_cors::Cors,
@@ -27,11 +28,12 @@ use {
     parking_lot::RwLock,
     ruc::*,
     serde::{Deserialize, Serialize},
-    server::QueryServer,
+    server::{Page, QueryServer},
     std::{
         collections::{BTreeMap, HashSet},
         sync::Arc,
     },
+    subscription::subscribe,
     zei::{
         serialization::ZeiFromToBytes,
         xfr::{sig::XfrPublicKey, structs::OwnerMemo},
@@ -107,6 +109,23 @@ pub async fn get_owned_utxos(
         .map(|pk| web::Json(pnk!(ledger.get_owned_utxos(&pk)).keys().copied().collect()))
 }
 
+/// Returns the utxo sids currently spendable by a given address, paged by `TxoSID`
+pub async fn get_owned_utxos_paged(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    owner: web::Path<String>,
+    web::Query(cp): web::Query<CursorParams>,
+) -> actix_web::Result<web::Json<Page<TxoSID>>> {
+    let qs = data.read();
+    let ledger = &qs.ledger_cloned;
+    wallet::public_key_from_base64(owner.as_str())
+        .c(d!())
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))
+        .map(|pk| {
+            let sids = ledger.get_owned_utxo_sids(&pk);
+            web::Json(cp.cursor().collect_sorted(&sids, |sid| sid.0, |sid| sid))
+        })
+}
+
 /// Define interface type
 #[allow(missing_docs)]
 pub enum QueryServerRoutes {
@@ -114,11 +133,14 @@ pub enum QueryServerRoutes {
     GetOwnerMemo,
     GetOwnerMemoBatch,
     GetOwnedUtxos,
+    GetOwnedUtxosPaged,
     GetCreatedAssets,
     GetIssuedRecords,
     GetIssuedRecordsByCode,
     GetRelatedTxns,
+    GetRelatedTxnsPaged,
     GetRelatedXfrs,
+    GetRelatedXfrsPaged,
     GetAuthencatedTxnIDHash,
     GetTransactionHash,
     GetTransactionSid,
@@ -130,8 +152,11 @@ impl NetworkRoute for QueryServerRoutes {
         let endpoint = match *self {
             QueryServerRoutes::GetAddress => "get_address",
             QueryServerRoutes::GetRelatedTxns => "get_related_txns",
+            QueryServerRoutes::GetRelatedTxnsPaged => "get_related_txns_paged",
             QueryServerRoutes::GetRelatedXfrs => "get_related_xfrs",
+            QueryServerRoutes::GetRelatedXfrsPaged => "get_related_xfrs_paged",
             QueryServerRoutes::GetOwnedUtxos => "get_owned_utxos",
+            QueryServerRoutes::GetOwnedUtxosPaged => "get_owned_utxos_paged",
             QueryServerRoutes::GetOwnerMemo => "get_owner_memo",
             QueryServerRoutes::GetOwnerMemoBatch => "get_owner_memo_batch",
             QueryServerRoutes::GetCreatedAssets => "get_created_assets",
@@ -263,17 +288,9 @@ pub struct WalletQueryParams {
     order: OrderOption,
 }
 
-#[allow(missing_docs)]
-#[derive(Debug, Deserialize, PartialEq)]
-#[serde(rename_all = "snake_case")]
-enum OrderOption {
-    Desc,
-    Asc,
-}
-
 #[allow(missing_docs)]
 #[derive(Debug, Deserialize, Serialize)]
-struct CoinbaseTxnBody {
+pub struct CoinbaseTxnBody {
     height: u64,
     data: MintEntry,
 }
@@ -413,6 +430,102 @@ pub async fn get_related_xfrs(
     }
 }
 
+#[allow(missing_docs)]
+#[derive(Debug, Deserialize)]
+pub struct AddressQueryParams {
+    address: String,
+}
+
+/// paging Query coinbase payments according to `AddressQueryParams` and `CursorParams`,
+/// the cursor is the block height
+pub async fn get_coinbase_oper_list_paged(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    web::Query(info): web::Query<AddressQueryParams>,
+    web::Query(cp): web::Query<CursorParams>,
+) -> actix_web::Result<web::Json<Page<CoinbaseTxnBody>>> {
+    // Convert from base64 representation
+    let key: XfrPublicKey = wallet::public_key_from_base64(&info.address)
+        .c(d!())
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    let server = data.read();
+    let page = server.get_coinbase_entries_page(&XfrAddress { key }, &cp.cursor());
+
+    Ok(web::Json(Page {
+        total: page.total,
+        next: page.next,
+        items: page
+            .items
+            .into_iter()
+            .map(|(height, data)| CoinbaseTxnBody { height, data })
+            .collect(),
+    }))
+}
+
+/// Returns the list of claim transations of a given ledger address,
+/// the cursor is the `TxnSID`
+pub async fn get_claim_txns_paged(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    web::Query(info): web::Query<AddressQueryParams>,
+    web::Query(cp): web::Query<CursorParams>,
+) -> actix_web::Result<web::Json<Page<Option<Transaction>>>> {
+    // Convert from base64 representation
+    let key: XfrPublicKey = wallet::public_key_from_base64(&info.address)
+        .c(d!())
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    let server = data.read();
+    let records = server.get_claim_transactions_page(&XfrAddress { key }, &cp.cursor());
+
+    Ok(web::Json(records))
+}
+
+/// Returns the list of transations associated with a given ledger address,
+/// the cursor is the `TxnSID`
+pub async fn get_related_txns_paged(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    info: web::Path<String>,
+    web::Query(cp): web::Query<CursorParams>,
+) -> actix_web::Result<web::Json<Page<TxnSID>>> {
+    // Convert from base64 representation
+    let key: XfrPublicKey = XfrPublicKey::zei_from_bytes(
+        &b64dec(&*info)
+            .c(d!())
+            .map_err(|e| error::ErrorBadRequest(e.to_string()))?,
+    )
+    .c(d!())
+    .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+    let server = data.read();
+    let records =
+        server.get_related_transactions_page(&XfrAddress { key }, &cp.cursor());
+    Ok(web::Json(records.unwrap_or_default()))
+}
+
+/// Returns the list of transfer transations associated with a given asset,
+/// the cursor is the `TxnSID`
+pub async fn get_related_xfrs_paged(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    info: web::Path<String>,
+    web::Query(cp): web::Query<CursorParams>,
+) -> actix_web::Result<web::Json<Page<TxnSID>>> {
+    let server = data.read();
+    if let Ok(token_code) = AssetTypeCode::new_from_base64(&*info) {
+        if let Some(records) =
+            server.get_related_transfers_page(&token_code, &cp.cursor())
+        {
+            Ok(web::Json(records))
+        } else {
+            Err(actix_web::error::ErrorNotFound(
+                "Specified asset definition does not currently exist.",
+            ))
+        }
+    } else {
+        Err(actix_web::error::ErrorBadRequest(
+            "Invalid asset definition encoding.",
+        ))
+    }
+}
+
 #[allow(missing_docs)]
 #[allow(clippy::unnecessary_wraps)]
 pub async fn get_circulating_supply(
@@ -506,6 +619,10 @@ impl QueryApi {
                     &QueryServerRoutes::GetOwnedUtxos.with_arg_template("address"),
                     web::get().to(get_owned_utxos),
                 )
+                .route(
+                    &QueryServerRoutes::GetOwnedUtxosPaged.with_arg_template("address"),
+                    web::get().to(get_owned_utxos_paged),
+                )
                 .route(
                     &QueryServerRoutes::GetOwnerMemo.with_arg_template("txo_sid"),
                     web::get().to(get_owner_memo),
@@ -519,17 +636,34 @@ impl QueryApi {
                     &QueryServerRoutes::GetRelatedTxns.with_arg_template("address"),
                     web::get().to(get_related_txns),
                 )
+                .route(
+                    &QueryServerRoutes::GetRelatedTxnsPaged.with_arg_template("address"),
+                    web::get().to(get_related_txns_paged),
+                )
                 .service(
                     web::resource("claim_history").route(web::get().to(get_claim_txns)),
                 )
+                .service(
+                    web::resource("claim_history_paged")
+                        .route(web::get().to(get_claim_txns_paged)),
+                )
                 .service(
                     web::resource("coinbase_history")
                         .route(web::get().to(get_coinbase_oper_list)),
                 )
+                .service(
+                    web::resource("coinbase_history_paged")
+                        .route(web::get().to(get_coinbase_oper_list_paged)),
+                )
                 .route(
                     &QueryServerRoutes::GetRelatedXfrs.with_arg_template("asset_token"),
                     web::get().to(get_related_xfrs),
                 )
+                .route(
+                    &QueryServerRoutes::GetRelatedXfrsPaged
+                        .with_arg_template("asset_token"),
+                    web::get().to(get_related_xfrs_paged),
+                )
                 .route(
                     &QueryServerRoutes::GetCreatedAssets.with_arg_template("address"),
                     web::get().to(get_created_assets),
@@ -560,6 +694,7 @@ impl QueryApi {
                     &QueryServerRoutes::GetCommits.route(),
                     web::get().to(get_commits),
                 )
+                .service(web::resource("subscribe").route(web::get().to(subscribe)))
                 .route(
                     &ApiRoutes::UtxoSid.with_arg_template("sid"),
                     web::get().to(query_utxo),
@@ -592,6 +727,7 @@ impl QueryApi {
                     &ApiRoutes::TxnSidLight.with_arg_template("sid"),
                     web::get().to(query_txn_light),
                 )
+                .route(&ApiRoutes::TxnSidAt.route(), web::get().to(query_txn_at))
                 .route(
                     &ApiRoutes::GlobalStateVersion.with_arg_template("version"),
                     web::get().to(query_global_state_version),
@@ -600,6 +736,10 @@ impl QueryApi {
                     &ApiRoutes::OwnedUtxos.with_arg_template("owner"),
                     web::get().to(query_owned_utxos),
                 )
+                .route(
+                    &ApiRoutes::OwnedUtxosPaged.with_arg_template("owner"),
+                    web::get().to(query_owned_utxos_paged),
+                )
                 .route(
                     &ApiRoutes::ValidatorList.route(),
                     web::get().to(query_validators),
@@ -616,6 +756,14 @@ impl QueryApi {
                     web::resource("/delegator_list")
                         .route(web::get().to(get_delegators_with_params)),
                 )
+                .route(
+                    &ApiRoutes::DelegatorListPaged.with_arg_template("NodeAddress"),
+                    web::get().to(query_delegator_list_paged),
+                )
+                .service(
+                    web::resource("/delegator_list_paged")
+                        .route(web::get().to(get_delegators_with_params_paged)),
+                )
                 .service(
                     web::resource("/delegation_rewards")
                         .route(web::get().to(get_delegation_reward)),
@@ -628,6 +776,56 @@ impl QueryApi {
                     &ApiRoutes::ValidatorDetail.with_arg_template("NodeAddress"),
                     web::get().to(query_validator_detail),
                 )
+                .route(
+                    &ApiRoutes::GovernanceRules.route(),
+                    web::get().to(query_governance_rules),
+                )
+                .route(
+                    &ApiRoutes::ChainParams.route(),
+                    web::get().to(query_chain_params),
+                )
+                .route(
+                    &ApiRoutes::Block.with_arg_template("height"),
+                    web::get().to(query_block),
+                )
+                .route(&ApiRoutes::Blocks.route(), web::get().to(query_blocks))
+                .route(
+                    &ApiRoutes::CommittedHeight.route(),
+                    web::get().to(query_committed_height),
+                )
+                .route(
+                    &ApiRoutes::FeeEstimate.route(),
+                    web::get().to(query_fee_estimate),
+                )
+                .route(
+                    &ApiRoutes::NoReplayWindow.route(),
+                    web::get().to(query_no_replay_window),
+                )
+                .route(&ApiRoutes::Upgrades.route(), web::get().to(query_upgrades))
+                .route(
+                    &ApiRoutes::ValidatorJailHist.with_arg_template("NodeAddress"),
+                    web::get().to(query_validator_jail_hist),
+                )
+                .route(
+                    &ApiRoutes::StateCommitment.with_arg_template("height"),
+                    web::get().to(query_state_commitment),
+                )
+                .route(
+                    &ApiRoutes::StateCommitmentDiff.route(),
+                    web::get().to(query_state_commitment_diff),
+                )
+                .route(
+                    &ApiRoutes::LightHeader.with_arg_template("height"),
+                    web::get().to(query_light_header),
+                )
+                .route(
+                    &ApiRoutes::MerkleRoots.with_arg_template("version"),
+                    web::get().to(query_merkle_roots),
+                )
+                .route(
+                    &ApiRoutes::MerkleConsistency.route(),
+                    web::get().to(query_merkle_consistency),
+                )
         });
 
         for (host, port) in addrs.iter() {
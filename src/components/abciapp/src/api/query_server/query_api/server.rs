@@ -3,18 +3,23 @@
 //!
 
 use {
+    fbnc::Mapxnk,
     lazy_static::lazy_static,
     ledger::{
         data_model::{
-            AssetTypeCode, DefineAsset, IssuerPublicKey, Transaction, TxOutput,
-            TxnIDHash, TxnSID, TxoSID, XfrAddress,
+            AssetTypeCode, BlockSID, DefineAsset, IssuerPublicKey, Transaction,
+            TxOutput, TxnIDHash, TxnSID, TxoSID, XfrAddress,
         },
         staking::{ops::mint_fra::MintEntry, BlockHeight},
         store::LedgerState,
     },
     parking_lot::{Condvar, Mutex, RwLock},
     ruc::*,
-    std::{collections::HashSet, sync::Arc},
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::{HashSet, VecDeque},
+        sync::Arc,
+    },
     zei::xfr::structs::OwnerMemo,
 };
 
@@ -25,20 +30,31 @@ lazy_static! {
         Arc::new((Mutex::new(false), Condvar::new()));
 }
 
+/// Number of recent non-empty blocks used in fee estimation.
+pub const FEE_ESTIMATE_BLOCKS: usize = 100;
+
 /// A data container for API
 pub struct QueryServer {
     pub(crate) ledger: Arc<RwLock<LedgerState>>,
     pub(crate) ledger_cloned: LedgerState,
+    // priorities(fee per 1000 bytes) of the txns in recent blocks
+    fee_rates: VecDeque<Vec<u64>>,
+    // index of the next block to be added into `fee_rates`
+    fee_rates_next_block: usize,
 }
 
 impl QueryServer {
     /// create query server
     pub fn new(ledger: Arc<RwLock<LedgerState>>) -> QueryServer {
         let ledger_cloned = ledger.read().clone();
-        QueryServer {
+        let mut qs = QueryServer {
             ledger,
             ledger_cloned,
-        }
+            fee_rates: VecDeque::new(),
+            fee_rates_next_block: 0,
+        };
+        qs.update_fee_rates();
+        qs
     }
 
     /// Returns the set of records issued by a certain key.
@@ -239,6 +255,95 @@ impl QueryServer {
             .map(|d| d.iter().map(|(k, _)| k).collect())
     }
 
+    /// get coinbase entries of an address, paged by height
+    pub fn get_coinbase_entries_page(
+        &self,
+        address: &XfrAddress,
+        cursor: &Cursor,
+    ) -> Page<(BlockHeight, MintEntry)> {
+        self.ledger_cloned
+            .api_cache
+            .as_ref()
+            .unwrap()
+            .coinbase_oper_hist
+            .get(address)
+            .map(|hist| {
+                cursor.collect(hist.len() as u64, |h, desc| {
+                    alt!(
+                        desc,
+                        hist.get_closest_smaller(&h),
+                        hist.get_closest_larger(&h)
+                    )
+                    .map(|(h, me)| (h, (h, me)))
+                })
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns a list of claim transactions of a given ledger address, paged by txn sid
+    pub fn get_claim_transactions_page(
+        &self,
+        address: &XfrAddress,
+        cursor: &Cursor,
+    ) -> Page<Option<Transaction>> {
+        self.ledger_cloned
+            .api_cache
+            .as_ref()
+            .unwrap()
+            .claim_hist_txns
+            .get(address)
+            .map(|hist| {
+                cursor.collect(hist.len() as u64, |sid, desc| {
+                    let sid = TxnSID(sid as usize);
+                    alt!(
+                        desc,
+                        hist.get_closest_smaller(&sid),
+                        hist.get_closest_larger(&sid)
+                    )
+                    .map(|(sid, _)| {
+                        let tx =
+                            ruc::info!(self.ledger_cloned.get_transaction_light(sid))
+                                .map(|tx| tx.txn)
+                                .ok();
+                        (sid.0 as u64, tx)
+                    })
+                })
+            })
+            .unwrap_or_default()
+    }
+
+    /// Paged version of `get_related_transactions`, paged by txn sid
+    #[inline(always)]
+    pub fn get_related_transactions_page(
+        &self,
+        address: &XfrAddress,
+        cursor: &Cursor,
+    ) -> Option<Page<TxnSID>> {
+        self.ledger_cloned
+            .api_cache
+            .as_ref()
+            .unwrap()
+            .related_transactions
+            .get(&address)
+            .map(|d| cursor.collect_txn_sids(&d))
+    }
+
+    /// Paged version of `get_related_transfers`, paged by txn sid
+    #[inline(always)]
+    pub fn get_related_transfers_page(
+        &self,
+        code: &AssetTypeCode,
+        cursor: &Cursor,
+    ) -> Option<Page<TxnSID>> {
+        self.ledger_cloned
+            .api_cache
+            .as_ref()
+            .unwrap()
+            .related_transfers
+            .get(&code)
+            .map(|d| cursor.collect_txn_sids(&d))
+    }
+
     /// Returns the owner of a given txo_sid.
     #[inline(always)]
     pub fn get_address_of_sid(&self, txo_sid: TxoSID) -> Option<XfrAddress> {
@@ -283,6 +388,21 @@ impl QueryServer {
             .get(&txn_hash)
     }
 
+    /// Returns the block and its txns at a tendermint height,
+    /// `None` if the block is empty or not committed yet.
+    #[inline(always)]
+    pub fn get_block_by_height(
+        &self,
+        height: BlockHeight,
+    ) -> Option<(BlockSID, Vec<TxnSID>)> {
+        self.ledger_cloned
+            .api_cache
+            .as_ref()
+            .unwrap()
+            .height_to_block
+            .get(&height)
+    }
+
     /// Returns most recent commits at query_server side.
     #[inline(always)]
     pub fn get_commits(&self) -> u64 {
@@ -311,11 +431,258 @@ impl QueryServer {
             .get(height)
     }
 
+    /// The minimum fee of a transaction in the next block,
+    /// a new value of the chain parameters may be activated in it.
+    pub fn get_tx_fee_min(&self) -> u64 {
+        let staking = self.ledger_cloned.get_staking();
+        staking
+            .chain_params_at_height(staking.cur_height() + 1)
+            .tx_fee_min
+    }
+
+    /// Percentiles of the priorities(fee per 1000 bytes) in recent blocks.
+    pub fn get_fee_estimate(&self) -> FeeEstimate {
+        let mut rates = self.fee_rates.iter().flatten().copied().collect::<Vec<_>>();
+        rates.sort_unstable();
+
+        let percentile =
+            |p: usize| alt!(rates.is_empty(), 0, rates[(rates.len() - 1) * p / 100]);
+
+        FeeEstimate {
+            blocks: self.fee_rates.len() as u64,
+            txns: rates.len() as u64,
+            tx_fee_min: self.get_tx_fee_min(),
+            min: percentile(0),
+            p25: percentile(25),
+            p50: percentile(50),
+            p75: percentile(75),
+            p90: percentile(90),
+            max: percentile(100),
+        }
+    }
+
+    // add the priorities of new blocks, and drop the outdated ones
+    fn update_fee_rates(&mut self) {
+        let cnt = self.ledger_cloned.get_block_count();
+        let start = self
+            .fee_rates_next_block
+            .max(cnt.saturating_sub(FEE_ESTIMATE_BLOCKS));
+
+        for idx in start..cnt {
+            if let Some(b) = self.ledger_cloned.blocks.get(idx) {
+                let rates = b
+                    .txns
+                    .iter()
+                    .filter(|t| !t.txn.is_coinbase_tx())
+                    .filter_map(|t| {
+                        serde_json::to_vec(&t.txn)
+                            .ok()
+                            .map(|bytes| t.txn.get_priority(bytes.len()))
+                    })
+                    .collect();
+                self.fee_rates.push_back(rates);
+            }
+        }
+
+        while self.fee_rates.len() > FEE_ESTIMATE_BLOCKS {
+            self.fee_rates.pop_front();
+        }
+        self.fee_rates_next_block = cnt;
+    }
+
     /// update after a new block is created
     #[inline(always)]
     pub fn update(&mut self) {
         if let Some(l) = self.ledger.try_read() {
             self.ledger_cloned = l.clone();
         }
+        self.update_fee_rates();
+    }
+}
+
+/// Max number of entries in a page.
+pub const MAX_PAGE_LIMIT: usize = 1000;
+
+/// Cursor of a page.
+#[derive(Clone, Copy, Debug)]
+pub struct Cursor {
+    /// The first key(sid or height) of the page, inclusive,
+    /// starts from the first or the last entry if `None`.
+    pub start: Option<u64>,
+    /// Max number of entries in the page.
+    pub limit: usize,
+    /// Iterate in descending order.
+    pub desc: bool,
+}
+
+impl Cursor {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn new(start: Option<u64>, limit: usize, desc: bool) -> Self {
+        Cursor {
+            start,
+            limit: limit.max(1).min(MAX_PAGE_LIMIT),
+            desc,
+        }
+    }
+
+    /// Collect a page by seeking repeatedly,
+    /// `seek(key, desc)` should return the closest entry at or after `key`,
+    /// or at or before `key` if `desc` is true.
+    pub fn collect<T>(
+        &self,
+        total: u64,
+        seek: impl Fn(u64, bool) -> Option<(u64, T)>,
+    ) -> Page<T> {
+        let mut items = vec![];
+        let mut next = None;
+        let mut key = self.start.unwrap_or(alt!(self.desc, u64::MAX, 0));
+
+        while let Some((k, v)) = seek(key, self.desc) {
+            if items.len() == self.limit {
+                next = Some(k);
+                break;
+            }
+            items.push(v);
+            key = if self.desc {
+                if let Some(k) = k.checked_sub(1) {
+                    k
+                } else {
+                    break;
+                }
+            } else if let Some(k) = k.checked_add(1) {
+                k
+            } else {
+                break;
+            };
+        }
+
+        Page { total, next, items }
+    }
+
+    /// Collect a page from keys in ascending order.
+    pub fn collect_sorted<K: Copy, T>(
+        &self,
+        keys: &[K],
+        key_to_u64: impl Fn(K) -> u64,
+        get: impl Fn(K) -> T,
+    ) -> Page<T> {
+        self.collect(keys.len() as u64, |k, desc| {
+            let idx = match keys.binary_search_by_key(&k, |i| key_to_u64(*i)) {
+                Ok(idx) => Some(idx),
+                Err(idx) => alt!(desc, idx.checked_sub(1), Some(idx)),
+            };
+            idx.and_then(|idx| keys.get(idx))
+                .map(|i| (key_to_u64(*i), get(*i)))
+        })
+    }
+
+    #[inline(always)]
+    fn collect_txn_sids(&self, sids: &Mapxnk<TxnSID, bool>) -> Page<TxnSID> {
+        self.collect(sids.len() as u64, |sid, desc| {
+            let sid = TxnSID(sid as usize);
+            alt!(
+                desc,
+                sids.get_closest_smaller(&sid),
+                sids.get_closest_larger(&sid)
+            )
+            .map(|(sid, _)| (sid.0 as u64, sid))
+        })
+    }
+}
+
+/// Fee estimation based on the priorities(fee per 1000 bytes) of recent txns.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct FeeEstimate {
+    /// Number of blocks sampled.
+    pub blocks: u64,
+    /// Number of txns sampled.
+    pub txns: u64,
+    /// The minimum fee of a transaction in the next block.
+    pub tx_fee_min: u64,
+    #[allow(missing_docs)]
+    pub min: u64,
+    #[allow(missing_docs)]
+    pub p25: u64,
+    #[allow(missing_docs)]
+    pub p50: u64,
+    #[allow(missing_docs)]
+    pub p75: u64,
+    #[allow(missing_docs)]
+    pub p90: u64,
+    #[allow(missing_docs)]
+    pub max: u64,
+}
+
+/// A page of entries.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Page<T> {
+    /// Total number of entries.
+    pub total: u64,
+    /// Start of the next page, `None` if this is the last page.
+    pub next: Option<u64>,
+    #[allow(missing_docs)]
+    pub items: Vec<T>,
+}
+
+impl<T> Default for Page<T> {
+    fn default() -> Self {
+        Page {
+            total: 0,
+            next: None,
+            items: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cursor_collect_sorted() {
+        let keys = (0..10u64).map(|i| i * 2).collect::<Vec<_>>();
+        let collect = |start, limit, desc| {
+            Cursor::new(start, limit, desc).collect_sorted(&keys, |k| k, |k| k)
+        };
+
+        let page = collect(None, 4, false);
+        assert_eq!(page.total, 10);
+        assert_eq!(page.items, vec![0, 2, 4, 6]);
+        assert_eq!(page.next, Some(8));
+
+        // start from a nonexistent key
+        let page = collect(Some(13), 4, false);
+        assert_eq!(page.items, vec![14, 16, 18]);
+        assert_eq!(page.next, None);
+
+        let page = collect(None, 3, true);
+        assert_eq!(page.items, vec![18, 16, 14]);
+        assert_eq!(page.next, Some(12));
+
+        let page = collect(page.next, 3, true);
+        assert_eq!(page.items, vec![12, 10, 8]);
+        assert_eq!(page.next, Some(6));
+
+        let page = collect(Some(3), 3, true);
+        assert_eq!(page.items, vec![2, 0]);
+        assert_eq!(page.next, None);
+
+        let page = Cursor::new(None, 3, false).collect_sorted(&[], |k: u64| k, |k| k);
+        assert_eq!(page.total, 0);
+        assert!(page.items.is_empty());
+        assert_eq!(page.next, None);
+    }
+
+    #[test]
+    fn cursor_limit() {
+        assert_eq!(Cursor::new(None, 0, false).limit, 1);
+        assert_eq!(Cursor::new(None, usize::MAX, false).limit, MAX_PAGE_LIMIT);
+
+        let keys = (0..MAX_PAGE_LIMIT as u64 + 10).collect::<Vec<_>>();
+        let page =
+            Cursor::new(None, usize::MAX, false).collect_sorted(&keys, |k| k, |k| k);
+        assert_eq!(page.items.len(), MAX_PAGE_LIMIT);
+        assert_eq!(page.next, Some(MAX_PAGE_LIMIT as u64));
     }
 }
@@ -5,7 +5,7 @@
 use {
     super::{
         server::{QueryServer, BLOCK_CREATED},
-        QueryApi,
+        subscription, QueryApi,
     },
     ledger::store::LedgerState,
     parking_lot::RwLock,
@@ -28,6 +28,7 @@ pub(crate) fn start_query_server(
                 BLOCK_CREATED.1.wait(&mut created);
             }
             qs2.write().update();
+            subscription::dispatch_events();
             *created = false;
         });
         qs
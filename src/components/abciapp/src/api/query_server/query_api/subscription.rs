@@ -0,0 +1,309 @@
+//!
+//! # Event Subscription
+//!
+//! Push events to clients by SSE(server-sent events),
+//! instead of polling the query server every block.
+//!
+
+use {
+    actix_web::{error, web, HttpResponse},
+    futures::channel::mpsc::{channel, Sender},
+    globutils::wallet,
+    lazy_static::lazy_static,
+    ledger::{
+        data_model::{TxoSID, XfrAddress},
+        store::api_cache::{ApiEvent, CHAN_API_EVENTS},
+    },
+    parking_lot::Mutex,
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::{collections::BTreeSet, mem},
+    zei::xfr::sig::XfrPublicKey,
+};
+
+/// At most this number of clients can subscribe at the same time.
+pub const MAX_SUBSCRIBERS: usize = 1000;
+
+/// The number of events buffered for a subscriber,
+/// a client which can not keep up will be dropped.
+pub const SUBSCRIBER_BUF_SIZE: usize = 1000;
+
+lazy_static! {
+    static ref SUBSCRIBERS: Mutex<Vec<Subscriber>> = Mutex::new(vec![]);
+}
+
+struct Subscriber {
+    filter: Filter,
+    sender: Sender<actix_web::Result<web::Bytes>>,
+}
+
+/// Parameters of a subscription, list items are separated by ','
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct SubscribeParams {
+    /// new blocks
+    pub block: Option<bool>,
+    /// transactions touching these addresses, in base64 format
+    pub address: Option<String>,
+    /// spends of these txos
+    pub txo_sid: Option<String>,
+    /// staking events of these addresses, in base64 format
+    pub staking: Option<String>,
+}
+
+#[derive(Default)]
+struct Filter {
+    block: bool,
+    addresses: BTreeSet<XfrAddress>,
+    txo_sids: BTreeSet<TxoSID>,
+    staking: BTreeSet<XfrPublicKey>,
+}
+
+impl Filter {
+    fn new(params: &SubscribeParams) -> Result<Self> {
+        let parse_pks = |s: &Option<String>| {
+            s.as_deref()
+                .unwrap_or_default()
+                .split(',')
+                .filter(|i| !i.is_empty())
+                .map(|i| wallet::public_key_from_base64(i).c(d!()))
+                .collect::<Result<BTreeSet<_>>>()
+        };
+
+        let filter = Filter {
+            block: params.block.unwrap_or(false),
+            addresses: parse_pks(&params.address)
+                .c(d!())?
+                .into_iter()
+                .map(|key| XfrAddress { key })
+                .collect(),
+            txo_sids: params
+                .txo_sid
+                .as_deref()
+                .unwrap_or_default()
+                .split(',')
+                .filter(|i| !i.is_empty())
+                .map(|i| i.parse::<u64>().c(d!()).map(TxoSID))
+                .collect::<Result<_>>()
+                .c(d!())?,
+            staking: parse_pks(&params.staking).c(d!())?,
+        };
+
+        if !filter.block
+            && filter.addresses.is_empty()
+            && filter.txo_sids.is_empty()
+            && filter.staking.is_empty()
+        {
+            return Err(eg!("nothing to subscribe"));
+        }
+
+        Ok(filter)
+    }
+
+    fn matches(&self, ev: &ApiEvent) -> bool {
+        match ev {
+            ApiEvent::NewBlock { .. } => self.block,
+            ApiEvent::RelatedTxn { address, .. } => self.addresses.contains(address),
+            ApiEvent::TxoSpent { txo_sid, .. } => self.txo_sids.contains(txo_sid),
+            ApiEvent::Staking { pubkey, .. } => self.staking.contains(pubkey),
+        }
+    }
+}
+
+/// Subscribe events, the response is an endless `text/event-stream`
+pub async fn subscribe(
+    params: web::Query<SubscribeParams>,
+) -> actix_web::Result<HttpResponse> {
+    let filter =
+        Filter::new(&params).map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    let (sender, receiver) = channel(SUBSCRIBER_BUF_SIZE);
+    add_subscriber(&mut SUBSCRIBERS.lock(), Subscriber { filter, sender })
+        .map_err(|e| error::ErrorServiceUnavailable(e.to_string()))?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .streaming(receiver))
+}
+
+// Disconnected clients are removed first, so they don't count.
+fn add_subscriber(subs: &mut Vec<Subscriber>, sub: Subscriber) -> Result<()> {
+    subs.retain(|s| !s.sender.is_closed());
+    if subs.len() >= MAX_SUBSCRIBERS {
+        return Err(eg!("too many subscribers"));
+    }
+    subs.push(sub);
+    Ok(())
+}
+
+/// Send new events to all the subscribers,
+/// will be called after the query server has been updated
+pub(crate) fn dispatch_events() {
+    let events = CHAN_API_EVENTS.1.lock().try_iter().collect::<Vec<_>>();
+    dispatch(&mut SUBSCRIBERS.lock(), &events);
+}
+
+// Send events to the subscribers who are interested in them,
+// clients that have been disconnected or whose buffers are full will be removed.
+fn dispatch(subs: &mut Vec<Subscriber>, events: &[ApiEvent]) {
+    let msgs = events
+        .iter()
+        .flat_map(|ev| {
+            ruc::info!(serde_json::to_string(ev).c(d!()))
+                .ok()
+                .map(|data| {
+                    let msg = format!("event: {}\ndata: {}\n\n", event_name(ev), data);
+                    (ev, web::Bytes::from(msg))
+                })
+        })
+        .collect::<Vec<_>>();
+
+    // the liveness is checked separately,
+    // a subscriber may have no matched events for a long time
+    let all = mem::take(subs);
+    subs.extend(all.into_iter().filter_map(|mut sub| {
+        let alive = !sub.sender.is_closed()
+            && msgs
+                .iter()
+                .filter(|(ev, _)| sub.filter.matches(ev))
+                .all(|(_, msg)| sub.sender.try_send(Ok(msg.clone())).is_ok());
+        alt!(alive, Some(sub), None)
+    }));
+}
+
+#[inline(always)]
+fn event_name(ev: &ApiEvent) -> &'static str {
+    match ev {
+        ApiEvent::NewBlock { .. } => "new_block",
+        ApiEvent::RelatedTxn { .. } => "related_txn",
+        ApiEvent::TxoSpent { .. } => "txo_spent",
+        ApiEvent::Staking { .. } => "staking",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*, futures::channel::mpsc::Receiver as MpscReceiver,
+        ledger::data_model::TxnSID,
+    };
+
+    type Receiver = MpscReceiver<actix_web::Result<web::Bytes>>;
+
+    fn gen_subscriber(params: SubscribeParams) -> (Subscriber, Receiver) {
+        let filter = pnk!(Filter::new(&params));
+        let (sender, receiver) = channel(SUBSCRIBER_BUF_SIZE);
+        (Subscriber { filter, sender }, receiver)
+    }
+
+    fn recv(r: &mut Receiver) -> Option<String> {
+        r.try_next()
+            .ok()
+            .flatten()
+            .map(|msg| String::from_utf8(msg.unwrap().to_vec()).unwrap())
+    }
+
+    #[test]
+    fn subscription_filter() {
+        assert!(Filter::new(&SubscribeParams::default()).is_err());
+        assert!(Filter::new(&SubscribeParams {
+            txo_sid: Some("1,x".to_owned()),
+            ..Default::default()
+        })
+        .is_err());
+        assert!(Filter::new(&SubscribeParams {
+            address: Some("not a pubkey".to_owned()),
+            ..Default::default()
+        })
+        .is_err());
+
+        let f = pnk!(Filter::new(&SubscribeParams {
+            txo_sid: Some("1,2,".to_owned()),
+            ..Default::default()
+        }));
+        assert_eq!(f.txo_sids.len(), 2);
+        assert!(!f.matches(&ApiEvent::NewBlock {
+            height: 1,
+            txns: vec![],
+        }));
+    }
+
+    #[test]
+    fn subscription_dispatch() {
+        let (s0, mut r0) = gen_subscriber(SubscribeParams {
+            block: Some(true),
+            ..Default::default()
+        });
+        let (s1, mut r1) = gen_subscriber(SubscribeParams {
+            txo_sid: Some("7".to_owned()),
+            ..Default::default()
+        });
+        let (s2, r2) = gen_subscriber(SubscribeParams {
+            txo_sid: Some("8".to_owned()),
+            ..Default::default()
+        });
+        let mut subs = vec![s0, s1, s2];
+
+        // disconnected, and there is nothing matched for it
+        drop(r2);
+
+        let events = [
+            ApiEvent::NewBlock {
+                height: 1,
+                txns: vec![],
+            },
+            ApiEvent::TxoSpent {
+                txo_sid: TxoSID(7),
+                txn: (TxnSID(0), "hash".to_owned()),
+            },
+        ];
+        dispatch(&mut subs, &events);
+        assert_eq!(subs.len(), 2);
+
+        assert!(pnk!(recv(&mut r0)).starts_with("event: new_block\n"));
+        assert!(recv(&mut r0).is_none());
+        assert!(pnk!(recv(&mut r1)).starts_with("event: txo_spent\n"));
+        assert!(recv(&mut r1).is_none());
+
+        // removed even if there are no events at all
+        drop(r1);
+        dispatch(&mut subs, &[]);
+        assert_eq!(subs.len(), 1);
+
+        // a client which does not read its events is dropped,
+        // the capacity of a channel is its buffer size plus one
+        let event = ApiEvent::NewBlock {
+            height: 2,
+            txns: vec![],
+        };
+        for _ in 0..SUBSCRIBER_BUF_SIZE + 2 {
+            dispatch(&mut subs, &[event.clone()]);
+        }
+        assert!(subs.is_empty());
+        assert!(recv(&mut r0).is_some());
+    }
+
+    #[test]
+    fn subscription_limit() {
+        let params = || SubscribeParams {
+            block: Some(true),
+            ..Default::default()
+        };
+
+        let mut subs = vec![];
+        let mut receivers = vec![];
+        for _ in 0..MAX_SUBSCRIBERS {
+            let (s, r) = gen_subscriber(params());
+            pnk!(add_subscriber(&mut subs, s));
+            receivers.push(r);
+        }
+
+        let (s, _r) = gen_subscriber(params());
+        assert!(add_subscriber(&mut subs, s).is_err());
+
+        // disconnected clients make room for new ones
+        receivers.pop();
+        let (s, _r) = gen_subscriber(params());
+        pnk!(add_subscriber(&mut subs, s));
+        assert_eq!(subs.len(), MAX_SUBSCRIBERS);
+    }
+}
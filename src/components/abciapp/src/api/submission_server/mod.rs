@@ -6,14 +6,21 @@ pub mod submission_api;
 
 use {
     ledger::{
-        data_model::{BlockEffect, Transaction, TxnEffect, TxnSID, TxnTempSID, TxoSID},
+        data_model::{
+            BlockEffect, Transaction, TxOutput, TxnEffect, TxnSID, TxnTempSID, TxoSID,
+        },
         store::LedgerState,
     },
     parking_lot::RwLock,
     rand_core::{CryptoRng, RngCore},
     ruc::*,
     serde::{Deserialize, Serialize},
-    std::{collections::HashMap, fmt, sync::Arc},
+    serde_json::Value,
+    std::{
+        collections::{BTreeSet, HashMap},
+        fmt,
+        sync::Arc,
+    },
 };
 
 /// Query handle for user
@@ -42,6 +49,34 @@ pub enum TxnStatus {
     Pending,
 }
 
+/// The result of a simulated(dry-run) transaction
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TxnSimulation {
+    /// the reason of rejection, `None` if the transaction would be accepted
+    pub rejected: Option<String>,
+    /// FRA paid as fee
+    pub fee: u64,
+    /// fee per 1000 bytes
+    pub priority: u64,
+    /// txos that would be spent
+    pub inputs: Vec<TxoSID>,
+    /// would-be outputs, `None` for the internally spent ones
+    pub outputs: Vec<Option<TxOutput>>,
+    /// changes of the staking state
+    pub staking_changes: Vec<StakingChange>,
+}
+
+/// A changed field of the staking state
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StakingChange {
+    /// path of the changed field, see `Staking::changed_entries`, separated by '/'
+    pub path: String,
+    /// `None` if the field is newly created
+    pub before: Option<Value>,
+    /// `None` if the field is removed
+    pub after: Option<Value>,
+}
+
 /// use to create submissionServer
 pub enum CommitMode {
     /// all block
@@ -158,6 +193,22 @@ where
     #[allow(missing_docs)]
     pub fn end_commit(&mut self) {}
 
+    /// The minimum fee of the transactions in current block,
+    /// the staking simulator of the block is used if there is one,
+    /// since the chain parameters adopted in it are not committed yet.
+    pub fn get_tx_fee_min(&self) -> u64 {
+        self.block
+            .as_ref()
+            .map(|b| b.staking_simulator.chain_params().tx_fee_min)
+            .unwrap_or_else(|| {
+                self.committed_state
+                    .read()
+                    .get_staking()
+                    .chain_params()
+                    .tx_fee_min
+            })
+    }
+
     /// Get the `block_ctx` in `ledgerState`
     pub fn begin_block(&mut self) {
         self.block = Some(pnk!(self.committed_state.write().start_block()));
@@ -235,6 +286,86 @@ where
         }
     }
 
+    /// Run a transaction without committing anything,
+    /// against the pending block if there is one, or else a block started
+    /// from the committed state, which is discarded after the simulation.
+    pub fn simulate_transaction(&self, txn: Transaction) -> TxnSimulation {
+        let mut res = TxnSimulation {
+            fee: txn.get_fee(),
+            priority: serde_json::to_vec(&txn)
+                .map(|bytes| txn.get_priority(bytes.len()))
+                .unwrap_or_default(),
+            ..Default::default()
+        };
+
+        let fee_min = self.get_tx_fee_min();
+        if txn.is_coinbase_tx() {
+            res.rejected = Some("Coinbase transaction can not be submitted".to_owned());
+            return res;
+        } else if !txn.check_fee_with_min(fee_min) {
+            res.rejected = Some(format!(
+                "Insufficient fee: {} paid, at least {}",
+                res.fee, fee_min
+            ));
+            return res;
+        }
+
+        let txn_effect = match TxnEffect::compute_effect(txn) {
+            Ok(txn_effect) => txn_effect,
+            Err(e) => {
+                res.rejected = Some(e.to_string());
+                return res;
+            }
+        };
+
+        res.inputs = txn_effect.input_txos.keys().copied().collect();
+        res.inputs.sort_unstable();
+        res.outputs = txn_effect.txos.clone();
+
+        let mut ledger = self.committed_state.write();
+
+        // the block context of the ledger is held by the pending block, if any
+        let mut block = if let Some(b) = self.block.as_ref() {
+            b.clone()
+        } else {
+            match ledger.start_block().c(d!()) {
+                Ok(b) => b,
+                Err(e) => {
+                    res.rejected = Some(e.to_string());
+                    return res;
+                }
+            }
+        };
+        let staking = block.staking_simulator.clone();
+
+        let applied = ledger
+            .apply_transaction(&mut block, txn_effect)
+            .c(d!("Failed to apply transaction"))
+            .and_then(|_| staking.changed_entries(&block.staking_simulator).c(d!()));
+
+        if self.block.is_none() {
+            ledger.discard_block(block);
+        }
+
+        match applied {
+            Ok(entries) => {
+                entries.into_iter().for_each(|(path, before, after)| {
+                    diff_json(
+                        path,
+                        before.as_ref(),
+                        after.as_ref(),
+                        &mut res.staking_changes,
+                    );
+                });
+            }
+            Err(e) => {
+                res.rejected = Some(e.to_string());
+            }
+        }
+
+        res
+    }
+
     /// Handle the whole process when there's a new transaction
     pub fn handle_transaction(&mut self, txn: Transaction) -> Result<TxnHandle> {
         let txn_handle = TxnHandle::new(&txn);
@@ -248,6 +379,39 @@ where
     }
 }
 
+// collect the changed leaves of two JSON values
+fn diff_json(
+    path: String,
+    before: Option<&Value>,
+    after: Option<&Value>,
+    changes: &mut Vec<StakingChange>,
+) {
+    match (before, after) {
+        (Some(Value::Object(b)), Some(Value::Object(a))) => {
+            b.keys()
+                .chain(a.keys())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .for_each(|k| {
+                    diff_json(format!("{}/{}", path, k), b.get(k), a.get(k), changes)
+                });
+        }
+        (Some(Value::Array(b)), Some(Value::Array(a))) if b.len() == a.len() => {
+            b.iter().zip(a.iter()).enumerate().for_each(|(i, (b, a))| {
+                diff_json(format!("{}/{}", path, i), Some(b), Some(a), changes)
+            });
+        }
+        (b, a) if b != a => {
+            changes.push(StakingChange {
+                path,
+                before: b.cloned(),
+                after: a.cloned(),
+            });
+        }
+        _ => {}
+    }
+}
+
 /// Convert incoming tx data to the proper Transaction format
 #[inline(always)]
 pub fn convert_tx(tx: &[u8]) -> Result<Transaction> {
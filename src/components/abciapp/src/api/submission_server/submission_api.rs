@@ -3,7 +3,7 @@
 //!
 
 use {
-    super::{SubmissionServer, TxnForward, TxnHandle},
+    super::{SubmissionServer, TxnForward, TxnHandle, TxnSimulation},
     actix_cors::Cors,
     actix_web::{error, middleware, web, App, HttpServer},
     finutils::api::NetworkRoute,
@@ -53,6 +53,20 @@ where
         })
 }
 
+/// Run a transaction against the current state without submitting it,
+/// the rejection reason will be returned if it is invalid.
+pub async fn simulate_transaction<RNG, TF>(
+    data: web::Data<Arc<RwLock<SubmissionServer<RNG, TF>>>>,
+    body: web::Json<Transaction>,
+) -> StdResult<web::Json<TxnSimulation>, actix_web::error::Error>
+where
+    RNG: RngCore + CryptoRng,
+    TF: TxnForward + Sync + Send,
+{
+    let tx = body.into_inner();
+    Ok(web::Json(data.read().simulate_transaction(tx)))
+}
+
 /// Queries the status of a transaction by its handle. Returns either a not committed message or a
 /// serialized TxnStatus.
 pub async fn txn_status<RNG, TF>(
@@ -85,6 +99,7 @@ pub struct SubmissionApi;
 #[allow(missing_docs)]
 pub enum SubmissionRoutes {
     SubmitTransaction,
+    SimulateTransaction,
     TxnStatus,
     Ping,
     Version,
@@ -94,6 +109,7 @@ impl NetworkRoute for SubmissionRoutes {
     fn route(&self) -> String {
         let endpoint = match *self {
             SubmissionRoutes::SubmitTransaction => "submit_transaction",
+            SubmissionRoutes::SimulateTransaction => "simulate_transaction",
             SubmissionRoutes::TxnStatus => "txn_status",
             SubmissionRoutes::Ping => "ping",
             SubmissionRoutes::Version => "version",
@@ -124,6 +140,10 @@ impl SubmissionApi {
                     &SubmissionRoutes::SubmitTransaction.route(),
                     web::post().to(submit_transaction::<RNG, TF>),
                 )
+                .route(
+                    &SubmissionRoutes::SimulateTransaction.route(),
+                    web::post().to(simulate_transaction::<RNG, TF>),
+                )
                 .route(&SubmissionRoutes::Ping.route(), web::get().to(ping))
                 .route(&SubmissionRoutes::Version.route(), web::get().to(version))
                 .route(
@@ -20,6 +20,11 @@ fn main() {
         env!("VERGEN_BUILD_DATE")
     ));
 
+    if abci::is_replay_mode() {
+        pnk!(abci::replay());
+        return;
+    }
+
     let thread = thread::spawn(|| pnk!(abci::run()));
 
     let (tx, rx) = channel();
@@ -121,6 +121,26 @@ fn node_command() -> Result<()> {
     .c(d!())
 }
 
+fn replay_command() -> Result<()> {
+    let replay_from = CFG.replay_from.as_deref().c(d!("missing `--from`"))?;
+    let status = Command::new(format!("/tmp/abcid_{}", *SUFFIX))
+        .arg("--ledger-dir")
+        .arg(&CFG.ledger_dir)
+        .arg("--replay-from")
+        .arg(replay_from)
+        .stdin(Stdio::null())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .status()
+        .c(d!())?;
+
+    alt!(
+        status.success(),
+        Ok(()),
+        Err(eg!(format!("abcid exited with {}", status)))
+    )
+}
+
 fn init_command() -> Result<()> {
     Command::new(format!("/tmp/tendermint_{}", *SUFFIX))
         .arg("init")
@@ -242,8 +262,9 @@ fn main() {
     let res = match CFG.command.as_str() {
         "init" => unpack().c(d!()).and_then(|_| init_command().c(d!())),
         "node" => unpack().c(d!()).and_then(|_| node_command().c(d!())),
+        "replay" => unpack().c(d!()).and_then(|_| replay_command().c(d!())),
         "pack" => pack().c(d!()),
-        _ => Err(eg!("The available options are 'node'/'init'")),
+        _ => Err(eg!("The available options are 'node'/'init'/'replay'")),
     };
 
     pnk!(res);
@@ -414,6 +435,7 @@ mod config {
         pub snapshot_rollback: bool,
         pub snapshot_rollback_to: Option<String>,
         pub snapshot_rollback_to_exact: Option<String>,
+        pub replay_from: Option<String>,
     }
 
     fn get_config() -> Result<Config> {
@@ -456,6 +478,11 @@ mod config {
                         "-b, --base-dir=[DIR] 'The root directory for tendermint config, aka $TENDERMINT_HOME'",
                     );
 
+            let replay = SubCommand::with_name("replay")
+                    .about("Rebuild a fresh ledger from an exported stream of blocks, verify the app hash of every height.")
+                    .arg_from_usage("-f, --from=<FILE> 'JSON lines of blocks, one block per line'")
+                    .arg_from_usage("-d, --ledger-dir=<Path> 'an empty directory for the new ledger'");
+
             let pack = SubCommand::with_name("pack");
 
             App::new("findorad")
@@ -464,6 +491,7 @@ mod config {
                 .about("An ABCI node implementation of FindoraNetwork.")
                 .subcommand(node)
                 .subcommand(init)
+                .subcommand(replay)
                 .subcommand(pack)
                 .arg(Arg::with_name("_a").long("ignored").hidden(true))
                 .arg(Arg::with_name("_b").long("nocapture").hidden(true))
@@ -586,6 +614,7 @@ mod config {
             snapshot_rollback_to_exact: m
                 .value_of("snapshot-rollback-to-exact")
                 .map(|v| v.to_owned()),
+            replay_from: m.value_of("from").map(|v| v.to_owned()),
         };
 
         Ok(res)
@@ -3,10 +3,16 @@
 //!
 
 use {
-    ledger::staking::{
-        self, StakerMemo, TendermintAddr, MAX_POWER_PERCENT_PER_VALIDATOR,
+    globutils::HashOf,
+    ledger::{
+        data_model::{BlockSID, Operation, StateCommitmentData, TxnSID, TxoSID, Utxo},
+        staking::{
+            self, BlockHeight, CommissionLimit, CommissionSchedule, Jail, StakerMemo,
+            TendermintAddr,
+        },
     },
     serde::{Deserialize, Serialize},
+    zei::xfr::structs::OwnerMemo,
 };
 
 /// A list of basic validator information of current height
@@ -20,9 +26,13 @@ pub struct ValidatorList {
 
 impl ValidatorList {
     #[allow(missing_docs)]
-    pub fn new(cur_height: u64, validators: Vec<Validator>) -> Self {
+    pub fn new(
+        cur_height: u64,
+        threshold: [u128; 2],
+        validators: Vec<Validator>,
+    ) -> Self {
         ValidatorList {
-            threshold: MAX_POWER_PERCENT_PER_VALIDATOR,
+            threshold,
             validator_cnt: validators.len() as u64,
             cur_height,
             validators,
@@ -95,6 +105,15 @@ pub struct ValidatorDetail {
     pub expected_annualization: [u128; 2],
     /// total number of its delegators
     pub delegator_cnt: u64,
+    /// `Some(_)` if it is in jail
+    #[serde(default)]
+    pub jail: Option<Jail>,
+    /// limits on the commission rate
+    #[serde(default)]
+    pub commission_limit: CommissionLimit,
+    /// a pending increase of the commission rate
+    #[serde(default)]
+    pub commission_schedule: Option<CommissionSchedule>,
 }
 
 #[allow(missing_docs)]
@@ -152,6 +171,9 @@ pub struct DelegationInfo {
     pub delegation_rwd_cnt: u64,
     /// how many times of proposing this validator has performed
     pub proposer_rwd_cnt: u64,
+    /// whether the rewards are folded into the principal periodically
+    #[serde(default)]
+    pub auto_compound: bool,
 }
 
 impl DelegationInfo {
@@ -185,6 +207,42 @@ impl DelegationInfo {
     }
 }
 
+/// The max number of blocks that can be returned by one query.
+pub const MAX_BLOCKS_PER_QUERY: u64 = 100;
+
+/// A transaction of a block, with decoded operations
+#[allow(missing_docs)]
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BlockTxn {
+    pub sid: TxnSID,
+    /// tendermint hash
+    pub hash: String,
+    /// FRA paid as fee
+    pub fee: u64,
+    pub operations: Vec<Operation>,
+    /// the txos created by this txn(spent or not) along with their owner memos,
+    /// internally spent ones are not included
+    #[serde(default)]
+    pub outputs: Vec<(TxoSID, Utxo, Option<OwnerMemo>)>,
+    /// the txos spent by this txn
+    #[serde(default)]
+    pub inputs: Vec<TxoSID>,
+}
+
+/// Details of a non-empty block
+#[allow(missing_docs)]
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BlockDetail {
+    /// tendermint height
+    pub height: BlockHeight,
+    pub block_sid: BlockSID,
+    pub txns: Vec<BlockTxn>,
+    /// sum of the fees of all txns
+    pub fee_total: u64,
+    /// state commitment after this block has been applied
+    pub state_commitment: Option<HashOf<Option<StateCommitmentData>>>,
+}
+
 #[allow(missing_docs)]
 pub trait NetworkRoute {
     fn route(&self) -> String;
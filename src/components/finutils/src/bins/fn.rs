@@ -15,19 +15,45 @@
 //! - claim
 //!     - "--amount=[Amout <Optional, default to 'all'>]"
 //! - unstake
+//! - redelegate
+//!     - "--from=[Validator Address]" "--to=[Validator Address]"
+//!     - "--amount=[Amout <Optional, default to 'all'>]"
+//! - unjail, release your validator from jail
+//! - auto-compound, fold the delegation rewards into the principal every day
+//!     - "--disable"
 //! - show, query real-time state of your staking
+//! - wallet, manipulate a findora wallet
+//!     - "--sync" "--rescan <Optional>" "--watch=[PubKey <Optional, multiple>]"
+//!         - scan new blocks into the local index, and show the balances
+//! - state-commitment, dump the components of the state commitment at a height
+//!     - "--height=[Height]" "--compare-to=[Height <Optional>]"
 //! - setup
 //!     - "--serv-addr=[URL/IP]"
 //!     - "--owner-mnemonic-path=[File Path]"
 //!         - the `id` of your validator will be drived from this
+//! - transfer/transfer-batch, the inputs can be picked in different ways
+//!     - "--coin-selection=[largest-first|exact|random|consolidate]"
+//!     - "--dust-threshold=[Amount]"
+//!     - "--confidential-inputs-only" or "--non-confidential-inputs-only"
+//! - utxo consolidate, merge the small utxos of an asset into one
+//!     - "--asset=[Asset Code]" "--dust-threshold=[Amount]" "--max-inputs=[Number]"
+//! - tx, sign transactions offline or collect signatures of a multisig transfer
+//!     - sign "--file=[File Path]" "--seckey=[File Path]"
+//!     - combine "--file=[File Path]" "--file=[File Path]" "--out=[File Path]"
+//!     - submit/broadcast "--file=[File Path]"
+//!     - transactions to be signed offline are generated by `transfer`,
+//!       `delegate`, `stake` and `claim` with "--unsigned-out=[File Path]"
 //! ```
 //!
 
 #![deny(warnings)]
 
 use {
-    clap::{crate_authors, load_yaml, App},
-    finutils::common,
+    clap::{crate_authors, load_yaml, App, ArgMatches},
+    finutils::{
+        common,
+        txn_builder::{CoinSelection, CoinSelector},
+    },
     globutils::wallet,
     ledger::{
         data_model::{AssetTypeCode, FRA_DECIMALS},
@@ -35,6 +61,7 @@ use {
     },
     ruc::*,
     std::{fmt, fs},
+    zei::xfr::sig::XfrPublicKey,
 };
 
 fn main() {
@@ -76,6 +103,19 @@ fn run() -> Result<()> {
                 None
             };
             common::show_account(seckey.as_deref(), asset).c(d!())?;
+        } else if m.is_present("sync") {
+            let seckey = match m.value_of("seckey") {
+                Some(path) => {
+                    Some(fs::read_to_string(path).c(d!("Failed to read seckey file"))?)
+                }
+                None => None,
+            };
+            let watch = m
+                .values_of("watch")
+                .map(|v| v.collect::<Vec<_>>())
+                .unwrap_or_default();
+            common::sync_wallet(seckey.as_deref(), &watch, m.is_present("rescan"))
+                .c(d!())?;
         } else {
             println!("{}", m.usage());
         }
@@ -91,12 +131,14 @@ fn run() -> Result<()> {
         let show_info = m.is_present("info");
 
         if amount.is_some() && validator.is_some() {
-            common::delegate(
-                seckey.as_deref(),
-                amount.unwrap().parse::<u64>().c(d!())?,
-                validator.unwrap(),
-            )
-            .c(d!())?;
+            let amount = amount.unwrap().parse::<u64>().c(d!())?;
+            if let Some((pk, out)) = get_unsigned_out(m).c(d!())? {
+                common::delegate_unsigned(&pk, amount, validator.unwrap(), out)
+                    .c(d!())?;
+            } else {
+                common::delegate(seckey.as_deref(), amount, validator.unwrap())
+                    .c(d!())?;
+            }
         } else if show_info {
             common::show_delegations(seckey.as_deref()).c(d!())?;
         } else {
@@ -123,6 +165,37 @@ fn run() -> Result<()> {
             None
         };
         common::undelegate(seckey.as_deref(), param).c(d!())?;
+    } else if let Some(m) = matches.subcommand_matches("redelegate") {
+        let seckey = match m.value_of("seckey") {
+            Some(path) => {
+                Some(fs::read_to_string(path).c(d!("Failed to read seckey file"))?)
+            }
+            None => None,
+        };
+        let amount = m
+            .value_of("amount")
+            .map(|am| am.parse::<u64>().c(d!()))
+            .transpose()?;
+        // both are required by clap
+        let from = m.value_of("from").unwrap();
+        let to = m.value_of("to").unwrap();
+        common::redelegate(seckey.as_deref(), amount, from, to).c(d!())?;
+    } else if let Some(m) = matches.subcommand_matches("unjail") {
+        let seckey = match m.value_of("seckey") {
+            Some(path) => {
+                Some(fs::read_to_string(path).c(d!("Failed to read seckey file"))?)
+            }
+            None => None,
+        };
+        common::unjail(seckey.as_deref()).c(d!())?;
+    } else if let Some(m) = matches.subcommand_matches("auto-compound") {
+        let seckey = match m.value_of("seckey") {
+            Some(path) => {
+                Some(fs::read_to_string(path).c(d!("Failed to read seckey file"))?)
+            }
+            None => None,
+        };
+        common::auto_compound(seckey.as_deref(), !m.is_present("disable")).c(d!())?;
     } else if let Some(m) = matches.subcommand_matches("asset") {
         if m.is_present("create") {
             let seckey = match m.value_of("seckey") {
@@ -225,13 +298,15 @@ fn run() -> Result<()> {
         };
 
         let cr = m.value_of("commission-rate");
-        if vm.is_none() && cr.is_none() {
+        let max_cr = m.value_of("max-commission-rate");
+        let max_cr_change = m.value_of("max-commission-daily-change");
+        if vm.is_none() && cr.is_none() && max_cr.is_none() && max_cr_change.is_none() {
             println!("{}", m.usage());
             println!(
-                "Tips: to update the information of your node, please specify commission-rate or memo"
+                "Tips: to update the information of your node, please specify commission-rate, memo or commission limits"
             );
         } else {
-            common::staker_update(cr, vm).c(d!())?;
+            common::staker_update(cr, vm, max_cr, max_cr_change).c(d!())?;
         }
     } else if let Some(m) = matches.subcommand_matches("stake") {
         let am = m.value_of("amount");
@@ -257,6 +332,9 @@ fn run() -> Result<()> {
                 println!(
                     "Tips: if you want to raise the power of your node, please use `fn stake --append [OPTIONS]`"
                 );
+            } else if let Some((pk, out)) = get_unsigned_out(m).c(d!())? {
+                common::stake_unsigned(&pk, am.unwrap(), cr.unwrap(), vm, force, out)
+                    .c(d!())?;
             } else {
                 common::stake(am.unwrap(), cr.unwrap(), vm, force).c(d!())?;
             }
@@ -279,10 +357,21 @@ fn run() -> Result<()> {
             }
             None => None,
         };
-        common::claim(am, seckey.as_deref()).c(d!())?;
+        if let Some((pk, out)) = get_unsigned_out(m).c(d!())? {
+            common::claim_unsigned(&pk, am, out).c(d!())?;
+        } else {
+            common::claim(am, seckey.as_deref()).c(d!())?;
+        }
     } else if let Some(m) = matches.subcommand_matches("show") {
         let basic = m.is_present("basic");
         common::show(basic).c(d!())?;
+    } else if let Some(m) = matches.subcommand_matches("state-commitment") {
+        let height = m.value_of("height");
+        if let Some(h) = height {
+            common::show_state_commitment(h, m.value_of("compare-to")).c(d!())?;
+        } else {
+            println!("{}", m.usage());
+        }
     } else if let Some(m) = matches.subcommand_matches("setup") {
         let sa = m.value_of("serv-addr");
         let om = m.value_of("owner-mnemonic-path");
@@ -319,15 +408,40 @@ fn run() -> Result<()> {
             } else {
                 None
             };
-            common::transfer_asset(
-                f.as_deref(),
-                t,
-                token_code,
-                am.unwrap(),
-                m.is_present("confidential-amount"),
-                m.is_present("confidential-type"),
-            )
-            .c(d!())?;
+            if let Some((pk, out)) = get_unsigned_out(m).c(d!())? {
+                common::transfer_asset_unsigned(
+                    &pk,
+                    t,
+                    token_code,
+                    am.unwrap(),
+                    m.is_present("confidential-amount"),
+                    m.is_present("confidential-type"),
+                    out,
+                )
+                .c(d!())?;
+            } else if let Some(out) = m.value_of("multisig-out") {
+                common::transfer_asset_multisig(
+                    f.as_deref(),
+                    t,
+                    token_code,
+                    am.unwrap(),
+                    m.is_present("confidential-amount"),
+                    m.is_present("confidential-type"),
+                    out,
+                )
+                .c(d!())?;
+            } else {
+                common::transfer_asset(
+                    f.as_deref(),
+                    t,
+                    token_code,
+                    am.unwrap(),
+                    m.is_present("confidential-amount"),
+                    m.is_present("confidential-type"),
+                    &get_coin_selector(m).c(d!())?,
+                )
+                .c(d!())?;
+            }
         }
     } else if let Some(m) = matches.subcommand_matches("transfer-batch") {
         let f = match m.value_of("from-seckey") {
@@ -370,9 +484,57 @@ fn run() -> Result<()> {
                 am.unwrap(),
                 m.is_present("confidential-amount"),
                 m.is_present("confidential-type"),
+                &get_coin_selector(m).c(d!())?,
             )
             .c(d!())?;
         }
+    } else if let Some(m) = matches.subcommand_matches("utxo") {
+        if let Some(m) = m.subcommand_matches("consolidate") {
+            let seckey = match m.value_of("seckey") {
+                Some(path) => {
+                    Some(fs::read_to_string(path).c(d!("Failed to read seckey file"))?)
+                }
+                None => None,
+            };
+            let token_code = match m.value_of("asset") {
+                Some(code) if "FRA" != code.to_uppercase() => {
+                    Some(AssetTypeCode::new_from_base64(code).c(d!())?)
+                }
+                _ => None,
+            };
+            common::utxo_consolidate(
+                seckey.as_deref(),
+                token_code,
+                m.value_of("dust-threshold").c(d!())?,
+                m.value_of("max-inputs").c(d!())?,
+            )
+            .c(d!())?;
+        } else {
+            println!("{}", m.usage());
+        }
+    } else if let Some(m) = matches.subcommand_matches("tx") {
+        if let Some(m) = m.subcommand_matches("sign") {
+            let sk = match m.value_of("seckey") {
+                Some(path) => {
+                    Some(fs::read_to_string(path).c(d!("Failed to read seckey file"))?)
+                }
+                None => None,
+            };
+            let file = m.value_of("file").c(d!())?;
+            common::tx_sign(sk.as_deref(), file, m.value_of("out")).c(d!())?;
+        } else if let Some(m) = m.subcommand_matches("combine") {
+            let files = m.values_of("file").c(d!())?.collect::<Vec<_>>();
+            let out = m.value_of("out").c(d!())?;
+            common::tx_combine(&files, out).c(d!())?;
+        } else if let Some(m) = m
+            .subcommand_matches("submit")
+            .or_else(|| m.subcommand_matches("broadcast"))
+        {
+            let file = m.value_of("file").c(d!())?;
+            common::tx_submit(file).c(d!())?;
+        } else {
+            println!("{}", m.usage());
+        }
     } else {
         println!("{}", matches.usage());
     }
@@ -380,6 +542,39 @@ fn run() -> Result<()> {
     Ok(())
 }
 
+// the public key of the signer and the output file of `--unsigned-out`
+fn get_unsigned_out<'a>(m: &'a ArgMatches) -> Result<Option<(XfrPublicKey, &'a str)>> {
+    if let Some(out) = m.value_of("unsigned-out") {
+        m.value_of("pubkey")
+            .c(d!("'--pubkey' is required by '--unsigned-out'"))
+            .and_then(|pk| wallet::public_key_from_base64(pk).c(d!()))
+            .map(|pk| Some((pk, out)))
+    } else {
+        Ok(None)
+    }
+}
+
+// `--coin-selection`, `--dust-threshold` and the kind of inputs
+fn get_coin_selector(m: &ArgMatches) -> Result<CoinSelector> {
+    let mut selector = CoinSelector::new(
+        m.value_of("coin-selection")
+            .map(|s| s.parse::<CoinSelection>().c(d!()))
+            .transpose()?
+            .unwrap_or_default(),
+    );
+    if let Some(n) = m.value_of("dust-threshold") {
+        selector.dust_threshold = n
+            .parse::<u64>()
+            .c(d!("'dust-threshold' must be an integer"))?;
+    }
+    if m.is_present("confidential-inputs-only") {
+        selector.confidential = Some(true);
+    } else if m.is_present("non-confidential-inputs-only") {
+        selector.confidential = Some(false);
+    }
+    Ok(selector)
+}
+
 fn tip_fail(e: impl fmt::Display) {
     eprintln!("\n\x1b[31;01mFAIL !!!\x1b[00m");
     eprintln!(
@@ -23,6 +23,7 @@ use {
             FRA_PRE_ISSUE_AMOUNT,
         },
         store::utils::fra_gen_initial_tx,
+        upgrade::{self, NetworkProfile},
     },
     ruc::*,
     serde::Serialize,
@@ -59,6 +60,9 @@ fn main() {
 }
 
 fn run() -> Result<()> {
+    // the validator list depends on the network profile
+    upgrade::check_profile().c(d!())?;
+
     let subcmd_init = SubCommand::with_name("init")
         .arg_from_usage("--mainnet")
         .arg_from_usage("-i, --interval=[Interval] 'block interval'");
@@ -457,18 +461,22 @@ fn gen_valiator_list() -> BTreeMap<Name, Validator> {
     const NUM: usize = 20;
 
     // locale env
-    #[cfg(feature = "debug_env")]
-    const TD_ADDR_LIST: [&str; NUM] = include!("td_addr_list.const.debug_env");
+    const TD_ADDR_LIST_LOCAL: [&str; NUM] = include!("td_addr_list.const.debug_env");
 
     // online env
-    #[cfg(not(feature = "debug_env"))]
     const TD_ADDR_LIST: [&str; NUM] = include!("td_addr_list.const");
 
+    let td_addr_list = alt!(
+        NetworkProfile::Local == *upgrade::PROFILE,
+        TD_ADDR_LIST_LOCAL,
+        TD_ADDR_LIST
+    );
+
     const MNEMONIC_LIST: [&str; NUM] = include!("mnemonic_list.const");
 
     (0..NUM)
         .map(|i| {
-            let td_addr = TD_ADDR_LIST[i].to_owned();
+            let td_addr = td_addr_list[i].to_owned();
             let keypair = pnk!(wallet::restore_keypair_from_mnemonic_default(
                 MNEMONIC_LIST[i]
             ));
@@ -0,0 +1,465 @@
+//!
+//! # Wallet Indexer
+//!
+//! Follow the committed blocks incrementally and keep the utxos of some
+//! findora accounts on disk, along with the balance of every asset,
+//! so balances and coin selection need not to pull all the utxos each time.
+//!
+//! Confidential records are opened locally with the keypair of the owner,
+//! an account which has only a public key is watch-only,
+//! and only the non-confidential records of it can be indexed.
+//!
+//! A new index is bootstrapped from the `owned_utxos` of the query server,
+//! and then the blocks after the bootstrap height are applied to it;
+//! applying a block is idempotent, so the two steps can overlap safely.
+//!
+//! The implicit syncs before building transactions or getting balances
+//! rebuild an index in the same way if it is far behind,
+//! the blocks are only scanned all by the explicit `fn wallet --sync`.
+//!
+//! Every server address has its own indexes, and an index which is ahead of
+//! the server, e.g. the chain has been restarted, is rebuilt.
+//!
+
+use {
+    super::{get_serv_addr, utils, CFG_PATH},
+    crate::api::{BlockTxn, MAX_BLOCKS_PER_QUERY},
+    globutils::wallet,
+    ledger::{
+        data_model::{AssetTypeCode, TxoSID, Utxo},
+        staking::BlockHeight,
+    },
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::{collections::BTreeMap, fs},
+    zei::xfr::{
+        asset_record::open_blind_asset_record,
+        sig::{XfrKeyPair, XfrPublicKey},
+        structs::{OwnerMemo, XfrAmount, XfrAssetType},
+    },
+};
+
+/// An index which is behind more than this many blocks is rebuilt
+/// instead of being synced by `sync_one`, the blocks can be applied
+/// by `fn wallet --sync` explicitly.
+pub const MAX_IMPLICIT_SYNC_BLOCKS: u64 = 10 * MAX_BLOCKS_PER_QUERY;
+
+/// A findora account to be indexed.
+#[derive(Clone, Copy)]
+pub enum Account<'a> {
+    /// all the records can be opened
+    KeyPair(&'a XfrKeyPair),
+    /// only non-confidential records can be opened
+    WatchOnly(XfrPublicKey),
+}
+
+impl<'a> Account<'a> {
+    #[inline(always)]
+    fn get_pk(&self) -> XfrPublicKey {
+        match self {
+            Account::KeyPair(kp) => kp.get_pk(),
+            Account::WatchOnly(pk) => *pk,
+        }
+    }
+
+    #[inline(always)]
+    fn is_watch_only(&self) -> bool {
+        matches!(self, Account::WatchOnly(_))
+    }
+
+    // return the asset type and amount of a record,
+    // `None` if it can not be opened by this account
+    fn open(
+        &self,
+        utxo: &Utxo,
+        owner_memo: &Option<OwnerMemo>,
+    ) -> Option<(AssetTypeCode, u64)> {
+        match self {
+            Account::KeyPair(kp) => {
+                open_blind_asset_record(&utxo.0.record, owner_memo, kp)
+                    .ok()
+                    .map(|oar| {
+                        (
+                            AssetTypeCode {
+                                val: oar.asset_type,
+                            },
+                            oar.amount,
+                        )
+                    })
+            }
+            Account::WatchOnly(_) => {
+                if let (
+                    XfrAssetType::NonConfidential(ty),
+                    XfrAmount::NonConfidential(am),
+                ) = (&utxo.0.record.asset_type, &utxo.0.record.amount)
+                {
+                    Some((AssetTypeCode { val: *ty }, *am))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+/// An unspent record of an indexed account.
+#[allow(missing_docs)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IndexedUtxo {
+    pub utxo: Utxo,
+    pub owner_memo: Option<OwnerMemo>,
+    /// the opened asset type
+    pub asset_type: AssetTypeCode,
+    /// the opened amount
+    pub amount: u64,
+}
+
+/// The local index of a findora account.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AccountIndex {
+    /// all blocks up to this height have been applied,
+    /// `0` means the index has not been bootstrapped
+    pub height: BlockHeight,
+    /// whether confidential records are skipped
+    pub watch_only: bool,
+    /// `TxoSID` => unspent record,
+    /// the raw `u64` is used as the key to keep the JSON format simple
+    pub utxos: BTreeMap<u64, IndexedUtxo>,
+    /// base64-formated asset code => balance
+    pub balances: BTreeMap<String, u64>,
+}
+
+impl AccountIndex {
+    /// The balance of an asset, `0` if not found.
+    #[inline(always)]
+    pub fn get_balance(&self, code: AssetTypeCode) -> u64 {
+        self.balances.get(&code.to_base64()).copied().unwrap_or(0)
+    }
+
+    fn insert(
+        &mut self,
+        account: &Account,
+        sid: TxoSID,
+        utxo: Utxo,
+        owner_memo: Option<OwnerMemo>,
+    ) {
+        if self.utxos.contains_key(&sid.0) {
+            return;
+        }
+        if let Some((asset_type, amount)) = account.open(&utxo, &owner_memo) {
+            *self.balances.entry(asset_type.to_base64()).or_insert(0) += amount;
+            self.utxos.insert(
+                sid.0,
+                IndexedUtxo {
+                    utxo,
+                    owner_memo,
+                    asset_type,
+                    amount,
+                },
+            );
+        }
+    }
+
+    fn remove(&mut self, sid: TxoSID) {
+        if let Some(iu) = self.utxos.remove(&sid.0) {
+            let k = iu.asset_type.to_base64();
+            if let Some(b) = self.balances.get_mut(&k) {
+                *b = b.saturating_sub(iu.amount);
+                if 0 == *b {
+                    self.balances.remove(&k);
+                }
+            }
+        }
+    }
+
+    fn apply_txn(&mut self, account: &Account, txn: &BlockTxn) {
+        let pk = account.get_pk();
+        txn.outputs
+            .iter()
+            .filter(|(_, utxo, _)| utxo.0.record.public_key == pk)
+            .for_each(|(sid, utxo, memo)| {
+                self.insert(account, *sid, utxo.clone(), memo.clone())
+            });
+        txn.inputs.iter().for_each(|sid| self.remove(*sid));
+    }
+}
+
+/// Local indexes of some findora accounts, which are synced together.
+pub struct Indexer<'a> {
+    entries: Vec<(Account<'a>, AccountIndex)>,
+}
+
+impl<'a> Indexer<'a> {
+    /// Load the local indexes of `accounts`,
+    /// an index will be rebuilt if its kind(watch-only or not) has changed.
+    pub fn new(accounts: Vec<Account<'a>>) -> Result<Self> {
+        let entries = accounts
+            .into_iter()
+            .map(|account| {
+                load(&account.get_pk()).c(d!()).map(|idx| {
+                    let idx = idx
+                        .filter(|idx| idx.watch_only == account.is_watch_only())
+                        .unwrap_or_else(|| AccountIndex {
+                            watch_only: account.is_watch_only(),
+                            ..Default::default()
+                        });
+                    (account, idx)
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Indexer { entries })
+    }
+
+    /// Drop all the local data, the indexes will be rebuilt by the next `sync`.
+    pub fn reset(&mut self) {
+        self.entries.iter_mut().for_each(|(account, idx)| {
+            *idx = AccountIndex {
+                watch_only: account.is_watch_only(),
+                ..Default::default()
+            };
+        });
+    }
+
+    /// Apply all the blocks committed by the query server,
+    /// the indexes are saved after every batch of blocks.
+    #[inline(always)]
+    pub fn sync(&mut self) -> Result<()> {
+        self.sync_x(None).c(d!())
+    }
+
+    /// Same as `sync`, but an index which is behind more than `max_blocks`
+    /// is rebuilt from the `owned_utxos` of the query server,
+    /// instead of applying all the blocks after it.
+    #[inline(always)]
+    pub fn sync_capped(&mut self, max_blocks: u64) -> Result<()> {
+        self.sync_x(Some(max_blocks)).c(d!())
+    }
+
+    fn sync_x(&mut self, max_blocks: Option<u64>) -> Result<()> {
+        let tip = utils::get_committed_height().c(d!())?;
+
+        for (account, idx) in self.entries.iter_mut().filter(|(_, idx)| {
+            0 == idx.height
+                || idx.height > tip
+                || max_blocks.map_or(false, |n| idx.height.saturating_add(n) < tip)
+        }) {
+            *idx = AccountIndex {
+                watch_only: account.is_watch_only(),
+                ..Default::default()
+            };
+            utils::get_owned_utxos(&account.get_pk())
+                .c(d!())?
+                .into_iter()
+                .for_each(|(sid, (utxo, memo))| idx.insert(account, sid, utxo, memo));
+            // the snapshot is not older than `tip`
+            idx.height = tip;
+        }
+        self.save().c(d!())?;
+
+        let mut from = 1 + self
+            .entries
+            .iter()
+            .map(|(_, idx)| idx.height)
+            .min()
+            .unwrap_or(tip);
+        while from <= tip {
+            let to = tip.min(from + MAX_BLOCKS_PER_QUERY - 1);
+            for b in utils::get_blocks(from, to).c(d!())? {
+                for (account, idx) in self
+                    .entries
+                    .iter_mut()
+                    .filter(|(_, idx)| idx.height < b.height)
+                {
+                    b.txns.iter().for_each(|txn| idx.apply_txn(account, txn));
+                }
+            }
+            self.entries
+                .iter_mut()
+                .for_each(|(_, idx)| idx.height = idx.height.max(to));
+            self.save().c(d!())?;
+            from = 1 + to;
+        }
+
+        Ok(())
+    }
+
+    /// The local index of an account.
+    #[inline(always)]
+    pub fn get_index(&self, pk: &XfrPublicKey) -> Option<&AccountIndex> {
+        self.entries
+            .iter()
+            .find(|(account, _)| &account.get_pk() == pk)
+            .map(|(_, idx)| idx)
+    }
+
+    fn save(&self) -> Result<()> {
+        self.entries
+            .iter()
+            .try_for_each(|(account, idx)| save(&account.get_pk(), idx).c(d!()))
+    }
+}
+
+/// Sync the local index of a single account and return it,
+/// an index which is far behind is rebuilt from the `owned_utxos`,
+/// use `Indexer::sync` to apply all the blocks.
+pub fn sync_one(account: Account) -> Result<AccountIndex> {
+    let pk = account.get_pk();
+    let mut indexer = Indexer::new(vec![account]).c(d!())?;
+    indexer.sync_capped(MAX_IMPLICIT_SYNC_BLOCKS).c(d!())?;
+    indexer.get_index(&pk).cloned().c(d!())
+}
+
+// the indexes of different servers are kept apart
+fn index_dir() -> Result<String> {
+    let serv_addr = get_serv_addr()
+        .c(d!())?
+        .chars()
+        .map(|c| alt!(c.is_ascii_alphanumeric(), c, '_'))
+        .collect::<String>();
+    Ok(format!("{}/wallet_index/{}", &*CFG_PATH, serv_addr))
+}
+
+#[inline(always)]
+fn index_path(pk: &XfrPublicKey) -> Result<String> {
+    index_dir()
+        .c(d!())
+        .map(|dir| format!("{}/{}.json", dir, wallet::public_key_to_bech32(pk)))
+}
+
+fn load(pk: &XfrPublicKey) -> Result<Option<AccountIndex>> {
+    match fs::read(index_path(pk).c(d!())?) {
+        Ok(b) => serde_json::from_slice(&b).c(d!()).map(Some),
+        Err(e) if std::io::ErrorKind::NotFound == e.kind() => Ok(None),
+        Err(e) => Err(e).c(d!()),
+    }
+}
+
+// write to a temporary file first,
+// so the index will not be corrupted by an interruption
+fn save(pk: &XfrPublicKey, idx: &AccountIndex) -> Result<()> {
+    fs::create_dir_all(index_dir().c(d!())?).c(d!())?;
+    let path = index_path(pk).c(d!())?;
+    let tmp = format!("{}.tmp", path);
+    serde_json::to_vec(idx)
+        .c(d!())
+        .and_then(|b| fs::write(&tmp, b).c(d!()))
+        .and_then(|_| fs::rename(&tmp, &path).c(d!()))
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        ledger::data_model::{TxOutput, TxnSID, ASSET_TYPE_FRA},
+        rand_chacha::ChaChaRng,
+        rand_core::SeedableRng,
+        zei::{
+            setup::PublicParams,
+            xfr::{
+                asset_record::{build_blind_asset_record, AssetRecordType},
+                structs::AssetRecordTemplate,
+            },
+        },
+    };
+
+    fn gen_output(
+        prng: &mut ChaChaRng,
+        pk: XfrPublicKey,
+        amount: u64,
+        art: AssetRecordType,
+    ) -> (Utxo, Option<OwnerMemo>) {
+        let template =
+            AssetRecordTemplate::with_no_asset_tracing(amount, ASSET_TYPE_FRA, art, pk);
+        let (record, _, memo) = build_blind_asset_record(
+            prng,
+            &PublicParams::default().pc_gens,
+            &template,
+            vec![],
+        );
+        let utxo = Utxo(TxOutput {
+            id: None,
+            record,
+            lien: None,
+        });
+        (utxo, memo)
+    }
+
+    #[test]
+    fn indexer_apply_txn() {
+        let mut prng = ChaChaRng::from_entropy();
+        let kp = XfrKeyPair::generate(&mut prng);
+        let other = XfrKeyPair::generate(&mut prng);
+        let fra = AssetTypeCode {
+            val: ASSET_TYPE_FRA,
+        };
+
+        let outputs = vec![
+            (
+                TxoSID(1),
+                gen_output(
+                    &mut prng,
+                    kp.get_pk(),
+                    10,
+                    AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+                ),
+            ),
+            (
+                TxoSID(2),
+                gen_output(
+                    &mut prng,
+                    kp.get_pk(),
+                    20,
+                    AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+                ),
+            ),
+            (
+                TxoSID(3),
+                gen_output(
+                    &mut prng,
+                    other.get_pk(),
+                    30,
+                    AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+                ),
+            ),
+        ];
+        let txn = BlockTxn {
+            sid: TxnSID(0),
+            hash: String::new(),
+            fee: 0,
+            operations: vec![],
+            outputs: outputs
+                .into_iter()
+                .map(|(sid, (utxo, memo))| (sid, utxo, memo))
+                .collect(),
+            inputs: vec![],
+        };
+
+        let mut idx = AccountIndex::default();
+        idx.apply_txn(&Account::KeyPair(&kp), &txn);
+        assert_eq!(idx.utxos.len(), 2);
+        assert_eq!(idx.get_balance(fra), 30);
+
+        // idempotent
+        idx.apply_txn(&Account::KeyPair(&kp), &txn);
+        assert_eq!(idx.get_balance(fra), 30);
+
+        // confidential records are skipped for watch-only accounts
+        let mut watched = AccountIndex::default();
+        watched.apply_txn(&Account::WatchOnly(kp.get_pk()), &txn);
+        assert_eq!(watched.utxos.len(), 1);
+        assert_eq!(watched.get_balance(fra), 10);
+
+        let spend = BlockTxn {
+            sid: TxnSID(1),
+            hash: String::new(),
+            fee: 0,
+            operations: vec![],
+            outputs: vec![],
+            inputs: vec![TxoSID(2), TxoSID(3)],
+        };
+        idx.apply_txn(&Account::KeyPair(&kp), &spend);
+        assert_eq!(idx.utxos.len(), 1);
+        assert_eq!(idx.get_balance(fra), 10);
+    }
+}
@@ -6,16 +6,21 @@
 //! This module is the library part of FN.
 //!
 
+pub mod indexer;
 pub mod utils;
 
 use {
-    crate::api::DelegationInfo,
+    crate::{
+        api::DelegationInfo,
+        txn_builder::{CoinSelector, PartiallySignedTxn, TransactionBuilder},
+    },
     globutils::wallet,
+    indexer::{Account, Indexer},
     lazy_static::lazy_static,
     ledger::{
         data_model::{
-            gen_random_keypair, AssetRules, AssetTypeCode, Transaction,
-            BLACK_HOLE_PUBKEY_STAKING,
+            gen_random_keypair, AssetRules, AssetTypeCode, Transaction, TxoSID, Utxo,
+            ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY_STAKING,
         },
         staking::{
             check_delegation_amount, td_addr_to_bytes, td_pubkey_to_td_addr,
@@ -35,6 +40,7 @@ use {
         xfr::{
             asset_record::AssetRecordType,
             sig::{XfrKeyPair, XfrPublicKey, XfrSecretKey},
+            structs::OwnerMemo,
         },
     },
 };
@@ -54,8 +60,14 @@ lazy_static! {
     static ref SERV_ADDR_FILE: String = format!("{}/serv_addr", &*CFG_PATH);
 }
 
-/// Updating the information of a staker includes commission_rate and staker_memo
-pub fn staker_update(cr: Option<&str>, memo: Option<StakerMemo>) -> Result<()> {
+/// Updating the information of a staker includes commission_rate and staker_memo,
+/// the limits of commission_rate can be tightened at the same time
+pub fn staker_update(
+    cr: Option<&str>,
+    memo: Option<StakerMemo>,
+    max_cr: Option<&str>,
+    max_cr_change: Option<&str>,
+) -> Result<()> {
     let addr = get_td_pubkey().map(|i| td_pubkey_to_td_addr(&i)).c(d!())?;
     let vd = get_validator_detail(&addr).c(d!())?;
 
@@ -68,6 +80,24 @@ pub fn staker_update(cr: Option<&str>, memo: Option<StakerMemo>) -> Result<()> {
         .c(d!())?;
     let memo = memo.unwrap_or(vd.memo);
 
+    let parse_rate = |s: &str| {
+        s.parse::<f64>()
+            .c(d!("commission rate must be a float number"))
+            .and_then(convert_commission_rate)
+    };
+    let limit = if max_cr.is_none() && max_cr_change.is_none() {
+        None
+    } else {
+        let mut limit = vd.commission_limit;
+        if let Some(s) = max_cr {
+            limit.max_rate = parse_rate(s).c(d!())?;
+        }
+        if let Some(s) = max_cr_change {
+            limit.max_daily_change = parse_rate(s).c(d!())?;
+        }
+        Some(limit)
+    };
+
     let td_pubkey = get_td_pubkey().c(d!())?;
 
     let kp = get_keypair().c(d!())?;
@@ -76,7 +106,7 @@ pub fn staker_update(cr: Option<&str>, memo: Option<StakerMemo>) -> Result<()> {
     let mut builder = utils::new_tx_builder().c(d!())?;
 
     builder
-        .add_operation_update_staker(&kp, &vkp, td_pubkey, cr, memo)
+        .add_operation_update_staker(&kp, &vkp, td_pubkey, cr, memo, limit)
         .c(d!())?;
     utils::gen_fee_op(&kp)
         .c(d!())
@@ -85,6 +115,30 @@ pub fn staker_update(cr: Option<&str>, memo: Option<StakerMemo>) -> Result<()> {
     utils::send_tx(&builder.take_transaction()).c(d!())
 }
 
+/// Print every component of the state commitment at `height`,
+/// and the changed ones compared to another height if `compare_to` is specified
+pub fn show_state_commitment(height: &str, compare_to: Option<&str>) -> Result<()> {
+    let height = height.parse::<u64>().c(d!("height must be an integer"))?;
+    let info = utils::get_state_commitment(height).c(d!())?;
+    println!(
+        "\x1b[31;01mState Commitment at {}:\x1b[00m\n{}\n",
+        height,
+        serde_json::to_string_pretty(&info).c(d!())?
+    );
+
+    if let Some(h) = compare_to {
+        let h = h.parse::<u64>().c(d!("height must be an integer"))?;
+        let diff = utils::get_state_commitment_diff(h, height).c(d!())?;
+        println!(
+            "\x1b[31;01mChanged Since {}:\x1b[00m\n{}\n",
+            h,
+            serde_json::to_string_pretty(&diff).c(d!())?
+        );
+    }
+
+    Ok(())
+}
+
 /// Perform a staking operation to add current tendermint node to validator list
 /// The cli tool user will be alert if the block height of local node is too small
 pub fn stake(
@@ -92,6 +146,29 @@ pub fn stake(
     commission_rate: &str,
     memo: Option<&str>,
     force: bool,
+) -> Result<()> {
+    stake_x(amount, commission_rate, memo, force, None).c(d!())
+}
+
+/// Like `stake`, but the transaction is built with the public key of the staker,
+/// and saved to `out`, the staker can sign it on an offline machine.
+pub fn stake_unsigned(
+    staker_pk: &XfrPublicKey,
+    amount: &str,
+    commission_rate: &str,
+    memo: Option<&str>,
+    force: bool,
+    out: &str,
+) -> Result<()> {
+    stake_x(amount, commission_rate, memo, force, Some((staker_pk, out))).c(d!())
+}
+
+fn stake_x(
+    amount: &str,
+    commission_rate: &str,
+    memo: Option<&str>,
+    force: bool,
+    unsigned_out: Option<(&XfrPublicKey, &str)>,
 ) -> Result<()> {
     let am = amount.parse::<u64>().c(d!("'amount' must be an integer"))?;
     check_delegation_amount(am, false).c(d!())?;
@@ -100,8 +177,6 @@ pub fn stake(
         .c(d!("commission rate must be a float number"))
         .and_then(|cr| convert_commission_rate(cr).c(d!()))?;
     let td_pubkey = get_td_pubkey().c(d!())?;
-
-    let kp = get_keypair().c(d!())?;
     let vkp = get_td_privkey().c(d!())?;
 
     macro_rules! diff {
@@ -131,6 +206,32 @@ pub fn stake(
     }
 
     let mut builder = utils::new_tx_builder().c(d!())?;
+
+    if let Some((pk, out)) = unsigned_out {
+        builder
+            .add_operation_staking_unsigned(
+                *pk,
+                am,
+                &vkp,
+                td_pubkey,
+                cr,
+                memo.map(|m| m.to_owned()),
+            )
+            .c(d!())?;
+        let (principal_op, utxos) = utils::gen_transfer_op_unsigned(
+            pk,
+            vec![(&BLACK_HOLE_PUBKEY_STAKING, am)],
+            None,
+            false,
+            false,
+            Some(AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType),
+        )
+        .c(d!())?;
+        builder.add_operation(principal_op);
+        return save_unsigned_tx(builder, utxos, out).c(d!());
+    }
+
+    let kp = get_keypair().c(d!())?;
     builder
         .add_operation_staking(&kp, am, &vkp, td_pubkey, cr, memo.map(|m| m.to_owned()))
         .c(d!())?;
@@ -251,6 +352,27 @@ pub fn claim(am: Option<&str>, sk_str: Option<&str>) -> Result<()> {
     utils::send_tx(&builder.take_transaction()).c(d!())
 }
 
+/// Like `claim`, but the transaction is built with the public key of the owner,
+/// and saved to `out`, the owner can sign it on an offline machine.
+pub fn claim_unsigned(
+    owner_pk: &XfrPublicKey,
+    am: Option<&str>,
+    out: &str,
+) -> Result<()> {
+    let am = if let Some(i) = am {
+        Some(i.parse::<u64>().c(d!("'amount' must be an integer"))?)
+    } else {
+        None
+    };
+
+    let (fee_op, utxos) = utils::gen_fee_op_unsigned(owner_pk).c(d!())?;
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    builder.add_operation(fee_op);
+    builder.add_operation_claim_unsigned(*owner_pk, am);
+
+    save_unsigned_tx(builder, utxos, out).c(d!())
+}
+
 /// Show information of current node, including following sections:
 ///     Server URL
 ///     Findora Wallet Address
@@ -387,6 +509,7 @@ pub fn transfer_asset(
     am: &str,
     confidential_am: bool,
     confidential_ty: bool,
+    selector: &CoinSelector,
 ) -> Result<()> {
     transfer_asset_batch(
         owner_sk,
@@ -395,6 +518,7 @@ pub fn transfer_asset(
         am,
         confidential_am,
         confidential_ty,
+        selector,
     )
     .c(d!())
 }
@@ -427,17 +551,18 @@ pub fn transfer_asset_batch(
     am: &str,
     confidential_am: bool,
     confidential_ty: bool,
+    selector: &CoinSelector,
 ) -> Result<()> {
     let from = restore_keypair_from_str_with_default(owner_sk)?;
     let am = am.parse::<u64>().c(d!("'amount' must be an integer"))?;
 
-    transfer_asset_batch_x(
+    utils::transfer_batch_x(
         &from,
-        target_addr,
+        target_addr.iter().map(|addr| (addr, am)).collect(),
         token_code,
-        am,
         confidential_am,
         confidential_ty,
+        selector,
     )
     .c(d!())
 }
@@ -461,6 +586,191 @@ pub fn transfer_asset_batch_x(
     .c(d!())
 }
 
+/// Merge the small utxos of an asset into one output, see `utils::gen_consolidate_op`
+pub fn utxo_consolidate(
+    sk_str: Option<&str>,
+    token_code: Option<AssetTypeCode>,
+    dust_threshold: &str,
+    max_inputs: &str,
+) -> Result<()> {
+    let kp = restore_keypair_from_str_with_default(sk_str)?;
+    let dust_threshold = dust_threshold
+        .parse::<u64>()
+        .c(d!("'dust-threshold' must be an integer"))?;
+    let max_inputs = max_inputs
+        .parse::<usize>()
+        .c(d!("'max-inputs' must be an integer"))?;
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    utils::gen_consolidate_op(&kp, token_code, dust_threshold, max_inputs)
+        .c(d!())
+        .map(|op| builder.add_operation(op))?;
+    if token_code.is_some() {
+        utils::gen_fee_op(&kp)
+            .c(d!())
+            .map(|op| builder.add_operation(op))?;
+    }
+
+    utils::send_tx(&builder.take_transaction()).c(d!())
+}
+
+/// Like `transfer_asset`, but the transaction is built with the public key
+/// of the owner, and saved to `out`, the owner can sign it on an offline machine.
+pub fn transfer_asset_unsigned(
+    owner_pk: &XfrPublicKey,
+    target_addr: XfrPublicKey,
+    token_code: Option<AssetTypeCode>,
+    am: &str,
+    confidential_am: bool,
+    confidential_ty: bool,
+    out: &str,
+) -> Result<()> {
+    let am = am.parse::<u64>().c(d!("'amount' must be an integer"))?;
+
+    let (op, utxos) = utils::gen_transfer_op_unsigned(
+        owner_pk,
+        vec![(&target_addr, am)],
+        token_code,
+        confidential_am,
+        confidential_ty,
+        None,
+    )
+    .c(d!())?;
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    builder.add_operation(op);
+
+    save_unsigned_tx(builder, utxos, out).c(d!())
+}
+
+/// Build a transfer like `transfer_asset`, but instead of sending it,
+/// save it to `out` with the signature of the owner,
+/// the other signers of a multisig asset can then sign it offline.
+pub fn transfer_asset_multisig(
+    owner_sk: Option<&str>,
+    target_addr: XfrPublicKey,
+    token_code: Option<AssetTypeCode>,
+    am: &str,
+    confidential_am: bool,
+    confidential_ty: bool,
+    out: &str,
+) -> Result<()> {
+    let from = restore_keypair_from_str_with_default(owner_sk)?;
+    let am = am.parse::<u64>().c(d!("'amount' must be an integer"))?;
+
+    let op = utils::gen_transfer_op(
+        &from,
+        vec![(&target_addr, am)],
+        token_code,
+        confidential_am,
+        confidential_ty,
+        None,
+    )
+    .c(d!())?;
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    builder.add_operation(op);
+
+    let tx = builder.take_transaction();
+    let sig_rules = utils::get_sig_rules(&tx).c(d!())?;
+    let ptx = PartiallySignedTxn::new(tx, sig_rules);
+
+    save_partially_signed_tx(&ptx, out).c(d!())?;
+    print_partially_signed_tx_status(&ptx);
+    Ok(())
+}
+
+/// Add the signatures of a key holder to a partially-signed transaction,
+/// the result will be written back to `file` if `out` is not specified.
+pub fn tx_sign(sk_str: Option<&str>, file: &str, out: Option<&str>) -> Result<()> {
+    let kp = restore_keypair_from_str_with_default(sk_str)?;
+    let mut ptx = load_partially_signed_tx(file).c(d!())?;
+
+    if !ptx.utxos.is_empty() {
+        for (code, am) in ptx.spent_amounts(&kp).c(d!())? {
+            println!("Spend {} units of asset {}", am, code.to_base64());
+        }
+    }
+
+    let n = ptx.sign(&kp).c(d!())?;
+    save_partially_signed_tx(&ptx, out.unwrap_or(file)).c(d!())?;
+
+    println!("{} signature(s) added.", n);
+    print_partially_signed_tx_status(&ptx);
+    Ok(())
+}
+
+/// Merge the signatures in several copies of the same partially-signed transaction.
+pub fn tx_combine(files: &[&str], out: &str) -> Result<()> {
+    let mut files = files.iter();
+    let mut ptx = files
+        .next()
+        .c(d!("no input files"))
+        .and_then(|f| load_partially_signed_tx(f).c(d!()))?;
+
+    for f in files {
+        load_partially_signed_tx(f)
+            .c(d!())
+            .and_then(|other| ptx.combine(&other).c(d!(f.to_string())))?;
+    }
+
+    save_partially_signed_tx(&ptx, out).c(d!())?;
+    print_partially_signed_tx_status(&ptx);
+    Ok(())
+}
+
+/// Submit a partially-signed transaction
+/// if the signature rules on chain have been satisfied.
+pub fn tx_submit(file: &str) -> Result<()> {
+    let mut ptx = load_partially_signed_tx(file).c(d!())?;
+
+    // the rules in the file come from its creator,
+    // always check against the latest ones on chain
+    ptx.sig_rules = utils::get_sig_rules(&ptx.txn).c(d!())?;
+    ptx.check().c(d!("signatures are not enough"))?;
+
+    utils::send_tx(&ptx.txn).c(d!())
+}
+
+fn save_unsigned_tx(
+    builder: TransactionBuilder,
+    utxos: Vec<(TxoSID, Utxo, Option<OwnerMemo>)>,
+    out: &str,
+) -> Result<()> {
+    let seq_id = builder.get_seq_id();
+    let tx = builder.take_transaction();
+    let sig_rules = utils::get_sig_rules(&tx).c(d!())?;
+    let ptx = PartiallySignedTxn::new(tx, sig_rules).with_utxos(utxos);
+
+    save_partially_signed_tx(&ptx, out).c(d!())?;
+    println!("The unsigned transaction has been saved to '{}'.", out);
+    if let Ok(w) = utils::get_no_replay_window() {
+        println!(
+            "It must be submitted within {} non-empty blocks.",
+            (seq_id + w.width).saturating_sub(w.max_seq_id)
+        );
+    }
+    Ok(())
+}
+
+fn load_partially_signed_tx(file: &str) -> Result<PartiallySignedTxn> {
+    fs::read(file)
+        .c(d!())
+        .and_then(|bytes| serde_json::from_slice(&bytes).c(d!("invalid file")))
+}
+
+fn save_partially_signed_tx(ptx: &PartiallySignedTxn, file: &str) -> Result<()> {
+    serde_json::to_vec_pretty(ptx)
+        .c(d!())
+        .and_then(|bytes| fs::write(file, bytes).c(d!()))
+}
+
+fn print_partially_signed_tx_status(ptx: &PartiallySignedTxn) {
+    if ptx.check().is_ok() {
+        println!("All signatures are collected, ready to submit.");
+    } else {
+        println!("Waiting for more signatures.");
+    }
+}
+
 /// Mainly for official usage,
 /// and can be also used in test scenes.
 pub fn set_initial_validators() -> Result<()> {
@@ -571,6 +881,52 @@ pub fn show_account(sk_str: Option<&str>, asset: Option<&str>) -> Result<()> {
     Ok(())
 }
 
+/// Sync the local indexes of a findora account and some watch-only public keys,
+/// then print the balance of every asset of them
+pub fn sync_wallet(sk_str: Option<&str>, watch: &[&str], rescan: bool) -> Result<()> {
+    let kp = restore_keypair_from_str_with_default(sk_str)?;
+    let watched = watch
+        .iter()
+        .map(|pk| {
+            wallet::public_key_from_base64(pk)
+                .or_else(|_| wallet::public_key_from_bech32(pk))
+                .c(d!("invalid public key"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let accounts = Some(Account::KeyPair(&kp))
+        .into_iter()
+        .chain(watched.iter().map(|pk| Account::WatchOnly(*pk)))
+        .collect();
+    let mut indexer = Indexer::new(accounts).c(d!())?;
+    if rescan {
+        indexer.reset();
+    }
+    indexer.sync().c(d!())?;
+
+    for pk in Some(kp.get_pk()).iter().chain(watched.iter()) {
+        let idx = indexer.get_index(pk).c(d!())?;
+        println!(
+            "\x1b[31;01m{}\x1b[00m{}, {} utxos at height {}",
+            wallet::public_key_to_bech32(pk),
+            alt!(idx.watch_only, " (watch-only)", ""),
+            idx.utxos.len(),
+            idx.height
+        );
+        for (code, balance) in idx.balances.iter() {
+            let name = alt!(
+                AssetTypeCode::new_from_base64(code)
+                    .map_or(false, |c| c.val == ASSET_TYPE_FRA),
+                "FRA",
+                code.as_str()
+            );
+            println!("    {}: {}", name, balance);
+        }
+    }
+
+    Ok(())
+}
+
 #[inline(always)]
 #[allow(missing_docs)]
 pub fn delegate(sk_str: Option<&str>, amount: u64, validator: &str) -> Result<()> {
@@ -587,6 +943,31 @@ pub fn delegate_x(kp: &XfrKeyPair, amount: u64, validator: &str) -> Result<()> {
         .and_then(|tx| utils::send_tx(&tx).c(d!()))
 }
 
+/// Like `delegate`, but the transaction is built with the public key of the owner,
+/// and saved to `out`, the owner can sign it on an offline machine.
+pub fn delegate_unsigned(
+    owner_pk: &XfrPublicKey,
+    amount: u64,
+    validator: &str,
+    out: &str,
+) -> Result<()> {
+    let (principal_op, utxos) = utils::gen_transfer_op_unsigned(
+        owner_pk,
+        vec![(&BLACK_HOLE_PUBKEY_STAKING, amount)],
+        None,
+        false,
+        false,
+        Some(AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType),
+    )
+    .c(d!())?;
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    builder.add_operation(principal_op);
+    builder.add_operation_delegation_unsigned(*owner_pk, amount, validator.to_owned());
+
+    save_unsigned_tx(builder, utxos, out).c(d!())
+}
+
 #[inline(always)]
 #[allow(missing_docs)]
 pub fn undelegate(sk_str: Option<&str>, param: Option<(u64, &str)>) -> Result<()> {
@@ -603,6 +984,51 @@ pub fn undelegate_x(kp: &XfrKeyPair, param: Option<(u64, &str)>) -> Result<()> {
         .and_then(|tx| utils::send_tx(&tx).c(d!()))
 }
 
+/// Move the delegated FRAs from one validator to another,
+/// `amount: None` means all the FRAs delegated to `from`.
+pub fn redelegate(
+    sk_str: Option<&str>,
+    amount: Option<u64>,
+    from: &str,
+    to: &str,
+) -> Result<()> {
+    let kp = restore_keypair_from_str_with_default(sk_str).c(d!())?;
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    utils::gen_fee_op(&kp).c(d!()).map(|op| {
+        builder.add_operation(op);
+    })?;
+    builder.add_operation_redelegation(&kp, from.to_owned(), to.to_owned(), amount);
+
+    utils::send_tx(&builder.take_transaction()).c(d!())
+}
+
+/// Release the validator from jail
+pub fn unjail(sk_str: Option<&str>) -> Result<()> {
+    let kp = restore_keypair_from_str_with_default(sk_str).c(d!())?;
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    utils::gen_fee_op(&kp).c(d!()).map(|op| {
+        builder.add_operation(op);
+    })?;
+    builder.add_operation_unjail(&kp);
+
+    utils::send_tx(&builder.take_transaction()).c(d!())
+}
+
+/// Turn on/off the auto-compounding of the delegation rewards
+pub fn auto_compound(sk_str: Option<&str>, enable: bool) -> Result<()> {
+    let kp = restore_keypair_from_str_with_default(sk_str).c(d!())?;
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    utils::gen_fee_op(&kp).c(d!()).map(|op| {
+        builder.add_operation(op);
+    })?;
+    builder.add_operation_auto_compound(&kp, enable);
+
+    utils::send_tx(&builder.take_transaction()).c(d!())
+}
+
 /// Display delegation information of a findora account
 pub fn show_delegations(sk_str: Option<&str>) -> Result<()> {
     let pk = restore_keypair_from_str_with_default(sk_str)?.get_pk();
@@ -4,18 +4,29 @@
 
 use {
     crate::{
-        api::{DelegationInfo, ValidatorDetail},
-        common::get_serv_addr,
-        txn_builder::{TransactionBuilder, TransferOperationBuilder},
+        api::{BlockDetail, DelegationInfo, ValidatorDetail},
+        common::{
+            get_serv_addr,
+            indexer::{self, Account},
+        },
+        txn_builder::{
+            CoinCandidate, CoinSelector, PartiallySignedTxn, TransactionBuilder,
+            TransferOperationBuilder,
+        },
     },
     globutils::{wallet, HashOf, SignatureOf},
     ledger::{
         data_model::{
-            AssetType, AssetTypeCode, DefineAsset, Operation, StateCommitmentData,
-            Transaction, TransferType, TxoRef, TxoSID, Utxo, ASSET_TYPE_FRA,
-            BLACK_HOLE_PUBKEY, TX_FEE_MIN,
+            gen_random_keypair, AssetType, AssetTypeCode, DefineAsset, NoReplayWindow,
+            Operation, SignatureRules, StateCommitmentData, Transaction, TransferType,
+            TxoRef, TxoSID, Utxo, ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY,
         },
-        staking::{init::get_inital_validators, TendermintAddrRef, FRA_TOTAL_AMOUNT},
+        light_client::SignedLightHeader,
+        staking::{
+            init::get_inital_validators, BlockHeight, TendermintAddrRef,
+            FRA_TOTAL_AMOUNT,
+        },
+        store::api_cache::{StateCommitmentDiff, StateCommitmentInfo},
     },
     ruc::*,
     serde::{self, Deserialize, Serialize},
@@ -24,7 +35,9 @@ use {
     zei::xfr::{
         asset_record::{open_blind_asset_record, AssetRecordType},
         sig::{XfrKeyPair, XfrPublicKey},
-        structs::{AssetRecordTemplate, OwnerMemo},
+        structs::{
+            AssetRecordTemplate, AssetType as ZeiAssetType, OwnerMemo, XfrAmount,
+        },
     },
 };
 
@@ -35,7 +48,12 @@ use {
 #[inline(always)]
 #[allow(missing_docs)]
 pub fn new_tx_builder() -> Result<TransactionBuilder> {
-    get_seq_id().c(d!()).map(TransactionBuilder::from_seq_id)
+    let fee_min = get_tx_fee_min().c(d!())?;
+    get_seq_id().c(d!()).map(|seq_id| {
+        let mut builder = TransactionBuilder::from_seq_id(seq_id);
+        builder.set_fee_min(fee_min);
+        builder
+    })
 }
 
 #[inline(always)]
@@ -97,15 +115,37 @@ pub fn transfer_batch(
     token_code: Option<AssetTypeCode>,
     confidential_am: bool,
     confidential_ty: bool,
+) -> Result<()> {
+    transfer_batch_x(
+        owner_kp,
+        target_list,
+        token_code,
+        confidential_am,
+        confidential_ty,
+        &CoinSelector::default(),
+    )
+    .c(d!())
+}
+
+/// Like `transfer_batch`, but the inputs are picked by `selector`
+pub fn transfer_batch_x(
+    owner_kp: &XfrKeyPair,
+    target_list: Vec<(&XfrPublicKey, u64)>,
+    token_code: Option<AssetTypeCode>,
+    confidential_am: bool,
+    confidential_ty: bool,
+    selector: &CoinSelector,
 ) -> Result<()> {
     let mut builder = new_tx_builder().c(d!())?;
-    let op = gen_transfer_op(
+    let op = gen_transfer_op_x(
         owner_kp,
         target_list,
         token_code,
+        true,
         confidential_am,
         confidential_ty,
         None,
+        selector,
     )
     .c(d!())?;
     builder.add_operation(op);
@@ -131,70 +171,145 @@ pub fn gen_transfer_op(
         confidential_am,
         confidential_ty,
         balance_type,
+        &CoinSelector::default(),
     )
     .c(d!())
 }
 
-#[allow(missing_docs)]
+/// The inputs are picked from the local index of the owner by `selector`
+#[allow(clippy::too_many_arguments)]
 pub fn gen_transfer_op_x(
     owner_kp: &XfrKeyPair,
-    mut target_list: Vec<(&XfrPublicKey, u64)>,
+    target_list: Vec<(&XfrPublicKey, u64)>,
     token_code: Option<AssetTypeCode>,
     auto_fee: bool,
     confidential_am: bool,
     confidential_ty: bool,
     balance_type: Option<AssetRecordType>,
+    selector: &CoinSelector,
 ) -> Result<Operation> {
-    let mut op_fee: u64 = 0;
+    gen_transfer_op_builder(
+        owner_kp.get_pk_ref(),
+        Some(owner_kp),
+        target_list,
+        token_code,
+        auto_fee,
+        confidential_am,
+        confidential_ty,
+        balance_type,
+        selector,
+    )
+    .c(d!())
+    .and_then(|(mut builder, _)| builder.sign(owner_kp).c(d!())?.transaction().c(d!()))
+}
+
+/// Like `gen_transfer_op`, but the operation will be signed by the owner offline,
+/// the spent utxos and their owner memos are returned along with it.
+///
+/// NOTE: only non-confidential utxos can be spent in this way,
+/// the others can not be opened without the owner key.
+pub fn gen_transfer_op_unsigned(
+    owner_pk: &XfrPublicKey,
+    target_list: Vec<(&XfrPublicKey, u64)>,
+    token_code: Option<AssetTypeCode>,
+    confidential_am: bool,
+    confidential_ty: bool,
+    balance_type: Option<AssetRecordType>,
+) -> Result<(Operation, Vec<(TxoSID, Utxo, Option<OwnerMemo>)>)> {
+    gen_transfer_op_builder(
+        owner_pk,
+        None,
+        target_list,
+        token_code,
+        true,
+        confidential_am,
+        confidential_ty,
+        balance_type,
+        &CoinSelector::default(),
+    )
+    .c(d!())
+    .and_then(|(builder, inputs)| builder.transaction().c(d!()).map(|op| (op, inputs)))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn gen_transfer_op_builder(
+    owner_pk: &XfrPublicKey,
+    owner_kp: Option<&XfrKeyPair>,
+    mut target_list: Vec<(&XfrPublicKey, u64)>,
+    token_code: Option<AssetTypeCode>,
+    auto_fee: bool,
+    confidential_am: bool,
+    confidential_ty: bool,
+    balance_type: Option<AssetRecordType>,
+    selector: &CoinSelector,
+) -> Result<(
+    TransferOperationBuilder,
+    Vec<(TxoSID, Utxo, Option<OwnerMemo>)>,
+)> {
+    let fee_min = alt!(auto_fee, get_tx_fee_min().c(d!())?, 0);
+    let mut op_fee = fee_min;
     if auto_fee {
-        target_list.push((&*BLACK_HOLE_PUBKEY, TX_FEE_MIN));
-        op_fee += TX_FEE_MIN;
+        target_list.push((&*BLACK_HOLE_PUBKEY, fee_min));
     }
     let asset_type = token_code.map(|code| code.val).unwrap_or(ASSET_TYPE_FRA);
 
     let mut trans_builder = TransferOperationBuilder::new();
 
-    let mut am = target_list.iter().map(|(_, am)| *am).sum();
+    let mut am: u64 = target_list.iter().map(|(_, am)| *am).sum();
     if asset_type != ASSET_TYPE_FRA {
         am -= op_fee;
     } else {
         // if this is a FRA asset, set op_fee to 0, because fee has been added to am already.
         op_fee = 0;
     }
-    let mut i_am;
-    let utxos = get_owned_utxos(owner_kp.get_pk_ref()).c(d!())?.into_iter();
-
-    for (sid, (utxo, owner_memo)) in utxos {
-        let oar =
-            open_blind_asset_record(&utxo.0.record, &owner_memo, owner_kp).c(d!())?;
-
-        if oar.asset_type != asset_type && oar.asset_type != ASSET_TYPE_FRA {
-            continue;
-        } else if oar.asset_type == ASSET_TYPE_FRA && op_fee != 0 {
-            // asset_type is a custom asset, need handle fee here
-            alt!(oar.amount < op_fee, i_am = oar.amount, i_am = op_fee);
-            op_fee -= i_am;
+    let account = owner_kp
+        .map(Account::KeyPair)
+        .unwrap_or(Account::WatchOnly(*owner_pk));
+    let index = indexer::sync_one(account).c(d!())?;
+    let candidates = |ty: ZeiAssetType| {
+        index
+            .utxos
+            .iter()
+            .filter(|(_, iu)| iu.asset_type.val == ty)
+            // non-confidential records can be opened with any key
+            .filter(|(_, iu)| owner_kp.is_some() || !is_confidential(&iu.utxo))
+            .map(|(sid, iu)| CoinCandidate {
+                sid: TxoSID(*sid),
+                amount: iu.amount,
+                confidential: is_confidential(&iu.utxo),
+            })
+            .collect::<Vec<_>>()
+    };
 
-            trans_builder
-                .add_input(TxoRef::Absolute(sid), oar, None, None, i_am)
-                .c(d!())?;
+    let mut selected = selector.select(&candidates(asset_type), am).c(d!())?.inputs;
+    if 0 != op_fee {
+        // asset_type is a custom asset, need handle fee here
+        selected.extend(
+            selector
+                .select(&candidates(ASSET_TYPE_FRA), op_fee)
+                .c(d!())?
+                .inputs,
+        );
+    }
 
-            continue;
-        } else if am != 0 {
-            alt!(oar.amount < am, i_am = oar.amount, i_am = am);
-            //am = am.saturating_sub(i_am);
-            am -= i_am;
-
-            trans_builder
-                .add_input(TxoRef::Absolute(sid), oar, None, None, i_am)
-                .c(d!())?;
+    let random_kp;
+    let opener = match owner_kp {
+        Some(kp) => kp,
+        None => {
+            random_kp = gen_random_keypair();
+            &random_kp
         }
+    };
 
-        alt!(0 == am && 0 == op_fee, break);
-    }
-
-    if 0 != am || 0 != op_fee {
-        return Err(eg!("insufficient balance"));
+    let mut inputs = vec![];
+    for (sid, i_am) in selected {
+        let iu = index.utxos.get(&sid.0).c(d!())?;
+        let oar = open_blind_asset_record(&iu.utxo.0.record, &iu.owner_memo, opener)
+            .c(d!())?;
+        trans_builder
+            .add_input(TxoRef::Absolute(sid), oar, None, None, i_am)
+            .c(d!())?;
+        inputs.push((sid, iu.utxo.clone(), iu.owner_memo.clone()));
     }
 
     if auto_fee {
@@ -202,7 +317,7 @@ pub fn gen_transfer_op_x(
         trans_builder
             .add_output(
                 &AssetRecordTemplate::with_no_asset_tracing(
-                    TX_FEE_MIN,
+                    fee_min,
                     ASSET_TYPE_FRA,
                     AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
                     *BLACK_HOLE_PUBKEY,
@@ -239,6 +354,104 @@ pub fn gen_transfer_op_x(
     trans_builder
         .balance(balance_type)
         .c(d!())?
+        .create(TransferType::Standard)
+        .c(d!())?;
+
+    Ok((trans_builder, inputs))
+}
+
+#[inline(always)]
+fn is_confidential(utxo: &Utxo) -> bool {
+    !matches!(utxo.0.record.amount, XfrAmount::NonConfidential(_))
+        || utxo.0.record.asset_type.get_asset_type().is_none()
+}
+
+/// Merge the dust(utxos below `dust_threshold`) of an asset into one output,
+/// at most `max_inputs` of the smallest ones are merged at a time,
+/// the output is confidential if any of the inputs is.
+///
+/// NOTE: the fee of a FRA consolidation is paid from the dust itself,
+/// a fee operation is needed for other assets.
+pub fn gen_consolidate_op(
+    owner_kp: &XfrKeyPair,
+    token_code: Option<AssetTypeCode>,
+    dust_threshold: u64,
+    max_inputs: usize,
+) -> Result<Operation> {
+    let asset_type = token_code.map(|code| code.val).unwrap_or(ASSET_TYPE_FRA);
+    let index = indexer::sync_one(Account::KeyPair(owner_kp)).c(d!())?;
+
+    let mut dust = index
+        .utxos
+        .iter()
+        .filter(|(_, iu)| iu.asset_type.val == asset_type)
+        .filter(|(_, iu)| iu.amount < dust_threshold)
+        .map(|(sid, iu)| (TxoSID(*sid), iu.amount))
+        .collect::<Vec<_>>();
+
+    // the smallest first, and all of them are spent fully, so no change
+    dust.sort_by_key(|(sid, am)| (*am, *sid));
+    dust.truncate(max_inputs);
+    if 2 > dust.len() {
+        return Err(eg!("nothing to consolidate"));
+    }
+    let total = dust.iter().map(|(_, am)| am).sum::<u64>();
+
+    let fee = alt!(ASSET_TYPE_FRA == asset_type, get_tx_fee_min().c(d!())?, 0);
+    if total <= fee {
+        return Err(eg!("the dust is not enough to pay the fee"));
+    }
+
+    let mut trans_builder = TransferOperationBuilder::new();
+    let mut confidential = (false, false);
+    for (sid, am) in dust.into_iter() {
+        let iu = index.utxos.get(&sid.0).c(d!())?;
+        confidential.0 |=
+            !matches!(iu.utxo.0.record.amount, XfrAmount::NonConfidential(_));
+        confidential.1 |= iu.utxo.0.record.asset_type.get_asset_type().is_none();
+        let oar = open_blind_asset_record(&iu.utxo.0.record, &iu.owner_memo, owner_kp)
+            .c(d!())?;
+        trans_builder
+            .add_input(TxoRef::Absolute(sid), oar, None, None, am)
+            .c(d!())?;
+    }
+
+    let art = match confidential {
+        (true, true) => AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+        (true, false) => AssetRecordType::ConfidentialAmount_NonConfidentialAssetType,
+        (false, true) => AssetRecordType::NonConfidentialAmount_ConfidentialAssetType,
+        _ => AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+    };
+    trans_builder
+        .add_output(
+            &AssetRecordTemplate::with_no_asset_tracing(
+                total - fee,
+                asset_type,
+                art,
+                owner_kp.get_pk(),
+            ),
+            None,
+            None,
+            None,
+        )
+        .c(d!())?;
+    if 0 < fee {
+        trans_builder
+            .add_output(
+                &AssetRecordTemplate::with_no_asset_tracing(
+                    fee_min,
+                    ASSET_TYPE_FRA,
+                    AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+                    *BLACK_HOLE_PUBKEY,
+                ),
+                None,
+                None,
+                None,
+            )
+            .c(d!())?;
+    }
+
+    trans_builder
         .create(TransferType::Standard)
         .c(d!())?
         .sign(owner_kp)
@@ -254,6 +467,14 @@ pub fn gen_fee_op(owner_kp: &XfrKeyPair) -> Result<Operation> {
     gen_transfer_op(owner_kp, vec![], None, false, false, None).c(d!())
 }
 
+/// Like `gen_fee_op`, but the operation will be signed by the owner offline
+#[inline(always)]
+pub fn gen_fee_op_unsigned(
+    owner_pk: &XfrPublicKey,
+) -> Result<(Operation, Vec<(TxoSID, Utxo, Option<OwnerMemo>)>)> {
+    gen_transfer_op_unsigned(owner_pk, vec![], None, false, false, None).c(d!())
+}
+
 /////////////////////////////////////////
 // Part 2: utils for query infomations //
 /////////////////////////////////////////
@@ -382,6 +603,23 @@ pub fn get_asset_type(code: &str) -> Result<AssetType> {
         .and_then(|b| serde_json::from_slice::<AssetType>(&b).c(d!()))
 }
 
+/// Retrieve the transfer signature rules of
+/// the non-confidential assets spent in a transaction
+pub fn get_sig_rules(tx: &Transaction) -> Result<Vec<(AssetTypeCode, SignatureRules)>> {
+    PartiallySignedTxn::input_asset_codes(tx)
+        .into_iter()
+        .map(|code| {
+            get_asset_type(&code.to_base64()).c(d!()).map(|at| {
+                at.properties
+                    .asset_rules
+                    .transfer_multisig_rules
+                    .map(|rules| (code, rules))
+            })
+        })
+        .filter_map(|r| r.transpose())
+        .collect()
+}
+
 /// Retrieve a list of assets created by the specified findora account
 pub fn get_created_assets(addr: &XfrPublicKey) -> Result<Vec<DefineAsset>> {
     let url = format!(
@@ -406,35 +644,63 @@ pub fn get_balance(kp: &XfrKeyPair) -> Result<u64> {
     get_asset_balance(kp, None).c(d!())
 }
 
-/// Retrieve Utxos of a findora keypair and calcultate the balance of the specified asset
+/// Sync the local index of a findora keypair and get the balance of the specified asset
 /// FRA is the default asset type
 pub fn get_asset_balance(kp: &XfrKeyPair, asset: Option<AssetTypeCode>) -> Result<u64> {
-    let asset_type = asset.map(|code| code.val).unwrap_or(ASSET_TYPE_FRA);
-    let balance = get_owned_utxos(kp.get_pk_ref())
-        .c(d!())?
-        .values()
-        .map(|(utxo, owner_memo)| {
-            open_blind_asset_record(&utxo.0.record, owner_memo, kp)
-                .c(d!())
-                .map(|obr| alt!(obr.asset_type == asset_type, obr.amount, 0))
-        })
-        .collect::<Result<Vec<_>>>()
-        .c(d!())?
-        .iter()
-        .sum();
-
-    Ok(balance)
+    let code = asset.unwrap_or(AssetTypeCode {
+        val: ASSET_TYPE_FRA,
+    });
+    indexer::sync_one(Account::KeyPair(kp))
+        .c(d!())
+        .map(|idx| idx.get_balance(code))
 }
 
-fn get_owned_utxos(
+/// Get all the unspent utxos of a findora account from the query server,
+/// it is only used to bootstrap the local index of an account,
+/// see [indexer](super::indexer) for more details.
+pub fn get_owned_utxos(
     addr: &XfrPublicKey,
 ) -> Result<HashMap<TxoSID, (Utxo, Option<OwnerMemo>)>> {
+    #[derive(Deserialize)]
+    struct Page {
+        next: Option<u64>,
+        items: Vec<(TxoSID, (Utxo, Option<OwnerMemo>))>,
+    }
+
     let url = format!(
-        "{}:8668/owned_utxos/{}",
+        "{}:8668/owned_utxos_paged/{}?limit=1000",
         get_serv_addr().c(d!())?,
         wallet::public_key_to_base64(addr)
     );
 
+    let mut res = HashMap::new();
+    let mut start = Some(0);
+    while let Some(s) = start {
+        let page = attohttpc::get(&format!("{}&start={}", url, s))
+            .send()
+            .c(d!())?
+            .error_for_status()
+            .c(d!())?
+            .bytes()
+            .c(d!())
+            .and_then(|b| serde_json::from_slice::<Page>(&b).c(d!()))?;
+        res.extend(page.items.into_iter());
+        start = page.next;
+    }
+
+    Ok(res)
+}
+
+/// Get the minimum fee of a transaction from the chain parameters
+/// of the next block, see the `fee_estimate` route.
+pub fn get_tx_fee_min() -> Result<u64> {
+    #[derive(Deserialize)]
+    struct Resp {
+        tx_fee_min: u64,
+    }
+
+    let url = format!("{}:8668/fee_estimate", get_serv_addr().c(d!())?);
+
     attohttpc::get(&url)
         .send()
         .c(d!())?
@@ -442,10 +708,8 @@ fn get_owned_utxos(
         .c(d!())?
         .bytes()
         .c(d!())
-        .and_then(|b| {
-            serde_json::from_slice::<HashMap<TxoSID, (Utxo, Option<OwnerMemo>)>>(&b)
-                .c(d!())
-        })
+        .and_then(|b| serde_json::from_slice::<Resp>(&b).c(d!()))
+        .map(|r| r.tx_fee_min)
 }
 
 #[inline(always)]
@@ -511,6 +775,134 @@ pub fn get_delegation_info(pk: &XfrPublicKey) -> Result<DelegationInfo> {
         .and_then(|b| serde_json::from_slice::<DelegationInfo>(&b).c(d!()))
 }
 
+/// Get every component of the state commitment at a height.
+pub fn get_state_commitment(height: BlockHeight) -> Result<StateCommitmentInfo> {
+    let url = format!(
+        "{}:8668/state_commitment/{}",
+        get_serv_addr().c(d!())?,
+        height
+    );
+
+    attohttpc::get(&url)
+        .send()
+        .c(d!())?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice::<StateCommitmentInfo>(&b).c(d!()))
+}
+
+/// Get the components of the state commitment which changed between two heights.
+pub fn get_state_commitment_diff(
+    from: BlockHeight,
+    to: BlockHeight,
+) -> Result<StateCommitmentDiff> {
+    let url = format!(
+        "{}:8668/state_commitment_diff?from={}&to={}",
+        get_serv_addr().c(d!())?,
+        from,
+        to
+    );
+
+    attohttpc::get(&url)
+        .send()
+        .c(d!())?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice::<StateCommitmentDiff>(&b).c(d!()))
+}
+
+/// Get the light header at a height from a node, it's only signed by that node.
+pub fn get_light_header(
+    serv_addr: &str,
+    height: BlockHeight,
+) -> Result<SignedLightHeader> {
+    let url = format!("{}:8668/light_header/{}", serv_addr, height);
+
+    attohttpc::get(&url)
+        .send()
+        .c(d!())?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice::<SignedLightHeader>(&b).c(d!()))
+}
+
+/// Collect the signatures of the light header at a height from several validators,
+/// the unreachable ones are skipped.
+pub fn collect_light_header(
+    serv_addrs: &[&str],
+    height: BlockHeight,
+) -> Result<SignedLightHeader> {
+    let mut res: Option<SignedLightHeader> = None;
+    for addr in serv_addrs.iter() {
+        let sh = if let Ok(sh) = info!(get_light_header(addr, height)) {
+            sh
+        } else {
+            continue;
+        };
+        if let Some(r) = res.as_mut() {
+            r.merge(&sh).c(d!(addr))?;
+        } else {
+            res = Some(sh);
+        }
+    }
+
+    res.c(d!("no light header found"))
+}
+
+/// Get the latest height committed by the query server.
+pub fn get_committed_height() -> Result<BlockHeight> {
+    let url = format!("{}:8668/committed_height", get_serv_addr().c(d!())?);
+
+    attohttpc::get(&url)
+        .send()
+        .c(d!())?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice::<BlockHeight>(&b).c(d!()))
+}
+
+/// Get the range of `seq_id` that can be used by a new transaction.
+pub fn get_no_replay_window() -> Result<NoReplayWindow> {
+    let url = format!("{}:8668/no_replay_window", get_serv_addr().c(d!())?);
+
+    attohttpc::get(&url)
+        .send()
+        .c(d!())?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice::<NoReplayWindow>(&b).c(d!()))
+}
+
+/// Get the non-empty blocks in the range of `[from, to]`,
+/// at most `MAX_BLOCKS_PER_QUERY` heights at a time.
+pub fn get_blocks(from: BlockHeight, to: BlockHeight) -> Result<Vec<BlockDetail>> {
+    let url = format!(
+        "{}:8668/blocks?from={}&to={}",
+        get_serv_addr().c(d!())?,
+        from,
+        to
+    );
+
+    attohttpc::get(&url)
+        .send()
+        .c(d!())?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice::<Vec<BlockDetail>>(&b).c(d!()))
+}
+
 /// Get validator infomations.
 pub fn get_validator_detail(td_addr: TendermintAddrRef) -> Result<ValidatorDetail> {
     let url = format!(
@@ -11,26 +11,33 @@ use {
     globutils::SignatureOf,
     ledger::{
         data_model::{
-            AssetRules, AssetTypeCode, ConfidentialMemo, DefineAsset, DefineAssetBody,
-            IndexedSignature, IssueAsset, IssueAssetBody, IssuerKeyPair,
-            IssuerPublicKey, Memo, NoReplayToken, Operation, Transaction,
-            TransactionBody, TransferAsset, TransferAssetBody, TransferType, TxOutput,
-            TxoRef, UpdateMemo, UpdateMemoBody, ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY,
+            AssetRules, AssetTypeCode, ConfidentialMemo, CredentialProof, DefineAsset,
+            DefineAssetBody, IndexedSignature, IssueAsset, IssueAssetBody,
+            IssuerKeyPair, IssuerPublicKey, Memo, NoReplayToken, Operation,
+            SignatureRules, Transaction, TransactionBody, TransferAsset,
+            TransferAssetBody, TransferType, TxOutput, TxoRef, TxoSID, UpdateMemo,
+            UpdateMemoBody, Utxo, ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY, PRIORITY_SIZE_UNIT,
             TX_FEE_MIN,
         },
         staking::{
+            chain_params::{ChainParams, ProposalId},
             is_valid_tendermint_addr,
             ops::{
+                auto_compound::AutoCompoundOps,
                 claim::ClaimOps,
                 delegation::DelegationOps,
                 fra_distribution::FraDistributionOps,
-                governance::{ByzantineKind, GovernanceOps},
+                governance::{ByzantineKind, GovernanceOps, RuleSet},
+                redelegation::ReDelegationOps,
                 undelegation::UnDelegationOps,
+                unjail::UnjailOps,
+                update_chain_params::UpdateChainParamsOps,
+                update_governance_rules::UpdateGovernanceRulesOps,
                 update_staker::UpdateStakerOps,
                 update_validator::UpdateValidatorOps,
             },
-            td_addr_to_string, BlockHeight, PartialUnDelegation, StakerMemo,
-            TendermintAddr, Validator,
+            td_addr_to_string, BlockHeight, CommissionLimit, PartialUnDelegation,
+            StakerMemo, TendermintAddr, Validator,
         },
     },
     rand_chacha::ChaChaRng,
@@ -70,6 +77,19 @@ macro_rules! no_transfer_err {
     };
 }
 
+/// Extra bytes counted when paying fee by priority,
+/// leave room for signatures and the growth of the fee amount.
+pub const FEE_SIZE_MARGIN: usize = 512;
+
+/// The minimum fee to reach `priority` with a transaction of `size` bytes,
+/// the inverse of `Transaction::get_priority`.
+#[inline(always)]
+pub fn fee_of_priority(priority: u64, size: usize) -> u64 {
+    let fee = (priority as u128 * size as u128 + PRIORITY_SIZE_UNIT as u128 - 1)
+        / PRIORITY_SIZE_UNIT as u128;
+    fee.min(u64::MAX as u128) as u64
+}
+
 /// Definition of a fee operation, as a inner data structure of FeeInputs
 pub struct FeeInput {
     /// Amount
@@ -116,6 +136,13 @@ pub struct TransactionBuilder {
     outputs: u64,
     #[allow(missing_docs)]
     pub no_replay_token: NoReplayToken,
+    // the `tx_fee_min` of the chain parameters
+    #[serde(default = "default_fee_min")]
+    fee_min: u64,
+}
+
+fn default_fee_min() -> u64 {
+    TX_FEE_MIN
 }
 
 impl TransactionBuilder {
@@ -165,16 +192,36 @@ impl TransactionBuilder {
             .collect()
     }
 
-    /// @param am: amount to pay
     /// @param kp: owner's XfrKeyPair
+    /// @param priority: the target fee per `PRIORITY_SIZE_UNIT` bytes,
+    ///     pay the minimum fee if `None`
     pub fn add_fee_relative_auto(
         &mut self,
         kp: &XfrKeyPair,
+        priority: Option<u64>,
     ) -> Result<&mut TransactionBuilder> {
+        let mut fee = self.fee_min;
+
+        if let Some(priority) = priority {
+            // estimate the final size with a minimum fee
+            let op = self.gen_fee_op_relative(kp, self.fee_min).c(d!())?;
+            let mut txn = self.txn.clone();
+            txn.add_operation(op);
+            let size = serde_json::to_vec(&txn).c(d!())?.len() + FEE_SIZE_MARGIN;
+            fee = fee.max(fee_of_priority(priority, size));
+        }
+
+        self.gen_fee_op_relative(kp, fee)
+            .c(d!())
+            .map(move |op| self.add_operation(op))
+    }
+
+    // pay `fee` with the relative outputs owned by `kp`
+    fn gen_fee_op_relative(&self, kp: &XfrKeyPair, fee: u64) -> Result<Operation> {
         let mut opb = TransferOperationBuilder::default();
         let outputs = self.get_relative_outputs();
 
-        let mut am = TX_FEE_MIN;
+        let mut am = fee;
         for (idx, (o, om)) in outputs.into_iter().enumerate() {
             if 0 < am {
                 if let Ok(oar) = open_blind_asset_record(&o, &om, &kp) {
@@ -192,7 +239,7 @@ impl TransactionBuilder {
 
         opb.add_output(
             &AssetRecordTemplate::with_no_asset_tracing(
-                TX_FEE_MIN,
+                fee,
                 ASSET_TYPE_FRA,
                 AssetRecordType::from_flags(false, false),
                 *BLACK_HOLE_PUBKEY,
@@ -206,7 +253,6 @@ impl TransactionBuilder {
         .and_then(|o| o.create(TransferType::Standard).c(d!()))
         .and_then(|o| o.sign(&kp).c(d!()))
         .and_then(|o| o.transaction().c(d!()))
-        .map(move |op| self.add_operation(op))
     }
 
     /// As the last operation of any transaction,
@@ -229,7 +275,7 @@ impl TransactionBuilder {
 
         opb.add_output(
             &AssetRecordTemplate::with_no_asset_tracing(
-                TX_FEE_MIN,
+                self.fee_min,
                 ASSET_TYPE_FRA,
                 AssetRecordType::from_flags(false, false),
                 *BLACK_HOLE_PUBKEY,
@@ -280,9 +326,22 @@ impl TransactionBuilder {
             txn: Transaction::from_seq_id(seq_id),
             outputs: 0,
             no_replay_token,
+            fee_min: TX_FEE_MIN,
         }
     }
 
+    /// Set the minimum fee paid by `add_fee` and `add_fee_relative_auto`,
+    /// it should be the `tx_fee_min` of the chain parameters.
+    pub fn set_fee_min(&mut self, fee_min: u64) -> &mut Self {
+        self.fee_min = fee_min;
+        self
+    }
+
+    #[allow(missing_docs)]
+    pub fn get_fee_min(&self) -> u64 {
+        self.fee_min
+    }
+
     #[allow(missing_docs)]
     pub fn get_seq_id(&self) -> u64 {
         self.no_replay_token.get_seq_id()
@@ -342,6 +401,13 @@ impl TransactionBuilder {
         self
     }
 
+    /// Attach a credential proof, it is required when the transaction
+    /// creates outputs of an asset with a credential requirement
+    pub fn add_credential_proof(&mut self, proof: CredentialProof) -> &mut Self {
+        self.txn.body.credentials.push(proof);
+        self
+    }
+
     /// Add asset creating operation to builder an return modified builder
     pub fn add_operation_create_asset(
         &mut self,
@@ -487,7 +553,27 @@ impl TransactionBuilder {
         self.add_operation(Operation::Delegation(op))
     }
 
-    /// Add a operation to updating staker memo and commission_rate
+    /// Like `add_operation_delegation`,
+    /// but the operation will be signed by the owner offline.
+    pub fn add_operation_delegation_unsigned(
+        &mut self,
+        pubkey: XfrPublicKey,
+        amount: u64,
+        validator: TendermintAddr,
+    ) -> &mut Self {
+        let op = DelegationOps::new_unsigned(
+            pubkey,
+            None,
+            amount,
+            validator,
+            None,
+            self.txn.body.no_replay_token,
+        );
+        self.add_operation(Operation::Delegation(op))
+    }
+
+    /// Add a operation to updating staker memo and commission_rate,
+    /// `commission_limit` can only be tightened
     pub fn add_operation_update_staker(
         &mut self,
         keypair: &XfrKeyPair,
@@ -495,10 +581,13 @@ impl TransactionBuilder {
         td_pubkey: Vec<u8>,
         commission_rate: [u64; 2],
         memo: StakerMemo,
+        commission_limit: Option<CommissionLimit>,
     ) -> Result<&mut Self> {
         let v_id = keypair.get_pk();
 
-        let v = Validator::new_staker(td_pubkey, v_id, commission_rate, memo).c(d!())?;
+        let mut v =
+            Validator::new_staker(td_pubkey, v_id, commission_rate, memo).c(d!())?;
+        v.commission_limit = commission_limit;
         let vaddr = td_addr_to_string(&v.td_addr);
 
         if !is_valid_tendermint_addr(&vaddr) {
@@ -526,8 +615,46 @@ impl TransactionBuilder {
         commission_rate: [u64; 2],
         memo: Option<String>,
     ) -> Result<&mut Self> {
-        let v_id = keypair.get_pk();
+        let mut op = self
+            .gen_staking_op(
+                keypair.get_pk(),
+                amount,
+                vltor_key,
+                td_pubkey,
+                commission_rate,
+                memo,
+            )
+            .c(d!())?;
+        op.sign(keypair).c(d!())?;
+
+        Ok(self.add_operation(Operation::Delegation(op)))
+    }
+
+    /// Like `add_operation_staking`,
+    /// but the operation will be signed by the owner offline.
+    pub fn add_operation_staking_unsigned(
+        &mut self,
+        pubkey: XfrPublicKey,
+        amount: u64,
+        vltor_key: &PrivateKey,
+        td_pubkey: Vec<u8>,
+        commission_rate: [u64; 2],
+        memo: Option<String>,
+    ) -> Result<&mut Self> {
+        self.gen_staking_op(pubkey, amount, vltor_key, td_pubkey, commission_rate, memo)
+            .c(d!())
+            .map(move |op| self.add_operation(Operation::Delegation(op)))
+    }
 
+    fn gen_staking_op(
+        &self,
+        v_id: XfrPublicKey,
+        amount: u64,
+        vltor_key: &PrivateKey,
+        td_pubkey: Vec<u8>,
+        commission_rate: [u64; 2],
+        memo: Option<String>,
+    ) -> Result<DelegationOps> {
         let memo = if memo.is_some() {
             serde_json::from_str(memo.unwrap().as_str()).c(d!())?
         } else {
@@ -541,16 +668,14 @@ impl TransactionBuilder {
             return Err(eg!("invalid pubkey, invalid address"));
         }
 
-        let op = DelegationOps::new(
-            keypair,
+        Ok(DelegationOps::new_unsigned(
+            v_id,
             Some(vltor_key),
             amount,
             vaddr,
             Some(v),
             self.txn.body.no_replay_token,
-        );
-
-        Ok(self.add_operation(Operation::Delegation(op)))
+        ))
     }
 
     /// Add a operation to reduce delegation amount of a findora account.
@@ -575,6 +700,48 @@ impl TransactionBuilder {
         self.add_operation(Operation::Claim(op))
     }
 
+    /// Like `add_operation_claim`,
+    /// but the operation will be signed by the owner offline.
+    pub fn add_operation_claim_unsigned(
+        &mut self,
+        pubkey: XfrPublicKey,
+        am: Option<u64>,
+    ) -> &mut Self {
+        let op = ClaimOps::new_unsigned(pubkey, am, self.txn.body.no_replay_token);
+        self.add_operation(Operation::Claim(op))
+    }
+
+    /// Add a operation to move the delegated FRAs from one validator to another,
+    /// `am: None` means all the FRAs delegated to `from`.
+    pub fn add_operation_redelegation(
+        &mut self,
+        keypair: &XfrKeyPair,
+        from: TendermintAddr,
+        to: TendermintAddr,
+        am: Option<u64>,
+    ) -> &mut Self {
+        let op =
+            ReDelegationOps::new(keypair, from, to, am, self.txn.body.no_replay_token);
+        self.add_operation(Operation::ReDelegation(op))
+    }
+
+    /// Add a operation to release a validator from jail,
+    /// the `keypair` must be the one of the validator
+    pub fn add_operation_unjail(&mut self, keypair: &XfrKeyPair) -> &mut Self {
+        let op = UnjailOps::new(keypair, self.txn.body.no_replay_token);
+        self.add_operation(Operation::Unjail(op))
+    }
+
+    /// Add a operation to turn on/off the auto-compounding of delegation rewards
+    pub fn add_operation_auto_compound(
+        &mut self,
+        keypair: &XfrKeyPair,
+        enable: bool,
+    ) -> &mut Self {
+        let op = AutoCompoundOps::new(keypair, enable, self.txn.body.no_replay_token);
+        self.add_operation(Operation::AutoCompound(op))
+    }
+
     #[allow(missing_docs)]
     pub fn add_operation_fra_distribution(
         &mut self,
@@ -605,6 +772,43 @@ impl TransactionBuilder {
         .map(move |op| self.add_operation(Operation::Governance(op)))
     }
 
+    /// Add a operation to update the penalty rules of governance,
+    /// the new rules will be effective since the specified block height.
+    pub fn add_operation_update_governance_rules(
+        &mut self,
+        kps: &[&XfrKeyPair],
+        h: BlockHeight,
+        rules: RuleSet,
+    ) -> Result<&mut Self> {
+        UpdateGovernanceRulesOps::new(kps, h, rules, self.txn.body.no_replay_token)
+            .c(d!())
+            .map(move |op| self.add_operation(Operation::UpdateGovernanceRules(op)))
+    }
+
+    /// Add a operation to propose new chain parameters,
+    /// they will be activated at the specified block height.
+    pub fn add_operation_propose_chain_params(
+        &mut self,
+        kps: &[&XfrKeyPair],
+        params: ChainParams,
+        h: BlockHeight,
+    ) -> Result<&mut Self> {
+        UpdateChainParamsOps::new_proposal(kps, params, h, self.txn.body.no_replay_token)
+            .c(d!())
+            .map(move |op| self.add_operation(Operation::UpdateChainParams(op)))
+    }
+
+    /// Add a operation to vote for a chain parameters proposal.
+    pub fn add_operation_vote_chain_params(
+        &mut self,
+        kps: &[&XfrKeyPair],
+        proposal_id: ProposalId,
+    ) -> Result<&mut Self> {
+        UpdateChainParamsOps::new_vote(kps, proposal_id, self.txn.body.no_replay_token)
+            .c(d!())
+            .map(move |op| self.add_operation(Operation::UpdateChainParams(op)))
+    }
+
     /// Add a operation update the validator set at specified block height.
     pub fn add_operation_update_validator(
         &mut self,
@@ -727,6 +931,272 @@ pub(crate) fn build_record_and_get_blinds<R: CryptoRng + RngCore>(
     ))
 }
 
+/// How many branches can be visited at most by `CoinSelection::BranchAndBound`.
+pub const BNB_MAX_TRIES: usize = 100_000;
+
+/// Strategies to pick the inputs of a transfer from the owned utxos.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum CoinSelection {
+    /// spend the largest utxos first, to minimise the number of inputs
+    LargestFirst,
+    /// search for a set of utxos whose total equals the target exactly,
+    /// so no change is needed, fall back to `LargestFirst` if not found
+    BranchAndBound,
+    /// pick utxos randomly, so the inputs reveal less about the wallet
+    Random,
+    /// spend the smallest utxos first, dust included,
+    /// to reduce the number of utxos along with the payment
+    Consolidate,
+}
+
+impl Default for CoinSelection {
+    fn default() -> Self {
+        CoinSelection::LargestFirst
+    }
+}
+
+impl std::str::FromStr for CoinSelection {
+    type Err = Box<dyn RucError>;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "largest-first" => Ok(CoinSelection::LargestFirst),
+            "exact" | "branch-and-bound" => Ok(CoinSelection::BranchAndBound),
+            "random" => Ok(CoinSelection::Random),
+            "consolidate" => Ok(CoinSelection::Consolidate),
+            _ => Err(eg!(format!(
+                "unknown coin selection: {}, expect one of \
+                 'largest-first', 'exact', 'random' and 'consolidate'",
+                s
+            ))),
+        }
+    }
+}
+
+/// A spendable utxo considered by coin selection,
+/// all the candidates should be of the same asset type.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CoinCandidate {
+    pub sid: TxoSID,
+    pub amount: u64,
+    /// the amount or the asset type is confidential
+    pub confidential: bool,
+}
+
+/// The result of coin selection.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CoinSelected {
+    /// `(sid, amount to spend)` in ascending order of sid,
+    /// all of them are spent fully except the last one
+    pub inputs: Vec<(TxoSID, u64)>,
+    /// what is left of the last input, it will be refunded to the owner
+    /// as the only change output by `TransferOperationBuilder::balance`
+    pub change: u64,
+}
+
+/// Pick inputs from the owned utxos with a strategy.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CoinSelector {
+    #[allow(missing_docs)]
+    pub strategy: CoinSelection,
+    /// `Some(true)` to spend confidential utxos only,
+    /// `Some(false)` to spend non-confidential ones only,
+    /// `None` to mix them
+    pub confidential: Option<bool>,
+    /// utxos below this amount are dust, they are only spent
+    /// when the others are not enough, except in `Consolidate` mode
+    pub dust_threshold: u64,
+}
+
+impl CoinSelector {
+    #[allow(missing_docs)]
+    pub fn new(strategy: CoinSelection) -> Self {
+        CoinSelector {
+            strategy,
+            ..Default::default()
+        }
+    }
+
+    /// Select inputs from `candidates` whose total is not less than `target`.
+    #[inline(always)]
+    pub fn select(
+        &self,
+        candidates: &[CoinCandidate],
+        target: u64,
+    ) -> Result<CoinSelected> {
+        self.select_with_prng(&mut ChaChaRng::from_entropy(), candidates, target)
+            .c(d!())
+    }
+
+    /// Like `select`, but the randomness comes from `prng`.
+    pub fn select_with_prng<R: RngCore>(
+        &self,
+        prng: &mut R,
+        candidates: &[CoinCandidate],
+        target: u64,
+    ) -> Result<CoinSelected> {
+        if 0 == target {
+            return Ok(CoinSelected::default());
+        }
+
+        let cands = candidates
+            .iter()
+            .filter(|c| 0 < c.amount)
+            .filter(|c| {
+                self.confidential
+                    .map_or(true, |conf| conf == c.confidential)
+            })
+            .copied()
+            .collect::<Vec<_>>();
+
+        let picked = if CoinSelection::Consolidate == self.strategy {
+            self.pick(prng, cands, target)
+        } else {
+            let (normal, dust): (Vec<_>, Vec<_>) = cands
+                .into_iter()
+                .partition(|c| c.amount >= self.dust_threshold);
+            self.pick(prng, normal.clone(), target).or_else(|| {
+                self.pick(prng, normal.into_iter().chain(dust).collect(), target)
+            })
+        };
+
+        picked
+            .c(d!("insufficient balance"))
+            .map(|picked| fill(picked, target))
+    }
+
+    fn pick<R: RngCore>(
+        &self,
+        prng: &mut R,
+        mut cands: Vec<CoinCandidate>,
+        target: u64,
+    ) -> Option<Vec<CoinCandidate>> {
+        // the order of candidates must not depend on their source
+        cands.sort_by_key(|c| c.sid);
+
+        match self.strategy {
+            CoinSelection::LargestFirst => {
+                cands.sort_by(|a, b| b.amount.cmp(&a.amount));
+            }
+            CoinSelection::BranchAndBound => {
+                cands.sort_by(|a, b| b.amount.cmp(&a.amount));
+                if let Some(picked) = exact_match(&cands, target) {
+                    return Some(picked);
+                }
+            }
+            CoinSelection::Random => {
+                // Fisher-Yates
+                for i in (1..cands.len()).rev() {
+                    let j = (prng.next_u64() % (1 + i as u64)) as usize;
+                    cands.swap(i, j);
+                }
+            }
+            CoinSelection::Consolidate => {
+                cands.sort_by_key(|c| c.amount);
+            }
+        }
+
+        let mut total = 0u128;
+        let mut picked = vec![];
+        for c in cands.into_iter() {
+            if total >= target as u128 {
+                break;
+            }
+            total += c.amount as u128;
+            picked.push(c);
+        }
+        alt!(total < target as u128, return None);
+
+        // drop the inputs which are not needed, the smallest first
+        picked.sort_by_key(|c| c.amount);
+        let mut idx = 0;
+        while idx < picked.len() {
+            if total - picked[idx].amount as u128 >= target as u128 {
+                total -= picked[idx].amount as u128;
+                picked.remove(idx);
+            } else {
+                idx += 1;
+            }
+        }
+
+        Some(picked)
+    }
+}
+
+// search for a subset of `cands`(in descending order of amount)
+// whose total equals `target` exactly
+fn exact_match(cands: &[CoinCandidate], target: u64) -> Option<Vec<CoinCandidate>> {
+    fn search(
+        cands: &[CoinCandidate],
+        rest_total: &[u128],
+        idx: usize,
+        remaining: u128,
+        chosen: &mut Vec<usize>,
+        tries: &mut usize,
+    ) -> bool {
+        if 0 == remaining {
+            return true;
+        }
+        *tries += 1;
+        if idx == cands.len() || rest_total[idx] < remaining || BNB_MAX_TRIES < *tries {
+            return false;
+        }
+
+        let am = cands[idx].amount as u128;
+        if am <= remaining {
+            chosen.push(idx);
+            if search(cands, rest_total, 1 + idx, remaining - am, chosen, tries) {
+                return true;
+            }
+            chosen.pop();
+        }
+        search(cands, rest_total, 1 + idx, remaining, chosen, tries)
+    }
+
+    // rest_total[i] = the sum of cands[i..]
+    let mut rest_total = vec![0u128; 1 + cands.len()];
+    for i in (0..cands.len()).rev() {
+        rest_total[i] = rest_total[1 + i] + cands[i].amount as u128;
+    }
+
+    let mut chosen = vec![];
+    let mut tries = 0;
+    alt!(
+        search(
+            cands,
+            &rest_total,
+            0,
+            target as u128,
+            &mut chosen,
+            &mut tries
+        ),
+        Some(chosen.into_iter().map(|i| cands[i]).collect()),
+        None
+    )
+}
+
+// spend the picked utxos in ascending order of sid,
+// removing any of them makes the total less than `target`,
+// so only the last one can be partially spent
+fn fill(mut picked: Vec<CoinCandidate>, target: u64) -> CoinSelected {
+    picked.sort_by_key(|c| c.sid);
+
+    let mut remaining = target;
+    let mut change = 0;
+    let inputs = picked
+        .into_iter()
+        .map(|c| {
+            let spend = c.amount.min(remaining);
+            remaining -= spend;
+            change += c.amount - spend;
+            (c.sid, spend)
+        })
+        .collect();
+
+    CoinSelected { inputs, change }
+}
+
 /// TransferOperationBuilder constructs transfer operations using the factory pattern
 /// Inputs and outputs are added iteratively before being signed by all input record owners
 #[derive(Clone, Serialize, Deserialize, Default)]
@@ -1096,6 +1566,265 @@ impl TransferOperationBuilder {
     }
 }
 
+/// A transaction whose operations are waiting for the signatures
+/// of one or more key holders, it is exchanged between them in JSON format,
+/// the key holders may sign it on offline machines.
+///
+/// NOTE: transaction-level signatures cover the body signatures,
+/// so they should only be added after all the body signatures are collected.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PartiallySignedTxn {
+    /// The transaction to be submitted
+    pub txn: Transaction,
+    /// Signature rules of the non-confidential assets spent in `txn`
+    #[serde(default)]
+    pub sig_rules: Vec<(AssetTypeCode, SignatureRules)>,
+    /// The utxos spent in `txn` and their owner memos,
+    /// an offline signer can review the inputs with them
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub utxos: Vec<(TxoSID, Utxo, Option<OwnerMemo>)>,
+}
+
+impl PartiallySignedTxn {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn new(
+        txn: Transaction,
+        sig_rules: Vec<(AssetTypeCode, SignatureRules)>,
+    ) -> Self {
+        PartiallySignedTxn {
+            txn,
+            sig_rules,
+            utxos: vec![],
+        }
+    }
+
+    /// Attach the data of the spent utxos, see `utxos`.
+    #[inline(always)]
+    pub fn with_utxos(mut self, utxos: Vec<(TxoSID, Utxo, Option<OwnerMemo>)>) -> Self {
+        self.utxos = utxos;
+        self
+    }
+
+    /// Amounts of each asset that `kp` is going to spend,
+    /// all the inputs owned by `kp` must be found in `utxos`.
+    pub fn spent_amounts(
+        &self,
+        kp: &XfrKeyPair,
+    ) -> Result<BTreeMap<AssetTypeCode, u64>> {
+        let mut res = BTreeMap::new();
+
+        for op in self.txn.body.operations.iter() {
+            if let Operation::TransferAsset(trn) = op {
+                for (input, record) in trn
+                    .body
+                    .inputs
+                    .iter()
+                    .zip(trn.body.transfer.inputs.iter())
+                    .filter(|(_, r)| r.public_key == *kp.get_pk_ref())
+                {
+                    let (_, utxo, owner_memo) = self
+                        .utxos
+                        .iter()
+                        .find(|(sid, utxo, _)| {
+                            TxoRef::Absolute(*sid) == *input && utxo.0.record == *record
+                        })
+                        .c(d!("unknown input"))?;
+                    let oar = open_blind_asset_record(&utxo.0.record, owner_memo, kp)
+                        .c(d!())?;
+                    let am = res
+                        .entry(AssetTypeCode {
+                            val: oar.asset_type,
+                        })
+                        .or_insert(0);
+                    *am = am.checked_add(oar.amount).c(d!("overflow"))?;
+                }
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Codes of the non-confidential assets spent in `txn`
+    pub fn input_asset_codes(txn: &Transaction) -> Vec<AssetTypeCode> {
+        let mut codes = txn
+            .body
+            .operations
+            .iter()
+            .filter_map(|op| match op {
+                Operation::TransferAsset(trn) => Some(trn),
+                _ => None,
+            })
+            .flat_map(Self::input_codes)
+            .collect::<Vec<_>>();
+        codes.sort_unstable();
+        codes.dedup();
+        codes
+    }
+
+    /// Sign all the transfer operations in which `kp` is
+    /// an input owner or a weighted signer of the spent assets,
+    /// return the number of new signatures.
+    pub fn sign(&mut self, kp: &XfrKeyPair) -> Result<usize> {
+        let pk = kp.get_pk_ref();
+        let sig_rules = &self.sig_rules;
+        let mut cnt = 0;
+
+        for op in self.txn.body.operations.iter_mut() {
+            match op {
+                Operation::TransferAsset(trn) => {
+                    let signed = trn
+                        .body_signatures
+                        .iter()
+                        .any(|sig| sig.address.key == *pk && sig.input_idx.is_none());
+                    let is_owner =
+                        trn.body.transfer.inputs.iter().any(|r| r.public_key == *pk);
+                    let is_signer = Self::input_codes(trn).into_iter().any(|code| {
+                        Self::get_rules(sig_rules, &code)
+                            .map(|rules| rules.weights.iter().any(|(k, _)| k == pk))
+                            .unwrap_or(false)
+                    });
+
+                    if !signed && (is_owner || is_signer) {
+                        trn.sign(kp);
+                        cnt += 1;
+                    }
+                }
+                // staking operations created by the `*_unsigned` methods
+                Operation::Delegation(op) => {
+                    if op.verify().is_err() && op.sign(kp).is_ok() {
+                        cnt += 1;
+                    }
+                }
+                Operation::Claim(op) => {
+                    if op.verify().is_err() && op.sign(kp).is_ok() {
+                        cnt += 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if 0 == cnt {
+            return Err(eg!("nothing to sign with this key"));
+        }
+
+        Ok(cnt)
+    }
+
+    /// Merge the body signatures collected by another key holder,
+    /// the two transactions must be the same except the signatures.
+    pub fn combine(&mut self, other: &PartiallySignedTxn) -> Result<()> {
+        if self.sig_rules != other.sig_rules
+            || Self::unsigned_body(&self.txn) != Self::unsigned_body(&other.txn)
+        {
+            return Err(eg!("not the same transaction"));
+        }
+
+        for (op, other_op) in self
+            .txn
+            .body
+            .operations
+            .iter_mut()
+            .zip(other.txn.body.operations.iter())
+        {
+            if let (Operation::TransferAsset(trn), Operation::TransferAsset(other_trn)) =
+                (op, other_op)
+            {
+                for sig in other_trn.body_signatures.iter() {
+                    if !trn.body.verify_body_signature(sig) {
+                        return Err(eg!("invalid signature"));
+                    }
+                    if !trn.body_signatures.iter().any(|s| {
+                        s.address == sig.address && s.input_idx == sig.input_idx
+                    }) {
+                        trn.body_signatures.push(sig.clone());
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that all the body signatures are valid,
+    /// all the input owners have signed,
+    /// and the signature rules of the spent assets are satisfied.
+    pub fn check(&self) -> Result<()> {
+        for op in self.txn.body.operations.iter() {
+            match op {
+                Operation::Delegation(op) => {
+                    op.verify().c(d!("delegation is not signed"))?;
+                }
+                Operation::Claim(op) => {
+                    op.verify().c(d!("claim is not signed"))?;
+                }
+                _ => {}
+            }
+
+            if let Operation::TransferAsset(trn) = op {
+                let mut sig_keys = HashSet::new();
+                for sig in trn.body_signatures.iter() {
+                    if !trn.body.verify_body_signature(sig) {
+                        return Err(eg!("invalid signature"));
+                    }
+                    sig_keys.insert(sig.address.key.zei_to_bytes());
+                }
+
+                for record in trn.body.transfer.inputs.iter() {
+                    if !sig_keys.contains(&record.public_key.zei_to_bytes()) {
+                        return Err(eg!(format!(
+                            "signature of the input owner {} is missing",
+                            globutils::wallet::public_key_to_base64(&record.public_key)
+                        )));
+                    }
+                }
+
+                for code in Self::input_codes(trn) {
+                    if let Some(rules) = Self::get_rules(&self.sig_rules, &code) {
+                        rules.check_signature_set(&sig_keys).c(d!(format!(
+                            "signature rules of asset {} are not satisfied",
+                            code.to_base64()
+                        )))?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn input_codes(trn: &TransferAsset) -> Vec<AssetTypeCode> {
+        trn.body
+            .transfer
+            .inputs
+            .iter()
+            .filter_map(|r| r.asset_type.get_asset_type())
+            .map(|val| AssetTypeCode { val })
+            .collect()
+    }
+
+    #[inline(always)]
+    fn get_rules<'a>(
+        sig_rules: &'a [(AssetTypeCode, SignatureRules)],
+        code: &AssetTypeCode,
+    ) -> Option<&'a SignatureRules> {
+        sig_rules.iter().find(|(c, _)| c == code).map(|(_, r)| r)
+    }
+
+    fn unsigned_body(txn: &Transaction) -> TransactionBody {
+        let mut body = txn.body.clone();
+        body.operations.iter_mut().for_each(|op| {
+            if let Operation::TransferAsset(trn) = op {
+                trn.body_signatures.clear();
+            }
+        });
+        body
+    }
+}
+
 #[cfg(test)]
 #[allow(missing_docs)]
 mod tests {
@@ -1380,10 +2109,22 @@ mod tests {
 
         let mut tx2 = TransactionBuilder::from_seq_id(1);
         tx2.add_operation(transfer_to_bob!(txo_sid, bob_kp.get_pk()))
-            .add_fee_relative_auto(&fra_owner_kp)
+            .add_fee_relative_auto(&fra_owner_kp, None)
             .unwrap();
         assert!(tx2.check_fee());
 
+        // paying by priority reaches the target priority
+        let size = serde_json::to_vec(tx2.transaction()).unwrap().len();
+        let priority = tx2.transaction().get_priority(size) * 3;
+        let mut tx2_p = TransactionBuilder::from_seq_id(1);
+        tx2_p
+            .add_operation(transfer_to_bob!(txo_sid, bob_kp.get_pk()))
+            .add_fee_relative_auto(&fra_owner_kp, Some(priority))
+            .unwrap();
+        let size = serde_json::to_vec(tx2_p.transaction()).unwrap().len();
+        assert!(tx2_p.transaction().get_fee() > TX_FEE_MIN);
+        assert!(tx2_p.transaction().get_priority(size) >= priority);
+
         let effect = TxnEffect::compute_effect(tx2.into_transaction()).unwrap();
         let mut block = ledger.start_block().unwrap();
         let tmp_sid = ledger.apply_transaction(&mut block, effect).unwrap();
@@ -1455,4 +2196,194 @@ mod tests {
         let mut block = ledger.start_block().unwrap();
         assert!(ledger.apply_transaction(&mut block, effect).is_err());
     }
+
+    #[test]
+    fn test_fee_of_priority() {
+        assert_eq!(fee_of_priority(0, 1000), 0);
+        assert_eq!(fee_of_priority(1, 1), 1);
+        assert_eq!(fee_of_priority(1, 1000), 1);
+        assert_eq!(fee_of_priority(1, 1001), 2);
+        assert_eq!(fee_of_priority(TX_FEE_MIN, 2000), 2 * TX_FEE_MIN);
+        assert_eq!(fee_of_priority(u64::MAX, usize::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn test_partially_signed_txn() {
+        let mut prng = ChaChaRng::from_entropy();
+        let params = PublicParams::default();
+        let code = AssetTypeCode::gen_random();
+        let alice = XfrKeyPair::generate(&mut prng);
+        let bob = XfrKeyPair::generate(&mut prng);
+        let charlie = XfrKeyPair::generate(&mut prng);
+        let dave = XfrKeyPair::generate(&mut prng);
+
+        let input_template = AssetRecordTemplate::with_no_asset_tracing(
+            100,
+            code.val,
+            NonConfidentialAmount_NonConfidentialAssetType,
+            alice.get_pk(),
+        );
+        let (ba, _, memo) = build_blind_asset_record(
+            &mut prng,
+            &params.pc_gens,
+            &input_template,
+            vec![],
+        );
+        let output_template = AssetRecordTemplate::with_no_asset_tracing(
+            100,
+            code.val,
+            NonConfidentialAmount_NonConfidentialAssetType,
+            dave.get_pk(),
+        );
+        let op = TransferOperationBuilder::new()
+            .add_input(
+                TxoRef::Relative(0),
+                open_blind_asset_record(&ba, &memo, &alice).unwrap(),
+                None,
+                None,
+                100,
+            )
+            .unwrap()
+            .add_output(&output_template, None, None, None)
+            .unwrap()
+            .create(TransferType::Standard)
+            .unwrap()
+            .transaction()
+            .unwrap();
+
+        let mut builder = TransactionBuilder::from_seq_id(1);
+        builder.add_operation(op);
+        let rules = SignatureRules {
+            threshold: 2,
+            weights: vec![(bob.get_pk(), 1), (charlie.get_pk(), 1)],
+        };
+        let mut ptx =
+            PartiallySignedTxn::new(builder.into_transaction(), vec![(code, rules)]);
+        assert!(ptx.check().is_err());
+
+        // unrelated keys can not sign
+        assert!(ptx.sign(&dave).is_err());
+
+        assert_eq!(1, pnk!(ptx.sign(&alice)));
+        assert!(ptx.sign(&alice).is_err());
+        assert!(ptx.check().is_err());
+
+        let mut ptx_bob = ptx.clone();
+        let mut ptx_charlie = ptx.clone();
+        pnk!(ptx_bob.sign(&bob));
+        pnk!(ptx_charlie.sign(&charlie));
+        assert!(ptx_bob.check().is_err());
+
+        pnk!(ptx.combine(&ptx_bob));
+        assert!(ptx.check().is_err());
+        pnk!(ptx.combine(&ptx_charlie));
+        pnk!(ptx.check());
+
+        // combining twice changes nothing
+        pnk!(ptx.combine(&ptx_bob));
+        assert_eq!(3, ptx_signatures(&ptx));
+
+        // different transactions can not be combined
+        let mut other = ptx_bob.clone();
+        other.txn.body.no_replay_token = NoReplayToken::new(&mut prng, 2);
+        assert!(ptx.combine(&other).is_err());
+    }
+
+    #[test]
+    fn test_offline_signing() {
+        let mut prng = ChaChaRng::from_entropy();
+        let alice = XfrKeyPair::generate(&mut prng);
+        let bob = XfrKeyPair::generate(&mut prng);
+
+        let mut builder = TransactionBuilder::from_seq_id(1);
+        builder.add_operation_claim_unsigned(alice.get_pk(), Some(100));
+        let mut ptx = PartiallySignedTxn::new(builder.into_transaction(), vec![]);
+        assert!(ptx.check().is_err());
+
+        assert!(ptx.sign(&bob).is_err());
+        assert_eq!(1, pnk!(ptx.sign(&alice)));
+        pnk!(ptx.check());
+
+        // nothing left to sign
+        assert!(ptx.sign(&alice).is_err());
+    }
+
+    #[test]
+    fn test_coin_selection() {
+        let mut prng = ChaChaRng::from_entropy();
+        let cands = [(1, 50, false), (2, 5, false), (3, 30, true), (4, 20, false)]
+            .iter()
+            .map(|(sid, amount, confidential)| CoinCandidate {
+                sid: TxoSID(*sid),
+                amount: *amount,
+                confidential: *confidential,
+            })
+            .collect::<Vec<_>>();
+        let sids = |sel: &CoinSelected| {
+            sel.inputs.iter().map(|(sid, _)| sid.0).collect::<Vec<_>>()
+        };
+
+        // largest first, and only the last input is partially spent
+        let sel =
+            pnk!(CoinSelector::new(CoinSelection::LargestFirst).select(&cands, 60));
+        assert_eq!(vec![(TxoSID(1), 50), (TxoSID(3), 10)], sel.inputs);
+        assert_eq!(20, sel.change);
+
+        // exact match, no change
+        let sel =
+            pnk!(CoinSelector::new(CoinSelection::BranchAndBound).select(&cands, 55));
+        assert_eq!(vec![1, 2], sids(&sel));
+        assert_eq!(0, sel.change);
+
+        // no exact match, fall back to largest first
+        let sel =
+            pnk!(CoinSelector::new(CoinSelection::BranchAndBound).select(&cands, 104));
+        assert_eq!(vec![1, 2, 3, 4], sids(&sel));
+        assert_eq!(1, sel.change);
+
+        // dust is only spent when the others are not enough
+        let mut selector = CoinSelector::new(CoinSelection::LargestFirst);
+        selector.dust_threshold = 10;
+        assert!(!sids(&pnk!(selector.select(&cands, 100))).contains(&2));
+        assert!(sids(&pnk!(selector.select(&cands, 101))).contains(&2));
+
+        // consolidation spends the smallest ones first, dust included
+        let sel = pnk!(CoinSelector {
+            strategy: CoinSelection::Consolidate,
+            dust_threshold: 10,
+            ..Default::default()
+        }
+        .select(&cands, 25));
+        assert_eq!(vec![2, 4], sids(&sel));
+
+        // do not mix confidential and non-confidential inputs
+        let selector = CoinSelector {
+            confidential: Some(false),
+            ..Default::default()
+        };
+        assert!(!sids(&pnk!(selector.select(&cands, 70))).contains(&3));
+        assert!(selector.select(&cands, 80).is_err());
+
+        // random selection always covers the target, without unneeded inputs
+        let selector = CoinSelector::new(CoinSelection::Random);
+        for _ in 0..20 {
+            let sel = pnk!(selector.select_with_prng(&mut prng, &cands, 45));
+            assert_eq!(45, sel.inputs.iter().map(|(_, am)| am).sum::<u64>());
+            assert!(sel.inputs.iter().all(|(_, am)| 0 < *am));
+        }
+
+        assert!(selector.select(&cands, 106).is_err());
+    }
+
+    fn ptx_signatures(ptx: &PartiallySignedTxn) -> usize {
+        ptx.txn
+            .body
+            .operations
+            .iter()
+            .map(|op| match op {
+                Operation::TransferAsset(trn) => trn.body_signatures.len(),
+                _ => 0,
+            })
+            .sum()
+    }
 }
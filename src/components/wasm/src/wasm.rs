@@ -37,9 +37,9 @@ use {
     globutils::{wallet, HashOf},
     ledger::{
         data_model::{
-            gen_random_keypair, AssetTypeCode, AuthenticatedTransaction, Operation,
-            TransferType, TxOutput, ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY,
-            BLACK_HOLE_PUBKEY_STAKING, TX_FEE_MIN,
+            gen_random_keypair, AssetTypeCode, AuthenticatedTransaction,
+            AuthenticatedUtxoStatus, Operation, TransferType, TxOutput, ASSET_TYPE_FRA,
+            BLACK_HOLE_PUBKEY, BLACK_HOLE_PUBKEY_STAKING, TX_FEE_MIN,
         },
         staking::{
             td_addr_to_bytes, PartialUnDelegation, TendermintAddr,
@@ -127,6 +127,32 @@ pub fn verify_authenticated_txn(
     Ok(authenticated_txn.is_valid(state_commitment))
 }
 
+#[wasm_bindgen]
+/// Given a serialized state commitment and utxo status, returns true if the spent status
+/// is proven by the state commitment and false otherwise. After the bitmap merkle root is
+/// in the state commitment, the proof is a short merkle path instead of the whole bitmap.
+/// @param {string} state_commitment - String representing the state commitment.
+/// @param {string} utxo_status - String representing the utxo status.
+/// @see {@link module:Network~Network#getStateCommitment|Network.getStateCommitment}
+/// for instructions on fetching a ledger state commitment.
+/// @throws Will throw an error if the state commitment or the utxo status fails to deserialize.
+pub fn verify_authenticated_utxo_status(
+    state_commitment: String,
+    utxo_status: String,
+) -> Result<bool, JsValue> {
+    let utxo_status = serde_json::from_str::<AuthenticatedUtxoStatus>(&utxo_status)
+        .c(d!())
+        .map_err(|e| {
+            JsValue::from_str(&format!("Could not deserialize utxo status: {}", e))
+        })?;
+    let state_commitment = serde_json::from_str::<HashOf<_>>(&state_commitment)
+        .c(d!())
+        .map_err(|e| {
+            JsValue::from_str(&format!("Could not deserialize state commitment: {}", e))
+        })?;
+    Ok(utxo_status.is_valid(state_commitment))
+}
+
 #[wasm_bindgen]
 /// ...
 pub fn get_null_pk() -> XfrPublicKey {
@@ -228,14 +254,27 @@ impl FeeInputs {
 
 #[wasm_bindgen]
 impl TransactionBuilder {
-    /// @param am: amount to pay
     /// @param kp: owner's XfrKeyPair
     pub fn add_fee_relative_auto(
         mut self,
         kp: XfrKeyPair,
     ) -> Result<TransactionBuilder, JsValue> {
         self.transaction_builder
-            .add_fee_relative_auto(&kp)
+            .add_fee_relative_auto(&kp, None)
+            .c(d!())
+            .map_err(error_to_jsvalue)?;
+        Ok(self)
+    }
+
+    /// @param kp: owner's XfrKeyPair
+    /// @param priority: the target fee per 1000 bytes, see the `fee_estimate` route of the query server
+    pub fn add_fee_relative_auto_with_priority(
+        mut self,
+        kp: XfrKeyPair,
+        priority: u64,
+    ) -> Result<TransactionBuilder, JsValue> {
+        self.transaction_builder
+            .add_fee_relative_auto(&kp, Some(priority))
             .c(d!())
             .map_err(error_to_jsvalue)?;
         Ok(self)
@@ -290,6 +329,14 @@ impl TransactionBuilder {
         }
     }
 
+    /// Set the minimum fee paid by `add_fee` and `add_fee_relative_auto`,
+    /// the default value is `fra_get_minimal_fee()`.
+    /// @param {BigInt} fee_min - The `tx_fee_min` of the chain parameters, see the `fee_estimate` route of the query server.
+    pub fn set_fee_min(mut self, fee_min: u64) -> Self {
+        self.transaction_builder.set_fee_min(fee_min);
+        self
+    }
+
     /// Wraps around TransactionBuilder to add an asset definition operation to a transaction builder instance.
     /// @example <caption> Error handling </caption>
     /// try {
@@ -1336,7 +1383,8 @@ pub fn fra_get_asset_code() -> String {
     .to_base64()
 }
 
-/// Fee smaller than this value will be denied.
+/// Fee smaller than this value will be denied,
+/// it is the default value of the `tx_fee_min` chain parameter.
 #[wasm_bindgen]
 pub fn fra_get_minimal_fee() -> u64 {
     TX_FEE_MIN
@@ -6,10 +6,13 @@ use {
         Credential as PlatformCredential,
     },
     globutils::{wallet, HashOf},
-    ledger::data_model::{
-        AssetRules as PlatformAssetRules, AssetType as PlatformAssetType,
-        AuthenticatedUtxo, SignatureRules as PlatformSignatureRules, TxOutput,
-        TxoRef as PlatformTxoRef, TxoSID,
+    ledger::{
+        data_model::{
+            AssetRules as PlatformAssetRules, AssetType as PlatformAssetType,
+            AuthenticatedUtxo, SignatureRules as PlatformSignatureRules, TxOutput,
+            TxoRef as PlatformTxoRef, TxoSID,
+        },
+        light_client::{LightClient as PlatformLightClient, SignedLightHeader},
     },
     rand_chacha::ChaChaRng,
     rand_core::SeedableRng,
@@ -145,6 +148,77 @@ impl AuthenticatedAssetRecord {
     }
 }
 
+/// A light client which tracks the validator-signed headers from a checkpointed feed,
+/// the asset records returned by a node can be verified against the trusted state
+/// commitments without trusting the node.
+#[wasm_bindgen]
+pub struct LightClient {
+    pub(crate) client: PlatformLightClient,
+}
+
+#[wasm_bindgen]
+impl LightClient {
+    /// Start from a trusted checkpoint.
+    /// @param {JsValue} checkpoint - JSON-encoded checkpoint, which contains a height,
+    /// the state commitment and the validator set at that height.
+    /// @throws Will throw an error if the checkpoint is invalid.
+    pub fn new(checkpoint: &JsValue) -> Result<LightClient, JsValue> {
+        let cp = checkpoint.into_serde().c(d!()).map_err(error_to_jsvalue)?;
+        PlatformLightClient::new(cp)
+            .map(|client| LightClient { client })
+            .map_err(error_to_jsvalue)
+    }
+
+    /// Verify the header of the next height, signed by more than 2/3 of the voting power
+    /// of the known validators, then trust the state commitment in it.
+    /// @param {JsValue} header - JSON-encoded signed header from the feed, every validator
+    /// serves its own signature at the `light_header/{height}` route of its query server,
+    /// the signatures can be collected with `merge_light_headers`.
+    /// @throws Will throw an error if the header can not be verified.
+    pub fn update(&mut self, header: &JsValue) -> Result<(), JsValue> {
+        let header = header
+            .into_serde::<SignedLightHeader>()
+            .c(d!())
+            .map_err(error_to_jsvalue)?;
+        self.client.update(&header).map_err(error_to_jsvalue)
+    }
+
+    /// Collect the signatures of the same header from two validators.
+    /// @param {JsValue} a - JSON-encoded signed header.
+    /// @param {JsValue} b - JSON-encoded signed header.
+    /// @throws Will throw an error if the headers are different.
+    pub fn merge_light_headers(a: &JsValue, b: &JsValue) -> Result<JsValue, JsValue> {
+        let mut a = a
+            .into_serde::<SignedLightHeader>()
+            .c(d!())
+            .map_err(error_to_jsvalue)?;
+        let b = b
+            .into_serde::<SignedLightHeader>()
+            .c(d!())
+            .map_err(error_to_jsvalue)?;
+        a.merge(&b).map_err(error_to_jsvalue)?;
+        JsValue::from_serde(&a).c(d!()).map_err(error_to_jsvalue)
+    }
+
+    /// The height of the latest trusted header.
+    pub fn height(&self) -> u64 {
+        self.client.get_height()
+    }
+
+    /// Verify an asset record against the trusted state commitments,
+    /// returns the height of the state commitment used.
+    /// @param {AuthenticatedAssetRecord} record - asset record fetched from a ledger server.
+    /// @throws Will throw an error if the proofs are invalid or the state commitment is not trusted.
+    pub fn verify_asset_record(
+        &self,
+        record: &AuthenticatedAssetRecord,
+    ) -> Result<u64, JsValue> {
+        self.client
+            .verify_utxo(record.get_auth_record_ref())
+            .map_err(error_to_jsvalue)
+    }
+}
+
 #[wasm_bindgen]
 /// This object represents an asset record owned by a ledger key pair.
 /// @see {@link module:Findora-Wasm.open_client_asset_record|open_client_asset_record} for information about how to decrypt an encrypted asset
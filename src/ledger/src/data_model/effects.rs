@@ -8,10 +8,13 @@ use {
         staking::{
             self,
             ops::{
-                claim::ClaimOps, delegation::DelegationOps,
-                fra_distribution::FraDistributionOps, governance::GovernanceOps,
-                undelegation::UnDelegationOps, update_staker::UpdateStakerOps,
-                update_validator::UpdateValidatorOps,
+                auto_compound::AutoCompoundOps, claim::ClaimOps,
+                delegation::DelegationOps, fra_distribution::FraDistributionOps,
+                governance::GovernanceOps, redelegation::ReDelegationOps,
+                undelegation::UnDelegationOps, unjail::UnjailOps,
+                update_chain_params::UpdateChainParamsOps,
+                update_governance_rules::UpdateGovernanceRulesOps,
+                update_staker::UpdateStakerOps, update_validator::UpdateValidatorOps,
             },
         },
     },
@@ -75,6 +78,9 @@ pub struct TxnEffect {
     pub asset_types_involved: HashSet<AssetTypeCode>,
     /// Memo updates
     pub memo_updates: Vec<(AssetTypeCode, XfrPublicKey, Memo)>,
+    /// Owners of new non-confidential-type outputs, they must hold
+    /// credentials if the asset type has a credential requirement
+    pub credential_subjects: Vec<(AssetTypeCode, XfrPublicKey)>,
 
     /// Staking operations
     pub delegations: Vec<DelegationOps>,
@@ -83,8 +89,18 @@ pub struct TxnEffect {
     /// Staking operations
     pub claims: Vec<ClaimOps>,
     /// Staking operations
+    pub redelegations: Vec<ReDelegationOps>,
+    /// Staking operations
+    pub auto_compounds: Vec<AutoCompoundOps>,
+    /// Staking operations
+    pub unjails: Vec<UnjailOps>,
+    /// Staking operations
     pub governances: Vec<GovernanceOps>,
     /// Staking operations
+    pub update_governance_rules: Vec<UpdateGovernanceRulesOps>,
+    /// Staking operations
+    pub update_chain_params: Vec<UpdateChainParamsOps>,
+    /// Staking operations
     pub update_validators: HashMap<staking::BlockHeight, UpdateValidatorOps>,
     /// Staking operations
     pub fra_distributions: Vec<FraDistributionOps>,
@@ -143,6 +159,21 @@ impl TxnEffect {
                     i.verify().c(d!())?;
                     te.claims.push(i.clone());
                 }
+                Operation::ReDelegation(i) => {
+                    check_nonce!(i);
+                    i.verify().c(d!())?;
+                    te.redelegations.push(i.clone());
+                }
+                Operation::AutoCompound(i) => {
+                    check_nonce!(i);
+                    i.verify().c(d!())?;
+                    te.auto_compounds.push(i.clone());
+                }
+                Operation::Unjail(i) => {
+                    check_nonce!(i);
+                    i.verify().c(d!())?;
+                    te.unjails.push(i.clone());
+                }
                 Operation::Delegation(i) => {
                     check_nonce!(i);
                     i.verify().c(d!())?;
@@ -182,6 +213,14 @@ impl TxnEffect {
                     check_nonce!(i);
                     te.governances.push(i.clone());
                 }
+                Operation::UpdateGovernanceRules(i) => {
+                    check_nonce!(i);
+                    te.update_governance_rules.push(i.clone());
+                }
+                Operation::UpdateChainParams(i) => {
+                    check_nonce!(i);
+                    te.update_chain_params.push(i.clone());
+                }
                 Operation::FraDistribution(i) => {
                     check_nonce!(i);
                     te.fra_distributions.push(i.clone());
@@ -294,6 +333,9 @@ impl TxnEffect {
                 return Err(eg!());
             }
 
+            self.credential_subjects
+                .push((code, output.record.public_key));
+
             if let XfrAmount::NonConfidential(amt) = output.record.amount {
                 let issuance_amount = self.issuance_amounts.entry(code).or_insert(0);
                 *issuance_amount = (*issuance_amount).checked_add(amt).c(d!())?;
@@ -478,6 +520,8 @@ impl TxnEffect {
             if let Some(out_code) = out.asset_type.get_asset_type() {
                 self.asset_types_involved
                     .insert(AssetTypeCode { val: out_code });
+                self.credential_subjects
+                    .push((AssetTypeCode { val: out_code }, out.public_key));
             }
             self.txos.push(Some(TxOutput {
                 id: None,
@@ -678,6 +722,18 @@ impl BlockEffect {
             i.check_run(&mut self.staking_simulator).c(d!())?;
         }
 
+        for i in txn_effect.redelegations.iter() {
+            i.check_run(&mut self.staking_simulator).c(d!())?;
+        }
+
+        for i in txn_effect.auto_compounds.iter() {
+            i.check_run(&mut self.staking_simulator).c(d!())?;
+        }
+
+        for i in txn_effect.unjails.iter() {
+            i.check_run(&mut self.staking_simulator).c(d!())?;
+        }
+
         for i in txn_effect.update_validators.values() {
             i.check_run(&mut self.staking_simulator).c(d!())?;
         }
@@ -686,6 +742,14 @@ impl BlockEffect {
             i.check_run(&mut self.staking_simulator).c(d!())?;
         }
 
+        for i in txn_effect.update_governance_rules.iter() {
+            i.check_run(&mut self.staking_simulator).c(d!())?;
+        }
+
+        for i in txn_effect.update_chain_params.iter() {
+            i.check_run(&mut self.staking_simulator).c(d!())?;
+        }
+
         for i in txn_effect.fra_distributions.iter() {
             i.check_run(&mut self.staking_simulator, &txn_effect.txn)
                 .c(d!())?;
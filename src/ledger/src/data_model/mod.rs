@@ -14,19 +14,27 @@ pub use effects::{BlockEffect, TxnEffect};
 use {
     crate::staking::{
         ops::{
-            claim::ClaimOps, delegation::DelegationOps,
+            auto_compound::AutoCompoundOps, claim::ClaimOps, delegation::DelegationOps,
             fra_distribution::FraDistributionOps, governance::GovernanceOps,
-            mint_fra::MintFraOps, undelegation::UnDelegationOps,
+            mint_fra::MintFraOps, redelegation::ReDelegationOps,
+            undelegation::UnDelegationOps, unjail::UnjailOps,
+            update_chain_params::UpdateChainParamsOps,
+            update_governance_rules::UpdateGovernanceRulesOps,
             update_staker::UpdateStakerOps, update_validator::UpdateValidatorOps,
         },
         Staking,
     },
     __trash__::{Policy, PolicyGlobals, TxnPolicyData},
-    bitmap::SparseMap,
+    bitmap::{BitProof, SparseMap},
+    credentials::{
+        credential_verify, credential_verify_commitment, CredCommitment,
+        CredIssuerPublicKey, CredPoK, CredRevealProof,
+    },
     cryptohash::{sha256::Digest as BitDigest, HashValue},
     fbnc::NumKey,
     globutils::{HashOf, ProofOf, Serialized, SignatureOf},
     lazy_static::lazy_static,
+    merkle_tree::ConsistencyProof,
     rand::Rng,
     rand_chacha::{rand_core, ChaChaRng},
     rand_core::{CryptoRng, RngCore, SeedableRng},
@@ -529,6 +537,11 @@ pub struct AssetRules {
     pub max_units: Option<u64>,
     /// Decimals: default to FRA_DECIMALS
     pub decimals: u8,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_default")]
+    /// Credential requirement: every owner of a new output of this asset must
+    ///   attach a valid `CredentialProof` to the transaction.
+    pub credential_requirement: Option<CredentialRequirement>,
 }
 impl Default for AssetRules {
     #[inline(always)]
@@ -540,6 +553,7 @@ impl Default for AssetRules {
             max_units: None,
             transfer_multisig_rules: None,
             decimals: FRA_DECIMALS,
+            credential_requirement: None,
         }
     }
 }
@@ -592,6 +606,16 @@ impl AssetRules {
         self.decimals = decimals;
         Ok(self)
     }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn set_credential_requirement(
+        &mut self,
+        requirement: Option<CredentialRequirement>,
+    ) -> &mut Self {
+        self.credential_requirement = requirement;
+        self
+    }
 }
 
 #[allow(missing_docs)]
@@ -666,14 +690,57 @@ pub struct CustomAssetPolicy {
     policy: Vec<u8>, // serialized policy, underlying form TBD.
 }
 
-#[allow(missing_docs)]
-#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
-pub struct CredentialProofKey([u8; 16]);
+/// Credentials that must be held by the owners of an asset,
+/// eg. a KYC attestation required by a compliance-gated token.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CredentialRequirement {
+    /// The only credential issuer accepted by this asset
+    pub issuer_pub_key: CredIssuerPublicKey,
+    /// Attributes that must be revealed, with their exact values
+    pub required_attrs: Vec<(String, Vec<u8>)>,
+}
 
-#[allow(missing_docs)]
-#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+/// A proof that `owner` holds a credential satisfying some `CredentialRequirement`.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct CredentialProof {
-    pub key: CredentialProofKey,
+    /// The ledger address the credential commitment is bound to
+    pub owner: XfrPublicKey,
+    /// Commitment to the credential of `owner`
+    pub commitment: CredCommitment,
+    /// Proof that `commitment` is bound to `owner`
+    pub commitment_pok: CredPoK,
+    /// Proof that `commitment` reveals the required attributes
+    pub reveal_proof: CredRevealProof,
+}
+
+impl CredentialProof {
+    /// A credential proof satisfies a requirement iff:
+    /// 1) The commitment is signed by the required issuer and bound to `owner`
+    /// 2) The commitment reveals exactly the required attributes
+    pub fn verify(&self, requirement: &CredentialRequirement) -> Result<()> {
+        // 1)
+        credential_verify_commitment(
+            &requirement.issuer_pub_key,
+            &self.commitment,
+            &self.commitment_pok,
+            self.owner.as_bytes(),
+        )
+        .c(d!("invalid credential commitment"))?;
+
+        // 2)
+        let attrs = requirement
+            .required_attrs
+            .iter()
+            .map(|(k, v)| (k.clone(), v.as_slice()))
+            .collect::<Vec<_>>();
+        credential_verify(
+            &requirement.issuer_pub_key,
+            &attrs,
+            &self.commitment,
+            &self.reveal_proof,
+        )
+        .c(d!("invalid credential reveal proof"))
+    }
 }
 
 #[derive(
@@ -832,6 +899,27 @@ impl NoReplayToken {
     }
 }
 
+/// Default value of `ChainParams::tx_window_width`,
+/// in the unit of non-empty blocks.
+pub const TRANSACTION_WINDOW_WIDTH: u64 = 128;
+
+/// The max value of `ChainParams::tx_window_width`.
+pub const TRANSACTION_WINDOW_WIDTH_MAX: u64 = 64 * 1024;
+
+/// The range of `seq_id`(both ends included) that
+/// a new `NoReplayToken` can use at present.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct NoReplayWindow {
+    /// the oldest `seq_id` that will be accepted,
+    /// a transaction using it must be committed in the next block
+    pub min_seq_id: u64,
+    /// equal to the count of non-empty blocks,
+    /// using it gives a transaction the longest lifetime
+    pub max_seq_id: u64,
+    /// the width of the window, in the unit of non-empty blocks
+    pub width: u64,
+}
+
 /// The inner data of Transfer Operation
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct TransferAssetBody {
@@ -1223,10 +1311,20 @@ pub enum Operation {
     UnDelegation(Box<UnDelegationOps>),
     /// Claim rewards
     Claim(ClaimOps),
+    /// Move delegated FRA token from one validator to another
+    ReDelegation(ReDelegationOps),
+    /// Turn on/off the auto-compounding of delegation rewards
+    AutoCompound(AutoCompoundOps),
+    /// Release a validator from jail
+    Unjail(UnjailOps),
     /// Update initial validator list
     UpdateValidator(UpdateValidatorOps),
     /// Findora network goverance operation
     Governance(GovernanceOps),
+    /// Update the penalty rules of governance
+    UpdateGovernanceRules(UpdateGovernanceRulesOps),
+    /// Propose or vote for new chain parameters
+    UpdateChainParams(UpdateChainParamsOps),
     /// Update FRA distribution
     FraDistribution(FraDistributionOps),
     /// Coinbase operation
@@ -1247,6 +1345,15 @@ fn set_no_replay_token(op: &mut Operation, no_replay_token: NoReplayToken) {
         Operation::Claim(i) => {
             i.set_nonce(no_replay_token);
         }
+        Operation::ReDelegation(i) => {
+            i.set_nonce(no_replay_token);
+        }
+        Operation::AutoCompound(i) => {
+            i.set_nonce(no_replay_token);
+        }
+        Operation::Unjail(i) => {
+            i.set_nonce(no_replay_token);
+        }
         Operation::FraDistribution(i) => {
             i.set_nonce(no_replay_token);
         }
@@ -1256,6 +1363,12 @@ fn set_no_replay_token(op: &mut Operation, no_replay_token: NoReplayToken) {
         Operation::Governance(i) => {
             i.set_nonce(no_replay_token);
         }
+        Operation::UpdateGovernanceRules(i) => {
+            i.set_nonce(no_replay_token);
+        }
+        Operation::UpdateChainParams(i) => {
+            i.set_nonce(no_replay_token);
+        }
         Operation::UpdateMemo(i) => i.body.no_replay_token = no_replay_token,
         _ => {}
     }
@@ -1469,6 +1582,54 @@ impl AuthenticatedBlock {
     }
 }
 
+/// Proofs that the merkle trees of a historical state commitment
+/// are prefixes of the ones of a later state commitment.
+#[allow(missing_docs)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MerkleConsistencyProof {
+    pub old_state_commitment_data: StateCommitmentData,
+    pub new_state_commitment_data: StateCommitmentData,
+    pub txn_merkle_proof: ConsistencyProof,
+    pub block_merkle_proof: ConsistencyProof,
+}
+
+impl MerkleConsistencyProof {
+    /// A consistency proof is valid if
+    /// 1) Both state commitment data hash to the state commitments passed in
+    /// 2) The roots in both proofs match the values in the state commitment data
+    /// 3) Both proofs are valid
+    pub fn is_valid(
+        &self,
+        old_state_commitment: HashOf<Option<StateCommitmentData>>,
+        new_state_commitment: HashOf<Option<StateCommitmentData>>,
+    ) -> bool {
+        //1)
+        if old_state_commitment != self.old_state_commitment_data.compute_commitment()
+            || new_state_commitment
+                != self.new_state_commitment_data.compute_commitment()
+        {
+            return false;
+        }
+
+        //2)
+        let (old, new) = (
+            &self.old_state_commitment_data,
+            &self.new_state_commitment_data,
+        );
+        if old.transaction_merkle_commitment != self.txn_merkle_proof.old_root
+            || new.transaction_merkle_commitment != self.txn_merkle_proof.new_root
+            || old.block_merkle != self.block_merkle_proof.old_root
+            || new.block_merkle != self.block_merkle_proof.new_root
+        {
+            return false;
+        }
+
+        //3)
+        self.txn_merkle_proof.is_valid_proof()
+            && self.block_merkle_proof.is_valid_proof()
+    }
+}
+
 #[allow(missing_docs)]
 #[derive(Serialize, Clone, Deserialize)]
 pub struct AuthenticatedUtxoStatus {
@@ -1477,6 +1638,9 @@ pub struct AuthenticatedUtxoStatus {
     pub state_commitment_data: StateCommitmentData,
     pub utxo_map_bytes: Option<Vec<u8>>, // BitMap only needed for proof if the txo_sid exists
     pub state_commitment: HashOf<Option<StateCommitmentData>>,
+    /// Used instead of `utxo_map_bytes` if the state commitment has a bitmap merkle root
+    #[serde(default)]
+    pub utxo_map_proof: Option<BitProof>,
 }
 
 impl AuthenticatedUtxoStatus {
@@ -1486,6 +1650,9 @@ impl AuthenticatedUtxoStatus {
     /// 3) For txos that don't exist, simply show that the utxo_sid greater than max_sid
     /// 4) The status matches the bit stored in the bitmap
     /// 5) The bitmap checksum matches digest in state commitment data
+    ///
+    /// If the state commitment data has a bitmap merkle root,
+    /// 4) and 5) are replaced by the verification of `utxo_map_proof` against it.
     pub fn is_valid(
         &self,
         state_commitment: HashOf<Option<StateCommitmentData>>,
@@ -1505,6 +1672,16 @@ impl AuthenticatedUtxoStatus {
             return utxo_sid >= state_commitment_data.txo_count;
         }
 
+        if let Some(root) = state_commitment_data.bitmap_merkle.as_ref() {
+            return self.utxo_map_proof.as_ref().map_or(false, |proof| {
+                proof.bit == utxo_sid
+                    && proof
+                        .verify(root)
+                        .map(|unspent| unspent == (self.status == UtxoStatus::Unspent))
+                        .unwrap_or(false)
+            });
+        }
+
         // If the txo exists, the proof must also contain a bitmap
         let utxo_map = SparseMap::new(&self.utxo_map_bytes.as_ref().unwrap()).unwrap();
 
@@ -1585,8 +1762,14 @@ lazy_static! {
 }
 
 /// see [**mainnet-v0.1 defination**](https://www.notion.so/findora/Transaction-Fees-Analysis-d657247b70f44a699d50e1b01b8a2287)
+///
+/// Default value of `ChainParams::tx_fee_min`.
 pub const TX_FEE_MIN: u64 = 1_0000;
 
+/// The priority of a transaction is measured in fee per `PRIORITY_SIZE_UNIT` bytes,
+/// a per-byte rate would truncate the common fees to a few distinct values.
+pub const PRIORITY_SIZE_UNIT: u64 = 1000;
+
 impl Transaction {
     #[inline(always)]
     #[allow(missing_docs)]
@@ -1599,8 +1782,8 @@ impl Transaction {
 
     /// All-in-one checker
     #[inline(always)]
-    pub fn valid_in_abci(&self) -> bool {
-        self.check_fee() && !self.is_coinbase_tx()
+    pub fn valid_in_abci(&self, fee_min: u64) -> bool {
+        self.check_fee_with_min(fee_min) && !self.is_coinbase_tx()
     }
 
     /// A simple fee checker
@@ -1611,7 +1794,14 @@ impl Transaction {
     /// - Fee destination == BLACK_HOLE_PUBKEY
     /// - A transaction with an `Operation` of defining/issuing FRA need NOT fee
     /// - A transaction with all addresses of inputs equal to BLACK_HOLE_PUBKEY need NOT fee
+    #[inline(always)]
     pub fn check_fee(&self) -> bool {
+        self.check_fee_with_min(TX_FEE_MIN)
+    }
+
+    /// Same as `check_fee`, but with a custom minimum fee,
+    /// usually it is the `tx_fee_min` of current chain parameters.
+    pub fn check_fee_with_min(&self, fee_min: u64) -> bool {
         // This method can not completely solve the DOS risk,
         // we should further limit the number of txo[s] in every operation.
         //
@@ -1626,7 +1816,7 @@ impl Transaction {
                                 && *BLACK_HOLE_PUBKEY == o.record.public_key
                             {
                                 if let XfrAmount::NonConfidential(am) = o.record.amount {
-                                    if am > (TX_FEE_MIN - 1) {
+                                    if am >= fee_min {
                                         return true;
                                     }
                                 }
@@ -1649,6 +1839,45 @@ impl Transaction {
             })
     }
 
+    /// The amount of FRA paid to `BLACK_HOLE_PUBKEY` as fee.
+    pub fn get_fee(&self) -> u64 {
+        self.body
+            .operations
+            .iter()
+            .filter_map(|op| {
+                if let Operation::TransferAsset(ref x) = op {
+                    Some(x.body.outputs.iter())
+                } else {
+                    None
+                }
+            })
+            .flatten()
+            .filter(|o| *BLACK_HOLE_PUBKEY == o.record.public_key)
+            .filter_map(|o| {
+                if let (
+                    XfrAssetType::NonConfidential(ty),
+                    XfrAmount::NonConfidential(am),
+                ) = (o.record.asset_type, o.record.amount)
+                {
+                    alt!(ty == ASSET_TYPE_FRA, Some(am), None)
+                } else {
+                    None
+                }
+            })
+            .sum()
+    }
+
+    /// The priority in the fee market, namely the fee paid per
+    /// `PRIORITY_SIZE_UNIT` bytes, `size` is the length of the serialized transaction.
+    ///
+    /// The result is rounded up, so any non-zero fee gets a non-zero priority.
+    #[inline(always)]
+    pub fn get_priority(&self, size: usize) -> u64 {
+        let size = (size as u128).max(1);
+        let p = (self.get_fee() as u128 * PRIORITY_SIZE_UNIT as u128 + size - 1) / size;
+        p.min(u64::MAX as u128) as u64
+    }
+
     /// findora hash
     #[inline(always)]
     pub fn hash(&self, id: TxnSID) -> HashOf<(TxnSID, Transaction)> {
@@ -1817,6 +2046,11 @@ pub struct StateCommitmentData {
     #[serde(default)]
     #[serde(skip_serializing_if = "is_default")]
     pub staking: Option<HashOf<Staking>>,
+    /// The merkle root of the utxo_map, see `BitMap::compute_merkle_root`,
+    /// only present after the `BitmapMerkleRoot` upgrade
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_default")]
+    pub bitmap_merkle: Option<BitDigest>,
 }
 
 impl StateCommitmentData {
@@ -9,11 +9,15 @@
 #[macro_use]
 pub mod data_model;
 
+pub mod light_client;
+
 pub mod staking;
 
 #[cfg(not(target_arch = "wasm32"))]
 pub mod store;
 
+pub mod upgrade;
+
 use ruc::*;
 const LSSED_VAR: &str = "LEDGER_STATE_SNAPSHOT_ENTRIES_DIR";
 lazy_static::lazy_static! {
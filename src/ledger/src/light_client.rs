@@ -0,0 +1,487 @@
+//!
+//! # Light Client
+//!
+//! Track the headers signed by validators from a checkpointed feed,
+//! then the state commitments in them can be used to verify the proofs
+//! returned by a node, such as `AuthenticatedUtxo`, without trusting the node.
+//!
+//! A client starts from a trusted [Checkpoint](self::Checkpoint),
+//! and follows the headers one height after another,
+//! every header must be signed by more than 2/3 of the voting power
+//! of the validator set known at that time, and it commits to the hash of
+//! the validator set for the next header.
+//!
+//! ## The feed
+//!
+//! The signed message is `LightHeader::to_bytes()`, not a tendermint vote,
+//! so the headers are produced by the nodes instead of tendermint:
+//!
+//! - after committing a height, every node stores the `LightHeader` of it,
+//!   which is derived from the chain state only,
+//!   and a node which has a tendermint key signs it with `LightHeader::sign`;
+//! - the headers are served by the `light_header/{height}` route of the query server,
+//!   every node only returns its own signature;
+//! - a relayer, or the client itself, fetches the header of the same height
+//!   from several validators, and collects their signatures
+//!   with `SignedLightHeader::merge`.
+//!
+//! This module has no disk or network dependencies,
+//! it is shared by the wasm SDK and the native tools.
+//!
+
+use {
+    crate::{
+        data_model::{AuthenticatedUtxo, StateCommitmentData},
+        staking::{BlockHeight, Staking},
+    },
+    ed25519_dalek::{PublicKey, Signature, Signer, Verifier},
+    globutils::HashOf,
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        convert::TryFrom,
+    },
+    tendermint::PrivateKey,
+};
+
+/// How many trusted state commitments will be kept at most.
+pub const MAX_TRUSTED_COMMITMENTS: usize = 1024;
+
+/// The prefix of the signed message of a header,
+/// the tendermint key of a validator is reused to sign the headers,
+/// this tag keeps the signatures from being valid as any other message,
+/// such as a tendermint vote.
+pub const LIGHT_HEADER_DOMAIN: &[u8] = b"findora-light-header-v1";
+
+type Commitment = HashOf<Option<StateCommitmentData>>;
+
+/// A validator known by the light client.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct LightValidator {
+    /// ed25519 pubkey in the context of tendermint
+    pub td_pubkey: Vec<u8>,
+    /// vote power
+    pub power: u64,
+}
+
+/// The trusted starting point of a light client,
+/// it should be obtained from a source other than the node to be verified.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Checkpoint {
+    #[allow(missing_docs)]
+    pub height: BlockHeight,
+    /// the state commitment after this height
+    pub state_commitment: Commitment,
+    /// the validator set after this height
+    pub validators: Vec<LightValidator>,
+}
+
+/// A header in the feed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LightHeader {
+    #[allow(missing_docs)]
+    pub height: BlockHeight,
+    /// the state commitment(aka app hash) after this height
+    pub state_commitment: Commitment,
+    /// hash of the validator set after this height
+    pub next_validators_hash: HashOf<Vec<LightValidator>>,
+}
+
+impl LightHeader {
+    /// The message to be signed by validators,
+    /// `LIGHT_HEADER_DOMAIN` followed by the header in bincode.
+    #[inline(always)]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = LIGHT_HEADER_DOMAIN.to_vec();
+        bytes.extend(pnk!(bincode::serialize(self)));
+        bytes
+    }
+
+    /// Sign this header with the tendermint key of a validator.
+    pub fn sign(&self, td_key: &PrivateKey) -> Result<HeaderSignature> {
+        let kp = td_key.ed25519_keypair().c(d!("not an ed25519 key"))?;
+        Ok(HeaderSignature {
+            td_pubkey: kp.public.to_bytes().to_vec(),
+            signature: kp.sign(&self.to_bytes()).to_bytes().to_vec(),
+        })
+    }
+}
+
+/// The signature of a validator on a header.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HeaderSignature {
+    /// ed25519 pubkey in the context of tendermint
+    pub td_pubkey: Vec<u8>,
+    /// ed25519 signature on `LightHeader::to_bytes()`
+    pub signature: Vec<u8>,
+}
+
+/// A header along with the signatures of validators.
+#[allow(missing_docs)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SignedLightHeader {
+    pub header: LightHeader,
+    /// the validator set after this height, `Some(_)` if it has changed,
+    /// it is not signed, but checked with `header.next_validators_hash`
+    #[serde(default)]
+    pub next_validators: Option<Vec<LightValidator>>,
+    pub signatures: Vec<HeaderSignature>,
+}
+
+impl SignedLightHeader {
+    /// Collect the signatures of the same header from another source,
+    /// such as another validator, the duplicate ones are ignored.
+    pub fn merge(&mut self, other: &SignedLightHeader) -> Result<()> {
+        if self.header.to_bytes() != other.header.to_bytes() {
+            return Err(eg!("different headers"));
+        }
+
+        if self.next_validators.is_none() {
+            self.next_validators = other.next_validators.clone();
+        }
+
+        for s in other.signatures.iter() {
+            if self.signatures.iter().all(|i| i.td_pubkey != s.td_pubkey) {
+                self.signatures.push(s.clone());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The state of a light client.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LightClient {
+    height: BlockHeight,
+    validators: Vec<LightValidator>,
+    // height => trusted state commitment
+    commitments: BTreeMap<BlockHeight, Commitment>,
+}
+
+impl LightClient {
+    /// Start from a trusted checkpoint.
+    pub fn new(cp: Checkpoint) -> Result<Self> {
+        check_validators(&cp.validators).c(d!())?;
+        Ok(LightClient {
+            height: cp.height,
+            validators: cp.validators,
+            commitments: map! {B cp.height => cp.state_commitment },
+        })
+    }
+
+    /// The height of the latest trusted header.
+    #[inline(always)]
+    pub fn get_height(&self) -> BlockHeight {
+        self.height
+    }
+
+    /// The validator set used to verify the next header.
+    #[inline(always)]
+    pub fn get_validators(&self) -> &[LightValidator] {
+        &self.validators
+    }
+
+    /// Get the trusted state commitment at a height.
+    #[inline(always)]
+    pub fn get_commitment(&self, h: BlockHeight) -> Option<&Commitment> {
+        self.commitments.get(&h)
+    }
+
+    /// Verify the header of the next height and trust its state commitment,
+    /// it must be signed by more than 2/3 of the voting power
+    /// of the current validator set.
+    pub fn update(&mut self, sh: &SignedLightHeader) -> Result<()> {
+        let header = &sh.header;
+        if header.height != self.height + 1 {
+            return Err(eg!(format!(
+                "expect the header of height {}, got {}",
+                self.height + 1,
+                header.height
+            )));
+        }
+
+        let msg = header.to_bytes();
+        let total_power = self
+            .validators
+            .iter()
+            .map(|v| v.power as u128)
+            .sum::<u128>();
+
+        let mut signers = BTreeSet::new();
+        let signed_power = sh
+            .signatures
+            .iter()
+            .filter_map(|s| {
+                let v = self
+                    .validators
+                    .iter()
+                    .find(|v| v.td_pubkey == s.td_pubkey)?;
+                let pk = PublicKey::from_bytes(&s.td_pubkey).ok()?;
+                let sig = Signature::try_from(&s.signature[..]).ok()?;
+                pk.verify(&msg, &sig).ok()?;
+                Some(v)
+            })
+            // count every validator only once
+            .filter(|v| signers.insert(&v.td_pubkey))
+            .map(|v| v.power as u128)
+            .sum::<u128>();
+
+        if signed_power * 3 <= total_power * 2 {
+            return Err(eg!(format!(
+                "insufficient voting power: {}/{}",
+                signed_power, total_power
+            )));
+        }
+
+        if header.next_validators_hash != HashOf::new(&self.validators) {
+            let vs = sh
+                .next_validators
+                .as_ref()
+                .c(d!("the new validator set is missing"))?;
+            if header.next_validators_hash != HashOf::new(vs) {
+                return Err(eg!("the validator set does not match its hash"));
+            }
+            check_validators(vs).c(d!())?;
+            self.validators = vs.clone();
+        }
+
+        self.height = header.height;
+        self.commitments
+            .insert(header.height, header.state_commitment.clone());
+        while MAX_TRUSTED_COMMITMENTS < self.commitments.len() {
+            let h = *self.commitments.keys().next().unwrap();
+            self.commitments.remove(&h);
+        }
+
+        Ok(())
+    }
+
+    /// Verify an `AuthenticatedUtxo` returned by `query_utxo`,
+    /// its state commitment must be a trusted one,
+    /// return the height of that commitment.
+    pub fn verify_utxo(&self, utxo: &AuthenticatedUtxo) -> Result<BlockHeight> {
+        let commitment = utxo.state_commitment_data.compute_commitment();
+        let h = self
+            .commitments
+            .iter()
+            .rev()
+            .find(|(_, c)| **c == commitment)
+            .map(|(h, _)| *h)
+            .c(d!("untrusted state commitment"))?;
+
+        alt!(
+            utxo.is_valid(commitment),
+            Ok(h),
+            Err(eg!("invalid utxo proof"))
+        )
+    }
+}
+
+/// The validator set after the current height of `Staking`,
+/// selected in the same way as the one reported to tendermint:
+/// the unjailed ones which have voting power, at most `validator_limit`
+/// of them, in descending order of the power.
+pub fn get_light_validators(staking: &Staking) -> Vec<LightValidator> {
+    let mut vs = staking
+        .validator_get_current()
+        .map(|vd| {
+            vd.body
+                .values()
+                .filter(|v| !v.is_jailed() && 0 < v.td_power)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    // a stable sort, the ones of the same power keep the order of their ids
+    vs.sort_by(|a, b| b.td_power.cmp(&a.td_power));
+    vs.truncate(staking.chain_params().validator_limit as usize);
+
+    vs.into_iter()
+        .map(|v| LightValidator {
+            td_pubkey: v.td_pubkey.clone(),
+            power: v.td_power,
+        })
+        .collect()
+}
+
+fn check_validators(vs: &[LightValidator]) -> Result<()> {
+    if vs.iter().all(|v| 0 == v.power) {
+        return Err(eg!("empty validator set"));
+    }
+    if vs
+        .iter()
+        .map(|v| &v.td_pubkey)
+        .collect::<BTreeSet<_>>()
+        .len()
+        != vs.len()
+    {
+        return Err(eg!("duplicate validators"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        ed25519_dalek::{Keypair, Signer},
+        rand_chacha::ChaChaRng,
+        rand_core::SeedableRng,
+    };
+
+    fn gen_validators(n: usize) -> (Vec<Keypair>, Vec<LightValidator>) {
+        let mut prng = ChaChaRng::from_entropy();
+        let kps = (0..n)
+            .map(|_| Keypair::generate(&mut prng))
+            .collect::<Vec<_>>();
+        let vs = kps
+            .iter()
+            .map(|kp| LightValidator {
+                td_pubkey: kp.public.to_bytes().to_vec(),
+                power: 10,
+            })
+            .collect();
+        (kps, vs)
+    }
+
+    fn sign(
+        header: LightHeader,
+        next_validators: Option<Vec<LightValidator>>,
+        kps: &[&Keypair],
+    ) -> SignedLightHeader {
+        let msg = header.to_bytes();
+        SignedLightHeader {
+            header,
+            next_validators,
+            signatures: kps
+                .iter()
+                .map(|kp| HeaderSignature {
+                    td_pubkey: kp.public.to_bytes().to_vec(),
+                    signature: kp.sign(&msg).to_bytes().to_vec(),
+                })
+                .collect(),
+        }
+    }
+
+    fn gen_header(
+        height: BlockHeight,
+        next_validators: &[LightValidator],
+    ) -> LightHeader {
+        LightHeader {
+            height,
+            state_commitment: HashOf::new(&None),
+            next_validators_hash: HashOf::new(&next_validators.to_vec()),
+        }
+    }
+
+    #[test]
+    fn light_client_update() {
+        let (kps, vs) = gen_validators(4);
+        let mut lc = pnk!(LightClient::new(Checkpoint {
+            height: 10,
+            state_commitment: HashOf::new(&None),
+            validators: vs.clone(),
+        }));
+
+        let header = |height| gen_header(height, &vs);
+
+        // 2/3 is not enough
+        assert!(lc
+            .update(&sign(header(11), None, &[&kps[0], &kps[1]]))
+            .is_err());
+
+        // duplicate signatures are counted once
+        assert!(lc
+            .update(&sign(header(11), None, &[&kps[0], &kps[1], &kps[1]]))
+            .is_err());
+
+        // signatures without the domain tag are invalid
+        let mut sh = sign(header(11), None, &[&kps[0], &kps[1], &kps[2]]);
+        let msg = pnk!(bincode::serialize(&sh.header));
+        sh.signatures
+            .iter_mut()
+            .zip(kps.iter())
+            .for_each(|(s, kp)| s.signature = kp.sign(&msg).to_bytes().to_vec());
+        assert!(lc.update(&sh).is_err());
+
+        pnk!(lc.update(&sign(header(11), None, &[&kps[0], &kps[1], &kps[2]])));
+        assert_eq!(lc.get_height(), 11);
+        assert!(lc.get_commitment(11).is_some());
+
+        // outdated
+        assert!(lc
+            .update(&sign(header(11), None, &[&kps[0], &kps[1], &kps[2]]))
+            .is_err());
+
+        // no height can be skipped
+        assert!(lc
+            .update(&sign(header(13), None, &[&kps[0], &kps[1], &kps[2]]))
+            .is_err());
+
+        // change the validator set, which must match the hash in the header
+        let (new_kps, new_vs) = gen_validators(1);
+        let signers = [&kps[1], &kps[2], &kps[3]];
+        assert!(lc
+            .update(&sign(gen_header(12, &new_vs), None, &signers))
+            .is_err());
+        assert!(lc
+            .update(&sign(gen_header(12, &new_vs), Some(vs.clone()), &signers))
+            .is_err());
+        pnk!(lc.update(&sign(
+            gen_header(12, &new_vs),
+            Some(new_vs.clone()),
+            &signers
+        )));
+
+        // the old validators can not sign new headers any more
+        assert!(lc
+            .update(&sign(
+                gen_header(13, &new_vs),
+                None,
+                &[&kps[0], &kps[1], &kps[2]]
+            ))
+            .is_err());
+        pnk!(lc.update(&sign(gen_header(13, &new_vs), None, &[&new_kps[0]])));
+        assert_eq!(lc.get_height(), 13);
+    }
+
+    #[test]
+    fn light_header_merge() {
+        let (kps, vs) = gen_validators(2);
+        let mut lc = pnk!(LightClient::new(Checkpoint {
+            height: 10,
+            state_commitment: HashOf::new(&None),
+            validators: vs.clone(),
+        }));
+
+        let header = gen_header(11, &vs);
+
+        // every node signs the header with its own tendermint key
+        let mut shs = kps
+            .into_iter()
+            .map(|kp| SignedLightHeader {
+                header: header.clone(),
+                next_validators: None,
+                signatures: vec![pnk!(header.sign(&PrivateKey::Ed25519(kp)))],
+            })
+            .collect::<Vec<_>>();
+        assert!(lc.update(&shs[0]).is_err());
+
+        let mut other = shs.pop().unwrap();
+        other.next_validators = Some(vs);
+        let mut sh = shs.pop().unwrap();
+        pnk!(sh.merge(&other));
+        pnk!(sh.merge(&other));
+        assert_eq!(2, sh.signatures.len());
+        assert!(sh.next_validators.is_some());
+
+        let mut different = other;
+        different.header.height = 12;
+        assert!(sh.merge(&different).is_err());
+
+        pnk!(lc.update(&sh));
+        assert_eq!(lc.get_height(), 11);
+    }
+}
@@ -0,0 +1,156 @@
+//!
+//! # Chain Parameters
+//!
+//! Parameters that can be changed on-chain through a proposal/vote/activate lifecycle,
+//! their default values are the original compile-time constants.
+//!
+
+use {
+    super::{
+        cosig::CoSigRule, Amount, BlockHeight, COSIG_THRESHOLD_DEFAULT,
+        MAX_POWER_PERCENT_PER_VALIDATOR, STAKING_VALIDATOR_MIN_POWER, UNBOND_BLOCK_CNT,
+        VALIDATORS_MIN, VALIDATOR_LIMIT,
+    },
+    crate::data_model::{
+        TRANSACTION_WINDOW_WIDTH, TRANSACTION_WINDOW_WIDTH_MAX, TX_FEE_MIN,
+    },
+    cryptohash::sha256,
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::collections::{BTreeMap, BTreeSet},
+    zei::xfr::sig::XfrPublicKey,
+};
+
+/// Id of a `ChainParamsProposal`, `sha256(<height, params>)` in hex format.
+pub type ProposalId = String;
+
+/// Versioned parameters of the chain.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ChainParams {
+    /// Increased by one each time a new version is activated.
+    pub version: u64,
+    /// The lock time after the delegation expires.
+    pub unbond_block_cnt: u64,
+    /// The minimum investment to become a validator through staking.
+    pub staking_validator_min_power: Amount,
+    /// The max vote power of any validator.
+    pub max_power_percent_per_validator: [u128; 2],
+    /// The minimum weight threshold required
+    /// when updating validator information.
+    pub cosig_threshold_default: [u64; 2],
+    /// How many candidates can become official validators.
+    pub validator_limit: u64,
+    /// The minimum fee of a transaction.
+    pub tx_fee_min: u64,
+    /// How many non-empty blocks a `NoReplayToken` is valid for.
+    #[serde(default = "default_tx_window_width")]
+    pub tx_window_width: u64,
+}
+
+fn default_tx_window_width() -> u64 {
+    TRANSACTION_WINDOW_WIDTH
+}
+
+impl Default for ChainParams {
+    fn default() -> Self {
+        ChainParams {
+            version: 0,
+            unbond_block_cnt: UNBOND_BLOCK_CNT,
+            staking_validator_min_power: STAKING_VALIDATOR_MIN_POWER,
+            max_power_percent_per_validator: MAX_POWER_PERCENT_PER_VALIDATOR,
+            cosig_threshold_default: COSIG_THRESHOLD_DEFAULT,
+            validator_limit: VALIDATOR_LIMIT as u64,
+            tx_fee_min: TX_FEE_MIN,
+            tx_window_width: TRANSACTION_WINDOW_WIDTH,
+        }
+    }
+}
+
+impl ChainParams {
+    /// Check the validity of each field.
+    pub fn check(&self) -> Result<()> {
+        if 0 == self.unbond_block_cnt {
+            return Err(eg!("invalid unbond_block_cnt"));
+        }
+
+        if 0 == self.staking_validator_min_power {
+            return Err(eg!("invalid staking_validator_min_power"));
+        }
+
+        let p = self.max_power_percent_per_validator;
+        if 0 == p[0] || p[0] > p[1] {
+            return Err(eg!("invalid max_power_percent_per_validator"));
+        }
+
+        let t = self.cosig_threshold_default;
+        if 0 == t[0] {
+            return Err(eg!("invalid cosig_threshold_default"));
+        }
+        CoSigRule::new(t).c(d!("invalid cosig_threshold_default"))?;
+
+        if (VALIDATORS_MIN as u64) > self.validator_limit {
+            return Err(eg!("invalid validator_limit"));
+        }
+
+        if 0 == self.tx_fee_min {
+            return Err(eg!("invalid tx_fee_min"));
+        }
+
+        if 0 == self.tx_window_width
+            || TRANSACTION_WINDOW_WIDTH_MAX < self.tx_window_width
+        {
+            return Err(eg!("invalid tx_window_width"));
+        }
+
+        Ok(())
+    }
+}
+
+/// A proposal to change the chain parameters.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ChainParamsProposal {
+    /// The new parameters, `version` must be
+    /// exactly one bigger than the latest version.
+    pub params: ChainParams,
+    /// The new parameters will be activated at this height.
+    pub height: BlockHeight,
+    /// Validators who have voted for this proposal.
+    pub voters: BTreeSet<XfrPublicKey>,
+}
+
+impl ChainParamsProposal {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn new(params: ChainParams, height: BlockHeight) -> Self {
+        ChainParamsProposal {
+            params,
+            height,
+            voters: BTreeSet::new(),
+        }
+    }
+
+    /// Generate the id of a proposal.
+    #[inline(always)]
+    pub fn gen_id(params: &ChainParams, height: BlockHeight) -> Result<ProposalId> {
+        bincode::serialize(&(height, params))
+            .c(d!())
+            .map(|bytes| hex::encode(sha256::hash(&bytes)))
+    }
+}
+
+/// All the chain parameters, activated or pending.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ChainParamsInfo {
+    /// Accepted parameters and their activation heights.
+    pub hist: BTreeMap<BlockHeight, ChainParams>,
+    /// Proposals that are still waiting for votes.
+    pub proposals: BTreeMap<ProposalId, ChainParamsProposal>,
+}
+
+impl ChainParamsInfo {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn is_empty(&self) -> bool {
+        self.hist.is_empty() && self.proposals.is_empty()
+    }
+}
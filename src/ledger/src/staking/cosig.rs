@@ -79,18 +79,7 @@ where
         self.check_existence(vd)
             .c(d!())
             .and_then(|_| self.check_weight(vd).c(d!()))
-            .and_then(|_| {
-                let msg = bincode::serialize(&(self.nonce, &self.data)).c(d!())?;
-                if self
-                    .cosigs
-                    .values()
-                    .any(|sig| sig.pk.verify(&msg, &sig.sig).is_err())
-                {
-                    Err(eg!(CoSigErr::SigInvalid))
-                } else {
-                    Ok(())
-                }
-            })
+            .and_then(|_| self.check_sigs().c(d!()))
     }
 
     #[inline(always)]
@@ -104,25 +93,27 @@ where
 
     #[inline(always)]
     fn check_weight(&self, vd: &ValidatorData) -> Result<()> {
-        let rule_weights = vd.body.values().map(|v| v.td_power as u128).sum::<u128>();
-        let actual_weights = self
-            .cosigs
-            .values()
-            .flat_map(|s| vd.body.get(&s.pk).map(|v| v.td_power as u128))
-            .sum::<u128>();
+        check_weight(self.signers(), vd).c(d!())
+    }
 
-        let rule = [
-            vd.cosig_rule.threshold[0] as u128,
-            vd.cosig_rule.threshold[1] as u128,
-        ];
+    /// Public keys of all the signers.
+    #[inline(always)]
+    pub fn signers(&self) -> impl Iterator<Item = &XfrPublicKey> {
+        self.cosigs.values().map(|s| &s.pk)
+    }
 
-        if actual_weights.checked_mul(rule[1]).ok_or(eg!())?
-            < rule[0].checked_mul(rule_weights).ok_or(eg!())?
+    #[inline(always)]
+    fn check_sigs(&self) -> Result<()> {
+        let msg = bincode::serialize(&(self.nonce, &self.data)).c(d!())?;
+        if self
+            .cosigs
+            .values()
+            .any(|sig| sig.pk.verify(&msg, &sig.sig).is_err())
         {
-            return Err(eg!(CoSigErr::WeightInsufficient));
+            Err(eg!(CoSigErr::SigInvalid))
+        } else {
+            Ok(())
         }
-
-        Ok(())
     }
 
     /// Verify co-signatures based on current validators.
@@ -133,6 +124,20 @@ where
             .and_then(|vd| self.check_cosigs(vd).c(d!()))
     }
 
+    /// Verify co-signatures based on current validators,
+    /// but ignore the weight, used in a voting process
+    /// which collects signatures in multiple transactions.
+    pub fn verify_without_weight(&self, staking: &Staking) -> Result<()> {
+        if self.cosigs.is_empty() {
+            return Err(eg!("no signatures"));
+        }
+        staking.validator_get_current().ok_or(eg!()).and_then(|vd| {
+            self.check_existence(vd)
+                .c(d!())
+                .and_then(|_| self.check_sigs().c(d!()))
+        })
+    }
+
     /// Generate sha256 digest.
     #[inline(always)]
     pub fn hash(&self) -> Result<Digest> {
@@ -154,6 +159,31 @@ where
     }
 }
 
+/// Check if the total weight of some validators reaches
+/// the threshold of the co-signature rule.
+pub fn check_weight<'a>(
+    keys: impl Iterator<Item = &'a XfrPublicKey>,
+    vd: &ValidatorData,
+) -> Result<()> {
+    let rule_weights = vd.body.values().map(|v| v.td_power as u128).sum::<u128>();
+    let actual_weights = keys
+        .flat_map(|k| vd.body.get(k).map(|v| v.td_power as u128))
+        .sum::<u128>();
+
+    let rule = [
+        vd.cosig_rule.threshold[0] as u128,
+        vd.cosig_rule.threshold[1] as u128,
+    ];
+
+    if actual_weights.checked_mul(rule[1]).ok_or(eg!())?
+        < rule[0].checked_mul(rule_weights).ok_or(eg!())?
+    {
+        return Err(eg!(CoSigErr::WeightInsufficient));
+    }
+
+    Ok(())
+}
+
 /// The rule for a kind of data.
 #[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
 pub struct CoSigRule {
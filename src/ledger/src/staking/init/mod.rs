@@ -7,6 +7,7 @@ use {
         td_addr_to_bytes, BlockHeight, Power, Validator, ValidatorKind,
         STAKING_VALIDATOR_MIN_POWER,
     },
+    crate::upgrade::{self, NetworkProfile},
     indexmap::IndexMap,
     ruc::*,
     serde::{Deserialize, Serialize},
@@ -54,6 +55,11 @@ impl TryFrom<ValidatorStr> for Validator {
             kind: v.kind.unwrap_or(ValidatorKind::Initor),
             signed_last_block: false,
             signed_cnt: 0,
+            missed_blocks_in_row: 0,
+            jail: None,
+            commission_limit: None,
+            commission_schedule: None,
+            commission_changed_at: 0,
             delegators: IndexMap::new(),
         })
     }
@@ -70,28 +76,29 @@ pub fn get_inital_validators() -> Result<Vec<Validator>> {
     })
 }
 
-#[allow(missing_docs)]
-#[cfg(not(feature = "debug_env"))]
-pub fn get_cfg_data() -> Result<InitialValidatorInfo> {
-    serde_json::from_slice(&include_bytes!("staking_config.json")[..]).c(d!())
+/// the local networks use their own initial validators
+#[inline(always)]
+fn is_local_profile() -> bool {
+    NetworkProfile::Local == *upgrade::PROFILE
 }
 
 #[allow(missing_docs)]
-#[cfg(feature = "debug_env")]
 pub fn get_cfg_data() -> Result<InitialValidatorInfo> {
-    serde_json::from_slice(&include_bytes!("staking_config_debug_env.json")[..]).c(d!())
+    let cfg = if is_local_profile() {
+        &include_bytes!("staking_config_debug_env.json")[..]
+    } else {
+        &include_bytes!("staking_config.json")[..]
+    };
+    serde_json::from_slice(cfg).c(d!())
 }
 
 /// used in `cfg_generator` binary
-#[cfg(not(feature = "debug_env"))]
 pub fn get_cfg_path() -> Option<&'static str> {
-    option_env!("STAKING_INITIAL_VALIDATOR_CONFIG")
-}
-
-#[allow(missing_docs)]
-#[cfg(feature = "debug_env")]
-pub fn get_cfg_path() -> Option<&'static str> {
-    option_env!("STAKING_INITIAL_VALIDATOR_CONFIG_DEBUG_ENV")
+    if is_local_profile() {
+        option_env!("STAKING_INITIAL_VALIDATOR_CONFIG_DEBUG_ENV")
+    } else {
+        option_env!("STAKING_INITIAL_VALIDATOR_CONFIG")
+    }
 }
 
 #[cfg(test)]
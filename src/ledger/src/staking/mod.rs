@@ -14,6 +14,7 @@
 
 use num_bigint::BigUint;
 
+pub mod chain_params;
 pub mod cosig;
 pub mod init;
 pub mod ops;
@@ -23,8 +24,10 @@ use {
         data_model::{
             ConsensusRng, Operation, Transaction, TransferAsset, TxoRef, FRA_DECIMALS,
         },
+        upgrade::{self, Feature},
         SNAPSHOT_ENTRIES_DIR,
     },
+    chain_params::{ChainParams, ChainParamsInfo, ChainParamsProposal, ProposalId},
     cosig::CoSigRule,
     cryptohash::sha256::{self, Digest},
     fbnc::{new_mapx, Mapx},
@@ -33,6 +36,7 @@ use {
     lazy_static::lazy_static,
     ops::{
         fra_distribution::FraDistributionOps,
+        governance::{ByzantineKind, Rule, RuleSet, DEFAULT_RULES},
         mint_fra::{MintKind, MINT_AMOUNT_LIMIT},
     },
     parking_lot::Mutex,
@@ -41,6 +45,7 @@ use {
     serde::{Deserialize, Serialize},
     sha2::Digest as _,
     std::{
+        cmp::Ordering,
         collections::{BTreeMap, BTreeSet},
         convert::TryFrom,
         env, mem,
@@ -69,6 +74,9 @@ type DRH = (
     Option<TendermintAddr>,
 );
 type DRHCP = (Arc<Mutex<Sender<DRH>>>, Arc<Mutex<Receiver<DRH>>>);
+// pk, height, <enum JailEvent>
+type JH = (XfrPublicKey, BlockHeight, JailEvent);
+type JHCP = (Arc<Mutex<Sender<JH>>>, Arc<Mutex<Receiver<JH>>>);
 
 macro_rules! chan {
     () => {{
@@ -100,6 +108,8 @@ lazy_static! {
     pub static ref CHAN_D_AMOUNT_HIST: DAHCP = chan!();
     #[allow(missing_docs)]
     pub static ref CHAN_D_RWD_HIST: DRHCP = chan!();
+    #[allow(missing_docs)]
+    pub static ref CHAN_JAIL_HIST: JHCP = chan!();
 }
 
 // Reserved accounts of Findora Foundation.
@@ -149,7 +159,8 @@ pub const MIN_DELEGATION_AMOUNT: Amount = 1;
 /// Maximum allowable delegation amount.
 pub const MAX_DELEGATION_AMOUNT: Amount = FRA_TOTAL_AMOUNT;
 
-/// The minimum investment to become a validator through staking.
+/// The minimum investment to become a validator through staking,
+/// default value of `ChainParams::staking_validator_min_power`.
 pub const STAKING_VALIDATOR_MIN_POWER: Power = 1_0000 * FRA;
 
 /// The highest height in the context of tendermint.
@@ -166,22 +177,56 @@ pub const BLOCK_HEIGHT_MAX: u64 = i64::MAX as u64;
 pub const MAX_TOTAL_POWER: Amount = Amount::MAX / 8;
 
 /// The max vote power of any validator
-/// can not exceed 20% of global power.
+/// can not exceed 20% of global power,
+/// default value of `ChainParams::max_power_percent_per_validator`.
 pub const MAX_POWER_PERCENT_PER_VALIDATOR: [u128; 2] = [1, 5];
 
 /// Block time interval, in seconds.
 pub const BLOCK_INTERVAL: u64 = 15 + 1;
 
-/// The lock time after the delegation expires, about 21 days.
+/// The lock time after the delegation expires, about 21 days,
+/// default value of `ChainParams::unbond_block_cnt`.
 pub const UNBOND_BLOCK_CNT: u64 = 3600 * 24 * 21 / BLOCK_INTERVAL;
 
+/// A jailed validator can not unjail itself within this period, about 2 days.
+pub const MIN_JAIL_BLOCK_CNT: u64 = 3600 * 24 * 2 / BLOCK_INTERVAL;
+
+/// A validator will be jailed if it misses
+/// this many blocks in a row, about 1 hour.
+pub const MAX_MISSED_BLOCKS_IN_ROW: u64 = 3600 / BLOCK_INTERVAL;
+
+/// Rewards of the delegations with `auto_compound` set
+/// are folded into their principals every this many blocks, about one day.
+pub const AUTO_COMPOUND_BLOCK_ITV: u64 = 3600 * 24 / BLOCK_INTERVAL;
+
+/// A validator can change its commission rate at most once in this period,
+/// and an increase of the rate takes effect after it, about one day.
+pub const COMMISSION_CHANGE_BLOCK_ITV: u64 = 3600 * 24 / BLOCK_INTERVAL;
+
+/// default value of `CommissionLimit::max_rate`.
+pub const MAX_COMMISSION_RATE: [u64; 2] = [1, 1];
+
+/// default value of `CommissionLimit::max_daily_change`.
+pub const MAX_COMMISSION_DAILY_CHANGE: [u64; 2] = [5, 100];
+
+/// FRAs moved by a re-delegation can not be moved again,
+/// and are still exposed to the penalties of the source validator,
+/// within this period, about 21 days.
+pub const REDELEGATION_COOLDOWN_BLOCK_CNT: u64 = UNBOND_BLOCK_CNT;
+
 // minimal number of validators
 pub(crate) const VALIDATORS_MIN: usize = 5;
 
 /// The minimum weight threshold required
-/// when updating validator information, 9/10.
+/// when updating validator information, 9/10,
+/// default value of `ChainParams::cosig_threshold_default`.
 pub const COSIG_THRESHOLD_DEFAULT: [u64; 2] = [9, 10];
 
+/// The top 50~ candidate validators
+/// will become official validators,
+/// default value of `ChainParams::validator_limit`.
+pub const VALIDATOR_LIMIT: usize = 58;
+
 /// block height of tendermint
 pub type BlockHeight = u64;
 
@@ -204,6 +249,13 @@ pub type TendermintAddrBytes = Vec<u8>;
 
 type ValidatorInfo = BTreeMap<BlockHeight, ValidatorData>;
 
+type GovernanceRuleInfo = BTreeMap<BlockHeight, RuleSet>;
+
+/// A changed entry of the staking state, `(path, old value, new value)`,
+/// see `Staking::changed_entries`.
+pub type StakingChangedEntry =
+    (String, Option<serde_json::Value>, Option<serde_json::Value>);
+
 /// Staking entry
 ///
 /// Init:
@@ -226,6 +278,22 @@ pub struct Staking {
     // FRA CoinBase.
     coinbase: CoinBase,
     cr: ConsensusRng,
+    // penalty rules of byzantine behaviors,
+    // updated by `UpdateGovernanceRules` operations,
+    // the built-in `DEFAULT_RULES` will be used if it is empty.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    gr: GovernanceRuleInfo,
+    // on-chain parameters and the proposals to change them,
+    // the default parameters will be used if no one has been activated.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "ChainParamsInfo::is_empty")]
+    cp: ChainParamsInfo,
+    // re-delegations that are still in their cooldown period,
+    // delegator => records
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    rd: BTreeMap<XfrPublicKey, Vec<ReDelegation>>,
 }
 
 impl Default for Staking {
@@ -257,6 +325,9 @@ impl Staking {
             cur_height: 0,
             coinbase: CoinBase::gen(),
             cr: ConsensusRng::default(),
+            gr: BTreeMap::new(),
+            cp: ChainParamsInfo::default(),
+            rd: BTreeMap::new(),
         }
     }
 
@@ -444,13 +515,14 @@ impl Staking {
     // after they have been removed from tendermint core.
     fn validator_clean_invalid_items(&mut self) {
         let h = self.cur_height;
+        let unbond_block_cnt = self.chain_params().unbond_block_cnt;
 
-        if UNBOND_BLOCK_CNT > h {
+        if unbond_block_cnt > h {
             return;
         }
 
         if let Some(old) = self
-            .validator_get_effective_at_height(h - UNBOND_BLOCK_CNT)
+            .validator_get_effective_at_height(h - unbond_block_cnt)
             .map(|ovd| {
                 ovd.body
                     .iter()
@@ -552,20 +624,36 @@ impl Staking {
     #[inline(always)]
     /// update staker
     pub fn update_staker(&mut self, new: &Validator) -> Result<()> {
-        let vd = self.validator_get_current_mut().c(d!())?;
-        let res = vd.body.values_mut().any(|v| {
-            if v.id == new.id {
-                v.memo = new.memo.clone();
-                v.commission_rate = new.commission_rate;
-                return true;
-            }
-            false
-        });
+        let h = self.cur_height;
+        let v = self
+            .validator_get_current_mut_one_by_id(&new.id)
+            .c(d!("Cannot update staker"))?;
 
-        if res {
-            Ok(())
-        } else {
-            Err(eg!("Cannot update staker"))
+        v.commission_update(new.commission_rate, new.commission_limit, h)
+            .c(d!())?;
+        v.memo = new.memo.clone();
+
+        Ok(())
+    }
+
+    /// Apply the scheduled commission changes which become effective.
+    fn validator_commission_process(&mut self) {
+        let h = self.cur_height;
+        if !upgrade::is_active(Feature::CommissionRule, h) {
+            return;
+        }
+
+        if let Some(vd) = self.validator_get_current_mut() {
+            vd.body.values_mut().for_each(|v| {
+                if v.commission_schedule
+                    .as_ref()
+                    .map(|s| s.effective_height <= h)
+                    .unwrap_or(false)
+                {
+                    let s = v.commission_schedule.take().unwrap();
+                    v.commission_rate = s.rate;
+                }
+            });
         }
     }
 
@@ -581,12 +669,11 @@ impl Staking {
             return Err(eg!("global power overflow"));
         }
 
+        let max_percent = self.chain_params().max_power_percent_per_validator;
         if ((power + new_power) as u128)
-            .checked_mul(MAX_POWER_PERCENT_PER_VALIDATOR[1])
+            .checked_mul(max_percent[1])
             .c(d!())?
-            > MAX_POWER_PERCENT_PER_VALIDATOR[0]
-                .checked_mul(global_power as u128)
-                .c(d!())?
+            > max_percent[0].checked_mul(global_power as u128).c(d!())?
         {
             return Err(eg!("validator power overflow"));
         }
@@ -639,6 +726,9 @@ impl Staking {
                 return Err(eg!("self-delegation has not been finished"));
             }
             check_delegation_amount(am, true).c(d!())?;
+            if self.validator_is_jailed(&validator) {
+                return Err(eg!("validator is jailed"));
+            }
             self.validator_check_power(am, &validator).c(d!())?;
         }
 
@@ -654,6 +744,7 @@ impl Staking {
             rwd_amount: 0,
             delegation_rwd_cnt: 0,
             proposer_rwd_cnt: 0,
+            auto_compound: false,
         };
 
         let d = self.di.addr_map.entry(owner).or_insert_with(new);
@@ -726,6 +817,7 @@ impl Staking {
         }
 
         let h = self.cur_height;
+        let unbond_block_cnt = self.chain_params().unbond_block_cnt;
         let mut orig_h = None;
         let mut is_validator = false;
 
@@ -733,7 +825,7 @@ impl Staking {
             if BLOCK_HEIGHT_MAX == d.end_height {
                 if d.end_height != h {
                     orig_h = Some(d.end_height);
-                    d.end_height = h + UNBOND_BLOCK_CNT;
+                    d.end_height = h + unbond_block_cnt;
                 }
             } else {
                 return Err(eg!("delegator is not bonded"));
@@ -787,7 +879,7 @@ impl Staking {
                 .map(|set| set.remove(addr));
             self.di
                 .end_height_map
-                .entry(h + UNBOND_BLOCK_CNT)
+                .entry(h + unbond_block_cnt)
                 .or_insert_with(BTreeSet::new)
                 .insert(*addr);
         }
@@ -809,6 +901,7 @@ impl Staking {
 
         let new_tmp_delegator;
         let h = self.cur_height;
+        let params = self.chain_params();
         let is_validator = self.addr_is_validator(addr);
 
         let target_validator = self
@@ -818,7 +911,7 @@ impl Staking {
         let actual_am;
         if let Some(d) = self.di.addr_map.get_mut(addr) {
             if is_validator
-                && STAKING_VALIDATOR_MIN_POWER > d.amount().saturating_sub(pu.am)
+                && params.staking_validator_min_power > d.amount().saturating_sub(pu.am)
             {
                 return Err(eg!("Requested amount exceeds limits"));
             }
@@ -846,11 +939,12 @@ impl Staking {
                     receiver_pk: Some(d.id),
                     tmp_delegators: map! {B},
                     start_height: d.start_height,
-                    end_height: h + UNBOND_BLOCK_CNT,
+                    end_height: h + params.unbond_block_cnt,
                     state: DelegationState::Bond,
                     rwd_amount: 0,
                     delegation_rwd_cnt: 0,
                     proposer_rwd_cnt: 0,
+                    auto_compound: false,
                 };
                 // record per-block-height self-delegation amount for a validator
                 if target_validator == *addr && *KEEP_HIST {
@@ -875,7 +969,7 @@ impl Staking {
             .insert(pu.new_delegator_id, new_tmp_delegator);
         self.di
             .end_height_map
-            .entry(h + UNBOND_BLOCK_CNT)
+            .entry(h + params.unbond_block_cnt)
             .or_insert_with(BTreeSet::new)
             .insert(pu.new_delegator_id);
 
@@ -894,6 +988,278 @@ impl Staking {
         Ok(())
     }
 
+    /// Turn on/off the auto-compounding of the rewards of a delegation.
+    pub fn delegation_set_auto_compound(
+        &mut self,
+        addr: &XfrPublicKey,
+        enable: bool,
+    ) -> Result<()> {
+        let d = self
+            .di
+            .addr_map
+            .get_mut(addr)
+            .c(d!("delegator not found"))?;
+        if BLOCK_HEIGHT_MAX != d.end_height || DelegationState::Bond != d.state {
+            return Err(eg!("delegator is not bonded"));
+        }
+        d.auto_compound = enable;
+        Ok(())
+    }
+
+    // Fold the rewards of the delegations with `auto_compound` set
+    // into their principals, each validator entry gets a share
+    // in proportion to its amount, the shares that would break
+    // the power limit of the validator stay in the rewards.
+    fn delegation_compound_rewards(&mut self) {
+        if 0 != self.cur_height % AUTO_COMPOUND_BLOCK_ITV {
+            return;
+        }
+
+        let plan = self
+            .di
+            .addr_map
+            .values()
+            .filter(|d| {
+                d.auto_compound
+                    && BLOCK_HEIGHT_MAX == d.end_height
+                    && DelegationState::Bond == d.state
+                    && 0 < d.rwd_amount
+                    && 0 < d.amount()
+            })
+            .map(|d| {
+                let total = d.amount() as u128;
+                let shares = d
+                    .entries
+                    .iter()
+                    .map(|(vid, am)| {
+                        let share = d.rwd_amount as u128 * *am as u128 / total;
+                        (*vid, share as Amount)
+                    })
+                    .filter(|(_, share)| 0 < *share)
+                    .collect::<Vec<_>>();
+                (d.id, shares)
+            })
+            .collect::<Vec<_>>();
+
+        plan.into_iter().for_each(|(owner, shares)| {
+            shares.into_iter().for_each(|(vid, am)| {
+                ruc::info_omit!(self.delegation_compound_one(&owner, &vid, am));
+            });
+        });
+    }
+
+    fn delegation_compound_one(
+        &mut self,
+        owner: &XfrPublicKey,
+        validator: &XfrPublicKey,
+        am: Amount,
+    ) -> Result<()> {
+        let h = self.cur_height;
+
+        // rewards are paid from the balance of coinbase
+        let am = am.min(self.coinbase.balance);
+        if 0 == am {
+            return Ok(());
+        }
+        self.validator_check_power(am, validator).c(d!())?;
+
+        let d = self.di.addr_map.get_mut(owner).c(d!())?;
+        d.rwd_amount = d.rwd_amount.checked_sub(am).c(d!())?;
+        *d.entries.entry(*validator).or_insert(0) += am;
+        let bond = d.amount();
+
+        if owner == validator {
+            if *KEEP_HIST {
+                CHAN_V_SELF_D_HIST.0.lock().send((*owner, h, bond)).unwrap();
+            }
+        } else if let Some(v) = self.validator_get_current_mut_one_by_id(validator) {
+            *v.delegators.entry(*owner).or_insert(0) += am;
+            v.delegators.sort_by(|_, v1, _, v2| v2.cmp(&v1));
+            if *KEEP_HIST {
+                CHAN_D_AMOUNT_HIST
+                    .0
+                    .lock()
+                    .send((v.id, h, v.delegators.values().sum()))
+                    .unwrap();
+            }
+        }
+
+        // There should be no failure here !!
+        // NOTE: set this operation after updating delegators!
+        pnk!(self.validator_change_power(validator, am, false));
+
+        self.di.global_amount += am;
+
+        // rewards become principals
+        self.coinbase.balance -= am;
+        self.coinbase.principal_balance += am;
+
+        if *KEEP_HIST {
+            let r = DelegationRwdDetail {
+                bond,
+                compound_amount: am,
+                block_height: h,
+                ..Default::default()
+            };
+            let td_addr = self.validator_app_pk_to_td_addr(validator).ok();
+            CHAN_D_RWD_HIST
+                .0
+                .lock()
+                .send((*owner, h, r, td_addr))
+                .unwrap();
+        }
+
+        Ok(())
+    }
+
+    /// Move the FRAs delegated to the `from` validator to the `to` validator,
+    /// `am: None` means all of them.
+    ///
+    /// Different from an un-delegation, there is no unbond period,
+    /// but the moved FRAs can not be moved again during the cooldown period,
+    /// and they will be punished together with the `from` validator.
+    pub fn redelegate(
+        &mut self,
+        owner: &XfrPublicKey,
+        from: TendermintAddrRef,
+        to: TendermintAddrRef,
+        am: Option<Amount>,
+    ) -> Result<()> {
+        let from = self.validator_td_addr_to_app_pk(from).c(d!())?;
+        let to = self.validator_td_addr_to_app_pk(to).c(d!())?;
+        let h = self.cur_height;
+
+        // check everything in advance before changing the data
+        let am = {
+            if from == to {
+                return Err(eg!("the source and the target are the same validator"));
+            }
+            if owner == &from || owner == &to {
+                return Err(eg!("self-delegation can not be re-delegated"));
+            }
+            if !self.delegation_has_addr(&to) {
+                return Err(eg!("self-delegation of the target has not been finished"));
+            }
+            if self.validator_is_jailed(&to) {
+                return Err(eg!("the target validator is jailed"));
+            }
+            if self
+                .redelegation_get(owner)
+                .iter()
+                .any(|r| r.to == from && r.end_height > h)
+            {
+                return Err(eg!("FRAs of the source are still in the cooldown period"));
+            }
+
+            let d = self.delegation_get(owner).c(d!("delegator not found"))?;
+            if BLOCK_HEIGHT_MAX != d.end_height || DelegationState::Bond != d.state {
+                return Err(eg!("delegator is not bonded"));
+            }
+            let total = d
+                .validator_entry(&from)
+                .c(d!("source validator does not exist"))?;
+            let am = am.unwrap_or(total);
+            if 0 == am || am > total {
+                return Err(eg!(format!(
+                    "Invalid amount, requested: {}, total: {}",
+                    am, total
+                )));
+            }
+
+            // the global power does not change
+            let v = self
+                .validator_get_current_one_by_id(&to)
+                .c(d!("validator not found"))?;
+            if ValidatorKind::Staker == v.kind {
+                let max_percent = self.chain_params().max_power_percent_per_validator;
+                if ((v.td_power + am) as u128)
+                    .checked_mul(max_percent[1])
+                    .c(d!())?
+                    > max_percent[0]
+                        .checked_mul(self.validator_global_power() as u128)
+                        .c(d!())?
+                {
+                    return Err(eg!("validator power overflow"));
+                }
+            }
+
+            am
+        };
+
+        // unwrap is safe here
+        let d = self.di.addr_map.get_mut(owner).unwrap();
+        let left = d.entries.get_mut(&from).unwrap();
+        *left -= am;
+        if 0 == *left {
+            d.entries.remove(&from);
+        }
+        *d.entries.entry(to).or_insert(0) += am;
+
+        // update delegator entries of both validators
+        if let Some(v) = self.validator_get_current_mut_one_by_id(&from) {
+            if let Some(v_am) = v.delegators.get_mut(owner) {
+                *v_am = v_am.saturating_sub(am);
+                if 0 == *v_am {
+                    v.delegators.remove(owner);
+                }
+            }
+            v.delegators.sort_by(|_, v1, _, v2| v2.cmp(&v1));
+            if *KEEP_HIST {
+                CHAN_D_AMOUNT_HIST
+                    .0
+                    .lock()
+                    .send((v.id, h, v.delegators.values().sum()))
+                    .unwrap();
+            }
+        }
+        if let Some(v) = self.validator_get_current_mut_one_by_id(&to) {
+            *v.delegators.entry(*owner).or_insert(0) += am;
+            v.delegators.sort_by(|_, v1, _, v2| v2.cmp(&v1));
+            if *KEEP_HIST {
+                CHAN_D_AMOUNT_HIST
+                    .0
+                    .lock()
+                    .send((v.id, h, v.delegators.values().sum()))
+                    .unwrap();
+            }
+        }
+
+        // There should be no failure here !!
+        // NOTE: set this operation after updating delegators!
+        pnk!(self.validator_change_power(&from, am, true));
+        pnk!(self.validator_change_power(&to, am, false));
+
+        self.rd
+            .entry(*owner)
+            .or_insert_with(Vec::new)
+            .push(ReDelegation {
+                from,
+                to,
+                amount: am,
+                end_height: h + REDELEGATION_COOLDOWN_BLOCK_CNT,
+            });
+
+        Ok(())
+    }
+
+    /// Get the re-delegations of `addr` which are still in their cooldown period.
+    #[inline(always)]
+    pub fn redelegation_get(&self, addr: &XfrPublicKey) -> &[ReDelegation] {
+        self.rd
+            .get(addr)
+            .map(|rs| rs.as_slice())
+            .unwrap_or_default()
+    }
+
+    // remove the re-delegations whose cooldown period has expired
+    fn redelegation_clean_expired(&mut self) {
+        let h = self.cur_height;
+        self.rd.values_mut().for_each(|rs| {
+            rs.retain(|r| r.end_height > h);
+        });
+        self.rd.retain(|_, rs| !rs.is_empty());
+    }
+
     #[inline(always)]
     fn delegation_clean_paid(
         &mut self,
@@ -1072,6 +1438,8 @@ impl Staking {
     pub fn delegation_process(&mut self) {
         let h = self.cur_height;
 
+        self.validator_commission_process();
+
         self.di
             .end_height_map
             .range(..=h)
@@ -1116,8 +1484,14 @@ impl Staking {
                 }
             });
 
+        self.delegation_compound_rewards();
+
         self.delegation_process_finished_before_height(h);
 
+        self.redelegation_clean_expired();
+
+        self.chain_params_clean_outdated_proposals();
+
         self.validator_clean_invalid_items();
     }
 
@@ -1186,6 +1560,28 @@ impl Staking {
                 ruc::info_omit!(self.governance_penalty_sub_amount(&pk, p_am));
             });
 
+            // punish the FRAs that have been re-delegated to other validators
+            // but are still in the cooldown period
+            let h = self.cur_height;
+            let rl = self
+                .rd
+                .iter_mut()
+                .flat_map(|(pk, rs)| {
+                    rs.iter_mut()
+                        .filter(|r| &r.from == addr && r.end_height > h)
+                        .map(|r| {
+                            let p_am = r.amount * percent[0] / percent[1];
+                            r.amount -= p_am;
+                            (*pk, r.to, p_am)
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+
+            rl.into_iter().for_each(|(pk, to, p_am)| {
+                ruc::info_omit!(self.governance_penalty_redelegated(&pk, &to, p_am));
+            });
+
             // punish its vote power
             self.validator_get_power(addr).c(d!()).and_then(|power| {
                 self.validator_change_power(addr, power * percent[0] / percent[1], true)
@@ -1209,20 +1605,7 @@ impl Staking {
         };
 
         if *KEEP_HIST {
-            let r = DelegationRwdDetail {
-                bond: d.amount(),
-                amount: 0,
-                penalty_amount: am,
-                return_rate: None,
-                commission_rate: None,
-                global_delegation_percent: None,
-                block_height: self.cur_height,
-            };
-            CHAN_D_RWD_HIST
-                .0
-                .lock()
-                .send((d.id, self.cur_height, r, None))
-                .unwrap();
+            governance_penalty_record(d, am, self.cur_height);
         }
 
         if DelegationState::Paid == d.state {
@@ -1245,6 +1628,150 @@ impl Staking {
         Ok(())
     }
 
+    // punish the FRAs re-delegated to the `to` validator,
+    // its vote power and its entry of the delegator are reduced together
+    fn governance_penalty_redelegated(
+        &mut self,
+        addr: &XfrPublicKey,
+        to: &XfrPublicKey,
+        am: Amount,
+    ) -> Result<()> {
+        let h = self.cur_height;
+
+        let d = self.di.addr_map.get_mut(addr).c(d!("not exists"))?;
+        if DelegationState::Paid == d.state {
+            return Err(eg!("delegation has been paid"));
+        }
+
+        if *KEEP_HIST {
+            governance_penalty_record(d, am, h);
+        }
+
+        let entry = d.entries.get_mut(to).c(d!("target validator not found"))?;
+        let am = am.min(*entry);
+        *entry -= am;
+        if 0 == *entry {
+            d.entries.remove(to);
+        }
+
+        if let Some(v) = self.validator_get_current_mut_one_by_id(to) {
+            if let Some(v_am) = v.delegators.get_mut(addr) {
+                *v_am = v_am.saturating_sub(am);
+                if 0 == *v_am {
+                    v.delegators.remove(addr);
+                }
+            }
+            v.delegators.sort_by(|_, v1, _, v2| v2.cmp(&v1));
+            if *KEEP_HIST {
+                CHAN_D_AMOUNT_HIST
+                    .0
+                    .lock()
+                    .send((v.id, h, v.delegators.values().sum()))
+                    .unwrap();
+            }
+        }
+
+        // NOTE: set this operation after updating delegators!
+        self.validator_change_power(to, am, true).c(d!())
+    }
+
+    /// Check if a validator is in jail at current height.
+    #[inline(always)]
+    pub fn validator_is_jailed(&self, vid: &XfrPublicKey) -> bool {
+        self.validator_get_current_one_by_id(vid)
+            .map(|v| v.is_jailed())
+            .unwrap_or(false)
+    }
+
+    /// Put a validator into jail, its power will be reported as zero
+    /// to tendermint, and it can not receive new delegations,
+    /// nothing will be changed if it is in jail already,
+    /// if `Feature::ValidatorJail` is not active yet,
+    /// or if less than `VALIDATORS_MIN` validators would be left in consensus.
+    pub fn validator_jail(
+        &mut self,
+        vid: &XfrPublicKey,
+        reason: ByzantineKind,
+    ) -> Result<()> {
+        let h = self.cur_height;
+        if !upgrade::is_active(Feature::ValidatorJail, h) {
+            return Ok(());
+        }
+
+        // the chain must not halt because of jailing
+        let left = self
+            .validator_get_current()
+            .c(d!())?
+            .body
+            .values()
+            .filter(|v| &v.id != vid && !v.is_jailed() && 0 < v.td_power)
+            .count()
+            .min(self.chain_params().validator_limit as usize);
+        if VALIDATORS_MIN > left {
+            return Ok(());
+        }
+
+        let v = self
+            .validator_get_current_mut_one_by_id(vid)
+            .c(d!("validator not found"))?;
+
+        if v.is_jailed() {
+            return Ok(());
+        }
+
+        let jail = Jail {
+            reason,
+            since: h,
+            until: h + MIN_JAIL_BLOCK_CNT,
+        };
+        v.jail = Some(jail.clone());
+
+        if *KEEP_HIST {
+            CHAN_JAIL_HIST
+                .0
+                .lock()
+                .send((*vid, h, JailEvent::Jailed(jail)))
+                .unwrap();
+        }
+
+        Ok(())
+    }
+
+    /// Release a validator from jail after the minimum jail period,
+    /// its self-delegation must be still bonded.
+    pub fn validator_unjail(&mut self, vid: &XfrPublicKey) -> Result<()> {
+        let h = self.cur_height;
+
+        if self
+            .delegation_get(vid)
+            .map(|d| BLOCK_HEIGHT_MAX != d.end_height)
+            .unwrap_or(true)
+        {
+            return Err(eg!("self-delegation is not bonded"));
+        }
+
+        let v = self
+            .validator_get_current_mut_one_by_id(vid)
+            .c(d!("validator not found"))?;
+        let jail = v.jail.as_ref().c(d!("validator is not jailed"))?;
+        if jail.until > h {
+            return Err(eg!(format!("validator is jailed until {}", jail.until)));
+        }
+
+        v.jail = None;
+        v.missed_blocks_in_row = 0;
+
+        if *KEEP_HIST {
+            CHAN_JAIL_HIST
+                .0
+                .lock()
+                .send((*vid, h, JailEvent::Unjailed))
+                .unwrap();
+        }
+
+        Ok(())
+    }
+
     /// Look up the `XfrPublicKey`
     /// co-responding to a specified 'tendermint node address'.
     #[inline(always)]
@@ -1276,6 +1803,201 @@ impl Staking {
         })
     }
 
+    /// Get the chain parameters that will be used for the specified height.
+    #[inline(always)]
+    pub fn chain_params_at_height(&self, h: BlockHeight) -> ChainParams {
+        self.cp
+            .hist
+            .range(0..=h)
+            .rev()
+            .next()
+            .map(|(_, p)| *p)
+            .unwrap_or_default()
+    }
+
+    /// Get the chain parameters of current height.
+    #[inline(always)]
+    pub fn chain_params(&self) -> ChainParams {
+        self.chain_params_at_height(self.cur_height)
+    }
+
+    /// Get the latest accepted chain parameters,
+    /// they may have not been activated yet.
+    #[inline(always)]
+    pub fn chain_params_latest(&self) -> ChainParams {
+        self.cp.hist.values().last().copied().unwrap_or_default()
+    }
+
+    /// Get all the chain parameters and pending proposals.
+    #[inline(always)]
+    pub fn chain_params_info(&self) -> &ChainParamsInfo {
+        &self.cp
+    }
+
+    /// Create a new proposal to change the chain parameters,
+    /// the proposers will be treated as the first voters.
+    pub fn chain_params_propose<'a>(
+        &mut self,
+        params: ChainParams,
+        h: BlockHeight,
+        proposers: impl Iterator<Item = &'a XfrPublicKey>,
+    ) -> Result<ProposalId> {
+        self.chain_params_clean_outdated_proposals();
+
+        params.check().c(d!())?;
+
+        if h <= self.cur_height
+            || self.cp.hist.keys().last().map(|lh| h <= *lh) == Some(true)
+        {
+            return Err(eg!(
+                "the activation height must be bigger than existing ones"
+            ));
+        }
+
+        if params.version != 1 + self.chain_params_latest().version {
+            return Err(eg!("invalid version"));
+        }
+
+        let id = ChainParamsProposal::gen_id(&params, h).c(d!())?;
+        if self.cp.proposals.contains_key(&id) {
+            return Err(eg!("already exists"));
+        }
+
+        let mut proposal = ChainParamsProposal::new(params, h);
+        proposal.voters.extend(proposers);
+        self.cp.proposals.insert(id.clone(), proposal);
+
+        self.chain_params_try_accept(&id).c(d!()).map(|_| id)
+    }
+
+    /// Vote for an existing proposal,
+    /// the proposal will be accepted when it gets enough votes.
+    pub fn chain_params_vote<'a>(
+        &mut self,
+        id: &str,
+        voters: impl Iterator<Item = &'a XfrPublicKey>,
+    ) -> Result<()> {
+        self.chain_params_clean_outdated_proposals();
+
+        self.cp
+            .proposals
+            .get_mut(id)
+            .c(d!("proposal not found"))?
+            .voters
+            .extend(voters);
+
+        self.chain_params_try_accept(id).c(d!())
+    }
+
+    // Accept a proposal if the weight of its voters has reached
+    // the threshold of current validators' co-signature rule,
+    // the new parameters will be activated at the declared height.
+    fn chain_params_try_accept(&mut self, id: &str) -> Result<()> {
+        let proposal = self.cp.proposals.get(id).c(d!())?;
+        let vd = self.validator_get_current().c(d!())?;
+        if cosig::check_weight(proposal.voters.iter(), vd).is_err() {
+            return Ok(());
+        }
+
+        // unwrap is safe here
+        let proposal = self.cp.proposals.remove(id).unwrap();
+        let version = proposal.params.version;
+        let height = proposal.height;
+        self.cp.hist.insert(height, proposal.params);
+
+        // proposals based on an old version or an earlier height
+        // will never be accepted
+        self.cp
+            .proposals
+            .retain(|_, p| p.params.version > version && p.height > height);
+
+        Ok(())
+    }
+
+    // Clean proposals whose activation height has been passed,
+    // and the parameters that have been replaced by a newer effective one.
+    fn chain_params_clean_outdated_proposals(&mut self) {
+        let h = self.cur_height;
+        self.cp.proposals.retain(|_, p| p.height > h);
+        if let Some(eh) = self.cp.hist.range(0..=h).rev().next().map(|(h, _)| *h) {
+            self.cp.hist = self.cp.hist.split_off(&eh);
+        }
+    }
+
+    /// Get the penalty rules that will be used for the specified height.
+    #[inline(always)]
+    pub fn governance_get_rules_at_height(&self, h: BlockHeight) -> &RuleSet {
+        self.gr
+            .range(0..=h)
+            .rev()
+            .next()
+            .map(|(_, r)| r)
+            .unwrap_or(&*DEFAULT_RULES)
+    }
+
+    /// Get the penalty rule of a kind of byzantine behavior at current height.
+    #[inline(always)]
+    pub fn governance_get_rule(&self, kind: &ByzantineKind) -> Option<&Rule> {
+        self.governance_get_rules_at_height(self.cur_height)
+            .get(kind)
+    }
+
+    /// Get the current penalty rules and all the scheduled ones,
+    /// the first entry is the one in effect.
+    pub fn governance_get_rules_hist(&self) -> BTreeMap<BlockHeight, RuleSet> {
+        let h = self.cur_height;
+        let (cur_h, cur) = self
+            .gr
+            .range(0..=h)
+            .rev()
+            .next()
+            .map(|(h, r)| (*h, r.clone()))
+            .unwrap_or_else(|| (0, DEFAULT_RULES.clone()));
+
+        let mut res = self
+            .gr
+            .range((h + 1)..)
+            .map(|(h, r)| (*h, r.clone()))
+            .collect::<BTreeMap<_, _>>();
+        res.insert(cur_h, cur);
+        res
+    }
+
+    /// Set the penalty rules that will be used since the specified height,
+    /// the height must be bigger than the current height,
+    /// kinds not contained in `rules` will inherit the settings
+    /// which are effective at that height.
+    pub fn governance_set_rules_at_height(
+        &mut self,
+        h: BlockHeight,
+        rules: RuleSet,
+    ) -> Result<()> {
+        if h <= self.cur_height {
+            return Err(eg!("the effective height must be in the future"));
+        }
+
+        if self.gr.contains_key(&h) {
+            return Err(eg!("already exists"));
+        }
+
+        let mut new = self.governance_get_rules_at_height(h).clone();
+        new.extend(rules.into_iter());
+        self.gr.insert(h, new);
+
+        self.governance_clean_outdated_rules();
+
+        Ok(())
+    }
+
+    // Clean the rules that have been replaced by a newer effective one.
+    #[inline(always)]
+    fn governance_clean_outdated_rules(&mut self) {
+        let h = self.cur_height;
+        if let Some(eh) = self.gr.range(0..=h).rev().next().map(|(h, _)| *h) {
+            self.gr = self.gr.split_off(&eh);
+        }
+    }
+
     /// Generate sha256 digest.
     #[inline(always)]
     pub fn hash(&self) -> Result<Digest> {
@@ -1284,6 +2006,52 @@ impl Staking {
             .map(|bytes| sha256::hash(&bytes))
     }
 
+    /// Generate sha256 digests of every top-level field separately,
+    /// field name => digest in hex format,
+    /// used to find out which parts differ between two states,
+    /// it is only computed for the API cache, when `KEEP_HIST` is on.
+    pub fn hash_fields(&self) -> Result<BTreeMap<String, String>> {
+        self.json_fields()
+            .c(d!())?
+            .into_iter()
+            .map(|(k, v)| {
+                serde_json::to_vec(&v)
+                    .c(d!())
+                    .map(|bytes| (k, hex::encode(sha256::hash(&bytes))))
+            })
+            .collect()
+    }
+
+    // the top-level fields of the JSON form, field name => value,
+    // no field is missed when a new one is added to the struct
+    fn json_fields(&self) -> Result<serde_json::Map<String, serde_json::Value>> {
+        match serde_json::to_value(self).c(d!())? {
+            serde_json::Value::Object(fields) => Ok(fields),
+            _ => Err(eg!("not a JSON object")),
+        }
+    }
+
+    /// The top-level fields changed from `self` to `new`, as `(path, old, new)`,
+    /// an old or new value is `None` if the field does not exist on that side.
+    pub fn changed_entries(&self, new: &Staking) -> Result<Vec<StakingChangedEntry>> {
+        let old = self.json_fields().c(d!())?;
+        let mut new = new.json_fields().c(d!())?;
+
+        let mut res = old
+            .into_iter()
+            .filter_map(|(k, o)| match new.remove(&k) {
+                Some(n) if n == o => None,
+                n => Some((format!("/{}", k), Some(o), n)),
+            })
+            .collect::<Vec<_>>();
+        res.extend(
+            new.into_iter()
+                .map(|(k, n)| (format!("/{}", k), None, Some(n))),
+        );
+
+        Ok(res)
+    }
+
     /// Add new fra distribution plan.
     pub fn coinbase_config_fra_distribution(
         &mut self,
@@ -1717,6 +2485,27 @@ pub struct Validator {
     pub signed_last_block: bool,
     /// how many blocks has the validator signed
     pub signed_cnt: u64,
+    /// how many blocks has the validator missed in a row
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_zero")]
+    pub missed_blocks_in_row: u64,
+    /// `Some(_)` if the validator is in jail
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jail: Option<Jail>,
+    /// limits on the changes of `commission_rate`,
+    /// `None` means `CommissionLimit::default()`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commission_limit: Option<CommissionLimit>,
+    /// a pending increase of `commission_rate`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commission_schedule: Option<CommissionSchedule>,
+    /// the height of the last change of `commission_rate`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_zero")]
+    pub commission_changed_at: BlockHeight,
 
     /// delegator pubkey => amount
     ///   - delegator entries on current block height
@@ -1747,6 +2536,11 @@ impl Validator {
             kind,
             signed_last_block: false,
             signed_cnt: 0,
+            missed_blocks_in_row: 0,
+            jail: None,
+            commission_limit: None,
+            commission_schedule: None,
+            commission_changed_at: 0,
             delegators: IndexMap::new(),
         })
     }
@@ -1775,18 +2569,139 @@ impl Validator {
         self.kind.to_string()
     }
 
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn is_jailed(&self) -> bool {
+        self.jail.is_some()
+    }
+
     #[inline(always)]
     #[allow(missing_docs)]
     pub fn get_commission_rate(&self) -> [u64; 2] {
         self.commission_rate
     }
 
+    /// Limits on the commission rate, the defaults will be used if not set.
+    #[inline(always)]
+    pub fn get_commission_limit(&self) -> CommissionLimit {
+        self.commission_limit.unwrap_or_default()
+    }
+
+    /// Check if the commission rate and its limits
+    /// can be updated at height `h`, return the limits to be used.
+    ///
+    /// Any rate is accepted and the limits are ignored
+    /// before `Feature::CommissionRule` is active.
+    pub fn commission_check_update(
+        &self,
+        rate: [u64; 2],
+        limit: Option<CommissionLimit>,
+        h: BlockHeight,
+    ) -> Result<CommissionLimit> {
+        if !upgrade::is_active(Feature::CommissionRule, h) {
+            return Ok(self.get_commission_limit());
+        }
+
+        let cur_limit = self.get_commission_limit();
+        let limit = if let Some(l) = limit {
+            if !l.is_valid() {
+                return Err(eg!("invalid commission limit"));
+            }
+            if !l.is_within(&cur_limit) {
+                return Err(eg!("commission limit can only be tightened"));
+            }
+            if let Some(s) = self.commission_schedule.as_ref() {
+                if Ordering::Greater == rate_cmp(s.rate, l.max_rate) {
+                    return Err(eg!("the scheduled rate exceeds the new limit"));
+                }
+            }
+            l
+        } else {
+            cur_limit
+        };
+
+        if rate == self.commission_rate {
+            if Ordering::Greater == rate_cmp(rate, limit.max_rate) {
+                return Err(eg!("the current rate exceeds the new limit"));
+            }
+            return Ok(limit);
+        }
+
+        if 0 == rate[1] || rate[0] > rate[1] {
+            return Err(eg!("invalid commission rate"));
+        }
+        if Ordering::Greater == rate_cmp(rate, limit.max_rate) {
+            return Err(eg!("commission rate exceeds the limit"));
+        }
+        if 0 < self.commission_changed_at
+            && h < self.commission_changed_at + COMMISSION_CHANGE_BLOCK_ITV
+        {
+            return Err(eg!(format!(
+                "commission rate can not be changed until {}",
+                self.commission_changed_at + COMMISSION_CHANGE_BLOCK_ITV
+            )));
+        }
+        if rate_diff_gt(self.commission_rate, rate, limit.max_daily_change) {
+            return Err(eg!("commission rate changes too much"));
+        }
+
+        Ok(limit)
+    }
+
+    /// A decrease of the commission rate takes effect at once,
+    /// an increase will be scheduled after `COMMISSION_CHANGE_BLOCK_ITV`,
+    /// the rate is replaced directly before `Feature::CommissionRule` is active.
+    fn commission_update(
+        &mut self,
+        rate: [u64; 2],
+        limit: Option<CommissionLimit>,
+        h: BlockHeight,
+    ) -> Result<()> {
+        self.commission_check_update(rate, limit, h).c(d!())?;
+        if !upgrade::is_active(Feature::CommissionRule, h) {
+            self.commission_rate = rate;
+            return Ok(());
+        }
+
+        if limit.is_some() {
+            self.commission_limit = limit;
+        }
+
+        if rate == self.commission_rate {
+            return Ok(());
+        }
+
+        if Ordering::Greater == rate_cmp(rate, self.commission_rate) {
+            self.commission_schedule = Some(CommissionSchedule {
+                rate,
+                effective_height: h + COMMISSION_CHANGE_BLOCK_ITV,
+            });
+        } else {
+            self.commission_rate = rate;
+        }
+        self.commission_changed_at = h;
+
+        Ok(())
+    }
+
+    /// A new validator has no commission changes,
+    /// and its commission limit, if any, is within the default one.
     #[inline(always)]
-    #[allow(missing_docs)]
     pub fn staking_is_basic_valid(&self) -> bool {
         self.td_power == 0
             && self.td_addr == td_pubkey_to_td_addr_bytes(&self.td_pubkey)
             && self.commission_rate[0] < self.commission_rate[1]
+            && self.commission_schedule.is_none()
+            && 0 == self.commission_changed_at
+            && self
+                .commission_limit
+                .map(|l| {
+                    l.is_valid()
+                        && l.is_within(&CommissionLimit::default())
+                        && Ordering::Greater
+                            != rate_cmp(self.commission_rate, l.max_rate)
+                })
+                .unwrap_or(true)
     }
 }
 
@@ -1822,6 +2737,10 @@ pub struct Delegation {
     pub proposer_rwd_cnt: u64,
     /// how many times you get delegation rewards
     pub delegation_rwd_cnt: u64,
+    /// fold the rewards into the principal every `AUTO_COMPOUND_BLOCK_ITV` blocks
+    #[serde(default)]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub auto_compound: bool,
 }
 
 /// Detail of each reward entry.
@@ -1833,6 +2752,9 @@ pub struct DelegationRwdDetail {
     pub amount: Amount,
     /// total penalties
     pub penalty_amount: Amount,
+    /// rewards that have been folded into the principal
+    #[serde(default)]
+    pub compound_amount: Amount,
     /// global reward rate
     pub return_rate: Option<[u128; 2]>,
     /// commission rate of the target validator
@@ -1903,12 +2825,6 @@ impl Delegation {
         coinbase_bl: Amount,
         td_addr: TendermintAddrRef,
     ) -> Result<u64> {
-        #[cfg(feature = "debug_env")]
-        const ZERO_AMOUNT_FIX_HEIGHT: BlockHeight = 0;
-
-        #[cfg(not(feature = "debug_env"))]
-        const ZERO_AMOUNT_FIX_HEIGHT: BlockHeight = 120_0000;
-
         if self.end_height < cur_height || DelegationState::Bond != self.state {
             return Ok(0);
         }
@@ -1927,7 +2843,7 @@ impl Delegation {
             .c(d!())
             .and_then(|mut am| {
                 if 0 == am {
-                    if ZERO_AMOUNT_FIX_HEIGHT < cur_height {
+                    if upgrade::is_active(Feature::ZeroAmountFix, cur_height) {
                         return Ok(0);
                     } else {
                         return Err(eg!("set rewards on zero amount"));
@@ -1957,6 +2873,7 @@ impl Delegation {
                         bond: self.amount(),
                         amount: n,
                         penalty_amount: 0,
+                        compound_amount: 0,
                         return_rate: Some(return_rate),
                         commission_rate: Some(commission_rate),
                         global_delegation_percent: Some(global_delegation_percent),
@@ -1986,36 +2903,20 @@ fn calculate_delegation_rewards(
     is_delegation_rwd: bool,
     cur_height: BlockHeight,
 ) -> Result<Amount> {
-    #[cfg(feature = "debug_env")]
-    const APY_FIX_HEIGHT: BlockHeight = 0;
+    let apy_fixed = upgrade::is_active(Feature::ApyFix, cur_height);
 
-    #[cfg(feature = "debug_env")]
-    const OVERFLOW_FIX_HEIGHT: BlockHeight = 0;
-
-    #[cfg(feature = "debug_env")]
-    const SECOND_FIX_HEIGHT: BlockHeight = 0;
-
-    #[cfg(not(feature = "debug_env"))]
-    const APY_FIX_HEIGHT: BlockHeight = 117_7000;
-
-    // logic apply at about 2021-11-11 14:30
-    #[cfg(not(feature = "debug_env"))]
-    const OVERFLOW_FIX_HEIGHT: BlockHeight = 124_7000;
-
-    #[cfg(not(feature = "debug_env"))]
-    const SECOND_FIX_HEIGHT: BlockHeight = 131_0000;
-
-    if OVERFLOW_FIX_HEIGHT < cur_height {
+    if upgrade::is_active(Feature::OverflowFix, cur_height) {
         let am = BigUint::from(amount);
         let total_am = BigUint::from(total_amount);
         let global_am = BigUint::from(global_amount);
         let block_itv = BLOCK_INTERVAL as u128;
 
-        let second_per_year: u128 = if SECOND_FIX_HEIGHT < cur_height {
-            365 * 24 * 3600
-        } else {
-            356 * 24 * 3600
-        };
+        let second_per_year: u128 =
+            if upgrade::is_active(Feature::SecondsPerYearFix, cur_height) {
+                365 * 24 * 3600
+            } else {
+                356 * 24 * 3600
+            };
 
         let calculate_self_only = || {
             let a1 = am.clone() * return_rate[0] * block_itv;
@@ -2024,7 +2925,7 @@ fn calculate_delegation_rewards(
             a1 / a2
         };
 
-        let n = if APY_FIX_HEIGHT < cur_height {
+        let n = if apy_fixed {
             if is_delegation_rwd {
                 // global_amount * am * return_rate[0] * block_itv / (return_rate[1] * (365 * 24 * 3600) * total_amount)
                 let a1 = global_am * am * return_rate[0] * block_itv;
@@ -2056,7 +2957,7 @@ fn calculate_delegation_rewards(
                 })
         };
 
-        if APY_FIX_HEIGHT < cur_height {
+        if apy_fixed {
             if is_delegation_rwd {
                 // # For delegation rewards:
                 //
@@ -2113,6 +3014,84 @@ impl Default for DelegationState {
     }
 }
 
+/// The jailed state of a validator.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Jail {
+    /// the byzantine behavior that caused the jailing
+    pub reason: ByzantineKind,
+    /// the height at which the validator was jailed
+    pub since: BlockHeight,
+    /// the validator can unjail itself since this height
+    pub until: BlockHeight,
+}
+
+/// Jail history of a validator.
+#[allow(missing_docs)]
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub enum JailEvent {
+    Jailed(Jail),
+    Unjailed,
+}
+
+/// Limits on the commission rate of a validator,
+/// they can only be tightened after being set.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct CommissionLimit {
+    /// the commission rate can not exceed this value
+    pub max_rate: [u64; 2],
+    /// the max change of the commission rate in `COMMISSION_CHANGE_BLOCK_ITV`
+    pub max_daily_change: [u64; 2],
+}
+
+impl Default for CommissionLimit {
+    fn default() -> Self {
+        CommissionLimit {
+            max_rate: MAX_COMMISSION_RATE,
+            max_daily_change: MAX_COMMISSION_DAILY_CHANGE,
+        }
+    }
+}
+
+impl CommissionLimit {
+    #[inline(always)]
+    fn is_valid(&self) -> bool {
+        0 < self.max_rate[1]
+            && self.max_rate[0] <= self.max_rate[1]
+            && 0 < self.max_daily_change[1]
+            && self.max_daily_change[0] <= self.max_daily_change[1]
+    }
+
+    #[inline(always)]
+    fn is_within(&self, other: &Self) -> bool {
+        Ordering::Greater != rate_cmp(self.max_rate, other.max_rate)
+            && Ordering::Greater
+                != rate_cmp(self.max_daily_change, other.max_daily_change)
+    }
+}
+
+/// A scheduled increase of the commission rate,
+/// delegators can see it before it takes effect.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct CommissionSchedule {
+    /// the new commission rate
+    pub rate: [u64; 2],
+    /// the height at which the new rate takes effect
+    pub effective_height: BlockHeight,
+}
+
+/// A re-delegation in its cooldown period.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ReDelegation {
+    /// the source validator
+    pub from: XfrPublicKey,
+    /// the target validator
+    pub to: XfrPublicKey,
+    /// the amount that is still exposed to the penalties of `from`
+    pub amount: Amount,
+    /// the height at which the cooldown period ends
+    pub end_height: BlockHeight,
+}
+
 #[allow(missing_docs)]
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
 pub struct PartialUnDelegation {
@@ -2136,6 +3115,39 @@ impl PartialUnDelegation {
     }
 }
 
+// record a penalty in the reward history of the delegator
+fn governance_penalty_record(d: &Delegation, am: Amount, h: BlockHeight) {
+    let r = DelegationRwdDetail {
+        bond: d.amount(),
+        amount: 0,
+        penalty_amount: am,
+        compound_amount: 0,
+        return_rate: None,
+        commission_rate: None,
+        global_delegation_percent: None,
+        block_height: h,
+    };
+    CHAN_D_RWD_HIST.0.lock().send((d.id, h, r, None)).unwrap();
+}
+
+#[inline(always)]
+fn is_zero(n: &u64) -> bool {
+    0 == *n
+}
+
+#[inline(always)]
+fn rate_cmp(a: [u64; 2], b: [u64; 2]) -> Ordering {
+    (a[0] as u128 * b[1] as u128).cmp(&(b[0] as u128 * a[1] as u128))
+}
+
+// `|a - b| > max`
+fn rate_diff_gt(a: [u64; 2], b: [u64; 2], max: [u64; 2]) -> bool {
+    let x = BigUint::from(a[0]) * b[1];
+    let y = BigUint::from(b[0]) * a[1];
+    let diff = if x > y { x - y } else { y - x };
+    diff * max[1] > BigUint::from(max[0]) * a[1] * b[1]
+}
+
 // All transactions sent from CoinBase must support idempotence.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 struct CoinBase {
@@ -2256,7 +3268,10 @@ pub fn deny_relative_inputs(x: &TransferAsset) -> Result<()> {
 #[cfg(test)]
 #[allow(missing_docs)]
 mod test {
-    use super::*;
+    use {
+        super::*, crate::upgrade::NetworkProfile, rand_chacha::ChaChaRng,
+        rand_core::SeedableRng,
+    };
 
     // **NOTE**
     //
@@ -2289,6 +3304,456 @@ mod test {
         });
     }
 
+    #[test]
+    fn staking_governance_rules() {
+        let mut s = Staking::new();
+        s.set_custom_block_height(10);
+
+        let kind = ByzantineKind::DuplicateVote;
+        assert_eq!(s.governance_get_rule(&kind), DEFAULT_RULES.get(&kind));
+
+        // must be a future height
+        assert!(s
+            .governance_set_rules_at_height(
+                10,
+                map! {B kind.clone() => Rule::new([1, 2])}
+            )
+            .is_err());
+
+        pnk!(s.governance_set_rules_at_height(
+            20,
+            map! {B kind.clone() => Rule::new([1, 2])}
+        ));
+        assert!(s
+            .governance_set_rules_at_height(
+                20,
+                map! {B kind.clone() => Rule::new([1, 3])}
+            )
+            .is_err());
+        assert_eq!(s.governance_get_rule(&kind), DEFAULT_RULES.get(&kind));
+        assert_eq!(2, s.governance_get_rules_hist().len());
+
+        s.set_custom_block_height(20);
+        assert_eq!(s.governance_get_rule(&kind), Some(&Rule::new([1, 2])));
+
+        // other kinds are inherited
+        let other = ByzantineKind::OffLine;
+        assert_eq!(s.governance_get_rule(&other), DEFAULT_RULES.get(&other));
+    }
+
+    #[test]
+    fn staking_chain_params() {
+        let mut prng = ChaChaRng::from_entropy();
+        let (mut s, kps, _) = gen_staking(&mut prng, 10, 999);
+        pnk!(s.validator_get_current_mut()).cosig_rule = pnk!(CoSigRule::new([2, 3]));
+        assert_eq!(s.chain_params(), ChainParams::default());
+
+        let mut params = ChainParams {
+            unbond_block_cnt: 100,
+            ..Default::default()
+        };
+
+        // version must be increased by one
+        assert!(s
+            .chain_params_propose(
+                params,
+                10,
+                kps.iter().take(1).map(|kp| kp.get_pk_ref())
+            )
+            .is_err());
+
+        params.version = 1;
+        let id = pnk!(s.chain_params_propose(
+            params,
+            10,
+            kps.iter().take(3).map(|kp| kp.get_pk_ref())
+        ));
+        assert!(s.chain_params_info().proposals.contains_key(&id));
+
+        // weight is not enough
+        pnk!(s.chain_params_vote(&id, kps.iter().take(6).map(|kp| kp.get_pk_ref())));
+        assert!(s.chain_params_info().proposals.contains_key(&id));
+
+        pnk!(s.chain_params_vote(
+            &id,
+            kps.iter().skip(6).take(1).map(|kp| kp.get_pk_ref())
+        ));
+        assert!(!s.chain_params_info().proposals.contains_key(&id));
+        assert_eq!(s.chain_params_latest(), params);
+        assert_eq!(s.chain_params(), ChainParams::default());
+
+        s.set_custom_block_height(10);
+        assert_eq!(s.chain_params(), params);
+
+        // expired proposals are cleaned along with each new block
+        let id = pnk!(s.chain_params_propose(
+            ChainParams {
+                version: 2,
+                ..params
+            },
+            20,
+            kps.iter().take(1).map(|kp| kp.get_pk_ref())
+        ));
+        s.set_custom_block_height(20);
+        s.delegation_process();
+        assert!(!s.chain_params_info().proposals.contains_key(&id));
+        assert_eq!(s.chain_params_info().hist.len(), 1);
+    }
+
+    // `n` initial validators, each of them has a power of `power`
+    fn gen_staking(
+        prng: &mut ChaChaRng,
+        n: usize,
+        power: Power,
+    ) -> (Staking, Vec<XfrKeyPair>, Vec<TendermintAddr>) {
+        let kps = (0..n)
+            .map(|_| XfrKeyPair::generate(prng))
+            .collect::<Vec<_>>();
+        let vs = kps
+            .iter()
+            .enumerate()
+            .map(|(i, kp)| {
+                Validator::new(
+                    vec![i as u8; 32],
+                    power,
+                    kp.get_pk(),
+                    [1, 5],
+                    Default::default(),
+                    ValidatorKind::Initor,
+                )
+            })
+            .collect::<Result<Vec<_>>>();
+        let addrs = pnk!(vs.as_ref())
+            .iter()
+            .map(|v| td_addr_to_string(&v.td_addr))
+            .collect::<Vec<_>>();
+
+        let mut s = Staking::new();
+        s.validator_set_at_height_force(1, pnk!(ValidatorData::new(1, pnk!(vs))));
+        s.set_custom_block_height(1);
+
+        (s, kps, addrs)
+    }
+
+    // five initial validators, each of them has a power of 1000,
+    // and has finished its self-delegation of 100
+    fn gen_staking_with_validators(
+        prng: &mut ChaChaRng,
+    ) -> (Staking, Vec<XfrKeyPair>, Vec<TendermintAddr>) {
+        let (mut s, kps, addrs) = gen_staking(prng, 5, 1000);
+        kps.iter().zip(addrs.iter()).for_each(|(kp, addr)| {
+            pnk!(s.delegate(kp.get_pk(), addr, 100));
+        });
+
+        (s, kps, addrs)
+    }
+
+    // a new delegator who has delegated `am` to `validator`
+    fn gen_delegator(
+        s: &mut Staking,
+        prng: &mut ChaChaRng,
+        validator: TendermintAddrRef,
+        am: Amount,
+    ) -> XfrPublicKey {
+        let owner = XfrKeyPair::generate(prng).get_pk();
+        pnk!(s.delegate(owner, validator, am));
+        owner
+    }
+
+    #[test]
+    fn staking_redelegation() {
+        let mut prng = ChaChaRng::from_entropy();
+        let (mut s, kps, addrs) = gen_staking_with_validators(&mut prng);
+
+        let (v0, v1) = (kps[0].get_pk(), kps[1].get_pk());
+        let owner = gen_delegator(&mut s, &mut prng, &addrs[0], 300);
+
+        // self-delegation can not be moved
+        assert!(s.redelegate(&v0, &addrs[0], &addrs[1], None).is_err());
+        // more than delegated
+        assert!(s
+            .redelegate(&owner, &addrs[0], &addrs[1], Some(301))
+            .is_err());
+
+        pnk!(s.redelegate(&owner, &addrs[0], &addrs[1], Some(200)));
+        let d = pnk!(s.delegation_get(&owner));
+        assert_eq!(d.entries, map! {B v0 => 100, v1 => 200});
+        assert_eq!(pnk!(s.validator_get_power(&v0)), 200);
+        assert_eq!(pnk!(s.validator_get_power(&v1)), 300);
+
+        // no hopping during the cooldown period
+        assert!(s.redelegate(&owner, &addrs[1], &addrs[0], None).is_err());
+
+        pnk!(s.redelegate(&owner, &addrs[0], &addrs[1], None));
+        let d = pnk!(s.delegation_get(&owner));
+        assert_eq!(d.entries, map! {B v1 => 300});
+        assert_eq!(2, s.redelegation_get(&owner).len());
+
+        // moved FRAs are still exposed to the penalties of the source validator,
+        // they are taken from the target validator
+        pnk!(s.governance_penalty_by_pubkey(&v0, [1, 2]));
+        let d = pnk!(s.delegation_get(&owner));
+        assert_eq!(d.amount(), 150);
+        assert_eq!(d.entries, map! {B v1 => 150});
+        assert_eq!(pnk!(s.delegation_get(&v0)).amount(), 50);
+
+        // the power and the delegations of the target stay consistent
+        let v = pnk!(s.validator_get_current_one_by_id(&v1));
+        assert_eq!(v.delegators.get(&owner), Some(&150));
+        assert_eq!(
+            pnk!(s.validator_get_power(&v1)),
+            pnk!(s.delegation_get(&v1)).amount() + v.delegators.values().sum::<u64>()
+        );
+        assert_eq!(pnk!(s.validator_get_power(&v1)), 250);
+
+        s.set_custom_block_height(1 + REDELEGATION_COOLDOWN_BLOCK_CNT);
+        s.delegation_process();
+        assert!(s.redelegation_get(&owner).is_empty());
+        pnk!(s.redelegate(&owner, &addrs[1], &addrs[0], Some(50)));
+    }
+
+    #[test]
+    fn staking_auto_compound() {
+        let mut prng = ChaChaRng::from_entropy();
+        let (mut s, kps, addrs) = gen_staking_with_validators(&mut prng);
+
+        let (v0, v1) = (kps[0].get_pk(), kps[1].get_pk());
+        let owner = gen_delegator(&mut s, &mut prng, &addrs[0], 300);
+        let whale = gen_delegator(&mut s, &mut prng, &addrs[1], 200);
+
+        assert!(s
+            .delegation_set_auto_compound(
+                &XfrKeyPair::generate(&mut prng).get_pk(),
+                true
+            )
+            .is_err());
+        pnk!(s.delegation_set_auto_compound(&owner, true));
+        pnk!(s.delegation_set_auto_compound(&whale, true));
+        pnk!(s.delegation_get_mut(&owner)).rwd_amount = 30;
+        // too much for the power limit of `v1`
+        pnk!(s.delegation_get_mut(&whale)).rwd_amount = 10000;
+
+        let principal = s.coinbase_principal_balance();
+        let global = s.get_global_delegation_amount();
+
+        s.set_custom_block_height(AUTO_COMPOUND_BLOCK_ITV);
+        s.delegation_process();
+
+        let d = pnk!(s.delegation_get(&owner));
+        assert_eq!(d.entries, map! {B v0 => 330});
+        assert_eq!(d.rwd_amount, 0);
+        assert_eq!(pnk!(s.validator_get_power(&v0)), 430);
+
+        let d = pnk!(s.delegation_get(&whale));
+        assert_eq!(d.entries, map! {B v1 => 200});
+        assert_eq!(d.rwd_amount, 10000);
+        assert_eq!(pnk!(s.validator_get_power(&v1)), 300);
+
+        assert_eq!(s.coinbase_principal_balance(), principal + 30);
+        assert_eq!(s.get_global_delegation_amount(), global + 30);
+    }
+
+    #[test]
+    fn staking_jail() {
+        let mut prng = ChaChaRng::from_entropy();
+        let (mut s, kps, addrs) = gen_staking(&mut prng, VALIDATORS_MIN + 1, 1000);
+        kps.iter().zip(addrs.iter()).for_each(|(kp, addr)| {
+            pnk!(s.delegate(kp.get_pk(), addr, 100));
+        });
+
+        let v0 = kps[0].get_pk();
+        let owner = XfrKeyPair::generate(&mut prng).get_pk();
+        let kind = ByzantineKind::OffLine;
+
+        pnk!(s.validator_get_current_mut_one_by_id(&v0)).missed_blocks_in_row =
+            MAX_MISSED_BLOCKS_IN_ROW;
+
+        // nobody will be jailed before the activation
+        upgrade::with_profile(NetworkProfile::Mainnet, || {
+            pnk!(ops::governance::governance_penalty_tendermint_auto(
+                &mut s, &addrs[0], &kind
+            ));
+            assert!(!s.validator_is_jailed(&v0));
+        });
+
+        upgrade::with_profile(NetworkProfile::Local, || {
+            // an offline validator will not be jailed at once
+            pnk!(s.validator_get_current_mut_one_by_id(&v0)).missed_blocks_in_row = 0;
+            pnk!(ops::governance::governance_penalty_tendermint_auto(
+                &mut s, &addrs[0], &kind
+            ));
+            assert!(!s.validator_is_jailed(&v0));
+
+            pnk!(s.validator_get_current_mut_one_by_id(&v0)).missed_blocks_in_row =
+                MAX_MISSED_BLOCKS_IN_ROW;
+            pnk!(ops::governance::governance_penalty_tendermint_auto(
+                &mut s, &addrs[0], &kind
+            ));
+            assert!(s.validator_is_jailed(&v0));
+
+            // no new delegations
+            assert!(s.delegate(owner, &addrs[0], 100).is_err());
+
+            // too few validators would be left
+            pnk!(s.validator_jail(&kps[1].get_pk(), ByzantineKind::DuplicateVote));
+            assert!(!s.validator_is_jailed(&kps[1].get_pk()));
+
+            // too early
+            assert!(s.validator_unjail(&v0).is_err());
+
+            s.set_custom_block_height(1 + MIN_JAIL_BLOCK_CNT);
+            pnk!(s.validator_unjail(&v0));
+            assert!(!s.validator_is_jailed(&v0));
+            assert!(s.validator_unjail(&v0).is_err());
+            pnk!(s.delegate(owner, &addrs[0], 100));
+        });
+    }
+
+    #[test]
+    fn staking_commission_limit() {
+        let mut prng = ChaChaRng::from_entropy();
+        let (mut s, kps, _) = gen_staking_with_validators(&mut prng);
+
+        let v0 = kps[0].get_pk();
+        let new_v = |rate: [u64; 2], limit: Option<CommissionLimit>| {
+            let mut v = pnk!(Validator::new_staker(
+                vec![0; 32],
+                v0,
+                rate,
+                Default::default()
+            ));
+            v.commission_limit = limit;
+            v
+        };
+        let rate_of =
+            |s: &Staking| pnk!(s.validator_get_current_one_by_id(&v0)).commission_rate;
+
+        // any change is accepted at once before the activation
+        upgrade::with_profile(NetworkProfile::Mainnet, || {
+            pnk!(s.update_staker(&new_v([30, 100], None)));
+            assert_eq!(rate_of(&s), [30, 100]);
+            pnk!(s.update_staker(&new_v([1, 5], None)));
+            assert_eq!(rate_of(&s), [1, 5]);
+            assert_eq!(
+                0,
+                pnk!(s.validator_get_current_one_by_id(&v0)).commission_changed_at
+            );
+
+            // the commission fields of a new validator are always checked
+            assert!(new_v([1, 100], None).staking_is_basic_valid());
+            let mut v = new_v([1, 100], None);
+            v.commission_changed_at = 1;
+            assert!(!v.staking_is_basic_valid());
+            let loose = CommissionLimit {
+                max_daily_change: [10, 100],
+                ..Default::default()
+            };
+            assert!(!new_v([1, 100], Some(loose)).staking_is_basic_valid());
+        });
+
+        upgrade::with_profile(NetworkProfile::Local, || {
+            // exceeds the max daily change
+            assert!(s.update_staker(&new_v([30, 100], None)).is_err());
+
+            // an increase is scheduled
+            pnk!(s.update_staker(&new_v([24, 100], None)));
+            assert_eq!(rate_of(&s), [1, 5]);
+            assert_eq!(
+                pnk!(s.validator_get_current_one_by_id(&v0)).commission_schedule,
+                Some(CommissionSchedule {
+                    rate: [24, 100],
+                    effective_height: 1 + COMMISSION_CHANGE_BLOCK_ITV,
+                })
+            );
+
+            // changed too frequently
+            assert!(s.update_staker(&new_v([20, 100], None)).is_err());
+
+            s.set_custom_block_height(1 + COMMISSION_CHANGE_BLOCK_ITV);
+            s.delegation_process();
+            assert_eq!(rate_of(&s), [24, 100]);
+            assert!(pnk!(s.validator_get_current_one_by_id(&v0))
+                .commission_schedule
+                .is_none());
+
+            // a decrease takes effect at once
+            pnk!(s.update_staker(&new_v([22, 100], None)));
+            assert_eq!(rate_of(&s), [22, 100]);
+
+            // limits can only be tightened
+            let limit = CommissionLimit {
+                max_rate: [22, 100],
+                max_daily_change: [1, 100],
+            };
+            pnk!(s.update_staker(&new_v([22, 100], Some(limit))));
+            let loose = CommissionLimit {
+                max_rate: [23, 100],
+                ..limit
+            };
+            assert!(s.update_staker(&new_v([22, 100], Some(loose))).is_err());
+
+            s.set_custom_block_height(1 + 2 * COMMISSION_CHANGE_BLOCK_ITV);
+            assert!(s.update_staker(&new_v([23, 100], None)).is_err());
+            assert_eq!(
+                pnk!(s.validator_get_current_one_by_id(&v0)).get_commission_limit(),
+                limit
+            );
+        });
+    }
+
+    #[test]
+    fn staking_hash_fields() {
+        let mut prng = ChaChaRng::from_entropy();
+        let (mut s, _, addrs) = gen_staking_with_validators(&mut prng);
+
+        let changed = |a: &BTreeMap<String, String>, b: &BTreeMap<String, String>| {
+            a.iter()
+                .filter(|(k, v)| b.get(*k) != Some(v))
+                .map(|(k, _)| k.as_str().to_owned())
+                .collect::<Vec<_>>()
+        };
+
+        let h0 = pnk!(s.hash_fields());
+        s.set_custom_block_height(2);
+        let h1 = pnk!(s.hash_fields());
+        assert_eq!(changed(&h0, &h1), vec!["cur_height".to_owned()]);
+
+        let owner = XfrKeyPair::generate(&mut prng).get_pk();
+        pnk!(s.delegate(owner, &addrs[0], 100));
+        let h2 = pnk!(s.hash_fields());
+        let c = changed(&h1, &h2);
+        assert!(c.contains(&"di".to_owned()) && c.contains(&"vi".to_owned()));
+        assert!(!c.contains(&"cur_height".to_owned()));
+    }
+
+    #[test]
+    fn staking_changed_entries() {
+        let mut prng = ChaChaRng::from_entropy();
+        let (s, _, addrs) = gen_staking_with_validators(&mut prng);
+        assert!(pnk!(s.changed_entries(&s.clone())).is_empty());
+
+        let mut s1 = s.clone();
+        gen_delegator(&mut s1, &mut prng, &addrs[0], 100);
+
+        let changes = pnk!(s.changed_entries(&s1))
+            .into_iter()
+            .map(|(path, old, new)| (path, (old, new)))
+            .collect::<BTreeMap<_, _>>();
+
+        assert!(changes.contains_key("/vi") && changes.contains_key("/di"));
+        assert!(!changes.contains_key("/cur_height") && !changes.contains_key("/cr"));
+        changes.values().for_each(|(old, new)| {
+            assert!(old.is_some() && new.is_some() && old != new);
+        });
+
+        // every field of the struct is compared
+        let mut s2 = s.clone();
+        s2.set_custom_block_height(s.cur_height() + 1);
+        let changes = pnk!(s.changed_entries(&s2));
+        assert_eq!(1, changes.len());
+        assert_eq!("/cur_height", changes[0].0);
+    }
+
     fn gen_round_vote_percent(lower_bound: u64, upper_bound: u64) -> [u64; 2] {
         let itv = upper_bound - lower_bound;
         let lb = if 0 == itv {
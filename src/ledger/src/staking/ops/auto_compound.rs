@@ -0,0 +1,106 @@
+//!
+//! # Auto-Compound Operation
+//!
+//! Turn on/off the auto-compounding of the rewards of a delegation,
+//! so that there is no need to claim and delegate them again by hand.
+//!
+
+use {
+    crate::{data_model::NoReplayToken, staking::Staking},
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    zei::xfr::sig::{XfrKeyPair, XfrPublicKey, XfrSignature},
+};
+
+/// Used as the inner object of a `AutoCompound Operation`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct AutoCompoundOps {
+    pub(crate) body: Data,
+    pub(crate) pubkey: XfrPublicKey,
+    signature: XfrSignature,
+}
+
+impl AutoCompoundOps {
+    /// Check the validity of an operation by running it in a staking simulator.
+    #[inline(always)]
+    pub fn check_run(&self, staking_simulator: &mut Staking) -> Result<()> {
+        self.apply(staking_simulator).c(d!())
+    }
+
+    /// Apply the new setting to the target `Staking` instance.
+    pub fn apply(&self, staking: &mut Staking) -> Result<()> {
+        self.verify().c(d!()).and_then(|_| {
+            staking
+                .delegation_set_auto_compound(&self.pubkey, self.body.enable)
+                .c(d!())
+        })
+    }
+
+    /// Verify signature.
+    #[inline(always)]
+    pub fn verify(&self) -> Result<()> {
+        self.pubkey
+            .verify(&self.body.to_bytes(), &self.signature)
+            .c(d!())
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn get_related_pubkeys(&self) -> Vec<XfrPublicKey> {
+        vec![self.pubkey]
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn new(keypair: &XfrKeyPair, enable: bool, nonce: NoReplayToken) -> Self {
+        let body = Data::new(enable, nonce);
+        let signature = keypair.sign(&body.to_bytes());
+        AutoCompoundOps {
+            body,
+            pubkey: keypair.get_pk(),
+            signature,
+        }
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn set_nonce(&mut self, nonce: NoReplayToken) {
+        self.body.set_nonce(nonce);
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn get_nonce(&self) -> NoReplayToken {
+        self.body.get_nonce()
+    }
+}
+
+/// The body of a auto-compound operation.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Data {
+    /// `false` to turn off
+    pub enable: bool,
+    nonce: NoReplayToken,
+}
+
+impl Data {
+    #[inline(always)]
+    fn new(enable: bool, nonce: NoReplayToken) -> Self {
+        Data { enable, nonce }
+    }
+
+    #[inline(always)]
+    fn to_bytes(&self) -> Vec<u8> {
+        pnk!(bincode::serialize(self))
+    }
+
+    #[inline(always)]
+    fn set_nonce(&mut self, nonce: NoReplayToken) {
+        self.nonce = nonce;
+    }
+
+    #[inline(always)]
+    fn get_nonce(&self) -> NoReplayToken {
+        self.nonce
+    }
+}
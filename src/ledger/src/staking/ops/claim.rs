@@ -5,7 +5,10 @@
 //!
 
 use {
-    crate::{data_model::NoReplayToken, staking::Staking},
+    crate::{
+        data_model::{gen_random_keypair, NoReplayToken},
+        staking::Staking,
+    },
     ruc::*,
     serde::{Deserialize, Serialize},
     zei::xfr::sig::{XfrKeyPair, XfrPublicKey, XfrSignature},
@@ -65,6 +68,28 @@ impl ClaimOps {
         }
     }
 
+    /// Create an operation without the signature of its owner,
+    /// it must be signed by `sign` before being submitted.
+    pub fn new_unsigned(
+        pubkey: XfrPublicKey,
+        amount: Option<u64>,
+        nonce: NoReplayToken,
+    ) -> Self {
+        // the signature of a random key is just a placeholder
+        let mut op = Self::new(&gen_random_keypair(), amount, nonce);
+        op.pubkey = pubkey;
+        op
+    }
+
+    /// Sign the body again, the key must match the public key of the owner.
+    pub fn sign(&mut self, keypair: &XfrKeyPair) -> Result<()> {
+        if keypair.get_pk_ref() != &self.pubkey {
+            return Err(eg!("public key mismatch"));
+        }
+        self.signature = keypair.sign(&self.body.to_bytes());
+        Ok(())
+    }
+
     #[inline(always)]
     #[allow(missing_docs)]
     pub fn set_nonce(&mut self, nonce: NoReplayToken) {
@@ -7,12 +7,12 @@
 use {
     crate::{
         data_model::{
-            NoReplayToken, Operation, Transaction, ASSET_TYPE_FRA,
+            gen_random_keypair, NoReplayToken, Operation, Transaction, ASSET_TYPE_FRA,
             BLACK_HOLE_PUBKEY_STAKING,
         },
         staking::{
             deny_relative_inputs, td_addr_to_string, Amount, Staking, TendermintAddr,
-            Validator, STAKING_VALIDATOR_MIN_POWER,
+            Validator,
         },
     },
     ed25519_dalek::Signer,
@@ -93,7 +93,7 @@ impl DelegationOps {
             let h = staking.cur_height;
 
             if !v.staking_is_basic_valid()
-                || am < STAKING_VALIDATOR_MIN_POWER
+                || am < staking.chain_params().staking_validator_min_power
                 || self.body.validator != td_addr_to_string(&v.td_addr)
             {
                 return Err(eg!("invalid"));
@@ -136,6 +136,39 @@ impl DelegationOps {
         }
     }
 
+    /// Create an operation without the signature of its owner,
+    /// it must be signed by `sign` before being submitted,
+    /// so that the owner key can be kept on an offline machine.
+    pub fn new_unsigned(
+        pubkey: XfrPublicKey,
+        vltor_key: Option<&PrivateKey>,
+        amount: Amount,
+        validator: TendermintAddr,
+        new_validator: Option<Validator>,
+        nonce: NoReplayToken,
+    ) -> Self {
+        // the signature of a random key is just a placeholder
+        let mut op = Self::new(
+            &gen_random_keypair(),
+            vltor_key,
+            amount,
+            validator,
+            new_validator,
+            nonce,
+        );
+        op.pubkey = pubkey;
+        op
+    }
+
+    /// Sign the body again, the key must match the public key of the owner.
+    pub fn sign(&mut self, keypair: &XfrKeyPair) -> Result<()> {
+        if keypair.get_pk_ref() != &self.pubkey {
+            return Err(eg!("public key mismatch"));
+        }
+        self.signature = keypair.sign(&self.body.to_bytes());
+        Ok(())
+    }
+
     #[inline(always)]
     #[allow(missing_docs)]
     pub fn set_nonce(&mut self, nonce: NoReplayToken) {
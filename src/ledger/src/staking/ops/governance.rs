@@ -10,7 +10,11 @@
 use {
     crate::{
         data_model::NoReplayToken,
-        staking::{cosig::CoSigOp, Staking, TendermintAddrRef, BLOCK_HEIGHT_MAX},
+        staking::{
+            cosig::CoSigOp, Staking, TendermintAddrRef, BLOCK_HEIGHT_MAX,
+            MAX_MISSED_BLOCKS_IN_ROW,
+        },
+        upgrade::{self, Feature},
     },
     lazy_static::lazy_static,
     ruc::*,
@@ -20,10 +24,9 @@ use {
 };
 
 lazy_static! {
-    // The current MVP version is a fixed rule,
-    // and it will be upgraded to a mechanism
-    // that can update rules by sending a specific transaction.
-    static ref RULES: BTreeMap<ByzantineKind, Rule> = {
+    /// The built-in penalty rules, they will be used
+    /// until new rules are set by an `UpdateGovernanceRules` operation.
+    pub static ref DEFAULT_RULES: RuleSet = {
         map! { B
             ByzantineKind::DuplicateVote => Rule::new([5, 100]),
             ByzantineKind::LightClientAttack => Rule::new([1, 100]),
@@ -48,7 +51,12 @@ impl GovernanceOps {
     pub fn apply(&self, staking: &mut Staking) -> Result<()> {
         self.verify(staking)
             .c(d!())
-            .and_then(|_| RULES.get(&self.data.kind).ok_or(eg!()))
+            .and_then(|_| {
+                staking
+                    .governance_get_rule(&self.data.kind)
+                    .cloned()
+                    .c(d!())
+            })
             .and_then(|rule| {
                 staking
                     .governance_penalty_by_pubkey(
@@ -59,6 +67,20 @@ impl GovernanceOps {
                     )
                     .c(d!())
             })
+            .and_then(|_| {
+                // only validators can be jailed
+                if upgrade::is_active(Feature::ValidatorJail, staking.cur_height())
+                    && staking
+                        .validator_get_current_one_by_id(&self.data.byzantine_id)
+                        .is_some()
+                {
+                    staking
+                        .validator_jail(&self.data.byzantine_id, self.data.kind.clone())
+                        .c(d!())
+                } else {
+                    Ok(())
+                }
+            })
     }
 
     #[inline(always)]
@@ -139,10 +161,23 @@ pub struct Rule {
 }
 
 impl Rule {
-    fn new(penalty_percent: [u64; 2]) -> Self {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn new(penalty_percent: [u64; 2]) -> Self {
         Rule { penalty_percent }
     }
 
+    /// A valid percent must be in the range of `[0, 1]`.
+    #[inline(always)]
+    pub fn check(&self) -> Result<()> {
+        let p = self.penalty_percent;
+        if 0 == p[1] || p[1] > i64::MAX as u64 || p[0] > p[1] {
+            Err(eg!("invalid penalty percent"))
+        } else {
+            Ok(())
+        }
+    }
+
     #[inline(always)]
     #[allow(missing_docs)]
     pub fn gen_penalty_percent(&self) -> [u64; 2] {
@@ -150,17 +185,22 @@ impl Rule {
     }
 }
 
-/// Penalize the FRAs by a specified address.
+/// Penalize the FRAs by a specified address,
+/// and put the validator into jail.
 ///
 /// Any validator who has unstaked itself should not be punished,
 /// its delegators should not be punished also.
+///
+/// An offline validator will only be jailed
+/// after it has missed `MAX_MISSED_BLOCKS_IN_ROW` blocks,
+/// and nobody will be jailed before `Feature::ValidatorJail` is active.
 #[inline(always)]
 pub fn governance_penalty_tendermint_auto(
     staking: &mut Staking,
     addr: TendermintAddrRef,
     bz_kind: &ByzantineKind,
 ) -> Result<()> {
-    let rule = RULES.get(bz_kind).ok_or(eg!())?;
+    let rule = staking.governance_get_rule(bz_kind).cloned().c(d!())?;
     staking
         .validator_td_addr_to_app_pk(addr)
         .c(d!())
@@ -175,7 +215,24 @@ pub fn governance_penalty_tendermint_auto(
                     }
                     staking
                         .governance_penalty_by_pubkey(&pk, rule.gen_penalty_percent())
-                        .c(d!())
+                        .c(d!())?;
+
+                    if !upgrade::is_active(Feature::ValidatorJail, staking.cur_height())
+                    {
+                        return Ok(());
+                    }
+
+                    let missed = staking
+                        .validator_get_current_one_by_id(&pk)
+                        .map(|v| v.missed_blocks_in_row)
+                        .c(d!())?;
+                    if ByzantineKind::OffLine != *bz_kind
+                        || MAX_MISSED_BLOCKS_IN_ROW <= missed
+                    {
+                        staking.validator_jail(&pk, bz_kind.clone()).c(d!())?;
+                    }
+
+                    Ok(())
                 })
         })
 }
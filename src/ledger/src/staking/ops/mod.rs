@@ -7,11 +7,16 @@
 //! In the current implementation, the first operation must be a `TransferAsset`.
 //!
 
+pub mod auto_compound;
 pub mod claim;
 pub mod delegation;
 pub mod fra_distribution;
 pub mod governance;
 pub mod mint_fra;
+pub mod redelegation;
 pub mod undelegation;
+pub mod unjail;
+pub mod update_chain_params;
+pub mod update_governance_rules;
 pub mod update_staker;
 pub mod update_validator;
@@ -0,0 +1,134 @@
+//!
+//! # Re-Delegation Operation
+//!
+//! Move some or all of the delegated FRAs from one validator to another,
+//! without waiting for the unbond period.
+//!
+
+use {
+    crate::{
+        data_model::NoReplayToken,
+        staking::{Amount, Staking, TendermintAddr},
+    },
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    zei::xfr::sig::{XfrKeyPair, XfrPublicKey, XfrSignature},
+};
+
+/// Used as the inner object of a `ReDelegation Operation`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ReDelegationOps {
+    pub(crate) body: Data,
+    pub(crate) pubkey: XfrPublicKey,
+    signature: XfrSignature,
+}
+
+impl ReDelegationOps {
+    /// Check the validity of an operation by running it in a staking simulator.
+    #[inline(always)]
+    pub fn check_run(&self, staking_simulator: &mut Staking) -> Result<()> {
+        self.apply(staking_simulator).c(d!())
+    }
+
+    /// Apply new re-delegation to the target `Staking` instance.
+    pub fn apply(&self, staking: &mut Staking) -> Result<()> {
+        self.verify().c(d!()).and_then(|_| {
+            staking
+                .redelegate(
+                    &self.pubkey,
+                    &self.body.from,
+                    &self.body.to,
+                    self.body.amount,
+                )
+                .c(d!())
+        })
+    }
+
+    /// Verify signature.
+    #[inline(always)]
+    pub fn verify(&self) -> Result<()> {
+        self.pubkey
+            .verify(&self.body.to_bytes(), &self.signature)
+            .c(d!())
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn get_related_pubkeys(&self) -> Vec<XfrPublicKey> {
+        vec![self.pubkey]
+    }
+
+    /// `amount: None` means moving all the FRAs delegated to `from`.
+    #[inline(always)]
+    pub fn new(
+        keypair: &XfrKeyPair,
+        from: TendermintAddr,
+        to: TendermintAddr,
+        amount: Option<Amount>,
+        nonce: NoReplayToken,
+    ) -> Self {
+        let body = Data::new(from, to, amount, nonce);
+        let signature = keypair.sign(&body.to_bytes());
+        ReDelegationOps {
+            body,
+            pubkey: keypair.get_pk(),
+            signature,
+        }
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn set_nonce(&mut self, nonce: NoReplayToken) {
+        self.body.set_nonce(nonce);
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn get_nonce(&self) -> NoReplayToken {
+        self.body.get_nonce()
+    }
+}
+
+/// The body of a re-delegation operation.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Data {
+    /// the source validator
+    pub from: TendermintAddr,
+    /// the target validator
+    pub to: TendermintAddr,
+    /// how much to move, `None` means all
+    pub amount: Option<Amount>,
+    nonce: NoReplayToken,
+}
+
+impl Data {
+    #[inline(always)]
+    fn new(
+        from: TendermintAddr,
+        to: TendermintAddr,
+        amount: Option<Amount>,
+        nonce: NoReplayToken,
+    ) -> Self {
+        Data {
+            from,
+            to,
+            amount,
+            nonce,
+        }
+    }
+
+    #[inline(always)]
+    fn to_bytes(&self) -> Vec<u8> {
+        pnk!(bincode::serialize(self))
+    }
+
+    #[inline(always)]
+    fn set_nonce(&mut self, nonce: NoReplayToken) {
+        self.nonce = nonce;
+    }
+
+    #[inline(always)]
+    fn get_nonce(&self) -> NoReplayToken {
+        self.nonce
+    }
+}
@@ -0,0 +1,106 @@
+//!
+//! # Update Chain Parameters
+//!
+//! propose new chain parameters or vote for an existing proposal,
+//! validators vote by attaching their signatures,
+//! which may be collected in multiple transactions.
+//!
+//! A proposal will be accepted when the weight of its voters reaches
+//! the co-signature threshold of current validators,
+//! and the new parameters will be activated at the declared height.
+//!
+
+use {
+    crate::{
+        data_model::NoReplayToken,
+        staking::{
+            chain_params::{ChainParams, ProposalId},
+            cosig::CoSigOp,
+            BlockHeight, Staking,
+        },
+    },
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    zei::xfr::sig::{XfrKeyPair, XfrPublicKey},
+};
+
+/// Used as the inner object of a `UpdateChainParams Operation`.
+pub type UpdateChainParamsOps = CoSigOp<Data>;
+
+impl UpdateChainParamsOps {
+    /// Check the validity of an operation by running it in a staking simulator.
+    #[inline(always)]
+    pub fn check_run(&self, staking_simulator: &mut Staking) -> Result<()> {
+        self.apply(staking_simulator).c(d!())
+    }
+
+    /// Apply the proposal or the votes to the target `Staking` instance.
+    pub fn apply(&self, staking: &mut Staking) -> Result<()> {
+        self.verify_without_weight(staking).c(d!())?;
+
+        match &self.data {
+            Data::Propose { params, height } => staking
+                .chain_params_propose(*params, *height, self.signers())
+                .c(d!())
+                .map(|_| ()),
+            Data::Vote { proposal_id } => staking
+                .chain_params_vote(proposal_id, self.signers())
+                .c(d!()),
+        }
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn get_related_pubkeys(&self) -> Vec<XfrPublicKey> {
+        self.cosigs.keys().copied().collect()
+    }
+
+    /// Create a new proposal.
+    #[inline(always)]
+    pub fn new_proposal(
+        kps: &[&XfrKeyPair],
+        params: ChainParams,
+        height: BlockHeight,
+        nonce: NoReplayToken,
+    ) -> Result<Self> {
+        params.check().c(d!())?;
+        Self::new(kps, Data::Propose { params, height }, nonce).c(d!())
+    }
+
+    /// Vote for an existing proposal.
+    #[inline(always)]
+    pub fn new_vote(
+        kps: &[&XfrKeyPair],
+        proposal_id: ProposalId,
+        nonce: NoReplayToken,
+    ) -> Result<Self> {
+        Self::new(kps, Data::Vote { proposal_id }, nonce).c(d!())
+    }
+
+    #[inline(always)]
+    fn new(kps: &[&XfrKeyPair], data: Data, nonce: NoReplayToken) -> Result<Self> {
+        if kps.is_empty() {
+            return Err(eg!("no signers"));
+        }
+
+        let mut op = CoSigOp::create(data, nonce);
+        op.batch_sign(kps).c(d!()).map(|_| op)
+    }
+}
+
+/// The body of a `UpdateChainParams Operation`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Data {
+    /// Propose new parameters which will be activated at `height`.
+    Propose {
+        #[allow(missing_docs)]
+        params: ChainParams,
+        #[allow(missing_docs)]
+        height: BlockHeight,
+    },
+    /// Vote for an existing proposal.
+    Vote {
+        #[allow(missing_docs)]
+        proposal_id: ProposalId,
+    },
+}
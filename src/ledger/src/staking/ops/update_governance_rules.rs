@@ -0,0 +1,91 @@
+//!
+//! # Update Governance Rules
+//!
+//! update the penalty rules of byzantine behaviors
+//! at a certain future block height
+//! by using a multi-signature transaction.
+//!
+//! **NOTE**: always use the same multi-signature rules as `UpdateValidator`.
+//!
+
+use {
+    crate::{
+        data_model::NoReplayToken,
+        staking::{cosig::CoSigOp, ops::governance::RuleSet, BlockHeight, Staking},
+    },
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    zei::xfr::sig::{XfrKeyPair, XfrPublicKey},
+};
+
+/// Used as the inner object of a `UpdateGovernanceRules Operation`.
+pub type UpdateGovernanceRulesOps = CoSigOp<Data>;
+
+impl UpdateGovernanceRulesOps {
+    /// Check the validity of an operation by running it in a staking simulator.
+    #[inline(always)]
+    pub fn check_run(&self, staking_simulator: &mut Staking) -> Result<()> {
+        self.apply(staking_simulator).c(d!())
+    }
+
+    /// Apply new rules to the target `Staking` instance,
+    /// will fail if existing rules are found at the same height.
+    pub fn apply(&self, staking: &mut Staking) -> Result<()> {
+        self.verify(staking)
+            .c(d!())
+            .and_then(|_| self.data.check().c(d!()))
+            .and_then(|_| {
+                staking
+                    .governance_set_rules_at_height(
+                        self.data.height,
+                        self.data.rules.clone(),
+                    )
+                    .c(d!())
+            })
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn get_related_pubkeys(&self) -> Vec<XfrPublicKey> {
+        self.cosigs.keys().copied().collect()
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn new(
+        kps: &[&XfrKeyPair],
+        height: BlockHeight,
+        rules: RuleSet,
+        nonce: NoReplayToken,
+    ) -> Result<Self> {
+        let data = Data::new(height, rules);
+        data.check().c(d!())?;
+
+        let mut op = CoSigOp::create(data, nonce);
+        op.batch_sign(kps).c(d!()).map(|_| op)
+    }
+}
+
+/// The body of a `UpdateGovernanceRules Operation`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Data {
+    /// The new rules will be effective since this height.
+    pub height: BlockHeight,
+    /// Kinds not listed here will keep their previous rules.
+    pub rules: RuleSet,
+}
+
+impl Data {
+    #[inline(always)]
+    fn new(height: BlockHeight, rules: RuleSet) -> Self {
+        Data { height, rules }
+    }
+
+    #[inline(always)]
+    fn check(&self) -> Result<()> {
+        if self.rules.is_empty() {
+            return Err(eg!("empty rules"));
+        }
+        self.rules.values().try_for_each(|r| r.check().c(d!()))
+    }
+}
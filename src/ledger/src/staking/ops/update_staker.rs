@@ -70,7 +70,16 @@ impl UpdateStakerOps {
         staking
             .validator_td_addr_to_app_pk(&td_addr_to_string(&v.td_addr))
             .c(d!())?;
-        Ok(())
+        staking
+            .validator_get_current_one_by_id(&v.id)
+            .c(d!("validator not found"))?
+            .commission_check_update(
+                v.commission_rate,
+                v.commission_limit,
+                staking.cur_height(),
+            )
+            .c(d!())
+            .map(|_| ())
     }
 
     #[inline(always)]
@@ -10,7 +10,7 @@ use {
         data_model::NoReplayToken,
         staking::{
             cosig::CoSigOp, BlockHeight, Staking, Validator, ValidatorData,
-            COSIG_THRESHOLD_DEFAULT, VALIDATORS_MIN,
+            VALIDATORS_MIN,
         },
     },
     ruc::*,
@@ -32,7 +32,7 @@ impl UpdateValidatorOps {
     pub fn apply(&self, staking: &mut Staking) -> Result<()> {
         self.verify(staking)
             .c(d!())
-            .and_then(|_| self.check_context().c(d!()))
+            .and_then(|_| self.check_context(staking).c(d!()))
             .and_then(|_| {
                 staking
                     .validator_set_at_height(self.data.height, self.data.clone())
@@ -46,20 +46,20 @@ impl UpdateValidatorOps {
     pub fn apply_force(self, staking: &mut Staking) -> Result<()> {
         self.verify(staking)
             .c(d!())
-            .and_then(|_| self.check_context().c(d!()))
+            .and_then(|_| self.check_context(staking).c(d!()))
             .map(|_| staking.validator_set_at_height_force(self.data.height, self.data))
     }
 
     #[inline(always)]
-    fn check_context(&self) -> Result<()> {
+    fn check_context(&self, staking: &Staking) -> Result<()> {
         if VALIDATORS_MIN > self.data.body.len() {
             return Err(eg!("too few validators"));
         }
 
         let t1 = self.data.cosig_rule.threshold;
-        let t2 = COSIG_THRESHOLD_DEFAULT;
+        let t2 = staking.chain_params().cosig_threshold_default;
 
-        // threshold must be bigger than COSIG_THRESHOLD_DEFAULT
+        // threshold must be bigger than `cosig_threshold_default`
         if t1[0] * t2[1] < t1[1] * t2[0] {
             return Err(eg!("invalid cosig threshold"));
         }
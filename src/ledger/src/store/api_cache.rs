@@ -6,26 +6,124 @@ use crate::staking::TendermintAddr;
 use {
     crate::{
         data_model::{
-            AssetTypeCode, DefineAsset, IssueAsset, IssuerPublicKey, Operation,
-            Transaction, TxOutput, TxnIDHash, TxnSID, TxoSID, XfrAddress,
+            AssetTypeCode, BlockSID, DefineAsset, FinalizedBlock, IssueAsset,
+            IssuerPublicKey, Operation, StateCommitmentData, Transaction, TxOutput,
+            TxnIDHash, TxnSID, TxoRef, TxoSID, XfrAddress,
         },
+        light_client::{get_light_validators, LightHeader, SignedLightHeader},
         staking::{
             ops::mint_fra::MintEntry, Amount, BlockHeight, DelegationRwdDetail,
-            CHAN_D_AMOUNT_HIST, CHAN_D_RWD_HIST, CHAN_GLOB_RATE_HIST,
-            CHAN_V_SELF_D_HIST, KEEP_HIST,
+            JailEvent, CHAN_D_AMOUNT_HIST, CHAN_D_RWD_HIST, CHAN_GLOB_RATE_HIST,
+            CHAN_JAIL_HIST, CHAN_V_SELF_D_HIST, KEEP_HIST,
         },
         store::LedgerState,
     },
-    fbnc::{new_mapx, new_mapxnk, Mapx, Mapxnk},
-    globutils::wallet,
+    fbnc::{new_mapx, new_mapxnk, Mapx, Mapxnk, Vecx},
+    globutils::{wallet, HashOf},
+    lazy_static::lazy_static,
+    parking_lot::Mutex,
     ruc::*,
     serde::{Deserialize, Serialize},
-    std::collections::HashSet,
+    std::{
+        collections::{BTreeMap, BTreeSet, HashSet},
+        env,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            mpsc::{channel, Receiver, Sender},
+            Arc,
+        },
+    },
+    tendermint::PrivateKey,
     zei::xfr::{sig::XfrPublicKey, structs::OwnerMemo},
 };
 
 type Issuances = Vec<(TxOutput, Option<OwnerMemo>)>;
 
+type AECP = (Arc<Mutex<Sender<ApiEvent>>>, Arc<Mutex<Receiver<ApiEvent>>>);
+
+lazy_static! {
+    /// Events generated when a new block is cached,
+    /// consumed by the subscription service of the query server.
+    pub static ref CHAN_API_EVENTS: AECP = {
+        let (s, r) = channel();
+        (Arc::new(Mutex::new(s)), Arc::new(Mutex::new(r)))
+    };
+
+    /// Only the state commitments and the light headers of the latest N heights are kept,
+    /// set by `FINDORAD_STATE_COMMITMENT_HIST_CNT`, 0 means disabled.
+    static ref STATE_COMMITMENT_HIST_CNT: BlockHeight =
+        env::var("FINDORAD_STATE_COMMITMENT_HIST_CNT")
+            .ok()
+            .and_then(|n| n.parse::<BlockHeight>().ok())
+            .unwrap_or(10_0000);
+}
+
+// `block_commit_count` of the last block whose events have been sent,
+// an empty block will not create a new block in the ledger.
+static LAST_EVENT_BLOCK: AtomicU64 = AtomicU64::new(0);
+
+// tendermint height of the last `NewBlock` event,
+// the cache may be updated more than once at a height.
+static LAST_EVENT_HEIGHT: AtomicU64 = AtomicU64::new(0);
+
+#[inline(always)]
+fn send_api_events(events: Vec<ApiEvent>) {
+    let sender = CHAN_API_EVENTS.0.lock();
+    events.into_iter().for_each(|ev| {
+        // the receiver is a static, so this will never fail
+        sender.send(ev).unwrap();
+    });
+}
+
+/// Events that can be subscribed from the query server.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "type")]
+pub enum ApiEvent {
+    /// A new block has been committed.
+    NewBlock {
+        #[allow(missing_docs)]
+        height: BlockHeight,
+        /// (sid, hash) of the transactions in this block
+        txns: Vec<TxnIDHash>,
+    },
+    /// A transaction related to an address has been committed.
+    RelatedTxn {
+        #[allow(missing_docs)]
+        address: XfrAddress,
+        #[allow(missing_docs)]
+        txn: TxnIDHash,
+    },
+    /// A txo has been spent by a transaction.
+    TxoSpent {
+        #[allow(missing_docs)]
+        txo_sid: TxoSID,
+        #[allow(missing_docs)]
+        txn: TxnIDHash,
+    },
+    /// A staking operation has been applied to an address.
+    Staking {
+        #[allow(missing_docs)]
+        kind: StakingEventKind,
+        #[allow(missing_docs)]
+        pubkey: XfrPublicKey,
+        /// `None` if the amount is unknown or not applicable
+        amount: Option<Amount>,
+        #[allow(missing_docs)]
+        txn: TxnIDHash,
+    },
+}
+
+/// Kinds of staking events.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub enum StakingEventKind {
+    Delegation,
+    UnDelegation,
+    ReDelegation,
+    Claim,
+    CoinbasePay,
+}
+
 /// Used in APIs
 #[derive(Clone, Deserialize, Serialize)]
 pub struct ApiCache {
@@ -54,6 +152,9 @@ pub struct ApiCache {
     pub txn_sid_to_hash: Mapxnk<TxnSID, String>,
     /// txn hash to txn sid
     pub txn_hash_to_sid: Mapx<String, TxnSID>,
+    /// tendermint height to the block and the txns in it,
+    /// empty blocks are not included
+    pub height_to_block: Mapxnk<BlockHeight, (BlockSID, Vec<TxnSID>)>,
     /// global rate history
     pub staking_global_rate_hist: Mapxnk<BlockHeight, [u128; 2]>,
     /// - self-delegation amount history
@@ -68,6 +169,68 @@ pub struct ApiCache {
         Mapx<XfrPublicKey, Mapxnk<BlockHeight, DelegationRwdDetail>>,
     /// reward for each delegator comes from the validator they pledge
     pub delegation_validator_rwd: Mapx<XfrPublicKey, Mapx<TendermintAddr, Amount>>,
+    /// jail history of validators,
+    /// a validator may be jailed and released at the same height
+    pub staking_jail_hist: Mapx<XfrPublicKey, Mapxnk<BlockHeight, Vec<JailEvent>>>,
+    /// components of the state commitment of the latest heights
+    pub state_commitment_hist: Mapxnk<BlockHeight, StateCommitmentInfo>,
+    /// headers for light clients of the latest heights,
+    /// only signed by this node
+    pub light_headers: Mapxnk<BlockHeight, SignedLightHeader>,
+}
+
+/// Components of the state commitment at a height.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StateCommitmentInfo {
+    /// the commitment(aka app hash) in hex format
+    pub commitment: String,
+    /// the data whose hash is the commitment
+    pub data: Option<StateCommitmentData>,
+    /// digests of the sub-structures of `Staking` at this height,
+    /// they may differ from `data.staking` because of empty blocks
+    pub staking: BTreeMap<String, String>,
+}
+
+impl StateCommitmentInfo {
+    /// List the changed components between two states.
+    pub fn diff(&self, other: &Self) -> StateCommitmentDiff {
+        let to_map = |d: &Option<StateCommitmentData>| {
+            d.as_ref()
+                .and_then(|d| serde_json::to_value(d).ok())
+                .and_then(|v| v.as_object().cloned())
+                .unwrap_or_default()
+        };
+
+        StateCommitmentDiff {
+            data: diff_keys(&to_map(&self.data), &to_map(&other.data)),
+            staking: diff_keys(&self.staking, &other.staking),
+        }
+    }
+}
+
+/// The changed components between two states.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct StateCommitmentDiff {
+    /// changed fields of `StateCommitmentData`
+    pub data: Vec<String>,
+    /// changed sub-structures of `Staking`
+    pub staking: Vec<String>,
+}
+
+// keys whose values differ between `a` and `b`
+fn diff_keys<'a, V: PartialEq + 'a>(
+    a: impl IntoIterator<Item = (&'a String, &'a V)>,
+    b: impl IntoIterator<Item = (&'a String, &'a V)>,
+) -> Vec<String> {
+    let a = a.into_iter().collect::<BTreeMap<_, _>>();
+    let b = b.into_iter().collect::<BTreeMap<_, _>>();
+    a.keys()
+        .chain(b.keys())
+        .filter(|k| a.get(*k) != b.get(*k))
+        .map(|k| (*k).to_owned())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
 }
 
 impl ApiCache {
@@ -101,6 +264,7 @@ impl ApiCache {
             txo_to_txnid: new_mapxnk!(format!("api_cache/{}txo_to_txnid", prefix)),
             txn_sid_to_hash: new_mapxnk!(format!("api_cache/{}txn_sid_to_hash", prefix)),
             txn_hash_to_sid: new_mapx!(format!("api_cache/{}txn_hash_to_sid", prefix)),
+            height_to_block: new_mapxnk!(format!("api_cache/{}height_to_block", prefix)),
             staking_global_rate_hist: new_mapxnk!(format!(
                 "api_cache/{}staking_global_rate_hist",
                 prefix
@@ -121,9 +285,47 @@ impl ApiCache {
                 "api_cache/{}delegation_validator_rwd",
                 prefix
             )),
+            staking_jail_hist: new_mapx!(format!(
+                "api_cache/{}staking_jail_hist",
+                prefix
+            )),
+            state_commitment_hist: new_mapxnk!(format!(
+                "api_cache/{}state_commitment_hist",
+                prefix
+            )),
+            light_headers: new_mapxnk!(format!("api_cache/{}light_headers", prefix)),
         }
     }
 
+    /// Index the blocks committed before `height_to_block` was introduced,
+    /// the tendermint height of a block is recovered from its pulse count,
+    /// return how many blocks have been indexed.
+    pub fn backfill_height_to_block(&mut self, blocks: &Vecx<FinalizedBlock>) -> usize {
+        let mut cnt = 0;
+        for sid in 0..blocks.len() {
+            let block = if let Some(b) = blocks.get(sid) {
+                b
+            } else {
+                break;
+            };
+
+            // `pulse_count` = <tendermint height> - <block count>
+            let height = block.state.pulse_count + sid as u64 + 1;
+
+            // blocks after this one have been indexed
+            if self.height_to_block.get(&height).is_some() {
+                break;
+            }
+
+            self.height_to_block.insert(
+                height,
+                (BlockSID(sid), block.txns.iter().map(|t| t.tx_id).collect()),
+            );
+            cnt += 1;
+        }
+        cnt
+    }
+
     /// Add created asset
     #[inline(always)]
     pub fn add_created_asset(&mut self, creation: &DefineAsset) {
@@ -196,6 +398,18 @@ impl ApiCache {
                     .insert(h, r);
             });
 
+        CHAN_JAIL_HIST.1.lock().try_iter().for_each(|(pk, h, ev)| {
+            #[allow(unused_mut)]
+            let mut hist =
+                self.staking_jail_hist
+                    .entry(pk)
+                    .or_insert(new_mapxnk!(format!(
+                        "staking_jail_hist_subdata/{}",
+                        wallet::public_key_to_base64(&pk)
+                    )));
+            hist.entry(h).or_insert_with(Vec::new).push(ev);
+        });
+
         CHAN_D_RWD_HIST.1.lock().try_iter().for_each(|(pk, h, r, td_addr)| {
             #[allow(unused_mut)]
             let mut dd =
@@ -210,6 +424,7 @@ impl ApiCache {
             dd.block_height = r.block_height;
             dd.amount += r.amount;
             dd.penalty_amount += r.penalty_amount;
+            dd.compound_amount += r.compound_amount;
 
             alt!(0 < r.bond, dd.bond = r.bond);
             alt!(r.return_rate.is_some(), dd.return_rate = r.return_rate);
@@ -312,8 +527,13 @@ where
             Operation::Delegation(i) => staking_gen!(i),
             Operation::UnDelegation(i) => staking_gen!(i),
             Operation::Claim(i) => staking_gen!(i),
+            Operation::ReDelegation(i) => staking_gen!(i),
+            Operation::AutoCompound(i) => staking_gen!(i),
+            Operation::Unjail(i) => staking_gen!(i),
             Operation::UpdateValidator(i) => staking_gen!(i),
             Operation::Governance(i) => staking_gen!(i),
+            Operation::UpdateGovernanceRules(i) => staking_gen!(i),
+            Operation::UpdateChainParams(i) => staking_gen!(i),
             Operation::FraDistribution(i) => staking_gen!(i),
             Operation::MintFra(i) => staking_gen!(i),
             Operation::TransferAsset(transfer) => {
@@ -366,6 +586,24 @@ pub fn get_transferred_nonconfidential_assets(
     transferred_assets
 }
 
+/// Sign the light header of the current height with the tendermint key of this node,
+/// it should be called after `update_api_cache`.
+pub fn sign_light_header(ledger: &mut LedgerState, td_key: &PrivateKey) -> Result<()> {
+    if !*KEEP_HIST {
+        return Ok(());
+    }
+
+    let height = ledger.get_tendermint_height();
+    let headers = &mut ledger.api_cache.as_mut().unwrap().light_headers;
+    // no header if the history is disabled
+    if let Some(mut sh) = headers.get(&height) {
+        sh.signatures = vec![sh.header.sign(td_key).c(d!())?];
+        headers.insert(height, sh);
+    }
+
+    Ok(())
+}
+
 /// update the data of QueryServer when we create a new block in ABCI
 pub fn update_api_cache(ledger: &mut LedgerState) -> Result<()> {
     if !*KEEP_HIST {
@@ -374,18 +612,95 @@ pub fn update_api_cache(ledger: &mut LedgerState) -> Result<()> {
 
     ledger.api_cache.as_mut().unwrap().cache_hist_data();
 
+    let height = ledger.get_tendermint_height();
+    let block_cnt = ledger.get_block_commit_count();
+
+    if 0 < *STATE_COMMITMENT_HIST_CNT {
+        let sci = StateCommitmentInfo {
+            commitment: hex::encode(ledger.get_state_commitment().0.as_ref()),
+            data: ledger.get_state_commitment_data().cloned(),
+            staking: ledger.get_staking().hash_fields().c(d!())?,
+        };
+        let hist = &mut ledger.api_cache.as_mut().unwrap().state_commitment_hist;
+        hist.insert(height, sci);
+
+        // drop the expired ones, the oldest first
+        while let Some(h) = hist.iter().next().map(|(h, _)| h) {
+            if h.saturating_add(*STATE_COMMITMENT_HIST_CNT) > height {
+                break;
+            }
+            hist.remove(&h);
+        }
+
+        let vs = get_light_validators(ledger.get_staking());
+        let header = LightHeader {
+            height,
+            state_commitment: ledger.get_state_commitment().0,
+            next_validators_hash: HashOf::new(&vs),
+        };
+
+        let headers = &mut ledger.api_cache.as_mut().unwrap().light_headers;
+        // the validator set is attached if it differs from the one
+        // of the previous header, or if that header does not exist
+        let changed = headers
+            .get(&height.saturating_sub(1))
+            .map(|sh| sh.header.next_validators_hash != header.next_validators_hash)
+            .unwrap_or(true);
+        headers.insert(
+            height,
+            SignedLightHeader {
+                header,
+                next_validators: alt!(changed, Some(vs), None),
+                signatures: vec![],
+            },
+        );
+        while let Some(h) = headers.iter().next().map(|(h, _)| h) {
+            if h.saturating_add(*STATE_COMMITMENT_HIST_CNT) > height {
+                break;
+            }
+            headers.remove(&h);
+        }
+    }
+
+    let is_new_height = LAST_EVENT_HEIGHT.swap(height, Ordering::Relaxed) != height;
+    let is_new_block = LAST_EVENT_BLOCK.swap(block_cnt, Ordering::Relaxed) != block_cnt;
+
     let block = if let Some(b) = ledger.blocks.last() {
         b
     } else {
+        if is_new_height {
+            send_api_events(vec![ApiEvent::NewBlock {
+                height,
+                txns: vec![],
+            }]);
+        }
         return Ok(());
     };
 
+    // an empty block will not create a new block in the ledger
+    let block_sid = BlockSID(ledger.blocks.len() - 1);
+    let api_cache = ledger.api_cache.as_mut().unwrap();
+    if api_cache
+        .height_to_block
+        .get_closest_smaller(&height)
+        .map(|(_, (sid, _))| sid != block_sid)
+        .unwrap_or(true)
+    {
+        api_cache.height_to_block.insert(
+            height,
+            (block_sid, block.txns.iter().map(|t| t.tx_id).collect()),
+        );
+    }
+
     let prefix = ledger.api_cache.as_mut().unwrap().prefix.clone();
+    let mut events = vec![];
+    let mut block_txns = vec![];
 
     // Update ownership status
     for (txn_sid, txo_sids) in block.txns.iter().map(|v| (v.tx_id, v.txo_ids.as_slice()))
     {
         let curr_txn = ledger.get_transaction_light(txn_sid).c(d!())?.txn;
+        let txn_hash = curr_txn.hash_tm().hex().to_uppercase();
         // get the transaction, ownership addresses, and memos associated with each transaction
         let (addresses, owner_memos) = {
             let addresses: Vec<XfrAddress> = txo_sids
@@ -533,6 +848,19 @@ pub fn update_api_cache(ledger: &mut LedgerState) -> Result<()> {
                 .insert(txn_sid, Default::default());
         }
 
+        // Collect events for subscribers
+        if is_new_block {
+            let txn = (txn_sid, txn_hash.clone());
+            block_txns.push(txn.clone());
+            events.extend(related_addresses.iter().map(|address| {
+                ApiEvent::RelatedTxn {
+                    address: *address,
+                    txn: txn.clone(),
+                }
+            }));
+            events.extend(gen_txn_events(&curr_txn, &txn));
+        }
+
         // Add created asset
         for op in &curr_txn.body.operations {
             match op {
@@ -565,7 +893,7 @@ pub fn update_api_cache(ledger: &mut LedgerState) -> Result<()> {
                 .unwrap()
                 .utxos_to_map_index
                 .insert(*txo_sid, *address);
-            let hash = curr_txn.hash_tm().hex().to_uppercase();
+            let hash = txn_hash.clone();
             ledger
                 .api_cache
                 .as_mut()
@@ -595,5 +923,66 @@ pub fn update_api_cache(ledger: &mut LedgerState) -> Result<()> {
         }
     }
 
+    if is_new_height {
+        events.insert(
+            0,
+            ApiEvent::NewBlock {
+                height,
+                txns: block_txns,
+            },
+        );
+    }
+    send_api_events(events);
+
     Ok(())
 }
+
+// Spends of txos and staking events in a transaction.
+fn gen_txn_events(txn: &Transaction, id: &TxnIDHash) -> Vec<ApiEvent> {
+    let mut events = vec![];
+
+    macro_rules! staking_ev {
+        ($kind: expr, $pk: expr, $am: expr) => {
+            events.push(ApiEvent::Staking {
+                kind: $kind,
+                pubkey: $pk,
+                amount: $am,
+                txn: id.clone(),
+            })
+        };
+    }
+
+    for op in txn.body.operations.iter() {
+        match op {
+            Operation::TransferAsset(i) => {
+                // relative inputs are created and spent in the same transaction
+                i.body.inputs.iter().for_each(|input| {
+                    if let TxoRef::Absolute(sid) = input {
+                        events.push(ApiEvent::TxoSpent {
+                            txo_sid: *sid,
+                            txn: id.clone(),
+                        });
+                    }
+                });
+            }
+            Operation::Delegation(i) => {
+                staking_ev!(StakingEventKind::Delegation, i.pubkey, Some(i.body.amount))
+            }
+            Operation::UnDelegation(i) => {
+                staking_ev!(StakingEventKind::UnDelegation, i.pubkey, None)
+            }
+            Operation::Claim(i) => {
+                staking_ev!(StakingEventKind::Claim, i.pubkey, i.body.amount)
+            }
+            Operation::ReDelegation(i) => {
+                staking_ev!(StakingEventKind::ReDelegation, i.pubkey, i.body.amount)
+            }
+            Operation::MintFra(i) => i.entries.iter().for_each(|me| {
+                staking_ev!(StakingEventKind::CoinbasePay, me.target_pk, Some(me.amount))
+            }),
+            _ => {}
+        }
+    }
+
+    events
+}
@@ -15,21 +15,24 @@ use {
             AssetType, AssetTypeCode, AuthenticatedBlock, AuthenticatedTransaction,
             AuthenticatedUtxo, AuthenticatedUtxoStatus, BlockEffect, BlockSID,
             FinalizedBlock, FinalizedTransaction, IssuerKeyPair, IssuerPublicKey,
-            OutputPosition, StateCommitmentData, Transaction, TransferType, TxnEffect,
-            TxnSID, TxnTempSID, TxoSID, UnAuthenticatedUtxo, Utxo, UtxoStatus,
-            BLACK_HOLE_PUBKEY,
+            MerkleConsistencyProof, NoReplayWindow, OutputPosition, StateCommitmentData,
+            Transaction, TransferType, TxnEffect, TxnSID, TxnTempSID, TxoSID,
+            UnAuthenticatedUtxo, Utxo, UtxoStatus, BLACK_HOLE_PUBKEY,
+            TRANSACTION_WINDOW_WIDTH,
         },
         staking::{
             Amount, BlockHeight, Power, Staking, TendermintAddrRef,
             FF_PK_EXTRA_120_0000, FF_PK_LIST, FRA_TOTAL_AMOUNT, KEEP_HIST,
         },
+        upgrade::{self, Feature},
         LSSED_VAR, SNAPSHOT_ENTRIES_DIR,
     },
     api_cache::ApiCache,
-    bitmap::{BitMap, SparseMap},
-    cryptohash::sha256::Digest as BitDigest,
+    bitmap::{BitMap, SparseMap, BITMAP_BLOCK_SIZE, SERIAL_VERSION_LEGACY},
+    cryptohash::{sha256::Digest as BitDigest, HashValue},
     fbnc::{new_mapx, new_mapxnk, new_vecx, Mapx, Mapxnk, Vecx},
     globutils::{HashOf, ProofOf},
+    lazy_static::lazy_static,
     merkle_tree::AppendOnlyMerkle,
     parking_lot::RwLock,
     rand_chacha::ChaChaRng,
@@ -40,10 +43,11 @@ use {
     std::{
         collections::{BTreeMap, HashMap, HashSet},
         env,
-        fs::{self, OpenOptions},
+        fs::{self, File, OpenOptions},
         io::ErrorKind,
         mem,
         ops::{Deref, DerefMut},
+        path::Path,
         sync::Arc,
     },
     zei::xfr::{
@@ -53,7 +57,25 @@ use {
     },
 };
 
-const TRANSACTION_WINDOW_WIDTH: u64 = 128;
+lazy_static! {
+    /// The memory cap(in MB) of the blocks of every merkle tree,
+    /// all blocks will be kept in memory if it is not set.
+    static ref MERKLE_CACHE_LIMIT: usize = env::var("FINDORAD_MERKLE_CACHE_MB")
+        .ok()
+        .and_then(|mb| mb.parse::<usize>().ok())
+        .map(|mb| mb.saturating_mul(1024 * 1024))
+        .unwrap_or(usize::MAX);
+
+    /// Only the latest N blocks of the utxo_map are kept in its main file,
+    /// the older ones are moved to the archive file, it's disabled if not set.
+    static ref UTXO_MAP_HOT_BLOCKS: Option<usize> = env::var("FINDORAD_UTXO_MAP_HOT_BLOCKS")
+        .ok()
+        .and_then(|n| n.parse::<usize>().ok());
+
+    /// The blocks of the utxo_map with all bits clear are kept as headers only
+    /// in its main file, it's disabled if not set.
+    static ref UTXO_MAP_COMPACT: bool = env::var("FINDORAD_UTXO_MAP_COMPACT").is_ok();
+}
 
 type TmpSidMap = HashMap<TxnTempSID, (TxnSID, Vec<TxoSID>)>;
 
@@ -82,6 +104,9 @@ pub struct LedgerState {
     txn_merkle: Arc<RwLock<AppendOnlyMerkle>>,
     // Bitmap tracing all the live TXOs
     utxo_map: Arc<RwLock<BitMap>>,
+    // The path of the main file of the utxo_map,
+    // it's replaced when the blocks are moved on disk
+    utxo_map_path: String,
 }
 
 impl LedgerState {
@@ -108,6 +133,14 @@ impl LedgerState {
             .c(d!())
     }
 
+    /// Drop a block started by `start_block` without applying it,
+    /// the block context is reset for the next block.
+    #[inline(always)]
+    pub fn discard_block(&mut self, block: BlockEffect) {
+        drop(block);
+        self.block_ctx = Some(BlockEffect::default());
+    }
+
     /// Check tx of a block context, and apply it to current block
     pub fn apply_transaction(
         &self,
@@ -342,6 +375,14 @@ impl LedgerState {
                 Some(HashOf::new(self.get_staking())),
                 None
             ),
+            bitmap_merkle: alt!(
+                upgrade::is_active(
+                    Feature::BitmapMerkleRoot,
+                    self.get_tendermint_height()
+                ),
+                Some(self.utxo_map.write().compute_merkle_root()),
+                None
+            ),
         };
 
         self.status
@@ -355,21 +396,55 @@ impl LedgerState {
     // We might be creating a new tree or opening an existing one.
     #[inline(always)]
     fn init_merkle_log(path: &str) -> Result<AppendOnlyMerkle> {
-        AppendOnlyMerkle::open(path)
+        AppendOnlyMerkle::open_with_cache_limit(path, *MERKLE_CACHE_LIMIT)
             .c(d!())
-            .or_else(|e| AppendOnlyMerkle::create(path).c(d!(e)))
+            .or_else(|e| {
+                AppendOnlyMerkle::create(path).c(d!(e)).map(|mut tree| {
+                    tree.set_cache_limit(*MERKLE_CACHE_LIMIT);
+                    tree
+                })
+            })
     }
 
-    // Initialize a bitmap to track the unspent utxos.
+    // Initialize a bitmap to track the unspent utxos,
+    // along with the archive file for its old blocks.
     #[inline(always)]
     fn init_utxo_map(path: &str) -> Result<BitMap> {
+        let archive = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(format!("{}_archive", path))
+            .c(d!())?;
+
         let mut file = OpenOptions::new();
         let f = file.read(true).write(true);
 
         f.open(path)
             .c(d!())
             .or_else(|e| f.create(true).truncate(true).open(path).c(d!(e)))
-            .and_then(|f| BitMap::open(f).c(d!()))
+            .and_then(|f| BitMap::open_with_archive(f, archive).c(d!()))
+    }
+
+    // The blocks of the utxo_map have been moved, so it's written
+    // to a new file, which replaces the old one. A crash leaves
+    // either of them, both are complete.
+    fn rewrite_utxo_map(utxo_map: &mut BitMap, path: &str) -> Result<()> {
+        let tmp_path = format!("{}.tmp", path);
+        let f = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&tmp_path)
+            .c(d!())?;
+
+        utxo_map.write_to(f).c(d!())?;
+        fs::rename(&tmp_path, path).c(d!())?;
+
+        // make the rename durable
+        let dir = Path::new(path).parent().c(d!())?;
+        File::open(dir).and_then(|d| d.sync_all()).c(d!())
     }
 
     /// Initialize a new Ledger structure.
@@ -407,12 +482,18 @@ impl LedgerState {
             utxo_map: Arc::new(RwLock::new(
                 LedgerState::init_utxo_map(&utxo_map_path).c(d!())?,
             )),
+            utxo_map_path,
             block_ctx: Some(BlockEffect::default()),
             api_cache: alt!(*KEEP_HIST, Some(ApiCache::new(&prefix)), None),
         };
 
         ledger.status.refresh_data();
 
+        if let Some(api_cache) = ledger.api_cache.as_mut() {
+            let cnt = api_cache.backfill_height_to_block(&ledger.blocks);
+            alt!(0 < cnt, println!("{} blocks are indexed by height", cnt));
+        }
+
         Ok(ledger)
     }
 
@@ -439,7 +520,25 @@ impl LedgerState {
             .cur_height()
             .saturating_sub(self.get_block_commit_count() + 1);
         self.compute_and_save_state_commitment_data(pulse_count);
-        self.utxo_map.write().write().c(d!())?;
+
+        // Compaction does not change the checksum of the utxo_map.
+        {
+            let mut utxo_map = self.utxo_map.write();
+            if *UTXO_MAP_COMPACT {
+                utxo_map.compact().c(d!())?;
+            }
+            if let Some(n) = *UTXO_MAP_HOT_BLOCKS {
+                let blocks =
+                    (utxo_map.size() + BITMAP_BLOCK_SIZE - 1) / BITMAP_BLOCK_SIZE;
+                utxo_map.archive(blocks.saturating_sub(n)).c(d!())?;
+            }
+            if utxo_map.is_moved() {
+                LedgerState::rewrite_utxo_map(&mut utxo_map, &self.utxo_map_path)
+                    .c(d!())?;
+            } else {
+                utxo_map.write().c(d!())?;
+            }
+        }
         self.txn_merkle.write().write().c(d!())?;
         self.block_merkle.write().write().c(d!())?;
 
@@ -524,13 +623,7 @@ impl LedgerState {
         let p = self.staking_get_global_delegation_percent();
         let p = [p[0] as u128, p[1] as u128];
 
-        #[cfg(feature = "debug_env")]
-        const APY_V7_UPGRADE_HEIGHT: BlockHeight = 0;
-
-        #[cfg(not(feature = "debug_env"))]
-        const APY_V7_UPGRADE_HEIGHT: BlockHeight = 131_0000;
-
-        if APY_V7_UPGRADE_HEIGHT < self.get_tendermint_height() {
+        if upgrade::is_active(Feature::ApyV7, self.get_tendermint_height()) {
             // This is an equal conversion of `1 / p% * 0.0536`
             let mut a0 = p[1] * 536;
             let mut a1 = p[0] * 10000;
@@ -567,15 +660,9 @@ impl LedgerState {
     /// Total amount of all freed FRAs, aka 'are not being locked'.
     #[inline(always)]
     pub fn staking_get_global_unlocked_amount(&self) -> Amount {
-        #[cfg(feature = "debug_env")]
-        const FF_ADDR_EXTRA_FIX_HEIGHT: BlockHeight = 0;
-
-        #[cfg(not(feature = "debug_env"))]
-        const FF_ADDR_EXTRA_FIX_HEIGHT: BlockHeight = 120_0000;
-
         let s = self.get_staking();
 
-        let extras = if FF_ADDR_EXTRA_FIX_HEIGHT < s.cur_height {
+        let extras = if upgrade::is_active(Feature::FfAddrExtraFix, s.cur_height) {
             vec![*BLACK_HOLE_PUBKEY, *FF_PK_EXTRA_120_0000]
         } else {
             vec![*BLACK_HOLE_PUBKEY]
@@ -601,13 +688,10 @@ impl LedgerState {
 
     #[inline(always)]
     fn staking_get_nonconfidential_balance(&self, addr: &XfrPublicKey) -> Result<u64> {
-        #[cfg(feature = "debug_env")]
-        const NONCONFIDENTIAL_BALANCE_FIX_HEIGHT: BlockHeight = 0;
-
-        #[cfg(not(feature = "debug_env"))]
-        const NONCONFIDENTIAL_BALANCE_FIX_HEIGHT: BlockHeight = 121_0000;
-
-        if NONCONFIDENTIAL_BALANCE_FIX_HEIGHT < self.get_tendermint_height() {
+        if upgrade::is_active(
+            Feature::NonconfidentialBalanceFix,
+            self.get_tendermint_height(),
+        ) {
             self.get_nonconfidential_balance(addr).c(d!())
         } else {
             Ok(0)
@@ -753,11 +837,29 @@ impl LedgerState {
     }
 
     /// Get unspent utxos owned by a findora account
+    #[inline(always)]
     pub fn get_owned_utxos(
         &self,
         addr: &XfrPublicKey,
     ) -> Result<BTreeMap<TxoSID, (Utxo, Option<OwnerMemo>)>> {
-        let sids = self.status.get_owned_utxos(addr);
+        self.get_utxos_with_memo(self.get_owned_utxo_sids(addr))
+            .c(d!())
+    }
+
+    /// Get sids of the unspent utxos owned by a findora account, in ascending order
+    #[inline(always)]
+    pub fn get_owned_utxo_sids(&self, addr: &XfrPublicKey) -> Vec<TxoSID> {
+        let mut sids = self.status.get_owned_utxos(addr);
+        sids.sort_unstable();
+        sids
+    }
+
+    /// Get unspent utxos along with their owner memos,
+    /// nonexistent or spent ones will be ignored
+    pub fn get_utxos_with_memo(
+        &self,
+        sids: Vec<TxoSID>,
+    ) -> Result<BTreeMap<TxoSID, (Utxo, Option<OwnerMemo>)>> {
         let aus = self.get_utxos_light(&sids).c(d!())?;
 
         let res = sids
@@ -801,6 +903,12 @@ impl LedgerState {
         self.status.block_commit_count
     }
 
+    /// The range of `seq_id` that a new `NoReplayToken` can use.
+    #[inline(always)]
+    pub fn get_no_replay_window(&self) -> NoReplayWindow {
+        self.status.get_no_replay_window()
+    }
+
     #[inline(always)]
     #[allow(missing_docs)]
     pub fn get_state_commitment(&self) -> (HashOf<Option<StateCommitmentData>>, u64) {
@@ -813,13 +921,33 @@ impl LedgerState {
         (commitment, block_count)
     }
 
-    /// Get utxo status and its proof data
+    /// The data whose hash is the latest state commitment.
+    #[inline(always)]
+    pub fn get_state_commitment_data(&self) -> Option<&StateCommitmentData> {
+        self.status.state_commitment_data.as_ref()
+    }
+
+    /// Get utxo status and its proof data,
+    /// the proof is a path of the bitmap merkle tree if the state commitment has its root,
+    /// or the whole bitmap otherwise.
     pub fn get_utxo_status(&self, addr: TxoSID) -> AuthenticatedUtxoStatus {
         let state_commitment_data = self.status.state_commitment_data.as_ref().unwrap();
+        let mut utxo_map_proof = None;
         let utxo_map_bytes;
         let status;
-        if addr.0 < state_commitment_data.txo_count {
-            utxo_map_bytes = Some(self.utxo_map.read().serialize(0));
+        if addr.0 < state_commitment_data.txo_count
+            && state_commitment_data.bitmap_merkle.is_some()
+        {
+            let utxo_map = self.utxo_map.read();
+            status = if utxo_map.query(addr.0 as usize).unwrap() {
+                UtxoStatus::Unspent
+            } else {
+                UtxoStatus::Spent
+            };
+            utxo_map_proof = Some(utxo_map.prove(addr.0 as usize).unwrap());
+            utxo_map_bytes = None;
+        } else if addr.0 < state_commitment_data.txo_count {
+            utxo_map_bytes = Some(self.utxo_map.read().serialize(SERIAL_VERSION_LEGACY));
             let utxo_map =
                 SparseMap::new(&utxo_map_bytes.as_ref().unwrap().clone()).unwrap();
             status = if utxo_map.query(addr.0).unwrap() {
@@ -838,6 +966,7 @@ impl LedgerState {
             state_commitment: state_commitment_data.compute_commitment(),
             utxo_sid: addr,
             utxo_map_bytes,
+            utxo_map_proof,
         }
     }
 
@@ -849,18 +978,40 @@ impl LedgerState {
 
     /// Query the transaction by a TxnSID along with its proof data
     pub fn get_transaction(&self, id: TxnSID) -> Result<AuthenticatedTransaction> {
-        self.get_transaction_light(id).c(d!()).and_then(|tx| {
-            let state_commitment_data =
-                self.status.state_commitment_data.as_ref().c(d!())?.clone();
-            let merkle = &self.txn_merkle;
-            let proof = ProofOf::new(merkle.read().get_proof(tx.merkle_id, 0).c(d!())?);
-
-            Ok(AuthenticatedTransaction {
-                finalized_txn: tx,
-                txn_inclusion_proof: proof,
-                state_commitment_data: state_commitment_data.clone(),
-                state_commitment: state_commitment_data.compute_commitment(),
-            })
+        self.get_transaction_at(id, self.blocks.len() as u64)
+            .c(d!())
+    }
+
+    /// Query the transaction by a TxnSID along with its proof data,
+    /// which is against the state commitment at the given block height
+    pub fn get_transaction_at(
+        &self,
+        id: TxnSID,
+        block_height: u64,
+    ) -> Result<AuthenticatedTransaction> {
+        let (txn_version, _) = self.get_merkle_versions_at(block_height).c(d!())?;
+        let tx = self.get_transaction_light(id).c(d!())?;
+        if tx.merkle_id >= txn_version {
+            return Err(eg!(format!(
+                "transaction {} does not exist at block height {}",
+                id.0, block_height
+            )));
+        }
+
+        let state_commitment_data =
+            self.get_state_commitment_data_at(block_height).c(d!())?;
+        let proof = ProofOf::new(
+            self.txn_merkle
+                .read()
+                .get_proof(tx.merkle_id, txn_version)
+                .c(d!())?,
+        );
+
+        Ok(AuthenticatedTransaction {
+            finalized_txn: tx,
+            txn_inclusion_proof: proof,
+            state_commitment_data: state_commitment_data.clone(),
+            state_commitment: state_commitment_data.compute_commitment(),
         })
     }
 
@@ -879,25 +1030,140 @@ impl LedgerState {
 
     /// Query the Block by a BlockSID along with its proof data
     pub fn get_block(&self, addr: BlockSID) -> Option<AuthenticatedBlock> {
-        match self.blocks.get(addr.0) {
-            None => None,
-            Some(finalized_block) => {
-                let block_inclusion_proof = ProofOf::new(
-                    self.block_merkle
-                        .read()
-                        .get_proof(finalized_block.merkle_id, 0)
-                        .unwrap(),
-                );
-                let state_commitment_data =
-                    self.status.state_commitment_data.as_ref().unwrap().clone();
-                Some(AuthenticatedBlock {
-                    block: finalized_block,
-                    block_inclusion_proof,
-                    state_commitment_data: state_commitment_data.clone(),
-                    state_commitment: state_commitment_data.compute_commitment(),
-                })
-            }
+        self.get_block_at(addr, self.blocks.len() as u64).ok()
+    }
+
+    /// Query the Block by a BlockSID along with its proof data,
+    /// which is against the state commitment at the given block height
+    pub fn get_block_at(
+        &self,
+        addr: BlockSID,
+        block_height: u64,
+    ) -> Result<AuthenticatedBlock> {
+        let (_, block_version) = self.get_merkle_versions_at(block_height).c(d!())?;
+        if addr.0 as u64 >= block_height {
+            return Err(eg!(format!(
+                "block {} does not exist at block height {}",
+                addr.0, block_height
+            )));
         }
+
+        let finalized_block = self.blocks.get(addr.0).c(d!())?;
+        let block_inclusion_proof = ProofOf::new(
+            self.block_merkle
+                .read()
+                .get_proof(finalized_block.merkle_id, block_version)
+                .c(d!())?,
+        );
+        let state_commitment_data =
+            self.get_state_commitment_data_at(block_height).c(d!())?;
+
+        Ok(AuthenticatedBlock {
+            block: finalized_block,
+            block_inclusion_proof,
+            state_commitment_data: state_commitment_data.clone(),
+            state_commitment: state_commitment_data.compute_commitment(),
+        })
+    }
+
+    /// The root hashes of the transaction merkle tree and the block merkle tree,
+    /// recomputed from the trees as they were at the given block height
+    pub fn get_merkle_roots_at(
+        &self,
+        block_height: u64,
+    ) -> Result<(HashValue, HashValue)> {
+        let (txn_version, block_version) =
+            self.get_merkle_versions_at(block_height).c(d!())?;
+
+        Ok((
+            self.txn_merkle
+                .read()
+                .get_root_hash_at(txn_version)
+                .c(d!())?,
+            self.block_merkle
+                .read()
+                .get_root_hash_at(block_version)
+                .c(d!())?,
+        ))
+    }
+
+    /// Prove that the merkle trees at `old_height` are
+    /// prefixes of the ones at `new_height`, so a client who trusts
+    /// the old state commitment can follow the new one
+    pub fn get_merkle_consistency_proof(
+        &self,
+        old_height: u64,
+        new_height: u64,
+    ) -> Result<MerkleConsistencyProof> {
+        if old_height > new_height {
+            return Err(eg!(format!(
+                "invalid block heights: {} -> {}",
+                old_height, new_height
+            )));
+        }
+
+        let (old_txn_version, old_block_version) =
+            self.get_merkle_versions_at(old_height).c(d!())?;
+        let (new_txn_version, new_block_version) =
+            self.get_merkle_versions_at(new_height).c(d!())?;
+
+        Ok(MerkleConsistencyProof {
+            old_state_commitment_data: self
+                .get_state_commitment_data_at(old_height)
+                .c(d!())?,
+            new_state_commitment_data: self
+                .get_state_commitment_data_at(new_height)
+                .c(d!())?,
+            txn_merkle_proof: self
+                .txn_merkle
+                .read()
+                .generate_consistency_proof(old_txn_version, new_txn_version)
+                .c(d!())?,
+            block_merkle_proof: self
+                .block_merkle
+                .read()
+                .generate_consistency_proof(old_block_version, new_block_version)
+                .c(d!())?,
+        })
+    }
+
+    // The sizes of the transaction merkle tree and the block merkle tree
+    // when the block at `block_height` was committed, i.e., the versions
+    // of them which are committed in `get_state_commitment_at_block_height`.
+    fn get_merkle_versions_at(&self, block_height: u64) -> Result<(u64, u64)> {
+        if 0 == block_height || block_height > self.blocks.len() as u64 {
+            return Err(eg!(format!("block height {} does not exist", block_height)));
+        }
+
+        let block_version = self
+            .blocks
+            .get(block_height as usize - 1)
+            .c(d!())?
+            .merkle_id
+            + 1;
+
+        // the latest transaction at that height is in the latest non-empty block
+        let txn_version = (0..block_height as usize)
+            .rev()
+            .find_map(|i| {
+                self.blocks
+                    .get(i)
+                    .and_then(|b| b.txns.last().map(|tx| tx.merkle_id + 1))
+            })
+            .unwrap_or(0);
+
+        Ok((txn_version, block_version))
+    }
+
+    #[inline(always)]
+    fn get_state_commitment_data_at(
+        &self,
+        block_height: u64,
+    ) -> Result<StateCommitmentData> {
+        self.blocks
+            .get(block_height as usize - 1)
+            .c(d!())
+            .map(|b| b.state.clone())
     }
 
     #[inline(always)]
@@ -1082,6 +1348,24 @@ impl LedgerStatus {
     pub fn incr_block_commit_count(&mut self) {
         self.block_commit_count += 1;
         self.sliding_set.incr_current();
+
+        // the width may have been changed through the chain parameters
+        let width = self.staking.chain_params().tx_window_width as usize;
+        if let Err(e) = self.sliding_set.set_width(width) {
+            pd!(format!("Error resizing window: {}", e));
+        }
+    }
+
+    /// The range of `seq_id` that a new `NoReplayToken` can use,
+    /// tokens out of it will be rejected or can not be protected.
+    #[inline(always)]
+    pub fn get_no_replay_window(&self) -> NoReplayWindow {
+        let (min, max) = self.sliding_set.range();
+        NoReplayWindow {
+            min_seq_id: min as u64,
+            max_seq_id: max as u64,
+            width: self.sliding_set.width() as u64,
+        }
     }
 
     // Check that `txn` can be safely applied to the current ledger.
@@ -1100,8 +1384,10 @@ impl LedgerStatus {
         );
         if seq_id > self.block_commit_count {
             return Err(eg!(("Transaction seq_id ahead of block_count")));
-        } else if seq_id + (TRANSACTION_WINDOW_WIDTH as u64) < self.block_commit_count {
+        } else if seq_id + (self.sliding_set.width() as u64) < self.block_commit_count {
             return Err(eg!(("Transaction seq_id too far behind block_count")));
+        } else if seq_id < (self.sliding_set.floor() as u64) {
+            return Err(eg!(("Transaction seq_id is older than the resized window")));
         } else {
             // Check to see that this nrpt has not been seen before
             if self.sliding_set.has_key_at(seq_id as usize, rand) {
@@ -1280,6 +1566,39 @@ impl LedgerStatus {
             }
         }
 
+        // Owners of new outputs of an asset with a credential requirement
+        // must attach a valid credential proof to the transaction
+        let mut checked = HashSet::new();
+        for (code, owner) in txn_effect.credential_subjects.iter() {
+            if !checked.insert((*code, owner.as_bytes().to_vec())) {
+                continue;
+            }
+            let asset_type = self
+                .asset_types
+                .get(&code)
+                .or_else(|| txn_effect.new_asset_codes.get(&code).cloned())
+                .c(d!())?;
+            if let Some(requirement) = asset_type
+                .properties
+                .asset_rules
+                .credential_requirement
+                .as_ref()
+            {
+                if !txn_effect
+                    .txn
+                    .body
+                    .credentials
+                    .iter()
+                    .any(|p| &p.owner == owner && p.verify(requirement).is_ok())
+                {
+                    return Err(eg!(format!(
+                        "Missing or invalid credential proof for asset type {:?}",
+                        code
+                    )));
+                }
+            }
+        }
+
         Ok(())
     }
 
@@ -4,9 +4,14 @@
 use {
     super::{helpers::*, *},
     crate::data_model::{
-        AssetRules, AssetTypeCode, IssueAsset, IssueAssetBody, Memo, Operation,
-        Transaction, TransferAsset, TransferAssetBody, TxOutput, TxnEffect, TxoRef,
-        TxoSID, ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY, TX_FEE_MIN,
+        AssetRules, AssetTypeCode, CredentialProof, CredentialRequirement, IssueAsset,
+        IssueAssetBody, Memo, Operation, Transaction, TransferAsset, TransferAssetBody,
+        TxOutput, TxnEffect, TxoRef, TxoSID, ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY,
+        TX_FEE_MIN,
+    },
+    credentials::{
+        credential_commit, credential_issuer_key_gen, credential_open_commitment,
+        credential_sign, credential_user_key_gen, Credential,
     },
     rand_core::SeedableRng,
     zei::{
@@ -50,6 +55,7 @@ fn test_compute_and_save_block_hash() {
         txo_count: 0,
         pulse_count: 0,
         staking: None,
+        bitmap_merkle: None,
     };
 
     let count_original = ledger_state.status.block_commit_count;
@@ -78,6 +84,127 @@ fn test_compute_and_save_block_hash() {
     assert_eq!(ledger_state.status.block_commit_count, count_original + 1);
 }
 
+#[test]
+fn test_utxo_status_merkle_proof() {
+    let mut ledger = LedgerState::tmp_ledger();
+    {
+        let mut utxo_map = ledger.utxo_map.write();
+        for _ in 0..100 {
+            pnk!(utxo_map.append());
+        }
+        pnk!(utxo_map.clear(7));
+    }
+
+    let b = ledger.start_block().unwrap();
+    ledger.finish_block(b).unwrap();
+
+    // As if the `BitmapMerkleRoot` upgrade is active.
+    let mut data = ledger.status.state_commitment_data.clone().unwrap();
+    data.txo_count = 100;
+    data.bitmap = ledger.utxo_map.write().compute_checksum();
+    data.bitmap_merkle = Some(ledger.utxo_map.write().compute_merkle_root());
+    ledger.status.state_commitment_data = Some(data.clone());
+    let state_commitment = data.compute_commitment();
+
+    for (sid, status, wrong_status) in [
+        (7, UtxoStatus::Spent, UtxoStatus::Unspent),
+        (8, UtxoStatus::Unspent, UtxoStatus::Spent),
+    ]
+    .iter()
+    {
+        let utxo_status = ledger.get_utxo_status(TxoSID(*sid));
+        assert!(utxo_status.status == *status);
+        assert!(utxo_status.utxo_map_bytes.is_none());
+        assert!(utxo_status.is_valid(state_commitment.clone()));
+
+        let mut bad = utxo_status.clone();
+        bad.status = wrong_status.clone();
+        assert!(!bad.is_valid(state_commitment.clone()));
+
+        // The proof of another utxo
+        let mut bad = utxo_status.clone();
+        bad.utxo_map_proof = ledger.get_utxo_status(TxoSID(sid + 1)).utxo_map_proof;
+        assert!(!bad.is_valid(state_commitment.clone()));
+
+        // The whole bitmap is not used any more
+        let mut bad = utxo_status.clone();
+        bad.utxo_map_proof = None;
+        bad.utxo_map_bytes = Some(ledger.utxo_map.read().serialize(0));
+        assert!(!bad.is_valid(state_commitment.clone()));
+    }
+
+    let utxo_status = ledger.get_utxo_status(TxoSID(100));
+    assert!(utxo_status.status == UtxoStatus::Nonexistent);
+    assert!(utxo_status.is_valid(state_commitment));
+}
+
+#[test]
+fn test_backfill_height_to_block() {
+    let mut ledger = LedgerState::tmp_ledger();
+    for h in [1, 3, 7].iter() {
+        ledger.get_staking_mut().set_custom_block_height(*h);
+        let b = ledger.start_block().unwrap();
+        ledger.finish_block(b).unwrap();
+    }
+
+    let mut api_cache = ApiCache::new("test_backfill_");
+    // as if the last block has been indexed after an upgrade
+    api_cache.height_to_block.insert(7, (BlockSID(2), vec![]));
+
+    assert_eq!(2, api_cache.backfill_height_to_block(&ledger.blocks));
+    let get = |h| api_cache.height_to_block.get(&h).map(|(sid, _)| sid);
+    assert_eq!(get(1), Some(BlockSID(0)));
+    assert_eq!(get(2), None);
+    assert_eq!(get(3), Some(BlockSID(1)));
+
+    // nothing to do when the ledger is opened again
+    assert_eq!(0, api_cache.backfill_height_to_block(&ledger.blocks));
+}
+
+#[test]
+fn test_no_replay_window() {
+    let mut ledger = LedgerState::tmp_ledger();
+    assert_eq!(
+        ledger.get_no_replay_window(),
+        NoReplayWindow {
+            min_seq_id: 0,
+            max_seq_id: 0,
+            width: TRANSACTION_WINDOW_WIDTH,
+        }
+    );
+
+    for _ in 0..5 {
+        let b = ledger.start_block().unwrap();
+        ledger.finish_block(b).unwrap();
+    }
+    let w = ledger.get_no_replay_window();
+    assert_eq!((w.min_seq_id, w.max_seq_id), (0, 5));
+
+    let check = |ledger: &LedgerState, seq_id| {
+        let te = pnk!(TxnEffect::compute_effect(Transaction::from_seq_id(seq_id)));
+        ledger.status.check_txn_effects(&te)
+    };
+    assert!(check(&ledger, 0).is_ok());
+    assert!(check(&ledger, 6).is_err());
+
+    // As if `tx_window_width` has been narrowed and then widened.
+    pnk!(ledger.status.sliding_set.set_width(2));
+    assert_eq!(ledger.get_no_replay_window().min_seq_id, 4);
+    assert!(check(&ledger, 2).is_err());
+    pnk!(ledger.status.sliding_set.set_width(8));
+    assert_eq!(
+        ledger.get_no_replay_window(),
+        NoReplayWindow {
+            min_seq_id: 4,
+            max_seq_id: 5,
+            width: 8,
+        }
+    );
+    // the tokens of it have been dropped
+    assert!(check(&ledger, 3).is_err());
+    assert!(check(&ledger, 4).is_ok());
+}
+
 #[test]
 fn test_asset_creation_valid() {
     let mut prng = ChaChaRng::from_entropy();
@@ -467,6 +594,93 @@ fn asset_issued() {
     );
 }
 
+#[test]
+fn test_historical_merkle_proofs() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let keypair = build_keys(&mut ledger.get_prng());
+
+    // blocks with 1, 0, 2 and 1 txns
+    let mut txn_sids = vec![];
+    for n in [1, 0, 2, 1].iter() {
+        let mut block = ledger.start_block().unwrap();
+        let mut temp_sids = vec![];
+        for _ in 0..*n {
+            let tx = create_definition_transaction(
+                &AssetTypeCode::gen_random(),
+                &keypair,
+                AssetRules::default(),
+                None,
+                ledger.get_block_commit_count(),
+            )
+            .unwrap();
+            let effect = TxnEffect::compute_effect(tx).unwrap();
+            temp_sids.push(ledger.apply_transaction(&mut block, effect).unwrap());
+        }
+        let mut tsm = ledger.finish_block(block).unwrap();
+        txn_sids.push(
+            temp_sids
+                .iter()
+                .map(|sid| tsm.remove(sid).unwrap().0)
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    let block_cnt = ledger.get_block_count() as u64;
+    assert_eq!(block_cnt, 4);
+    assert!(ledger.get_merkle_roots_at(0).is_err());
+    assert!(ledger.get_merkle_roots_at(block_cnt + 1).is_err());
+
+    for h in 1..=block_cnt {
+        let state_commitment = ledger.get_state_commitment_at_block_height(h).unwrap();
+        let data = ledger.blocks.get(h as usize - 1).unwrap().state.clone();
+        assert_eq!(
+            ledger.get_merkle_roots_at(h).unwrap(),
+            (data.transaction_merkle_commitment, data.block_merkle)
+        );
+
+        for (i, sids) in txn_sids.iter().enumerate() {
+            let included = (i as u64) < h;
+            for sid in sids.iter() {
+                let txn = ledger.get_transaction_at(*sid, h);
+                assert_eq!(included, txn.is_ok());
+                if let Ok(txn) = txn {
+                    assert!(txn.is_valid(state_commitment.clone()));
+                    let other_h = alt!(h == block_cnt, 1, block_cnt);
+                    let other = ledger.get_state_commitment_at_block_height(other_h);
+                    assert!(!txn.is_valid(other.unwrap()));
+                }
+            }
+
+            let block = ledger.get_block_at(BlockSID(i), h);
+            assert_eq!(included, block.is_ok());
+            if let Ok(block) = block {
+                assert!(block.is_valid(state_commitment.clone()));
+            }
+        }
+
+        for old_h in 1..=h {
+            let proof = ledger.get_merkle_consistency_proof(old_h, h).unwrap();
+            let old_commitment =
+                ledger.get_state_commitment_at_block_height(old_h).unwrap();
+            assert!(proof.is_valid(old_commitment.clone(), state_commitment.clone()));
+            if old_h < h {
+                assert!(!proof.is_valid(state_commitment.clone(), old_commitment));
+                assert!(ledger.get_merkle_consistency_proof(h, old_h).is_err());
+            }
+        }
+    }
+
+    // the current ones are the ones at the latest height
+    let (state_commitment, _) = ledger.get_state_commitment();
+    let txn = ledger.get_transaction(txn_sids[0][0]).unwrap();
+    assert!(txn.is_valid(state_commitment.clone()));
+    assert_eq!(txn.txn_inclusion_proof.0.proof.state, 4);
+    assert!(ledger
+        .get_block(BlockSID(0))
+        .unwrap()
+        .is_valid(state_commitment));
+}
+
 #[test]
 pub fn test_transferable() {
     let mut ledger = LedgerState::tmp_ledger();
@@ -787,3 +1001,110 @@ fn test_check_fee_with_ledger() {
     let mut block = ledger.start_block().unwrap();
     assert!(ledger.apply_transaction(&mut block, effect).is_err());
 }
+
+#[test]
+fn test_credential_requirement() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let mut prng = ChaChaRng::from_entropy();
+    let params = PublicParams::default();
+
+    let (issuer_pk, issuer_sk) = credential_issuer_key_gen(
+        &mut prng,
+        &[("kyc".to_owned(), 1), ("country".to_owned(), 2)],
+    );
+    let (user_pk, user_sk) = credential_user_key_gen(&mut prng, &issuer_pk);
+    let attrs = [
+        ("kyc".to_owned(), &b"1"[..]),
+        ("country".to_owned(), &b"CN"[..]),
+    ];
+    let credential = Credential {
+        attributes: attrs.iter().map(|(k, v)| (k.clone(), v.to_vec())).collect(),
+        issuer_pub_key: issuer_pk.clone(),
+        signature: credential_sign(&mut prng, &issuer_sk, &user_pk, &attrs).unwrap(),
+    };
+
+    let code = AssetTypeCode::gen_random();
+    let keypair = build_keys(&mut prng);
+    let mut rules = AssetRules::default();
+    rules.set_credential_requirement(Some(CredentialRequirement {
+        issuer_pub_key: issuer_pk,
+        required_attrs: vec![("kyc".to_owned(), b"1".to_vec())],
+    }));
+    let tx = create_definition_transaction(&code, &keypair, rules, None, 0).unwrap();
+    apply_transaction(&mut ledger, tx);
+
+    let ar = AssetRecordTemplate::with_no_asset_tracing(
+        100,
+        code.val,
+        AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+        keypair.get_pk(),
+    );
+    let (ba, _, _) = build_blind_asset_record(&mut prng, &params.pc_gens, &ar, vec![]);
+    let issue_body = IssueAssetBody::new(
+        &code,
+        0,
+        &[(
+            TxOutput {
+                id: None,
+                record: ba,
+                lien: None,
+            },
+            None,
+        )],
+    )
+    .unwrap();
+    let issue_op = Operation::IssueAsset(
+        IssueAsset::new(issue_body, &IssuerKeyPair { keypair: &keypair }).unwrap(),
+    );
+
+    // Issuance without any credential proof
+    let seq_id = ledger.get_block_commit_count();
+    let mut tx = Transaction::from_operation(issue_op, seq_id);
+    let effect = TxnEffect::compute_effect(tx.clone()).unwrap();
+    let mut block = ledger.start_block().unwrap();
+    assert!(ledger.apply_transaction(&mut block, effect).is_err());
+
+    // Proof that reveals the wrong attribute set
+    let (commitment, commitment_pok, key) = credential_commit(
+        &mut prng,
+        &user_sk,
+        &credential,
+        keypair.get_pk_ref().as_bytes(),
+    )
+    .unwrap();
+    let bad_reveal = credential_open_commitment(
+        &mut prng,
+        &user_sk,
+        &credential,
+        &key,
+        &["country".to_owned()],
+    )
+    .unwrap();
+    tx.body.credentials = vec![CredentialProof {
+        owner: keypair.get_pk(),
+        commitment: commitment.clone(),
+        commitment_pok: commitment_pok.clone(),
+        reveal_proof: bad_reveal,
+    }];
+    let effect = TxnEffect::compute_effect(tx.clone()).unwrap();
+    assert!(ledger.apply_transaction(&mut block, effect).is_err());
+
+    // Valid proof
+    let reveal_proof = credential_open_commitment(
+        &mut prng,
+        &user_sk,
+        &credential,
+        &key,
+        &["kyc".to_owned()],
+    )
+    .unwrap();
+    tx.body.credentials = vec![CredentialProof {
+        owner: keypair.get_pk(),
+        commitment,
+        commitment_pok,
+        reveal_proof,
+    }];
+    let effect = TxnEffect::compute_effect(tx).unwrap();
+    assert!(ledger.apply_transaction(&mut block, effect).is_ok());
+    ledger.finish_block(block).unwrap();
+}
@@ -0,0 +1,253 @@
+//!
+//! # Protocol Upgrades
+//!
+//! All the height-gated changes of the protocol are registered here,
+//! each of them has an activation height in every network profile.
+//!
+//! The profile is selected at runtime by the `FINDORAD_NETWORK_PROFILE`
+//! environment variable(`mainnet`, `testnet` or `local`), it defaults to
+//! `mainnet`, an invalid value is refused by `check_profile` at startup.
+//!
+
+use {
+    crate::staking::BlockHeight,
+    lazy_static::lazy_static,
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::{env, fmt, str::FromStr},
+};
+
+lazy_static! {
+    /// The network profile of the current process,
+    /// it should be checked by `check_profile` at startup.
+    pub static ref PROFILE: NetworkProfile =
+        load_profile().unwrap_or(NetworkProfile::Mainnet);
+}
+
+// `mainnet` if `FINDORAD_NETWORK_PROFILE` is not set
+fn load_profile() -> Result<NetworkProfile> {
+    match env::var("FINDORAD_NETWORK_PROFILE") {
+        Ok(p) => p.parse::<NetworkProfile>().c(d!()),
+        Err(env::VarError::NotPresent) => Ok(NetworkProfile::Mainnet),
+        Err(e) => Err(eg!(e)),
+    }
+}
+
+/// Check the value of `FINDORAD_NETWORK_PROFILE` and return the selected profile,
+/// a process should call this before using `PROFILE`,
+/// so that an invalid value stops it instead of falling back to `mainnet`.
+pub fn check_profile() -> Result<NetworkProfile> {
+    load_profile()
+        .c(d!(
+            "invalid FINDORAD_NETWORK_PROFILE, expect one of: mainnet, testnet, local"
+        ))
+        .map(|_| *PROFILE)
+}
+
+/// Network profiles which have their own upgrade schedules.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NetworkProfile {
+    #[allow(missing_docs)]
+    Mainnet,
+    #[allow(missing_docs)]
+    Testnet,
+    /// Local or debug networks, all the upgrades are active from the beginning.
+    Local,
+}
+
+impl FromStr for NetworkProfile {
+    type Err = Box<dyn RucError>;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "mainnet" => Ok(NetworkProfile::Mainnet),
+            "testnet" => Ok(NetworkProfile::Testnet),
+            "local" => Ok(NetworkProfile::Local),
+            _ => Err(eg!(format!("unknown network profile: {}", s))),
+        }
+    }
+}
+
+impl fmt::Display for NetworkProfile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            NetworkProfile::Mainnet => "mainnet",
+            NetworkProfile::Testnet => "testnet",
+            NetworkProfile::Local => "local",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Named changes of the protocol.
+#[derive(
+    Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Feature {
+    /// Delegation rewards are weighted by the global delegation amount.
+    ApyFix,
+    /// Zero-amount delegations get no rewards instead of an error.
+    ZeroAmountFix,
+    /// An extra reserved address is excluded from the unlocked amount.
+    FfAddrExtraFix,
+    /// Non-confidential balances are counted in the unlocked amount.
+    NonconfidentialBalanceFix,
+    /// Rewards are calculated with big integers.
+    OverflowFix,
+    /// A year has 365 days, instead of 356.
+    SecondsPerYearFix,
+    /// The new formula of the block rewards rate.
+    ApyV7,
+    /// Validators are counted for the missed blocks,
+    /// and get jailed for byzantine behaviors or being offline for too long.
+    ValidatorJail,
+    /// The commission rate is limited in its max value and daily change,
+    /// it can be changed once a day and an increase takes effect a day later.
+    CommissionRule,
+    /// The state commitment contains the merkle root of the utxo bitmap,
+    /// so the spent status of a utxo can be proven without the whole bitmap.
+    ///
+    /// It is opt-in: no height is scheduled on the mainnet or the testnet yet,
+    /// so the state commitments there keep having no bitmap root, and the
+    /// clients must keep verifying the utxo status with the whole bitmap.
+    BitmapMerkleRoot,
+}
+
+impl Feature {
+    /// All the registered features, in order of their mainnet activation.
+    pub const ALL: [Feature; 10] = [
+        Feature::ApyFix,
+        Feature::ZeroAmountFix,
+        Feature::FfAddrExtraFix,
+        Feature::NonconfidentialBalanceFix,
+        Feature::OverflowFix,
+        Feature::SecondsPerYearFix,
+        Feature::ApyV7,
+        Feature::ValidatorJail,
+        Feature::CommissionRule,
+        Feature::BitmapMerkleRoot,
+    ];
+
+    /// The activation height of this feature in the given profile.
+    pub fn activation_height(self, profile: NetworkProfile) -> BlockHeight {
+        match profile {
+            NetworkProfile::Mainnet => match self {
+                Feature::ApyFix => 117_7000,
+                Feature::ZeroAmountFix => 120_0000,
+                Feature::FfAddrExtraFix => 120_0000,
+                Feature::NonconfidentialBalanceFix => 121_0000,
+                // logic apply at about 2021-11-11 14:30
+                Feature::OverflowFix => 124_7000,
+                Feature::SecondsPerYearFix => 131_0000,
+                Feature::ApyV7 => 131_0000,
+                // not scheduled yet
+                Feature::ValidatorJail => BlockHeight::MAX,
+                Feature::CommissionRule => BlockHeight::MAX,
+                // opt-in, not scheduled yet
+                Feature::BitmapMerkleRoot => BlockHeight::MAX,
+            },
+            // the testnet has been running with the mainnet heights,
+            // they must be kept to replay its history
+            NetworkProfile::Testnet => self.activation_height(NetworkProfile::Mainnet),
+            NetworkProfile::Local => 0,
+        }
+    }
+}
+
+/// Check if a feature is active at the given height in the current profile,
+/// a feature takes effect after its activation height.
+#[inline(always)]
+pub fn is_active(feature: Feature, height: BlockHeight) -> bool {
+    #[cfg(test)]
+    if let Some(profile) = PROFILE_OVERRIDE.with(|p| p.get()) {
+        return is_active_in(profile, feature, height);
+    }
+
+    is_active_in(*PROFILE, feature, height)
+}
+
+#[cfg(test)]
+thread_local! {
+    static PROFILE_OVERRIDE: std::cell::Cell<Option<NetworkProfile>> =
+        std::cell::Cell::new(None);
+}
+
+/// Run `f` with a specified profile in the current thread,
+/// so the gated logics can be tested in every profile.
+#[cfg(test)]
+pub(crate) fn with_profile<T>(profile: NetworkProfile, f: impl FnOnce() -> T) -> T {
+    let orig = PROFILE_OVERRIDE.with(|p| p.replace(Some(profile)));
+    let ret = f();
+    PROFILE_OVERRIDE.with(|p| p.set(orig));
+    ret
+}
+
+/// Same as `is_active`, but with a specified profile.
+#[inline(always)]
+pub fn is_active_in(
+    profile: NetworkProfile,
+    feature: Feature,
+    height: BlockHeight,
+) -> bool {
+    feature.activation_height(profile) < height
+}
+
+/// The state of an upgrade at some height.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct UpgradeStatus {
+    #[allow(missing_docs)]
+    pub feature: Feature,
+    #[allow(missing_docs)]
+    pub activation_height: BlockHeight,
+    /// `false` means that it is still scheduled.
+    pub active: bool,
+}
+
+/// States of all the upgrades in the current profile.
+pub fn get_upgrades(height: BlockHeight) -> Vec<UpgradeStatus> {
+    Feature::ALL
+        .iter()
+        .map(|&feature| UpgradeStatus {
+            feature,
+            activation_height: feature.activation_height(*PROFILE),
+            active: is_active(feature, height),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn upgrade_activation() {
+        let h = Feature::ApyV7.activation_height(NetworkProfile::Mainnet);
+        assert!(!is_active_in(NetworkProfile::Mainnet, Feature::ApyV7, h));
+        assert!(is_active_in(NetworkProfile::Mainnet, Feature::ApyV7, h + 1));
+
+        for feature in Feature::ALL.iter() {
+            assert!(is_active_in(NetworkProfile::Local, *feature, 1));
+            assert_eq!(
+                feature.activation_height(NetworkProfile::Testnet),
+                feature.activation_height(NetworkProfile::Mainnet)
+            );
+        }
+
+        // opt-in features are not scheduled
+        for profile in [NetworkProfile::Mainnet, NetworkProfile::Testnet].iter() {
+            assert!(!is_active_in(
+                *profile,
+                Feature::BitmapMerkleRoot,
+                BlockHeight::MAX
+            ));
+        }
+
+        assert_eq!(
+            NetworkProfile::Testnet,
+            pnk!("TestNet".parse::<NetworkProfile>())
+        );
+        assert!("devnet".parse::<NetworkProfile>().is_err());
+    }
+}
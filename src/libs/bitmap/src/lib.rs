@@ -1,9 +1,10 @@
 //! # A Simple BitMap Implementation
 //!
 //! This module implements a simple persistent bitmap. The
-//! bitmap currently is stored in a single file. The caller
-//! is responsible for file creation and open operations; thus
-//! this module does not manage or use file paths.
+//! bitmap is stored in a single file, plus an optional archive
+//! file for old blocks. The caller is responsible for file
+//! creation and open operations; thus this module does not
+//! manage or use file paths.
 //!
 //! The bitmap is maintained in memory and on disk as a sequence
 //! of blocks. Each block is self-identifying and checksummed
@@ -15,12 +16,26 @@
 //! caller to append set bits, but not zero bits, as a minor
 //! check of correctness.
 //!
+//! Full blocks can be compacted on disk:  a block with all bits
+//! clear is stored as a header only, and an old block can be moved
+//! to the archive file, leaving a header in the main file. The
+//! headers keep the checksums of the full blocks, so the checksum
+//! of the bitmap doesn't change. See BitMap::compact and
+//! BitMap::archive. The blocks after a compacted or archived one
+//! are moved in the main file, which is done by writing a new file
+//! rather than in place, see BitMap::write_to.
+//!
 //! This module supports a mildly-compressed bitmap version that
 //! can be downloaded. The serialize and serialize_partial
 //! methods, q.v., produce a Vec<u8> that can be sent over the
 //! network. This Vec can be converted to a SparseMap structure.
 //! The SparseMap structure allows various queries on the contents
 //! of the map.
+//!
+//! The checksums of the blocks also are the leaves of a Merkle
+//! tree, so the value of a single bit can be proven with one
+//! block and a logarithmic path of hashes. See BitMap::prove and
+//! BitProof::verify.
 
 #![deny(warnings)]
 #![deny(missing_docs)]
@@ -32,11 +47,12 @@ use {
     cryptohash::sha256::{self, Digest, DIGESTBYTES},
     globutils::Commas,
     ruc::*,
+    serde::{Deserialize, Serialize},
     std::{
         cmp,
         collections::{HashMap, HashSet},
         fs::File,
-        io::{Read, Seek, SeekFrom, Write},
+        io::{BufWriter, Read, Seek, SeekFrom, Write},
         mem, slice,
     },
 };
@@ -77,6 +93,21 @@ const BIT_DESC_SET: u16 = 2;
 const BIT_DESC_CLEAR: u16 = 3;
 const BIT_HEADER: u16 = 4;
 
+// Define the types of headers for a full block of type BIT_ARRAY
+// kept only as a header, with the checksum of that block. An empty
+// block has all its bits clear, and is serialized as such from
+// SERIAL_VERSION_EMPTY on. An archived block is used only on disk.
+const BIT_EMPTY: u16 = 5;
+const BIT_ARCHIVED: u16 = 6;
+
+/// The serialization version that the deployed decoders understand,
+/// see serialize().
+pub const SERIAL_VERSION_LEGACY: usize = 0;
+
+/// The first serialization version that keeps only the header of
+/// the full blocks with all bits clear, see serialize().
+pub const SERIAL_VERSION_EMPTY: usize = 1;
+
 /// For users who download the bitmap, this is what they
 /// get for a block header.
 #[repr(C)]
@@ -106,6 +137,15 @@ impl BlockInfo {
             return Err(eg!(format!("Invalid list size {}", self.list_size)));
         }
 
+        if self.contents == BIT_EMPTY
+            && (self.count != BLOCK_BITS as u32 || self.list_size != 0)
+        {
+            return Err(eg!(format!(
+                "Invalid empty block:  count {}, list size {}",
+                self.count, self.list_size
+            )));
+        }
+
         Ok(())
     }
 
@@ -238,14 +278,7 @@ impl SparseMap {
             // If the bitmap is present, make sure that it matches
             // expectations.
             if let Some(bits) = self.map.get(&(i as u64)) {
-                // Recreate the block as it should have been on disk...
-                let mut block = BitBlock::new(BIT_ARRAY, i as u64).unwrap();
-                block.header.count = info.count;
-                block.bits.clone_from_slice(&bits[0..]);
-
-                let checksum = block.compute_checksum();
-
-                if checksum != info.checksum.bytes {
+                if block_checksum(i as u64, info, bits) != info.checksum.bytes {
                     return false;
                 }
             }
@@ -260,6 +293,214 @@ impl SparseMap {
     }
 }
 
+// Recreate a block as it should have been on disk, and
+// compute its checksum.
+fn block_checksum(id: u64, info: &BlockInfo, bits: &BlockBits) -> [u8; CHECK_SIZE] {
+    let mut block = BitBlock::new(BIT_ARRAY, id).unwrap();
+    block.header.count = info.count;
+    block.bits.clone_from_slice(&bits[0..]);
+    block.compute_checksum()
+}
+
+// Define the prefixes for the hashes in the Merkle tree of
+// block checksums, so that a leaf can't be taken as a node.
+// The root of the tree is hashed with the size of the bitmap,
+// so the position of the last block is committed as well.
+const MERKLE_LEAF: u8 = 0;
+const MERKLE_NODE: u8 = 1;
+const MERKLE_ROOT: u8 = 2;
+
+#[inline(always)]
+fn merkle_leaf(checksum: &[u8; CHECK_SIZE]) -> Digest {
+    let mut data = [0_u8; 1 + CHECK_SIZE];
+    data[0] = MERKLE_LEAF;
+    data[1..].clone_from_slice(&checksum[0..]);
+    sha256::hash(&data)
+}
+
+#[inline(always)]
+fn merkle_node(left: &Digest, right: &Digest) -> Digest {
+    let mut data = [0_u8; 1 + 2 * DIGESTBYTES];
+    data[0] = MERKLE_NODE;
+    data[1..1 + DIGESTBYTES].clone_from_slice(&left[0..]);
+    data[1 + DIGESTBYTES..].clone_from_slice(&right[0..]);
+    sha256::hash(&data)
+}
+
+#[inline(always)]
+fn merkle_commit(size: u64, root: &Digest) -> Digest {
+    let mut data = [0_u8; 1 + 8 + DIGESTBYTES];
+    data[0] = MERKLE_ROOT;
+    data[1..9].clone_from_slice(&size.to_le_bytes());
+    data[9..].clone_from_slice(&root[0..]);
+    sha256::hash(&data)
+}
+
+// The Merkle tree is shaped as the one in RFC 6962:  the
+// left subtree of a node with n leaves is the complete tree
+// with the largest power of two less than n leaves.
+#[inline(always)]
+fn merkle_split(n: usize) -> usize {
+    let mut k = 1;
+
+    while k * 2 < n {
+        k *= 2;
+    }
+
+    k
+}
+
+// Compute the root of the tree over the given leaves, which
+// must not be empty.
+fn merkle_root(leaves: &[Digest]) -> Digest {
+    if leaves.len() == 1 {
+        return leaves[0];
+    }
+
+    let k = merkle_split(leaves.len());
+    merkle_node(&merkle_root(&leaves[..k]), &merkle_root(&leaves[k..]))
+}
+
+// Append the hashes of the siblings of a leaf to the path,
+// from the leaf up to the root.
+fn merkle_path(index: usize, leaves: &[Digest], path: &mut Vec<Digest>) {
+    let n = leaves.len();
+
+    if n <= 1 {
+        return;
+    }
+
+    let k = merkle_split(n);
+
+    if index < k {
+        merkle_path(index, &leaves[..k], path);
+        path.push(merkle_root(&leaves[k..]));
+    } else {
+        merkle_path(index - k, &leaves[k..], path);
+        path.push(merkle_root(&leaves[..k]));
+    }
+}
+
+// Walk a path from a leaf up to the root, as in RFC 6962,
+// and return the root. A path of a wrong length gives None.
+fn merkle_walk(
+    mut index: u64,
+    count: u64,
+    leaf: Digest,
+    path: &[Digest],
+) -> Option<Digest> {
+    if index >= count {
+        return None;
+    }
+
+    let mut last = count - 1;
+    let mut digest = leaf;
+
+    for hash in path.iter() {
+        if last == 0 {
+            return None;
+        }
+
+        if index & 1 == 1 || index == last {
+            digest = merkle_node(hash, &digest);
+
+            while index & 1 == 0 && index != 0 {
+                index >>= 1;
+                last >>= 1;
+            }
+        } else {
+            digest = merkle_node(&digest, hash);
+        }
+
+        index >>= 1;
+        last >>= 1;
+    }
+
+    if last == 0 {
+        Some(digest)
+    } else {
+        None
+    }
+}
+
+/// A proof of the value of one bit in a bitmap. It contains
+/// the serialized block holding the bit, and the path of
+/// hashes from the checksum of that block up to the Merkle
+/// root, as computed by BitMap::compute_merkle_root.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct BitProof {
+    /// The index of the bit in the bitmap.
+    pub bit: u64,
+    /// The number of bits in the bitmap.
+    pub size: u64,
+    /// The block holding the bit, in the form produced by
+    /// BitMap::serialize.
+    pub block: Vec<u8>,
+    /// The hashes of the siblings, from the leaf up to the root.
+    pub path: Vec<Digest>,
+}
+
+impl BitProof {
+    /// Verify the proof against a Merkle root. If the proof
+    /// is valid, return the value of the bit. Otherwise,
+    /// return an error.
+    pub fn verify(&self, root: &Digest) -> Result<bool> {
+        if self.bit >= self.size {
+            return Err(eg!(format!(
+                "That index is out of range ({} vs {}).",
+                self.bit, self.size
+            )));
+        }
+
+        let id = self.bit / BLOCK_BITS as u64;
+        let block_index = (self.bit % BLOCK_BITS as u64) as usize;
+        let block_count = (self.size - 1) / BLOCK_BITS as u64 + 1;
+
+        let (next, info, bits) =
+            BitMap::decode_block(&self.block, 0, SERIAL_VERSION_LEGACY as u64)
+                .c(d!())?;
+
+        if next != self.block.len() {
+            return Err(eg!(format!(
+                "The block contained {} extra bytes.",
+                self.block.len() - next
+            )));
+        }
+
+        if info.bit_id != id * BLOCK_BITS as u64 {
+            return Err(eg!(format!("The block has a bad id:  {}", info.bit_id)));
+        }
+
+        // Only the last block of a bitmap can be partial.
+        let count = cmp::min(self.size - info.bit_id, BLOCK_BITS as u64);
+
+        if info.count as u64 != count {
+            return Err(eg!(format!(
+                "Block {} has a bad count:  {} vs {}",
+                id.commas(),
+                info.count,
+                count
+            )));
+        }
+
+        let bits = bits.c(d!("The block has no bits."))?;
+        let checksum = block_checksum(id, &info, &bits);
+
+        if checksum != info.checksum.bytes {
+            return Err(eg!(format!("Block {} has a bad checksum.", id.commas())));
+        }
+
+        let tree_root = merkle_walk(id, block_count, merkle_leaf(&checksum), &self.path)
+            .c(d!("The Merkle path has a bad length."))?;
+
+        if merkle_commit(self.size, &tree_root) != *root {
+            return Err(eg!("The Merkle path is invalid.".to_string()));
+        }
+
+        Ok(bit_set(&bits, block_index))
+    }
+}
+
 // Define the layout for a block header.
 //
 // This structure occupies the first bytes of each disk
@@ -270,12 +511,13 @@ impl SparseMap {
 // count     the count of valid bits in this block
 // bit_id    the bit index corresponding to the first bit in the block
 // offset    the offset in the file at which this block should appear
-// contents  the contents type, currently always BIT_ARRAY
+// contents  the contents type, always BIT_ARRAY in memory, or
+//           BIT_EMPTY or BIT_ARCHIVED for a header-only block on disk
 //
 // The size of this structure must match the HEADER_SIZE
 // constant.
 #[repr(C)]
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 struct BlockHeader {
     checksum: CheckBlock, // must be first
     magic: u32,           // must be second
@@ -294,6 +536,8 @@ impl BlockHeader {
             && block_contents != BIT_DESC_SET
             && block_contents != BIT_DESC_CLEAR
             && block_contents != BIT_HEADER
+            && block_contents != BIT_EMPTY
+            && block_contents != BIT_ARCHIVED
         {
             return Err(eg!(format!(
                 "That content type ({}) is invalid.",
@@ -369,6 +613,17 @@ impl BlockHeader {
 
         Ok(())
     }
+
+    // Create a slice for writing a header-only block to disk.
+    #[inline(always)]
+    fn as_ref(&self) -> &[u8] {
+        unsafe {
+            slice::from_raw_parts(
+                (self as *const BlockHeader) as *const u8,
+                mem::size_of::<BlockHeader>(),
+            )
+        }
+    }
 }
 
 // Define the block structure of the server version
@@ -485,6 +740,11 @@ const EMPTY_CHECKSUM: ChecksumData = [0_u8; CHECK_SIZE + DIGESTBYTES];
 ///
 /// Data kept for the bitmap as a whole:
 ///   file            a file structure for the disk data
+///   archive         a file structure for the archived blocks, if any
+///   moved           the index of the first block whose position in
+///                     the file has changed, so that it and all the
+///                     blocks after it have to be written to a new
+///                     file, see write_to()
 ///   size            the number of bits in the bitmap
 ///   checksum        a checksum for the entire bitmap, if valid
 ///   first_invalid   the index to the first invalidated checksum term
@@ -502,9 +762,14 @@ const EMPTY_CHECKSUM: ChecksumData = [0_u8; CHECK_SIZE + DIGESTBYTES];
 ///   dirty           the modification time for the block, or zero
 ///                     if the block is clean (valid on disk)
 ///   set_bits        the count of set bits in the block
+///   forms           the contents type of the block on disk, one of
+///                     BIT_ARRAY, BIT_EMPTY or BIT_ARCHIVED
+///   offsets         the offset of the block in the file
 ///
 pub struct BitMap {
     file: File,
+    archive: Option<File>,
+    moved: usize,
     size: usize,
     checksum: Digest,
     first_invalid: usize,
@@ -516,6 +781,8 @@ pub struct BitMap {
     checksum_valid: Vec<bool>,
     dirty: Vec<i64>,
     set_bits: Vec<u32>,
+    forms: Vec<u16>,
+    offsets: Vec<u64>,
 }
 
 // Write any dirty blocks when a bitmap is dropped.
@@ -596,9 +863,27 @@ fn encode(mut value: usize) -> [u8; INDEX_SIZE] {
     result
 }
 
+// Get the size of a block on disk for the given contents type.
+#[inline(always)]
+fn disk_size(form: u16) -> u64 {
+    if form == BIT_ARRAY {
+        BLOCK_SIZE as u64
+    } else {
+        HEADER_SIZE as u64
+    }
+}
+
 // Clippy requires this declaration, otherwise the type is
 // "too complicated".
-type StoredState = (usize, Vec<BitBlock>, Vec<i64>, Vec<bool>, Vec<u32>);
+type StoredState = (
+    usize,
+    Vec<BitBlock>,
+    Vec<i64>,
+    Vec<bool>,
+    Vec<u32>,
+    Vec<u16>,
+    Vec<u64>,
+);
 
 impl BitMap {
     /// Create a new bit map. The caller should pass a File
@@ -612,6 +897,8 @@ impl BitMap {
 
         let result = BitMap {
             file: data,
+            archive: None,
+            moved: usize::MAX,
             size: 0,
             blocks: Vec::new(),
             checksum_data: Vec::new(),
@@ -621,6 +908,8 @@ impl BitMap {
             dirty: Vec::new(),
             checksum_valid: Vec::new(),
             set_bits: Vec::new(),
+            forms: Vec::new(),
+            offsets: Vec::new(),
             map: create_map(),
             first_invalid: 0,
         };
@@ -629,13 +918,38 @@ impl BitMap {
     }
 
     /// Open an existing bitmap. The caller is responsible
-    /// for opening the file.
-    pub fn open(mut data: File) -> Result<BitMap> {
-        let (count, block_vector, state_vector, checksum_vector, set_vector) =
-            BitMap::read_file(&mut data).c(d!())?;
+    /// for opening the file. If the bitmap has archived
+    /// blocks, open_with_archive must be used instead.
+    #[inline(always)]
+    pub fn open(data: File) -> Result<BitMap> {
+        BitMap::open_files(data, None)
+    }
+
+    /// Open an existing bitmap along with its archive file.
+    /// The archived blocks are read from the archive file,
+    /// and the blocks archived later are written to it.
+    #[inline(always)]
+    pub fn open_with_archive(data: File, archive: File) -> Result<BitMap> {
+        BitMap::open_files(data, Some(archive))
+    }
+
+    fn open_files(mut data: File, mut archive: Option<File>) -> Result<BitMap> {
+        let (
+            count,
+            block_vector,
+            state_vector,
+            checksum_vector,
+            set_vector,
+            form_vector,
+            offset_vector,
+        ) = BitMap::read_file(&mut data, archive.as_mut()).c(d!())?;
 
         let mut result = BitMap {
             file: data,
+            archive,
+            moved: usize::MAX,
+            forms: form_vector,
+            offsets: offset_vector,
             size: count,
             blocks: block_vector,
             checksum_data: Vec::new(),
@@ -660,64 +974,127 @@ impl BitMap {
     }
 
     // Read the contents of a file into memory, checking the
-    // validity as we go.
-    fn read_file(file: &mut File) -> Result<StoredState> {
+    // validity as we go. The bits of archived blocks are read
+    // from the archive file.
+    fn read_file(
+        file: &mut File,
+        mut archive: Option<&mut File>,
+    ) -> Result<StoredState> {
         let mut blocks = Vec::new();
         let mut dirty = Vec::new();
         let mut checksum_valid = Vec::new();
         let mut set = Vec::new();
+        let mut forms = Vec::new();
+        let mut offsets = Vec::new();
         let mut count = 0;
 
         // Get a map to convert a byte value to a set bit count.
         let map = create_map();
 
-        // Compute the number of blocks in the file.
+        // The blocks are of different sizes, so read them one by
+        // one until the end of the file.
         let file_size = file.seek(SeekFrom::End(0)).c(d!())?;
-
-        if file_size % BLOCK_SIZE as u64 != 0 {
-            return Err(eg!(format!("That file size ({}) is invalid.", file_size)));
-        }
-
         file.seek(SeekFrom::Start(0)).c(d!())?;
-        let total_blocks = file_size / BLOCK_SIZE as u64;
 
-        // Reserve space in our vectors.
-        blocks.reserve(total_blocks as usize);
-        dirty.reserve(total_blocks as usize);
+        let mut offset = 0;
+        let mut index = 0;
 
-        // Read each block.
-        for index in 0..total_blocks {
-            let mut block = BitBlock::new(BIT_ARRAY, 0).c(d!())?;
+        while offset < file_size {
+            if file_size - offset < HEADER_SIZE as u64 {
+                return Err(eg!(format!("That file size ({}) is invalid.", file_size)));
+            }
 
+            let mut block = BitBlock::new(BIT_ARRAY, 0).c(d!())?;
             block.header.contents = BIT_INVALID;
+            file.read_exact(&mut block.as_mut()[0..HEADER_SIZE])
+                .c(d!())?;
 
-            match file.read_exact(block.as_mut()) {
-                Ok(_) => {
-                    block.validate(BIT_ARRAY, index).c(d!())?;
+            let form = block.header.contents;
 
-                    if index != total_blocks - 1
-                        && block.header.count != BLOCK_BITS as u32
-                    {
+            match form {
+                BIT_ARRAY => {
+                    if file_size - offset < BLOCK_SIZE as u64 {
                         return Err(eg!(format!(
-                            "Block {} is not full:  count {}",
-                            index, block.header.count
+                            "That file size ({}) is invalid.",
+                            file_size
                         )));
                     }
 
-                    let set_count = count_bits(&block.bits, map);
-                    count += block.header.count as usize;
-                    blocks.push(block);
-                    dirty.push(0_i64);
-                    checksum_valid.push(false);
-                    set.push(set_count);
+                    file.read_exact(&mut block.as_mut()[HEADER_SIZE..])
+                        .c(d!())?;
+                    block.validate(BIT_ARRAY, index).c(d!())?;
                 }
-                Err(e) => {
-                    return Err(eg!(e));
+                BIT_EMPTY => {
+                    // The bits are all clear, so the header is enough
+                    // to recreate the block.
+                    block.header.validate(BIT_EMPTY, index).c(d!())?;
+                    block.header.contents = BIT_ARRAY;
+                    block.validate(BIT_ARRAY, index).c(d!())?;
                 }
+                BIT_ARCHIVED => {
+                    block.header.validate(BIT_ARCHIVED, index).c(d!())?;
+                    let cold = archive.as_mut().c(d!(format!(
+                        "Block {} is archived, but no archive file is given.",
+                        index.commas()
+                    )))?;
+                    let header = block.header;
+                    BitMap::read_archived(cold, index, &mut block).c(d!())?;
+
+                    if header.checksum != block.header.checksum
+                        || header.count != block.header.count
+                    {
+                        return Err(eg!(format!(
+                            "Block {} doesn't match the archive.",
+                            index.commas()
+                        )));
+                    }
+                }
+                _ => {
+                    return Err(eg!(format!(
+                        "Block {} has a bad contents type:  {}",
+                        index.commas(),
+                        form
+                    )));
+                }
+            }
+
+            let size = disk_size(form);
+
+            if offset + size != file_size && block.header.count != BLOCK_BITS as u32 {
+                return Err(eg!(format!(
+                    "Block {} is not full:  count {}",
+                    index, block.header.count
+                )));
             }
+
+            let set_count = count_bits(&block.bits, map);
+            count += block.header.count as usize;
+            blocks.push(block);
+            dirty.push(0_i64);
+            checksum_valid.push(false);
+            set.push(set_count);
+            forms.push(form);
+            offsets.push(offset);
+
+            offset += size;
+            index += 1;
         }
 
-        Ok((count, blocks, dirty, checksum_valid, set))
+        Ok((count, blocks, dirty, checksum_valid, set, forms, offsets))
+    }
+
+    // Read an archived block from the archive file, where the
+    // block with index i is at offset i * BLOCK_SIZE.
+    fn read_archived(
+        archive: &mut File,
+        index: u64,
+        block: &mut BitBlock,
+    ) -> Result<()> {
+        archive
+            .seek(SeekFrom::Start(index * BLOCK_SIZE as u64))
+            .c(d!())?;
+        archive.read_exact(block.as_mut()).c(d!())?;
+        block.validate(BIT_ARRAY, index).c(d!())
     }
 
     // Check that the population count of bits for a given block
@@ -731,9 +1108,14 @@ impl BitMap {
     /// unless asked, since they might not be set. To validate
     /// the checksums, first call the write() method and then
     /// invoke validate().
+    ///
+    /// The on-disk form of each block is checked as well:  an
+    /// empty block must be full with all bits clear, and an
+    /// archived block must be full and have an archive file.
     pub fn validate(&self, validate_checksums: bool) -> bool {
         let mut pass = true;
         let mut bitmap_size: usize = 0;
+        let mut offset = 0;
 
         for i in 0..self.blocks.len() {
             let block = &self.blocks[i];
@@ -755,6 +1137,26 @@ impl BitMap {
                 pass &= header.count == BLOCK_BITS as u32;
             }
 
+            match self.forms[i] {
+                BIT_ARRAY => {}
+                BIT_EMPTY => {
+                    pass &= header.count == BLOCK_BITS as u32 && self.set_bits[i] == 0;
+                }
+                BIT_ARCHIVED => {
+                    pass &= header.count == BLOCK_BITS as u32 && self.archive.is_some();
+                }
+                form => {
+                    println!("Block {} has a bad form:  {}", i, form);
+                    pass = false;
+                }
+            }
+
+            // The offsets are recomputed when moved blocks are written.
+            if i < self.moved {
+                pass &= self.offsets[i] == offset;
+            }
+
+            offset += disk_size(self.forms[i]);
             bitmap_size += header.count as usize;
         }
 
@@ -852,15 +1254,30 @@ impl BitMap {
         // We might need to create a new block. If so,
         // push the new block and all the metadata entries.
         if block >= self.blocks.len() {
+            let offset = self.end_offset();
             self.blocks
                 .push(BitBlock::new(BIT_ARRAY, block as u64).c(d!())?);
             self.checksum_data.push(EMPTY_CHECKSUM);
             self.dirty.push(time());
             self.checksum_valid.push(false);
             self.set_bits.push(0);
+            self.forms.push(BIT_ARRAY);
+            self.offsets.push(offset);
         } else {
             self.dirty[block] = time();
             self.checksum_valid[block] = false;
+
+            // A set bit in an empty block makes it an archived
+            // block if possible, since they are of the same size
+            // on disk. Otherwise, the block needs to be moved.
+            if self.forms[block] == BIT_EMPTY {
+                if self.archive.is_some() {
+                    self.forms[block] = BIT_ARCHIVED;
+                } else {
+                    self.forms[block] = BIT_ARRAY;
+                    self.moved = cmp::min(self.moved, block);
+                }
+            }
         }
 
         // Update the first invalid spot in the checksum.
@@ -962,6 +1379,12 @@ impl BitMap {
 
     /// Serialize the entire bit map to a compressed representation.
     ///
+    /// The version is stored in the serialized form. From
+    /// SERIAL_VERSION_EMPTY on, the full blocks with all bits clear
+    /// are serialized as a header only, which the decoders before
+    /// that version reject, so SERIAL_VERSION_LEGACY should be used
+    /// for the deployed clients.
+    ///
     /// This method must only be called in cases where the cached checksum would be up to date.
     ///
     /// # Panics
@@ -974,7 +1397,7 @@ impl BitMap {
 
         // Reserve space for each block.
         for i in 0..self.blocks.len() {
-            bytes += self.serial_size(i);
+            bytes += self.serial_size(i, version);
         }
 
         let mut result = Vec::new();
@@ -986,7 +1409,7 @@ impl BitMap {
         result.extend_from_slice(&self.checksum[0..]);
 
         for i in 0..self.blocks.len() {
-            self.serialize_block(i, &mut result, INCLUDE_BITS);
+            self.serialize_block(i, &mut result, INCLUDE_BITS, version);
         }
 
         result
@@ -995,7 +1418,7 @@ impl BitMap {
     /// Serialize the bitmap to a compressed form that contains
     /// bit values only for blocks in a given list of bit ids.
     /// Other blocks are represented only by a header with a
-    /// checksum.
+    /// checksum. The version is used as in serialize().
     pub fn serialize_partial(
         &mut self,
         bit_list: Vec<usize>,
@@ -1012,7 +1435,7 @@ impl BitMap {
         // Add the space needed for each block.
         for i in 0..self.blocks.len() {
             if set.contains(&i) {
-                bytes += self.serial_size(i);
+                bytes += self.serial_size(i, version);
             } else {
                 bytes += BLOCK_INFO_SIZE;
             }
@@ -1023,12 +1446,68 @@ impl BitMap {
         self.append_descriptor(version as u64, &mut result);
 
         for i in 0..self.blocks.len() {
-            self.serialize_block(i, &mut result, set.contains(&i));
+            self.serialize_block(i, &mut result, set.contains(&i), version);
         }
 
         result
     }
 
+    /// Compute the root of the Merkle tree over the checksums
+    /// of the blocks, hashed together with the size of the map.
+    /// The checksums are updated first, as in compute_checksum.
+    ///
+    /// As with compute_checksum, the root of an empty bitmap is
+    /// an array of zeros.
+    pub fn compute_merkle_root(&mut self) -> Digest {
+        omit!(self.compute_checksum());
+
+        if self.blocks.is_empty() {
+            return Digest {
+                0: [0_u8; DIGESTBYTES],
+            };
+        }
+
+        merkle_commit(self.size as u64, &merkle_root(&self.merkle_leaves()))
+    }
+
+    /// Create a proof of the value of a bit, which can be
+    /// verified against the result of compute_merkle_root.
+    ///
+    /// As with serialize, this method must only be called in
+    /// cases where the cached checksums would be up to date.
+    pub fn prove(&self, bit: usize) -> Result<BitProof> {
+        if bit >= self.size {
+            return Err(eg!(format!(
+                "That index is out of range ({} vs {}).",
+                bit, self.size
+            )));
+        }
+
+        let block = bit / BLOCK_BITS;
+        let mut bytes =
+            Vec::with_capacity(self.serial_size(block, SERIAL_VERSION_LEGACY));
+        self.serialize_block(block, &mut bytes, INCLUDE_BITS, SERIAL_VERSION_LEGACY);
+
+        let mut path = Vec::new();
+        merkle_path(block, &self.merkle_leaves(), &mut path);
+
+        Ok(BitProof {
+            bit: bit as u64,
+            size: self.size as u64,
+            block: bytes,
+            path,
+        })
+    }
+
+    // Get the leaves of the Merkle tree from the cached
+    // checksums of the blocks.
+    fn merkle_leaves(&self) -> Vec<Digest> {
+        self.blocks
+            .iter()
+            .map(|b| merkle_leaf(&b.header.checksum.bytes))
+            .collect()
+    }
+
     // Append the global data for the bitmap. That data
     // currently consists of the version number from the
     // caller and the checksum of the tree.
@@ -1041,11 +1520,13 @@ impl BitMap {
 
     // Compute the expected size of the serialized form
     // of a given block.
-    fn serial_size(&self, index: usize) -> usize {
+    fn serial_size(&self, index: usize, version: usize) -> usize {
         let set_bits = self.set_bits[index];
         let clear_bits = BLOCK_BITS as u32 - set_bits;
 
-        if set_bits > LOWER_LIMIT && set_bits < UPPER_LIMIT {
+        if version >= SERIAL_VERSION_EMPTY && self.is_empty_block(index) {
+            BLOCK_INFO_SIZE
+        } else if set_bits > LOWER_LIMIT && set_bits < UPPER_LIMIT {
             BLOCK_INFO_SIZE + BLOCK_BITS_SIZE
         } else if set_bits <= LOWER_LIMIT {
             BLOCK_INFO_SIZE + set_bits as usize * INDEX_SIZE
@@ -1056,11 +1537,19 @@ impl BitMap {
 
     // Append the serialized form of a block to the Vec
     // representing the results.
-    fn serialize_block(&self, index: usize, result: &mut Vec<u8>, include: bool) {
+    fn serialize_block(
+        &self,
+        index: usize,
+        result: &mut Vec<u8>,
+        include: bool,
+        version: usize,
+    ) {
         let set_bits = self.set_bits[index];
 
         if !include {
             self.append_header(index, BIT_HEADER, 0, result);
+        } else if version >= SERIAL_VERSION_EMPTY && self.is_empty_block(index) {
+            self.append_header(index, BIT_EMPTY, 0, result);
         } else if set_bits > LOWER_LIMIT && set_bits < UPPER_LIMIT {
             self.append_block(index, result);
         } else if set_bits <= LOWER_LIMIT {
@@ -1070,6 +1559,13 @@ impl BitMap {
         }
     }
 
+    // Check whether a block is full and all its bits are clear,
+    // i.e., all the utxos in it are spent.
+    #[inline(always)]
+    fn is_empty_block(&self, index: usize) -> bool {
+        self.set_bits[index] == 0 && self.blocks[index].header.count == BLOCK_BITS as u32
+    }
+
     // Append the header information as a BlockInfo structure.
     fn append_header(
         &self,
@@ -1150,75 +1646,87 @@ impl BitMap {
         index = next;
 
         // Now loop retrieving any blocks in the map.
-        loop {
-            if bytes.len() == index {
-                break;
-            } else if bytes.len() - index < BLOCK_INFO_SIZE {
-                return Err(eg!("The input was too short.".to_string()));
+        while bytes.len() != index {
+            let (next, info, bits) =
+                BitMap::decode_block(bytes, index, version).c(d!())?;
+            index = next;
+
+            if let Some(bits) = bits {
+                bits_map.insert(info.bit_id / BLOCK_BITS as u64, bits);
             }
 
-            // Get the next BlockInfo structure from the stream.
-            let mut info = BlockInfo::default();
-            BitMap::clone_info(info.as_mut(), bytes, index);
-            index += BLOCK_INFO_SIZE;
-            info.validate().c(d!())?;
+            info_vec.push(info);
+        }
 
-            let block = info.bit_id / BLOCK_BITS as u64;
-            let mut bits: BlockBits;
+        Ok((version, checksum, info_vec, bits_map))
+    }
 
-            // Now retrieve the block contents, if present, and
-            // restore them to bitmaps.
-            match info.contents {
-                BIT_HEADER => {}
-                BIT_ARRAY => {
-                    bits = [0_u8; BITS_SIZE];
-                    bits.clone_from_slice(&bytes[index..index + BITS_SIZE]);
-                    index += BITS_SIZE;
-                    bits_map.insert(block, bits);
-                }
-                BIT_DESC_SET => {
-                    let (next, ids) =
-                        BitMap::decode(info.list_size, bytes, index).c(d!())?;
-                    bits = [0_u8; BITS_SIZE];
+    // Decode a serialized block, return the index after it, the
+    // BlockInfo structure and the bits, if they are present. The
+    // version is the one the block was serialized with.
+    fn decode_block(
+        bytes: &[u8],
+        start: usize,
+        version: u64,
+    ) -> Result<(usize, BlockInfo, Option<BlockBits>)> {
+        let mut index = start;
 
-                    for id in ids.iter() {
-                        BitMap::mutate_bit(&mut bits, *id, true);
-                    }
+        if bytes.len() < index + BLOCK_INFO_SIZE {
+            return Err(eg!("The input was too short.".to_string()));
+        }
 
-                    bits_map.insert(block, bits);
-                    index = next;
-                }
-                BIT_DESC_CLEAR => {
-                    let (next, ids) =
-                        BitMap::decode(info.list_size, bytes, index).c(d!())?;
-                    bits = [0xff_u8; BITS_SIZE];
+        // Get the next BlockInfo structure from the stream.
+        let mut info = BlockInfo::default();
+        BitMap::clone_info(info.as_mut(), bytes, index);
+        index += BLOCK_INFO_SIZE;
+        info.validate().c(d!())?;
 
-                    for id in ids.iter() {
-                        BitMap::mutate_bit(&mut bits, *id, false);
-                    }
+        let mut bits: BlockBits;
 
-                    bits_map.insert(block, bits);
-                    index = next;
+        // Now retrieve the block contents, if present, and
+        // restore them to bitmaps.
+        match info.contents {
+            BIT_HEADER => Ok((index, info, None)),
+            BIT_EMPTY if version >= SERIAL_VERSION_EMPTY as u64 => {
+                Ok((index, info, Some([0_u8; BITS_SIZE])))
+            }
+            BIT_ARRAY => {
+                if bytes.len() < index + BITS_SIZE {
+                    return Err(eg!("The input was too short.".to_string()));
                 }
-                _ => {
-                    return Err(eg!(format!(
-                        "Invalid info contents type:  {}",
-                        info.contents
-                    )));
+
+                bits = [0_u8; BITS_SIZE];
+                bits.clone_from_slice(&bytes[index..index + BITS_SIZE]);
+                index += BITS_SIZE;
+                Ok((index, info, Some(bits)))
+            }
+            BIT_DESC_SET => {
+                let (next, ids) =
+                    BitMap::decode(info.list_size, bytes, index).c(d!())?;
+                bits = [0_u8; BITS_SIZE];
+
+                for id in ids.iter() {
+                    BitMap::mutate_bit(&mut bits, *id, true);
                 }
+
+                Ok((next, info, Some(bits)))
             }
+            BIT_DESC_CLEAR => {
+                let (next, ids) =
+                    BitMap::decode(info.list_size, bytes, index).c(d!())?;
+                bits = [0xff_u8; BITS_SIZE];
 
-            info_vec.push(info);
-        }
+                for id in ids.iter() {
+                    BitMap::mutate_bit(&mut bits, *id, false);
+                }
 
-        if index != bytes.len() {
-            return Err(eg!(format!(
-                "The vector contained {} extra bytes.",
-                bytes.len() - index
-            )));
+                Ok((next, info, Some(bits)))
+            }
+            _ => Err(eg!(format!(
+                "Invalid info contents type:  {}",
+                info.contents
+            ))),
         }
-
-        Ok((version, checksum, info_vec, bits_map))
     }
 
     // Decode the global information from the byte stream.
@@ -1300,14 +1808,84 @@ impl BitMap {
     }
 
     /// Write the bitmap to disk.
+    ///
+    /// If some blocks have been moved, see compact() and archive(),
+    /// only the blocks before them are written, and an error is
+    /// returned. The bitmap must be written with write_to() then.
     pub fn write(&mut self) -> Result<()> {
+        // The headers of the archived blocks refer to the archive
+        // file, so it is written before them.
+        self.write_archive(cmp::min(self.moved, self.blocks.len()))
+            .c(d!())?;
+
         for i in 0..self.blocks.len() {
             if self.dirty[i] != 0 {
-                self.write_block(i).c(d!())?;
+                self.write_header(i).c(d!())?;
             }
         }
 
         self.file.sync_all().c(d!())?;
+
+        if self.is_moved() {
+            return Err(eg!(format!(
+                "Block {} has been moved, use write_to().",
+                self.moved.commas()
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Check whether some blocks have been moved since the
+    /// last write, so that write_to() is needed.
+    #[inline(always)]
+    pub fn is_moved(&self) -> bool {
+        self.moved < self.blocks.len()
+    }
+
+    /// Write the whole bitmap to a new file, which the caller
+    /// should pass opened to an empty file, and use that file
+    /// as the main file from then on.
+    ///
+    /// Moving the blocks in place could leave a file that can't
+    /// be opened after a crash, so the caller should create the
+    /// new file under a temporary path, and rename it over the
+    /// old one after this method returns. Either file is complete
+    /// at any time.
+    pub fn write_to(&mut self, mut data: File) -> Result<()> {
+        let file_size = data.seek(SeekFrom::End(0)).c(d!())?;
+
+        if file_size != 0 {
+            return Err(eg!("The file contains data!".to_string()));
+        }
+
+        // The archived blocks stay in place in the archive file,
+        // so they are written before the headers referring to them.
+        self.write_archive(self.blocks.len()).c(d!())?;
+
+        let mut writer = BufWriter::new(&mut data);
+        let mut offsets = Vec::with_capacity(self.blocks.len());
+        let mut offset = 0;
+
+        for i in 0..self.blocks.len() {
+            self.blocks[i].set_checksum();
+            BitMap::write_form(&mut writer, &self.blocks[i], self.forms[i]).c(d!())?;
+            offsets.push(offset);
+            offset += disk_size(self.forms[i]);
+        }
+
+        writer.flush().c(d!())?;
+        drop(writer);
+        data.sync_all().c(d!())?;
+
+        self.file = data;
+        self.offsets = offsets;
+        self.moved = usize::MAX;
+
+        for dirty in self.dirty.iter_mut() {
+            *dirty = 0;
+        }
+
         Ok(())
     }
 
@@ -1327,17 +1905,163 @@ impl BitMap {
         Ok(())
     }
 
+    /// Set the archive file for a bitmap that has none, see
+    /// archive().
+    pub fn set_archive(&mut self, archive: File) -> Result<()> {
+        if self.archive.is_some() {
+            return Err(eg!("The bitmap has an archive file already.".to_string()));
+        }
+
+        self.archive = Some(archive);
+        Ok(())
+    }
+
+    /// Compact the full blocks with all bits clear, so that
+    /// only their headers are kept on disk. Return the number
+    /// of blocks compacted. The change is written to disk by
+    /// the next write().
+    pub fn compact(&mut self) -> Result<usize> {
+        let mut count = 0;
+
+        for i in 0..self.blocks.len() {
+            if self.forms[i] != BIT_EMPTY && self.is_empty_block(i) {
+                // An archived block is of the same size as an
+                // empty one, so it stays in place.
+                if self.forms[i] == BIT_ARRAY {
+                    self.moved = cmp::min(self.moved, i);
+                }
+
+                self.forms[i] = BIT_EMPTY;
+                self.dirty[i] = time();
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Move the full blocks before the given block index to the
+    /// archive file, leaving only their headers in the main file.
+    /// Empty blocks are left to compact(). Return the number of
+    /// blocks archived. The change is written to disk by the next
+    /// write().
+    pub fn archive(&mut self, before: usize) -> Result<usize> {
+        if self.archive.is_none() {
+            return Err(eg!("The bitmap has no archive file.".to_string()));
+        }
+
+        let mut count = 0;
+
+        for i in 0..cmp::min(before, self.blocks.len()) {
+            if self.forms[i] == BIT_ARRAY
+                && self.blocks[i].header.count == BLOCK_BITS as u32
+                && self.set_bits[i] != 0
+            {
+                self.forms[i] = BIT_ARCHIVED;
+                self.moved = cmp::min(self.moved, i);
+                self.dirty[i] = time();
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Return the number of blocks in each form on disk:  full
+    /// blocks, empty blocks and archived blocks.
+    pub fn block_forms(&self) -> (usize, usize, usize) {
+        let count = |form| self.forms.iter().filter(|f| **f == form).count();
+        (count(BIT_ARRAY), count(BIT_EMPTY), count(BIT_ARCHIVED))
+    }
+
+    // Get the offset in the file after the last block.
+    #[inline(always)]
+    fn end_offset(&self) -> u64 {
+        match self.blocks.len() {
+            0 => 0,
+            n => self.offsets[n - 1] + disk_size(self.forms[n - 1]),
+        }
+    }
+
     // Write the given block to disk and clear the dirty flag.
     // We must set the checksum here since it's been changed,
     // although compute_checksum() actually might have fixed
     // it. TODO:  use first_invalid to avoid an unnecessary
     // update of the checksum?
+    //
+    // An archived block is written to the archive file, and
+    // its header to the main file once the archive is synced.
+    //
+    // A moved block is left dirty, since its position in the
+    // file is not known until write_to() is called.
     fn write_block(&mut self, index: usize) -> Result<()> {
+        if index >= self.moved {
+            return Ok(());
+        }
+
+        if self.forms[index] == BIT_ARCHIVED && self.dirty[index] != 0 {
+            self.write_archived(index).c(d!())?;
+            self.archive.as_mut().c(d!())?.sync_all().c(d!())?;
+        }
+
+        self.write_header(index).c(d!())
+    }
+
+    // Write a block to its position in the main file:  the whole
+    // block, or its header only. The archived blocks must have
+    // been written to the archive file already.
+    fn write_header(&mut self, index: usize) -> Result<()> {
+        if index >= self.moved {
+            return Ok(());
+        }
+
         self.blocks[index].set_checksum();
-        let offset = index as u64 * BLOCK_SIZE as u64;
-        self.file.seek(SeekFrom::Start(offset)).c(d!())?;
-        self.file.write_all(self.blocks[index].as_ref()).c(d!())?;
+
+        self.file
+            .seek(SeekFrom::Start(self.offsets[index]))
+            .c(d!())?;
+        BitMap::write_form(&mut self.file, &self.blocks[index], self.forms[index])
+            .c(d!())?;
+
         self.dirty[index] = 0;
         Ok(())
     }
+
+    // Write the dirty archived blocks before a given index to
+    // the archive file, and sync it.
+    fn write_archive(&mut self, end: usize) -> Result<()> {
+        for i in 0..end {
+            if self.forms[i] == BIT_ARCHIVED && self.dirty[i] != 0 {
+                self.write_archived(i).c(d!())?;
+            }
+        }
+
+        if let Some(archive) = self.archive.as_mut() {
+            archive.sync_all().c(d!())?;
+        }
+
+        Ok(())
+    }
+
+    // Write a block to its position in the archive file.
+    fn write_archived(&mut self, index: usize) -> Result<()> {
+        self.blocks[index].set_checksum();
+
+        let archive = self.archive.as_mut().c(d!())?;
+        let offset = index as u64 * BLOCK_SIZE as u64;
+        archive.seek(SeekFrom::Start(offset)).c(d!())?;
+        archive.write_all(self.blocks[index].as_ref()).c(d!())
+    }
+
+    // Write a block in its form on disk:  the whole block for
+    // a full block, or only its header otherwise.
+    fn write_form(file: &mut impl Write, block: &BitBlock, form: u16) -> Result<()> {
+        if form == BIT_ARRAY {
+            file.write_all(block.as_ref()).c(d!())
+        } else {
+            let mut header = block.header;
+            header.contents = form;
+            file.write_all(header.as_ref()).c(d!())
+        }
+    }
 }
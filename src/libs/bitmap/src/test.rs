@@ -410,3 +410,414 @@ fn test_counting() {
         assert!(map[value] == 2);
     }
 }
+
+#[test]
+fn test_merkle_path() {
+    for n in 1..=33_usize {
+        let leaves = (0..n)
+            .map(|i| merkle_leaf(&[i as u8; CHECK_SIZE]))
+            .collect::<Vec<_>>();
+        let root = merkle_root(&leaves);
+
+        for (i, leaf) in leaves.iter().enumerate() {
+            let mut path = Vec::new();
+            merkle_path(i, &leaves, &mut path);
+            assert!(path.len() <= 6);
+
+            let count = n as u64;
+            assert_eq!(merkle_walk(i as u64, count, *leaf, &path), Some(root));
+
+            // The path is bound to the position of the leaf.
+            if n > 1 {
+                let j = (i as u64 + 1) % count;
+                assert!(merkle_walk(j, count, *leaf, &path) != Some(root));
+                assert!(
+                    merkle_walk(i as u64, count, leaves[j as usize], &path)
+                        != Some(root)
+                );
+            }
+
+            assert_eq!(merkle_walk(count, count, *leaf, &path), None);
+
+            if let Some(hash) = path.pop() {
+                assert!(merkle_walk(i as u64, count, *leaf, &path) != Some(root));
+                path.push(hash);
+            }
+
+            path.push(root);
+            assert_eq!(merkle_walk(i as u64, count, *leaf, &path), None);
+        }
+    }
+}
+
+#[test]
+fn test_bit_proof() {
+    let path = "bit_proof_bitmap";
+    let _ = fs::remove_file(path);
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .unwrap();
+
+    let mut bitmap = BitMap::create(file).unwrap();
+
+    // The root of an empty tree is an array of zeros.
+    assert!(
+        bitmap.compute_merkle_root()
+            == (Digest {
+                0: [0_u8; DIGESTBYTES],
+            })
+    );
+    assert!(bitmap.prove(0).is_err());
+
+    for i in 0..2 * BLOCK_BITS + 100 {
+        bitmap.append().unwrap();
+
+        if i % 3 == 0 {
+            bitmap.clear(i).unwrap();
+        }
+    }
+
+    // Leave the first block with only a few clear bits.
+    for i in (3..BLOCK_BITS).step_by(3).skip(10) {
+        bitmap.set(i).unwrap();
+    }
+
+    let root = bitmap.compute_merkle_root();
+    let bits = [0, 1, 3, 30, BLOCK_BITS - 1, BLOCK_BITS, 2 * BLOCK_BITS + 99];
+
+    for bit in bits.iter() {
+        let proof = bitmap.prove(*bit).unwrap();
+        assert_eq!(proof.size, bitmap.size() as u64);
+        assert_eq!(proof.verify(&root).unwrap(), bitmap.query(*bit).unwrap());
+
+        // The size is committed in the root.
+        let mut bad = proof.clone();
+        bad.size += BLOCK_BITS as u64;
+        assert!(bad.verify(&root).is_err());
+
+        let mut bad = proof.clone();
+        bad.size -= 1;
+        assert!(bad.verify(&root).is_err());
+
+        let mut bad = proof.clone();
+        bad.bit = (*bit as u64 + BLOCK_BITS as u64) % bad.size;
+        assert!(bad.verify(&root).is_err());
+
+        let mut bad = proof.clone();
+        bad.path[0].0[0] ^= 1;
+        assert!(bad.verify(&root).is_err());
+
+        // Change the contents of the block.
+        let mut bad = proof.clone();
+        let last = bad.block.len() - 1;
+        bad.block[last] ^= 1;
+        assert!(bad.verify(&root).is_err());
+
+        let mut bad = proof.clone();
+        bad.block.push(0);
+        assert!(bad.verify(&root).is_err());
+
+        let mut wrong_root = root;
+        wrong_root.0[0] ^= 1;
+        assert!(proof.verify(&wrong_root).is_err());
+    }
+
+    assert!(bitmap.prove(bitmap.size()).is_err());
+
+    // A change of any bit changes the root, and old proofs
+    // don't match the new one.
+    let proof = bitmap.prove(BLOCK_BITS).unwrap();
+    bitmap.clear(1).unwrap();
+    let new_root = bitmap.compute_merkle_root();
+    assert!(new_root != root);
+    assert!(proof.verify(&new_root).is_err());
+    assert!(!bitmap.prove(1).unwrap().verify(&new_root).unwrap());
+
+    // The root survives a reopen.
+    bitmap.write().unwrap();
+    drop(bitmap);
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .unwrap();
+
+    let mut bitmap = BitMap::open(file).unwrap();
+    assert!(bitmap.compute_merkle_root() == new_root);
+
+    let _ = fs::remove_file(path);
+}
+
+fn open_file(path: &str, create: bool) -> File {
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(create)
+        .open(path)
+        .unwrap()
+}
+
+// Write a bitmap whose blocks have been moved to a new file,
+// and rename it over the old one.
+fn rewrite(bitmap: &mut BitMap, path: &str) {
+    assert!(bitmap.is_moved());
+    assert!(bitmap.write().is_err());
+
+    let tmp = format!("{}.tmp", path);
+    let _ = fs::remove_file(&tmp);
+    bitmap.write_to(open_file(&tmp, true)).unwrap();
+    fs::rename(&tmp, path).unwrap();
+
+    assert!(!bitmap.is_moved());
+    bitmap.write().unwrap();
+}
+
+fn file_size(path: &str) -> u64 {
+    fs::metadata(path).unwrap().len()
+}
+
+// Create a bitmap of four blocks, the last one is partial, and
+// all the bits in blocks 0 and 2 are clear.
+fn create_sparse_bitmap(path: &str) -> BitMap {
+    let _ = fs::remove_file(path);
+    let mut bitmap = BitMap::create(open_file(path, true)).unwrap();
+
+    for i in 0..3 * BLOCK_BITS + 100 {
+        bitmap.append().unwrap();
+
+        if i / BLOCK_BITS == 0 || i / BLOCK_BITS == 2 {
+            bitmap.clear(i).unwrap();
+        }
+    }
+
+    bitmap.write().unwrap();
+    assert_eq!(file_size(path), 4 * BLOCK_SIZE as u64);
+    bitmap
+}
+
+#[test]
+fn test_compaction() {
+    let path = "compact_bitmap";
+    let mut bitmap = create_sparse_bitmap(path);
+    let checksum = bitmap.compute_checksum();
+    let root = bitmap.compute_merkle_root();
+    let size = bitmap.size();
+
+    assert_eq!(bitmap.compact().unwrap(), 2);
+    assert_eq!(bitmap.compact().unwrap(), 0);
+    assert_eq!(bitmap.block_forms(), (2, 2, 0));
+
+    // The moved blocks are not written in place, so the old
+    // file is still complete, as if a crash happened here.
+    assert!(bitmap.write().is_err());
+    assert!(bitmap.write_to(open_file(path, false)).is_err());
+    assert_eq!(file_size(path), 4 * BLOCK_SIZE as u64);
+    let mut old = BitMap::open(open_file(path, false)).unwrap();
+    assert!(old.validate(true));
+    assert!(old.compute_checksum() == checksum);
+    drop(old);
+
+    rewrite(&mut bitmap, path);
+    assert!(bitmap.validate(true));
+
+    let compact_size = 2 * BLOCK_SIZE as u64 + 2 * HEADER_SIZE as u64;
+    assert_eq!(file_size(path), compact_size);
+
+    // The checksum doesn't change.
+    bitmap.clear_checksum_cache();
+    assert!(bitmap.compute_checksum() == checksum);
+    assert!(bitmap.compute_merkle_root() == root);
+
+    // The legacy serialization keeps the empty blocks as empty
+    // lists of set bits, which the deployed decoders understand.
+    let legacy = bitmap.serialize(SERIAL_VERSION_LEGACY);
+    let (_, _, info, _) = BitMap::deserialize(&legacy).unwrap();
+    assert!(info.iter().all(|i| i.contents != BIT_EMPTY));
+    assert_eq!(info[0].contents, BIT_DESC_SET);
+    assert_eq!(info[0].list_size, 0);
+
+    // Later versions keep only their headers.
+    let bytes = bitmap.serialize(SERIAL_VERSION_EMPTY);
+    let (version, _, info, bits) = BitMap::deserialize(&bytes).unwrap();
+    assert_eq!(version, SERIAL_VERSION_EMPTY as u64);
+    assert_eq!(info[0].contents, BIT_EMPTY);
+    assert_eq!(info[2].contents, BIT_EMPTY);
+    assert!(bits[&0].iter().all(|b| *b == 0));
+
+    for bytes in [legacy, bytes].iter() {
+        let sparse_map = SparseMap::new(bytes).unwrap();
+        assert!(sparse_map.validate_checksum());
+        assert!(sparse_map.checksum() == checksum);
+
+        for bit in [0, BLOCK_BITS - 1, BLOCK_BITS, 2 * BLOCK_BITS + 1, size - 1].iter() {
+            assert_eq!(
+                sparse_map.query(*bit as u64).unwrap(),
+                bitmap.query(*bit).unwrap()
+            );
+            let proof = bitmap.prove(*bit).unwrap();
+            assert_eq!(proof.verify(&root).unwrap(), bitmap.query(*bit).unwrap());
+        }
+    }
+
+    // The decoders of the legacy version reject the empty blocks,
+    // and an empty block must be full.
+    let mut info = BlockInfo {
+        magic: HEADER_MAGIC,
+        count: BLOCK_BITS as u32,
+        contents: BIT_EMPTY,
+        ..Default::default()
+    };
+    let version = SERIAL_VERSION_EMPTY as u64;
+    assert!(BitMap::decode_block(info.as_ref(), 0, version).is_ok());
+    assert!(BitMap::decode_block(info.as_ref(), 0, version - 1).is_err());
+    info.count -= 1;
+    assert!(BitMap::decode_block(info.as_ref(), 0, version).is_err());
+
+    drop(bitmap);
+
+    // Reopen the compacted bitmap, and append more bits.
+    let mut bitmap = BitMap::open(open_file(path, false)).unwrap();
+    assert!(bitmap.validate(true));
+    assert_eq!(bitmap.size(), size);
+    assert_eq!(bitmap.block_forms(), (2, 2, 0));
+    assert!(bitmap.compute_checksum() == checksum);
+    assert!(!bitmap.query(0).unwrap());
+    assert!(bitmap.query(BLOCK_BITS).unwrap());
+
+    bitmap.append().unwrap();
+    bitmap.write().unwrap();
+    assert_eq!(file_size(path), compact_size);
+
+    // A set bit in an empty block moves it back to a full one.
+    bitmap.set(1).unwrap();
+    assert!(bitmap.validate(false));
+    rewrite(&mut bitmap, path);
+    assert!(bitmap.validate(true));
+    assert_eq!(bitmap.block_forms(), (3, 1, 0));
+    assert_eq!(file_size(path), compact_size + BITS_SIZE as u64);
+    let checksum = bitmap.compute_checksum();
+    drop(bitmap);
+
+    let mut bitmap = BitMap::open(open_file(path, false)).unwrap();
+    assert!(bitmap.validate(true));
+    assert_eq!(bitmap.size(), size + 1);
+    assert!(bitmap.query(1).unwrap());
+    assert!(!bitmap.query(2 * BLOCK_BITS).unwrap());
+    assert!(bitmap.compute_checksum() == checksum);
+    drop(bitmap);
+
+    let _ = fs::remove_file(path);
+}
+
+#[test]
+fn test_archive() {
+    let path = "archive_bitmap";
+    let archive_path = "archive_bitmap_archive";
+    let _ = fs::remove_file(archive_path);
+
+    let mut bitmap = create_sparse_bitmap(path);
+    let checksum = bitmap.compute_checksum();
+    let size = bitmap.size();
+
+    assert!(bitmap.archive(2).is_err());
+    bitmap.set_archive(open_file(archive_path, true)).unwrap();
+    assert!(bitmap.set_archive(open_file(archive_path, true)).is_err());
+
+    // Only block 1 is full and has set bits.
+    assert_eq!(bitmap.archive(usize::MAX).unwrap(), 1);
+    assert_eq!(bitmap.compact().unwrap(), 2);
+    assert_eq!(bitmap.block_forms(), (1, 2, 1));
+    rewrite(&mut bitmap, path);
+    assert!(bitmap.validate(true));
+    assert_eq!(file_size(path), BLOCK_SIZE as u64 + 3 * HEADER_SIZE as u64);
+    assert_eq!(file_size(archive_path), 2 * BLOCK_SIZE as u64);
+
+    bitmap.clear_checksum_cache();
+    assert!(bitmap.compute_checksum() == checksum);
+    drop(bitmap);
+
+    // The archive file is needed to open the bitmap.
+    assert!(BitMap::open(open_file(path, false)).is_err());
+
+    let mut bitmap = BitMap::open_with_archive(
+        open_file(path, false),
+        open_file(archive_path, false),
+    )
+    .unwrap();
+    assert!(bitmap.validate(true));
+    assert_eq!(bitmap.size(), size);
+    assert_eq!(bitmap.block_forms(), (1, 2, 1));
+    assert!(bitmap.compute_checksum() == checksum);
+    assert!(bitmap.query(BLOCK_BITS + 1).unwrap());
+
+    // Archived blocks are changed in place, and a set bit in an
+    // empty block makes it archived.
+    bitmap.clear(BLOCK_BITS + 1).unwrap();
+    bitmap.set(2 * BLOCK_BITS).unwrap();
+    bitmap.write().unwrap();
+    assert!(bitmap.validate(true));
+    assert_eq!(bitmap.block_forms(), (1, 1, 2));
+    assert_eq!(file_size(path), BLOCK_SIZE as u64 + 3 * HEADER_SIZE as u64);
+    let checksum = bitmap.compute_checksum();
+    drop(bitmap);
+
+    let mut bitmap = BitMap::open_with_archive(
+        open_file(path, false),
+        open_file(archive_path, false),
+    )
+    .unwrap();
+    assert!(bitmap.validate(true));
+    assert!(!bitmap.query(BLOCK_BITS + 1).unwrap());
+    assert!(bitmap.query(BLOCK_BITS + 2).unwrap());
+    assert!(bitmap.query(2 * BLOCK_BITS).unwrap());
+    assert!(bitmap.compute_checksum() == checksum);
+
+    // An archived block with all bits clear becomes empty.
+    for i in BLOCK_BITS..2 * BLOCK_BITS {
+        bitmap.clear(i).unwrap();
+    }
+
+    assert_eq!(bitmap.compact().unwrap(), 1);
+    bitmap.write().unwrap();
+    assert_eq!(bitmap.block_forms(), (1, 2, 1));
+    assert_eq!(file_size(path), BLOCK_SIZE as u64 + 3 * HEADER_SIZE as u64);
+    let checksum = bitmap.compute_checksum();
+    drop(bitmap);
+
+    // A corrupted archive is detected.
+    let offset = 2 * BLOCK_SIZE as u64 + HEADER_SIZE as u64;
+    let mut byte = [0_u8; 1];
+    let mut archive = open_file(archive_path, false);
+    archive.seek(SeekFrom::Start(offset)).unwrap();
+    archive.read_exact(&mut byte).unwrap();
+    archive.seek(SeekFrom::Start(offset)).unwrap();
+    archive.write_all(&[byte[0] ^ 0x80]).unwrap();
+    drop(archive);
+
+    assert!(BitMap::open_with_archive(
+        open_file(path, false),
+        open_file(archive_path, false)
+    )
+    .is_err());
+
+    let mut archive = open_file(archive_path, false);
+    archive.seek(SeekFrom::Start(offset)).unwrap();
+    archive.write_all(&byte).unwrap();
+    drop(archive);
+
+    let mut bitmap = BitMap::open_with_archive(
+        open_file(path, false),
+        open_file(archive_path, false),
+    )
+    .unwrap();
+    assert!(bitmap.compute_checksum() == checksum);
+    drop(bitmap);
+
+    let _ = fs::remove_file(path);
+    let _ = fs::remove_file(archive_path);
+}
@@ -2,11 +2,11 @@
 //! # An Append-Only Merkle Tree Implementation
 //!
 //!  This module implements an append-only binary Merkle tree using
-//!  SHA256 as the hash function. The tree currently is kept in memory,
-//!  but this module will write the contents to disk when requested, and
-//!  can initialize a tree using a disk image. Eventually, it should
-//!  support a paged tree, i.e., one that is loaded on demand and paged
-//!  to disk as needed.
+//!  SHA256 as the hash function. The tree is paged:  blocks that are
+//!  full and written to disk can be dropped from memory under a
+//!  configurable limit, and are loaded again on demand. The module
+//!  writes the contents to disk when requested, and can initialize a
+//!  tree using a disk image.
 //!
 
 #![deny(warnings)]
@@ -17,18 +17,21 @@ use {
     chrono::Utc,
     cryptohash::{hash_pair, hash_partial, sha256, HashValue, Proof, HASH_SIZE},
     globutils::Commas,
+    parking_lot::Mutex,
     ruc::*,
     serde::{Deserialize, Deserializer, Serialize, Serializer},
     std::{
-        collections::HashMap,
+        collections::{HashMap, VecDeque},
         fmt,
         fs::{self, File, OpenOptions},
         io::{ErrorKind, Read, Seek, SeekFrom, Write},
         mem::{self, MaybeUninit},
+        ops::Range,
         path,
         ptr::copy_nonoverlapping,
         result::Result as StdResult,
         slice,
+        sync::Arc,
     },
 };
 
@@ -277,7 +280,7 @@ where
 // with each such interior node being the parent of two level zero
 // blocks.
 #[repr(C)]
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 struct Block {
     header: BlockHeader,
 
@@ -542,6 +545,26 @@ fn covered(numerator: u64, denominator: u64) -> u64 {
     (numerator + denominator - 1) / denominator
 }
 
+// Read exactly enough bytes to fill the buffer from a position
+// of the file, without moving its offset.
+#[cfg(unix)]
+#[inline(always)]
+fn read_exact_at(file: &File, buffer: &mut [u8], offset: u64) -> Result<()> {
+    use std::os::unix::fs::FileExt;
+    file.read_exact_at(buffer, offset).c(d!())
+}
+
+// There are no positioned reads on other targets, e.g., wasm32,
+// so seek to the position and then back to the original offset.
+#[cfg(not(unix))]
+fn read_exact_at(mut file: &File, buffer: &mut [u8], offset: u64) -> Result<()> {
+    let orig = file.seek(SeekFrom::Current(0)).c(d!())?;
+    file.seek(SeekFrom::Start(offset)).c(d!())?;
+    let ret = file.read_exact(buffer).c(d!());
+    file.seek(SeekFrom::Start(orig)).c(d!())?;
+    ret
+}
+
 // This structure is used only to pass data between internal
 // routines when reading data from files.
 struct LevelState {
@@ -561,23 +584,275 @@ fn next_level_leaves(blocks: u64, last_full: bool) -> u64 {
     full_blocks / 2
 }
 
-/// Defines an append-ony Merkle tree that eventually will support
-/// a sparse in-memory representation. We will need to use Box
-/// for the blocks at that point.
-#[derive(Debug, Serialize, Deserialize)]
+// Compute the height of the completed tree that holds the given
+// number of entries. The root of a completed tree is the root of
+// a block, and a tree grows by a level of blocks once the top
+// block can't hold all the entries.
+#[inline(always)]
+fn completed_height(entries: u64) -> usize {
+    let mut height = LEVELS_IN_BLOCK - 1;
+
+    while height < 64 && entries >= 1 << height {
+        height += LEVELS_IN_BLOCK;
+    }
+
+    height
+}
+
+/// A proof that a version of the tree is a prefix of a later
+/// version, in the style of the consistency proofs in RFC 6962.
+///
+/// The first hash is a subtree that is complete in the older version,
+/// containing its last entry. The rest are the siblings on the path
+/// from that subtree up to the root of the newer version: the left
+/// siblings are shared by both versions, the right siblings belong to
+/// the newer version only.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ConsistencyProof {
+    /// the number of entries in the older version
+    pub old_state: u64,
+    /// the number of entries in the newer version
+    pub new_state: u64,
+    #[allow(missing_docs)]
+    pub old_root: HashValue,
+    #[allow(missing_docs)]
+    pub new_root: HashValue,
+    #[allow(missing_docs)]
+    pub hash_array: Vec<HashValue>,
+}
+
+impl ConsistencyProof {
+    /// Check that both root hashes can be computed from the hashes
+    /// in this proof, i.e., that the first `old_state` entries of the
+    /// newer version are exactly the entries of the older version.
+    pub fn is_valid_proof(&self) -> bool {
+        if 0 == self.old_state || self.old_state > self.new_state {
+            return false;
+        }
+
+        let empty_hash = HashValue::new();
+        let old_height = completed_height(self.old_state);
+        let new_height = completed_height(self.new_state);
+        let last = self.old_state - 1;
+        let start = self.old_state.trailing_zeros() as usize;
+
+        let mut hashes = self.hash_array.iter();
+        let mut old = match hashes.next() {
+            Some(hash) => *hash,
+            None => return false,
+        };
+        let mut new = old;
+
+        for height in start..new_height {
+            let sibling = match hashes.next() {
+                Some(hash) => hash,
+                None => return false,
+            };
+
+            if last.checked_shr(height as u32).unwrap_or(0) & 1 == 1 {
+                old = hash_partial(sibling, &old);
+                new = hash_partial(sibling, &new);
+            } else {
+                if height < old_height {
+                    old = hash_partial(&old, &empty_hash);
+                }
+                new = hash_partial(&new, sibling);
+            }
+
+            if height + 1 == old_height && old != self.old_root {
+                return false;
+            }
+        }
+
+        hashes.next().is_none() && new == self.new_root
+    }
+}
+
+// The blocks of a tree that are resident in memory. A block that is
+// full and written to disk never changes, so it can be dropped from
+// memory and read back on demand. The other blocks stay resident
+// until they are written, so the limit can be exceeded for a while,
+// e.g., after reset_disk.
+#[derive(Debug)]
+struct Pages {
+    blocks: Vec<Vec<Option<Arc<Block>>>>,
+    clean: VecDeque<(usize, usize)>, // candidates for eviction, oldest first
+    resident: usize,
+    limit: usize, // the maximum number of resident blocks
+}
+
+impl Pages {
+    #[inline(always)]
+    fn new(levels: usize) -> Pages {
+        Pages {
+            blocks: vec![Vec::new(); levels],
+            clean: VecDeque::new(),
+            resident: 0,
+            limit: usize::MAX,
+        }
+    }
+
+    #[inline(always)]
+    fn levels(&self) -> usize {
+        self.blocks.len()
+    }
+
+    #[inline(always)]
+    fn len(&self, level: usize) -> usize {
+        self.blocks[level].len()
+    }
+
+    // Return a block, or None if it has been paged out.
+    fn get(&self, level: usize, id: usize) -> Result<Option<Arc<Block>>> {
+        self.blocks
+            .get(level)
+            .and_then(|list| list.get(id))
+            .cloned()
+            .c(d!(format!(
+                "Block {} at level {} does not exist.",
+                id, level
+            )))
+    }
+
+    // Return a resident block for modification.
+    fn get_mut(&mut self, level: usize, id: usize) -> Result<&mut Block> {
+        self.blocks[level]
+            .get_mut(id)
+            .and_then(|slot| slot.as_mut())
+            .map(Arc::make_mut)
+            .c(d!(format!(
+                "Block {} at level {} is not resident.",
+                id, level
+            )))
+    }
+
+    #[inline(always)]
+    fn push(&mut self, level: usize, block: Block) {
+        self.blocks[level].push(Some(Arc::new(block)));
+        self.resident += 1;
+    }
+
+    // Put a block that has been read back from disk into memory.
+    fn page_in(&mut self, level: usize, id: usize, block: Arc<Block>) {
+        if self.blocks[level][id].is_none() {
+            self.resident += 1;
+            self.clean.push_back((level, id));
+        }
+
+        self.blocks[level][id] = Some(block);
+    }
+
+    #[inline(always)]
+    fn add_level(&mut self) {
+        self.blocks.push(Vec::new());
+    }
+
+    // Drop all the blocks at the given level.
+    fn clear(&mut self, level: usize) {
+        self.resident -= self.blocks[level].iter().filter(|b| b.is_some()).count();
+        self.blocks[level].clear();
+    }
+
+    #[inline(always)]
+    fn add_clean(&mut self, level: usize, ids: Range<usize>) {
+        self.clean.extend(ids.map(|id| (level, id)));
+    }
+
+    // Evict the oldest clean blocks until the resident blocks fit
+    // in the limit. The last block on disk at each level is rewritten
+    // by the next write, so only the blocks before it are evicted.
+    fn shrink(&mut self, blocks_on_disk: &[u64]) {
+        while self.resident > self.limit {
+            let (level, id) = match self.clean.pop_front() {
+                Some(x) => x,
+                None => break,
+            };
+
+            if level < self.blocks.len()
+                && id < self.blocks[level].len()
+                && (id as u64 + 1) < blocks_on_disk[level]
+                && self.blocks[level][id].take().is_some()
+            {
+                self.resident -= 1;
+            }
+        }
+    }
+}
+
+// The serialized form of a tree holds all of its blocks.
+#[derive(Serialize, Deserialize)]
+struct TreeImage<B> {
+    entry_count: u64,
+    entries_on_disk: u64,
+    path: String,
+    blocks: Vec<Vec<B>>,
+}
+
+/// Defines an append-ony Merkle tree with a paged in-memory
+/// representation. By default, all the blocks are kept in memory,
+/// see `set_cache_limit`.
+#[derive(Debug)]
 pub struct AppendOnlyMerkle {
     entry_count: u64, // total entries in the tree
     entries_on_disk: u64,
     path: String, // the disk path for the stable store
-    blocks: Vec<Vec<Block>>,
-
-    #[serde(skip_serializing, skip_deserializing)]
+    pages: Mutex<Pages>,
     files: Vec<File>,
-
-    #[serde(skip_serializing, skip_deserializing)]
     blocks_on_disk: Vec<u64>, // the number of entries on stable store
 }
 
+impl Serialize for AppendOnlyMerkle {
+    fn serialize<S>(&self, serializer: S) -> StdResult<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let blocks = (0..self.levels())
+            .map(|level| {
+                (0..self.level_len(level))
+                    .map(|id| self.block(level, id))
+                    .collect::<Result<Vec<_>>>()
+            })
+            .collect::<Result<Vec<_>>>()
+            .map_err(|e| serde::ser::Error::custom(e.to_string()))?;
+
+        TreeImage {
+            entry_count: self.entry_count,
+            entries_on_disk: self.entries_on_disk,
+            path: self.path.clone(),
+            blocks: blocks
+                .iter()
+                .map(|list| list.iter().map(|b| b.as_ref()).collect())
+                .collect::<Vec<Vec<&Block>>>(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for AppendOnlyMerkle {
+    fn deserialize<D>(deserializer: D) -> StdResult<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let image = TreeImage::<Block>::deserialize(deserializer)?;
+        let mut pages = Pages::new(image.blocks.len());
+
+        for (level, list) in image.blocks.into_iter().enumerate() {
+            for block in list {
+                pages.push(level, block);
+            }
+        }
+
+        Ok(AppendOnlyMerkle {
+            entry_count: image.entry_count,
+            entries_on_disk: image.entries_on_disk,
+            path: image.path,
+            pages: Mutex::new(pages),
+            files: Vec::new(),
+            blocks_on_disk: Vec::new(),
+        })
+    }
+}
+
 // When a tree is dropped, write it to disk.
 impl Drop for AppendOnlyMerkle {
     #[inline(always)]
@@ -597,7 +872,7 @@ impl AppendOnlyMerkle {
             entry_count: 0,
             entries_on_disk: 0,
             path: path.to_string(),
-            blocks: vec![Vec::new()],
+            pages: Mutex::new(Pages::new(1)),
             files: vec![file],
             blocks_on_disk: vec![0],
         }
@@ -611,12 +886,28 @@ impl AppendOnlyMerkle {
     /// * `path` - a string specifying the path to the base file
     #[inline(always)]
     pub fn open(path: &str) -> Result<AppendOnlyMerkle> {
+        AppendOnlyMerkle::open_with_cache_limit(path, usize::MAX)
+    }
+
+    /// Open an existing Merkle tree with a memory cap for its blocks,
+    /// see `set_cache_limit`. The blocks are paged out while they are
+    /// read, so the tree never is fully loaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - a string specifying the path to the base file
+    /// * `cache_limit` - the memory cap in bytes
+    pub fn open_with_cache_limit(
+        path: &str,
+        cache_limit: usize,
+    ) -> Result<AppendOnlyMerkle> {
         let check_path = OpenOptions::new().read(true).write(true).open(path);
 
         match check_path {
             Ok(file) => {
                 let mut result = AppendOnlyMerkle::new(path, file);
 
+                result.pages.get_mut().limit = cache_limit / BLOCK_SIZE;
                 result.open_files().c(d!())?;
                 result.read_files(false).c(d!())?;
                 Ok(result)
@@ -729,7 +1020,7 @@ impl AppendOnlyMerkle {
 
             last_block_full = block.full();
             entries += block.valid_leaves();
-            self.blocks[0].push(block);
+            self.pages.get_mut().push(0, block);
         }
 
         if entries == 0 {
@@ -737,8 +1028,10 @@ impl AppendOnlyMerkle {
 
             // Level 0 file contails no valid leaves, then release level 0 initial resources:
             // self.files / self.blocks / self.blocks_on_disk
+            let pages = self.pages.get_mut();
+            pages.clear(0);
+            pages.blocks.remove(0);
             self.files.remove(0);
-            self.blocks.remove(0);
             self.blocks_on_disk.remove(0);
 
             return Err(eg!("The level 0 file contains no valid leaves."));
@@ -818,7 +1111,7 @@ impl AppendOnlyMerkle {
             }
 
             last_block_full = block.full();
-            self.blocks[level].push(block);
+            self.pages.get_mut().push(level, block);
         }
 
         Ok(last_block_full)
@@ -835,7 +1128,105 @@ impl AppendOnlyMerkle {
         let block_id = index / LEAVES_IN_BLOCK;
         let block_index = index % LEAVES_IN_BLOCK;
 
-        self.blocks[0][block_id].hashes[block_index]
+        pnk!(self.block(0, block_id)).hashes[block_index]
+    }
+
+    /// Set the memory cap, in bytes, for the blocks of this tree.
+    /// When it's exceeded, the blocks that are full and written to
+    /// disk are dropped from memory, oldest first, and read back on
+    /// demand. Blocks that are not on disk yet always stay in memory.
+    pub fn set_cache_limit(&mut self, bytes: usize) {
+        let pages = self.pages.get_mut();
+        pages.limit = bytes / BLOCK_SIZE;
+        pages.shrink(&self.blocks_on_disk);
+    }
+
+    #[inline(always)]
+    fn levels(&self) -> usize {
+        self.pages.lock().levels()
+    }
+
+    #[inline(always)]
+    fn level_len(&self, level: usize) -> usize {
+        self.pages.lock().len(level)
+    }
+
+    // Get a block of the tree, reading it back from disk if it
+    // has been paged out.
+    fn block(&self, level: usize, id: usize) -> Result<Arc<Block>> {
+        if let Some(block) = self.pages.lock().get(level, id).c(d!())? {
+            return Ok(block);
+        }
+
+        let block = Arc::new(self.read_page(level, id).c(d!())?);
+        let mut pages = self.pages.lock();
+
+        pages.page_in(level, id, Arc::clone(&block));
+        pages.shrink(&self.blocks_on_disk);
+        Ok(block)
+    }
+
+    // Read a block that has been paged out. The file offset
+    // is left alone, so the writes can keep appending.
+    fn read_page(&self, level: usize, id: usize) -> Result<Block> {
+        let mut block = Block::new(level as u32, id as u64);
+
+        let buffer = unsafe {
+            slice::from_raw_parts_mut(&mut block as *mut Block as *mut u8, BLOCK_SIZE)
+        };
+
+        read_exact_at(&self.files[level], buffer, (id * BLOCK_SIZE) as u64).c(d!())?;
+
+        if let Err(x) = block.check(level, id as u64, true) {
+            return Err(eg!(format!("Invalid disk block {}:  {}", id.commas(), x)));
+        }
+
+        if !block.full() {
+            return Err(eg!(format!(
+                "Block {} at level {} from disk is not full.",
+                id.commas(),
+                level
+            )));
+        }
+
+        Ok(block)
+    }
+
+    // Read all the blocks at a level that have been paged out back
+    // into memory. This is needed before the file for the level is
+    // truncated.
+    fn load_level(&mut self, level: usize) -> Result<()> {
+        for id in 0..self.level_len(level) {
+            if self.pages.get_mut().get(level, id).c(d!())?.is_none() {
+                let block = Arc::new(self.read_page(level, id).c(d!())?);
+                self.pages.get_mut().page_in(level, id, block);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Record the number of blocks on disk at a level. The blocks
+    // that can't change any more become candidates for eviction.
+    fn set_blocks_on_disk(&mut self, level: usize, count: u64) {
+        let old = self.blocks_on_disk[level];
+        self.blocks_on_disk[level] = count;
+
+        if count > old {
+            let pages = self.pages.get_mut();
+            let start = old.saturating_sub(1) as usize;
+            let end = (count - 1) as usize;
+
+            pages.add_clean(level, start..end);
+            pages.shrink(&self.blocks_on_disk);
+        }
+    }
+
+    // Get the hashes at the top of all the blocks at a level.
+    fn top_hashes(&self, level: usize) -> Result<Vec<Option<HashValue>>> {
+        (0..self.level_len(level))
+            .map(|id| self.block(level, id).map(|b| b.top_hash().copied()))
+            .collect()
     }
 
     /// Make a deserialized tree ready for use. The derived
@@ -846,7 +1237,7 @@ impl AppendOnlyMerkle {
         self.blocks_on_disk = Vec::new();
         self.files = Vec::new();
 
-        for i in 0..self.blocks.len() {
+        for i in 0..self.levels() {
             let file_check = OpenOptions::new()
                 .read(true)
                 .write(true)
@@ -925,7 +1316,7 @@ impl AppendOnlyMerkle {
     fn push_file(&mut self, file: File) {
         self.files.push(file);
         self.blocks_on_disk.push(0);
-        self.blocks.push(Vec::new());
+        self.pages.get_mut().add_level();
     }
 
     // Read the disk data into the Merkle tree.
@@ -987,6 +1378,12 @@ impl AppendOnlyMerkle {
         let mut last_block_full = true;
         let mut entries = 0;
 
+        let lower = if state.check_lower && level > 0 {
+            self.top_hashes(level - 1).c(d!())?
+        } else {
+            Vec::new()
+        };
+
         // Read each block, if possible.
         for i in 0..block_count {
             let last_block = i == block_count - 1;
@@ -1001,12 +1398,14 @@ impl AppendOnlyMerkle {
                     // have read agree with what's in the lower level.
                     if state.check_lower && level > 0 {
                         let lower_index = i as usize * LEAVES_IN_BLOCK * 2;
-                        let lower_list = &self.blocks[level - 1];
 
-                        self.check_lower(&block, lower_list, lower_index)?;
+                        self.check_lower(&block, &lower, lower_index)?;
                     }
 
-                    self.blocks[level].push(block);
+                    // The block is on disk, so it can be paged out as
+                    // soon as the next one is read.
+                    self.pages.get_mut().push(level, block);
+                    self.set_blocks_on_disk(level, i + 1);
                 }
                 Err(x) => {
                     // Loss of a level zero block is irrecoverable.
@@ -1020,7 +1419,8 @@ impl AppendOnlyMerkle {
                             self.rewrite_block(&block).c(d!())?;
                             last_block_full = block.full();
                             entries += block.valid_leaves();
-                            self.blocks[level].push(block);
+                            self.pages.get_mut().push(level, block);
+                            self.set_blocks_on_disk(level, i + 1);
                         }
                         Err(x) => {
                             return Err(eg!(x));
@@ -1042,7 +1442,7 @@ impl AppendOnlyMerkle {
             self.entries_on_disk = entries;
         }
 
-        self.blocks_on_disk[level] = block_count;
+        self.set_blocks_on_disk(level, block_count);
 
         // Compute the number of entries to expect at the next level as a
         // consistency check.
@@ -1093,11 +1493,11 @@ impl AppendOnlyMerkle {
             self.push_file(file);
         } else {
             self.files[level] = file;
-            self.blocks[level] = Vec::new();
+            self.pages.get_mut().clear(level);
             self.blocks_on_disk[level] = 0;
         }
 
-        let entry_count = self.blocks[level - 1].len() / 2;
+        let entry_count = self.level_len(level - 1) / 2;
         let block_count = covered(entry_count as u64, LEAVES_IN_BLOCK as u64);
 
         self.rebuild_level(level, block_count).c(d!())?;
@@ -1147,8 +1547,8 @@ impl AppendOnlyMerkle {
         }
     }
 
-    // Reconstruct a block. Eventually, this routine might need to read
-    // from the disk when we support paging, so allow an error return.
+    // Reconstruct a block. The lower level blocks might be read
+    // back from disk, so allow an error return.
     fn reconstruct(&mut self, level: usize, block_id: u64) -> Result<Block> {
         if level == 0 {
             return Err(eg!("Level zero cannot be reconstructed."));
@@ -1157,16 +1557,16 @@ impl AppendOnlyMerkle {
         let mut lower_index = block_id as usize * LEAVES_IN_BLOCK * 2;
         let mut block = Block::new(level as u32, block_id);
 
-        let last_lower = self.blocks[level - 1].len();
+        let last_lower = self.level_len(level - 1);
 
         // Go through as many hash pairs as there are. There are
         // "last_lower" blocks at "level" - 1, and we need two of
         // them to form a hash pair, so check against last_lower - 1.
         while lower_index < last_lower - 1 && !block.full() {
-            let left = self.blocks[level - 1][lower_index].top_hash();
-            let right = self.blocks[level - 1][lower_index + 1].top_hash();
+            let left = self.block(level - 1, lower_index).c(d!())?;
+            let right = self.block(level - 1, lower_index + 1).c(d!())?;
 
-            if let (Some(left), Some(right)) = (left, right) {
+            if let (Some(left), Some(right)) = (left.top_hash(), right.top_hash()) {
                 block.set_hash(&hash_pair(left, right)).c(d!())?;
             } else {
                 break;
@@ -1188,15 +1588,17 @@ impl AppendOnlyMerkle {
     /// * `hash_value` - a HashValue structure for the new transaction
     pub fn append_hash(&mut self, hash_value: &HashValue) -> Result<u64> {
         if self.entry_count == 0 {
-            if !self.blocks[0].is_empty() {
+            let pages = self.pages.get_mut();
+
+            if pages.len(0) != 0 {
                 return Err(eg!(format!(
                     "Level zero should be empty, but it has {} blocks",
-                    self.blocks[0].len()
+                    pages.len(0)
                 )));
             }
 
-            self.blocks[0].push(Block::new(0, 0));
-            self.blocks[0][0].set_hash(&hash_value).c(d!())?;
+            pages.push(0, Block::new(0, 0));
+            pages.get_mut(0, 0).c(d!())?.set_hash(&hash_value).c(d!())?;
             self.entry_count = 1;
             return Ok(0);
         }
@@ -1205,7 +1607,7 @@ impl AppendOnlyMerkle {
         //
         // We might need to add a level, so include the next level in the count.
         // The loop will terminate via a break if there is no data for a new level.
-        let levels = self.blocks.len() + 1;
+        let levels = self.levels() + 1;
 
         let mut current_hash = *hash_value;
 
@@ -1217,42 +1619,40 @@ impl AppendOnlyMerkle {
             // Pull what we need from the tree. That's the current
             // non-empty block, and the hash of the left subtree
             // for that block, if it has a left sub-stree.
-            let items = {
-                let block_list = &mut self.blocks[level];
+            let last = self.level_len(level).checked_sub(1).c(d!())?;
 
-                if block_list.last().c(d!())?.full() {
-                    let block_id = block_list.len() as u64;
-                    let block = Block::new(level as u32, block_id);
+            let index = if self.block(level, last).c(d!())?.full() {
+                let block = Block::new(level as u32, last as u64 + 1);
 
-                    block_list.push(block);
-                }
-
-                let index = block_list.len() - 1;
-
-                // Get the hash at the top of the older sibling of the
-                // current node, if it exists. If it does exist, it will
-                // be a left subtree, so the current index must be odd.
-                let prev_top = if index & 1 != 0 {
-                    let top_hash = match block_list[index - 1].top_hash() {
-                        Some(x) => *x,
-                        None => {
-                            return Err(eg!(format!(
-                                "No top hash for block {} at level {}",
-                                index - 1,
-                                level
-                            )));
-                        }
-                    };
+                self.pages.get_mut().push(level, block);
+                last + 1
+            } else {
+                last
+            };
 
-                    Some(top_hash)
-                } else {
-                    None
+            // Get the hash at the top of the older sibling of the
+            // current node, if it exists. If it does exist, it will
+            // be a left subtree, so the current index must be odd.
+            // The sibling might have been paged out.
+            let prev = if index & 1 != 0 {
+                let top_hash = match self.block(level, index - 1).c(d!())?.top_hash() {
+                    Some(x) => *x,
+                    None => {
+                        return Err(eg!(format!(
+                            "No top hash for block {} at level {}",
+                            index - 1,
+                            level
+                        )));
+                    }
                 };
 
-                (&mut block_list[index], prev_top)
+                Some(top_hash)
+            } else {
+                None
             };
 
-            let (block, prev) = items;
+            // The last block at each level always is resident.
+            let block = self.pages.get_mut().get_mut(level, index).c(d!())?;
 
             if let Err(x) = block.set_hash(&current_hash) {
                 return Err(eg!(format!("The tree is corrupted:  set_hash:  {}", x)));
@@ -1266,6 +1666,8 @@ impl AppendOnlyMerkle {
             // tree.reset_disk method has been invoked, we can't write
             // this block without adding a lot of logic, so just wait
             // for the next tree.write invocation.
+            let mut written = false;
+
             if block.full() && self.blocks_on_disk[level] >= block.id() as u64 {
                 let se = self.files[level]
                     .seek(SeekFrom::Start((block.id() * BLOCK_SIZE) as u64));
@@ -1273,21 +1675,27 @@ impl AppendOnlyMerkle {
                 if se.is_ok() {
                     let we = self.files[level].write_all(block.as_bytes());
 
-                    if we.is_ok() {
-                        self.blocks_on_disk[level] = block.id() as u64 + 1;
-                    }
+                    written = we.is_ok();
                 }
             }
 
+            let full = block.full();
+            let id = block.id();
+            let top = block.top_hash().copied();
+
+            if written {
+                self.set_blocks_on_disk(level, id as u64 + 1);
+            }
+
             // If this node of the tree is not full or doesn't
             // have a corresponding left subtree, we're done.
-            if !block.full() || block.id() & 1 == 0 {
+            if !full || id & 1 == 0 {
                 break;
             }
 
             // Okay, we have another hash to add to the tree. Compute it.
             let left = &prev.c(d!())?;
-            let right = block.top_hash().c(d!())?;
+            let right = &top.c(d!())?;
 
             current_hash = hash_pair(left, right);
         }
@@ -1302,7 +1710,7 @@ impl AppendOnlyMerkle {
     // when there's data for the new layer, so allocate a block here.
     #[inline(always)]
     fn add_level(&mut self) -> Result<()> {
-        let level = self.blocks.len();
+        let level = self.levels();
         let path = self.file_path(level);
 
         let file = OpenOptions::new()
@@ -1314,7 +1722,9 @@ impl AppendOnlyMerkle {
 
         self.push_file(file);
 
-        self.blocks[level].push(Block::new(level as u32, 0));
+        self.pages
+            .get_mut()
+            .push(level, Block::new(level as u32, 0));
         Ok(())
     }
 
@@ -1337,9 +1747,9 @@ impl AppendOnlyMerkle {
 
     /// Generate a proof given an index into the tree.
     ///
-    /// This routine probably will end up taking a tree state id as
-    /// an input so that verifiers don't need to keep a complete
-    /// copy of the tree.
+    /// Any version up to the current size of the tree can be used,
+    /// so a proof can be checked against a root hash that was
+    /// published when the tree was smaller.
     ///
     /// # Arguments
     ///
@@ -1353,14 +1763,25 @@ impl AppendOnlyMerkle {
             )));
         }
 
-        if version != self.entry_count {
-            return Err(eg!("Versioning is not yet supported."));
+        if version > self.entry_count {
+            return Err(eg!(format!("That version ({}) does not exist.", version)));
+        }
+
+        if transaction_id >= version {
+            return Err(eg!(format!(
+                "That transaction id ({}) is not valid for version {}.",
+                transaction_id, version
+            )));
+        }
+
+        if version < self.entry_count {
+            return self.generate_version_proof(transaction_id, version).c(d!());
         }
 
         // Generate a dictionary of all the blocks that
         // would change or be added to make a complete
         // Merkle tree.
-        let dictionary = self.generate_tree_completion();
+        let dictionary = self.generate_tree_completion().c(d!())?;
 
         let mut level = 0;
         let mut hashes = Vec::new();
@@ -1370,7 +1791,9 @@ impl AppendOnlyMerkle {
         // Loop through each level of the tree building
         // the list of hashes.
         loop {
-            root = self.append_proof_hashes(&mut hashes, level, id, &dictionary);
+            root = self
+                .append_proof_hashes(&mut hashes, level, id, &dictionary)
+                .c(d!())?;
 
             if level == dictionary.max_level() {
                 break;
@@ -1379,7 +1802,8 @@ impl AppendOnlyMerkle {
             // Now append the hash of the partner (sibling) for this block,
             // if one exists, or the empty hash.
             let block_id = id / LEAVES_IN_BLOCK;
-            self.push_partner_hash(&mut hashes, level, block_id, &dictionary);
+            self.push_partner_hash(&mut hashes, level, block_id, &dictionary)
+                .c(d!())?;
 
             level += 1;
             id /= LEAVES_IN_BLOCK * 2;
@@ -1427,6 +1851,157 @@ impl AppendOnlyMerkle {
         self.generate_proof(transaction, proof_state)
     }
 
+    /// Generate a proof that the tree at version `old_version` is a
+    /// prefix of the tree at version `new_version`.
+    ///
+    /// # Arguments
+    ///
+    /// * `old_version` - the size of the older tree, must not be zero
+    /// * `new_version` - the size of the newer tree
+    pub fn generate_consistency_proof(
+        &self,
+        old_version: u64,
+        new_version: u64,
+    ) -> Result<ConsistencyProof> {
+        if 0 == old_version || old_version > new_version {
+            return Err(eg!(format!(
+                "Invalid versions: {} -> {}.",
+                old_version, new_version
+            )));
+        }
+
+        if new_version > self.entry_count {
+            return Err(eg!(format!(
+                "That version ({}) does not exist.",
+                new_version
+            )));
+        }
+
+        let last = old_version - 1;
+        let start = old_version.trailing_zeros() as usize;
+        let new_height = completed_height(new_version);
+
+        // The subtree at the start of the path is complete in
+        // the older version, so it's the same in both of them.
+        let mut hashes = vec![self
+            .version_node_hash(start, last >> start, old_version)
+            .c(d!())?];
+
+        for height in start..new_height {
+            let index = last.checked_shr(height as u32).unwrap_or(0);
+
+            // A left sibling is complete in the older version, while
+            // a right sibling is empty there.
+            let sibling = if index & 1 == 1 {
+                self.version_node_hash(height, index ^ 1, old_version)
+            } else {
+                self.version_node_hash(height, index ^ 1, new_version)
+            };
+
+            hashes.push(sibling.c(d!())?);
+        }
+
+        Ok(ConsistencyProof {
+            old_state: old_version,
+            new_state: new_version,
+            old_root: self.get_root_hash_at(old_version).c(d!())?,
+            new_root: self.get_root_hash_at(new_version).c(d!())?,
+            hash_array: hashes,
+        })
+    }
+
+    /// Compute the root hash of the tree as it was when it
+    /// held the given number of entries.
+    pub fn get_root_hash_at(&self, version: u64) -> Result<HashValue> {
+        if version == 0 || version > self.entry_count {
+            return Ok(HashValue::default());
+        }
+
+        self.version_node_hash(completed_height(version), 0, version)
+    }
+
+    // Generate a proof against an older version of the tree. The
+    // completion dictionary only describes the current version, so
+    // walk up the path and compute each sibling for that version.
+    fn generate_version_proof(
+        &self,
+        transaction_id: u64,
+        version: u64,
+    ) -> Result<Proof> {
+        let height = completed_height(version);
+
+        let hashes = (0..height)
+            .map(|h| {
+                let index = transaction_id.checked_shr(h as u32).unwrap_or(0);
+                self.version_node_hash(h, index ^ 1, version)
+            })
+            .collect::<Result<Vec<_>>>()
+            .c(d!())?;
+
+        Ok(Proof {
+            version: PROOF_VERSION,
+            ledger: self.path.clone(),
+            state: version,
+            time: Utc::now().timestamp(),
+            tx_id: transaction_id,
+            root_hash: self.version_node_hash(height, 0, version).c(d!())?,
+            hash_array: hashes,
+        })
+    }
+
+    // Compute the hash of a node in the completed form of the tree
+    // as it was when it held `version` entries. Subtrees that were
+    // complete at that version are unchanged since, so their hashes
+    // are read from the working tree when it has them. The other
+    // nodes are computed from their children.
+    fn version_node_hash(
+        &self,
+        height: usize,
+        index: u64,
+        version: u64,
+    ) -> Result<HashValue> {
+        let first = u128::from(index) << height;
+        let end = u128::from(index + 1) << height;
+
+        if first >= u128::from(version) {
+            return Ok(HashValue::new());
+        }
+
+        if end <= u128::from(version) {
+            if let Some(hash) = self.stored_node_hash(height, index).c(d!())? {
+                return Ok(hash);
+            }
+        }
+
+        let left = self.version_node_hash(height - 1, 2 * index, version)?;
+        let right = self.version_node_hash(height - 1, 2 * index + 1, version)?;
+
+        Ok(hash_partial(&left, &right))
+    }
+
+    // Find the hash of a complete subtree in the working tree.
+    // Each level of blocks holds LEVELS_IN_BLOCK heights of the
+    // tree, but only full blocks have their upper hashes formed.
+    fn stored_node_hash(&self, height: usize, index: u64) -> Result<Option<HashValue>> {
+        let level = height / LEVELS_IN_BLOCK;
+        let layer = height % LEVELS_IN_BLOCK;
+        let per_block = (LEAVES_IN_BLOCK >> layer) as u64;
+        let block_id = (index / per_block) as usize;
+
+        if level >= self.levels() || block_id >= self.level_len(level) {
+            return Ok(None);
+        }
+
+        let block = self.block(level, block_id).c(d!())?;
+
+        if layer != 0 && !block.full() {
+            return Ok(None);
+        }
+
+        let base = 2 * LEAVES_IN_BLOCK - 2 * (LEAVES_IN_BLOCK >> layer);
+        Ok(Some(block.hashes[base + (index % per_block) as usize]))
+    }
+
     // Append the hash of the partner of the current block, or
     // the empty hash, if this block has no sibling in the tree.
     fn push_partner_hash(
@@ -1435,10 +2010,12 @@ impl AppendOnlyMerkle {
         level: usize,
         block_id: usize,
         dictionary: &Dictionary,
-    ) {
+    ) -> Result<()> {
         let partner_id = block_id ^ 1;
-        let block_hash = self.find_block_root(dictionary, level, block_id);
-        let partner_hash = self.find_block_root(dictionary, level, partner_id);
+        let block_hash = self.find_block_root(dictionary, level, block_id).c(d!())?;
+        let partner_hash = self
+            .find_block_root(dictionary, level, partner_id)
+            .c(d!())?;
 
         // Compute the hash of the parent of the block.
         // This is useful for debugging.
@@ -1449,6 +2026,7 @@ impl AppendOnlyMerkle {
         };
 
         hashes.push(partner_hash);
+        Ok(())
     }
 
     // Find the hash at the root of the given block. The
@@ -1459,17 +2037,17 @@ impl AppendOnlyMerkle {
         dictionary: &Dictionary,
         level: usize,
         block_id: usize,
-    ) -> HashValue {
+    ) -> Result<HashValue> {
         let empty_hash = HashValue::new();
 
         match dictionary.get(level, block_id) {
-            Some(entry) => entry.root(),
+            Some(entry) => Ok(entry.root()),
             None => {
-                if level >= self.blocks.len() || block_id >= self.blocks[level].len() {
-                    empty_hash
+                if level >= self.levels() || block_id >= self.level_len(level) {
+                    Ok(empty_hash)
                 } else {
-                    let block = &self.blocks[level][block_id];
-                    block.root()
+                    let block = self.block(level, block_id).c(d!())?;
+                    Ok(block.root())
                 }
             }
         }
@@ -1485,7 +2063,7 @@ impl AppendOnlyMerkle {
     // dictionary that holds an entry for each block modified (or
     // created) by this rippling.
     //
-    fn generate_tree_completion(&self) -> Dictionary {
+    fn generate_tree_completion(&self) -> Result<Dictionary> {
         let empty_hash = HashValue::new();
 
         let mut dictionary = Dictionary::new();
@@ -1496,10 +2074,10 @@ impl AppendOnlyMerkle {
 
         // Iterate over each level of the tree that's present
         // in the working copy.
-        while level < self.blocks.len() {
-            let length = self.blocks[level].len();
+        while level < self.levels() {
+            let length = self.level_len(level);
             let last_id = length - 1;
-            let last_block = &self.blocks[level][last_id];
+            let last_block = self.block(level, last_id).c(d!())?;
             let count = last_block.valid_leaves() as usize;
 
             //
@@ -1536,7 +2114,7 @@ impl AppendOnlyMerkle {
                 if last_id & 1 == 0 {
                     carried_hash = hash_partial(&carried_hash, &empty_hash);
                 } else {
-                    let left = self.blocks[level][last_id - 1].root();
+                    let left = self.block(level, last_id - 1).c(d!())?.root();
                     carried_hash = hash_partial(&left, &carried_hash);
                 }
             } else if carried_hash != empty_hash {
@@ -1548,14 +2126,14 @@ impl AppendOnlyMerkle {
                 carried_hash = entry.root();
                 carried = true;
                 dictionary.insert(level, entry);
-                solitary_block = level > self.blocks.len();
+                solitary_block = level > self.levels();
 
                 // Similarly to the previous case, compute the
                 // carried hash.
                 if new_block_id & 1 == 0 {
                     carried_hash = hash_partial(&carried_hash, &empty_hash);
                 } else {
-                    let left = self.blocks[level][new_block_id - 1].root();
+                    let left = self.block(level, new_block_id - 1).c(d!())?.root();
                     carried_hash = hash_partial(&left, &carried_hash);
                 }
             } else if !carried && length % 2 == 1 {
@@ -1589,7 +2167,7 @@ impl AppendOnlyMerkle {
             dictionary.insert(level, entry);
         }
 
-        dictionary
+        Ok(dictionary)
     }
 
     //
@@ -1609,7 +2187,7 @@ impl AppendOnlyMerkle {
         level: usize,
         id: usize,
         dictionary: &Dictionary,
-    ) -> HashValue {
+    ) -> Result<HashValue> {
         let block_id = id / LEAVES_IN_BLOCK;
         let block_index = id % LEAVES_IN_BLOCK;
         let last = HASHES_IN_BLOCK - 1;
@@ -1621,13 +2199,13 @@ impl AppendOnlyMerkle {
                 block_root_hash = entry.hashes[last];
             }
             None => {
-                let block = &self.blocks[level][id / LEAVES_IN_BLOCK];
+                let block = self.block(level, block_id).c(d!())?;
                 block.push(hashes, block_index, &[]);
                 block_root_hash = block.hashes[last];
             }
         }
 
-        block_root_hash
+        Ok(block_root_hash)
     }
 
     /// Compute the root hash of the Merkle tree.
@@ -1666,14 +2244,14 @@ impl AppendOnlyMerkle {
         let mut entries_at_this_level = self.entry_count;
 
         // Write each block level of the tree to its file.
-        for level in 0..self.blocks.len() {
+        for level in 0..self.levels() {
             let total_blocks = covered(entries_at_this_level, LEAVES_IN_BLOCK as u64);
 
-            if total_blocks != self.blocks[level].len() as u64 {
+            if total_blocks != self.level_len(level) as u64 {
                 return Err(eg!(format!(
                     "Level {} has {} blocks, but {} were expected",
                     level,
-                    self.blocks[level].len(),
+                    self.level_len(level),
                     total_blocks
                 )));
             }
@@ -1711,8 +2289,9 @@ impl AppendOnlyMerkle {
             let mut last_block_full = true;
 
             // Loop over each block on this level that needs to be sent to disk.
+            // These blocks are not on disk yet, so they are resident.
             for i in start_block as u64..total_blocks {
-                let block = &mut self.blocks[level][i as usize];
+                let block = self.pages.get_mut().get_mut(level, i as usize).c(d!())?;
 
                 // Set the checksum if needed.
                 if !block.full() {
@@ -1745,8 +2324,10 @@ impl AppendOnlyMerkle {
             let result = self.files[level].sync_all();
 
             // If there's an I/O error, truncate the file to try to get rid
-            // of any possible bad blocks.
+            // of any possible bad blocks. The blocks that have been paged
+            // out would be lost with the file, so read them back first.
             if let Err(x) = result {
+                self.load_level(level).c(d!())?;
                 let _ = self.files[level].set_len(0);
                 self.blocks_on_disk[level] = 0;
                 return Err(eg!(x));
@@ -1754,7 +2335,7 @@ impl AppendOnlyMerkle {
 
             // Save the number of blocks we have written to disk and
             // compute the entries at the next level.
-            self.blocks_on_disk[level] = total_blocks;
+            self.set_blocks_on_disk(level, total_blocks);
             entries_at_this_level = next_level_leaves(total_blocks, last_block_full);
         }
 
@@ -1768,7 +2349,7 @@ impl AppendOnlyMerkle {
         let mut lower = Vec::new();
 
         // Check the blocks at each level.
-        for level in 0..self.blocks.len() {
+        for level in 0..self.levels() {
             // First, get the file size check it against expectations.
             let disk_bytes = self.files[level].seek(SeekFrom::End(0)).c(d!())?;
 
@@ -1784,7 +2365,7 @@ impl AppendOnlyMerkle {
 
             // If the disk image is up to date, check that the number
             // of blocks on disk and in the list match.
-            let list_length = self.blocks[level].len() as u64;
+            let list_length = self.level_len(level) as u64;
 
             if flushed && blocks_on_disk != list_length {
                 return Err(eg!(format!(
@@ -1819,7 +2400,7 @@ impl AppendOnlyMerkle {
                             self.check_lower(&block, &lower, lower_index).c(d!())?;
                         }
 
-                        current.push(block);
+                        current.push(block.top_hash().copied());
                     }
                     Err(x) => {
                         return Err(eg!(format!("check_disk:  A read failed:  {}", x)));
@@ -1846,7 +2427,7 @@ impl AppendOnlyMerkle {
             // to match the in-memory version.
             entries_at_this_level = next_level_leaves(blocks_on_disk, last_block_full);
 
-            let last_level = level == self.blocks.len() - 1;
+            let last_level = level == self.levels() - 1;
 
             if last_level && entries_at_this_level > 0 {
                 return Err(eg!(format!(
@@ -1909,12 +2490,13 @@ impl AppendOnlyMerkle {
         let mut leaves_at_this_level = self.entry_count;
         let mut last_blocks = 0;
         let mut last_block_full = true;
+        let mut lower = Vec::new();
 
         // Check each level.
-        for level in 0..self.blocks.len() {
+        for level in 0..self.levels() {
             let blocks_at_this_level =
                 covered(leaves_at_this_level, LEAVES_IN_BLOCK as u64) as usize;
-            let list_length = self.blocks[level].len();
+            let list_length = self.level_len(level);
 
             if list_length != blocks_at_this_level {
                 return Err(eg!(format!(
@@ -1930,12 +2512,13 @@ impl AppendOnlyMerkle {
             }
 
             let mut leaf_count = 0;
+            let mut current = Vec::with_capacity(blocks_at_this_level);
             last_block_full = true;
 
             // Now check each block at this level.
             for block_id in 0..blocks_at_this_level {
                 let last = block_id == blocks_at_this_level - 1;
-                let block = &self.blocks[level][block_id];
+                let block = self.block(level, block_id).c(d!())?;
                 last_block_full = block.full();
 
                 if !last && !last_block_full {
@@ -1951,14 +2534,16 @@ impl AppendOnlyMerkle {
                 // against the values in the lower level.
                 if level > 0 {
                     let lower_index = block_id * LEAVES_IN_BLOCK * 2;
-                    let lower_list = &self.blocks[level - 1];
 
-                    self.check_lower(block, lower_list, lower_index).c(d!())?;
+                    self.check_lower(&block, &lower, lower_index).c(d!())?;
                 }
 
                 leaf_count += block.valid_leaves() as u64;
+                current.push(block.top_hash().copied());
             }
 
+            lower = current;
+
             if leaf_count != leaves_at_this_level {
                 return Err(eg!(format!(
                     "check:  The entry counts ({}, {}) at level {} did not match",
@@ -1974,7 +2559,7 @@ impl AppendOnlyMerkle {
 
             // Check that there's an entry in the vector for the next level.
             // If not, return an error.
-            let last_level = level == self.blocks.len() - 1;
+            let last_level = level == self.levels() - 1;
 
             if last_level && leaves_at_this_level > 0 {
                 return Err(eg!(format!(
@@ -1988,12 +2573,12 @@ impl AppendOnlyMerkle {
         Ok(())
     }
 
-    // Check that a block contains the correct hashes based on the lower-level
-    // blocks.
+    // Check that a block contains the correct hashes based on the top
+    // hashes of the lower-level blocks.
     fn check_lower(
         &self,
         block: &Block,
-        lower: &[Block],
+        lower: &[Option<HashValue>],
         start_block: usize,
     ) -> Result<()> {
         let mut block_index = start_block;
@@ -2009,7 +2594,7 @@ impl AppendOnlyMerkle {
                 )));
             }
 
-            let left = match lower[block_index].top_hash() {
+            let left = match lower[block_index].as_ref() {
                 None => {
                     return Err(eg!(format!(
                         "The left lower hash at {}, level {} is missing.",
@@ -2020,7 +2605,7 @@ impl AppendOnlyMerkle {
                 Some(x) => x,
             };
 
-            let right = match lower[block_index + 1].top_hash() {
+            let right = match lower[block_index + 1].as_ref() {
                 None => {
                     return Err(eg!(format!(
                         "The right lower hash at {}, level {} is missing.",
@@ -2052,9 +2637,15 @@ impl AppendOnlyMerkle {
     ///
     /// This action will cause the entire tree to be written to disk on
     /// the next write call, which can be useful in the presence of errors.
-    /// For this reason, the code attempts to recreate all the files.
-    #[inline(always)]
+    /// For this reason, the code attempts to recreate all the files,
+    /// after the blocks that have been paged out are read back.
     pub fn reset_disk(&mut self) -> Result<()> {
+        for level in 0..self.levels() {
+            self.load_level(level).c(d!())?;
+        }
+
+        self.pages.get_mut().clean.clear();
+
         for i in 0..self.files.len() {
             self.blocks_on_disk[i] = 0;
 
@@ -2588,11 +3179,13 @@ mod tests {
                     panic!("The reconstruct block is bad:  {}", e);
                 }
 
+                let expected = pnk!(tree.block(1, 0));
+
                 for i in 0..HASHES_IN_BLOCK {
-                    if block.hashes[i] != tree.blocks[1][0].hashes[i] {
+                    if block.hashes[i] != expected.hashes[i] {
                         panic!(
                             "Hash mismatch:  {}, {:?}, {:?}",
-                            i, block.hashes[i], tree.blocks[1][0].hashes[i]
+                            i, block.hashes[i], expected.hashes[i]
                         );
                     }
                 }
@@ -2969,19 +3562,12 @@ mod tests {
         }
 
         //
-        // Generating a proof for a version other than the
-        // current version should fail.
+        // Generating a proof for a version that doesn't exist yet,
+        // or for a transaction that isn't in the given version,
+        // should fail.
         //
-        match tree.generate_proof(0, tree.total_size() - 1) {
-            Err(e) => {
-                if !e.eq_any(eg!("Versioning is not yet supported.").as_ref()) {
-                    panic!("The error for an invalid generation was not valid.");
-                }
-            }
-            Ok(_x) => {
-                panic!("An invalid tree version passed.");
-            }
-        }
+        assert!(tree.generate_proof(0, tree.total_size() + 1).is_err());
+        assert!(tree.generate_proof(5, 5).is_err());
 
         assert!(!tree.validate_transaction_id(tree.total_size()));
 
@@ -2997,6 +3583,206 @@ mod tests {
         println!("Done with the proof test.");
     }
 
+    #[test]
+    fn test_version_proof() {
+        let path = "version_proof_tree".to_string();
+        let _ = fs::remove_file(&path);
+
+        let mut tree = match AppendOnlyMerkle::create(&path) {
+            Ok(x) => x,
+            Err(x) => {
+                panic!("Error on open:  {}", x);
+            }
+        };
+
+        // Cross the boundaries of the first two block levels, and
+        // remember the root hash of every version on the way.
+        let transactions = (2 * LEAVES_IN_BLOCK * LEAVES_IN_BLOCK + 3) as u64;
+        let mut roots = vec![HashValue::default()];
+
+        for i in 0..transactions {
+            test_append(&mut tree, i, false);
+            roots.push(tree.get_root_hash());
+        }
+
+        for (version, root) in roots.iter().enumerate() {
+            assert_eq!(pnk!(tree.get_root_hash_at(version as u64)), *root);
+        }
+
+        let versions = [
+            1,
+            2,
+            3,
+            LEAVES_IN_BLOCK as u64 - 1,
+            LEAVES_IN_BLOCK as u64,
+            LEAVES_IN_BLOCK as u64 + 1,
+            2 * LEAVES_IN_BLOCK as u64 + 7,
+            (LEAVES_IN_BLOCK * LEAVES_IN_BLOCK) as u64,
+            (LEAVES_IN_BLOCK * LEAVES_IN_BLOCK) as u64 + 300,
+            (2 * LEAVES_IN_BLOCK * LEAVES_IN_BLOCK) as u64,
+            transactions - 1,
+            transactions,
+        ];
+
+        // Inclusion proofs against older versions.
+        for &version in versions.iter() {
+            for &id in [0, version / 2, version - 1].iter() {
+                let proof = pnk!(tree.get_proof(id, version));
+                assert_eq!(proof.state, version);
+                assert_eq!(proof.root_hash, roots[version as usize]);
+                assert!(proof.is_valid_proof(tree.leaf(id as usize)));
+                assert!(!proof.is_valid_proof(create_test_hash(id + 1, false)));
+            }
+
+            assert!(tree.get_proof(version, version).is_err());
+        }
+
+        // Consistency proofs between any two of the versions.
+        for &old in versions.iter() {
+            for &new in versions.iter().filter(|&&v| v >= old) {
+                let mut proof = pnk!(tree.generate_consistency_proof(old, new));
+                assert_eq!(proof.old_root, roots[old as usize]);
+                assert_eq!(proof.new_root, roots[new as usize]);
+                assert!(proof.is_valid_proof());
+
+                for i in 0..proof.hash_array.len() {
+                    let saved = proof.hash_array[i];
+                    proof.hash_array[i] =
+                        create_test_hash(transactions + i as u64, false);
+                    assert!(!proof.is_valid_proof());
+                    proof.hash_array[i] = saved;
+                }
+
+                proof.old_root = roots[old as usize - 1];
+                assert!(!proof.is_valid_proof());
+            }
+        }
+
+        assert!(tree.generate_consistency_proof(0, 1).is_err());
+        assert!(tree.generate_consistency_proof(3, 2).is_err());
+        assert!(tree
+            .generate_consistency_proof(1, transactions + 1)
+            .is_err());
+
+        let _ = fs::remove_file(&path);
+
+        for i in 1..MAX_BLOCK_LEVELS {
+            let path = tree.file_path(i);
+            let _ = fs::remove_file(&path);
+        }
+    }
+
+    fn remove_tree_files(path: &str) {
+        let _ = fs::remove_file(path);
+
+        for i in 1..MAX_BLOCK_LEVELS {
+            let _ = fs::remove_file(path.to_owned() + "." + &i.to_string());
+        }
+    }
+
+    // Check that the paged-out blocks stay within the limit, except
+    // for the last two blocks at each level, which might not be on
+    // disk yet.
+    fn check_resident(tree: &AppendOnlyMerkle, limit: usize) {
+        let resident = tree.pages.lock().resident;
+        assert!(
+            resident <= limit + 2 * tree.levels(),
+            "{} resident",
+            resident
+        );
+    }
+
+    #[test]
+    fn test_paged_tree() {
+        let path = "paged_tree";
+        let full_path = "paged_tree_full";
+        let limit = 4;
+
+        remove_tree_files(path);
+        remove_tree_files(full_path);
+
+        let mut full = pnk!(AppendOnlyMerkle::create(full_path));
+        let mut tree = pnk!(AppendOnlyMerkle::create(path));
+        tree.set_cache_limit(limit * BLOCK_SIZE);
+
+        let transactions =
+            (2 * LEAVES_IN_BLOCK * LEAVES_IN_BLOCK + LEAVES_IN_BLOCK + 3) as u64;
+
+        for i in 0..transactions {
+            assert_eq!(
+                test_append(&mut tree, i, false),
+                test_append(&mut full, i, false)
+            );
+
+            if i % 10_000 == 0 {
+                write_tree(&mut tree);
+                check_resident(&tree, limit);
+            }
+        }
+
+        write_tree(&mut tree);
+        check_resident(&tree, limit);
+        check_tree(&tree);
+        check_disk_tree(&mut tree, true);
+        check_resident(&tree, limit);
+
+        let check_proofs = |tree: &AppendOnlyMerkle, full: &AppendOnlyMerkle| {
+            assert_eq!(tree.get_root_hash(), full.get_root_hash());
+
+            for id in (0..full.total_size()).step_by(997) {
+                for &version in [0, id + 1, full.total_size() / 2].iter() {
+                    if version != 0 && id >= version {
+                        continue;
+                    }
+
+                    let proof = pnk!(tree.get_proof(id, version));
+                    let expected = pnk!(full.get_proof(id, version));
+                    assert_eq!(proof.root_hash, expected.root_hash);
+                    assert_eq!(proof.hash_array, expected.hash_array);
+                }
+            }
+
+            check_resident(tree, limit);
+        };
+
+        check_proofs(&tree, &full);
+
+        // Reopen the tree, it's read with the same limit.
+        drop(tree);
+        let mut tree = pnk!(AppendOnlyMerkle::open_with_cache_limit(
+            path,
+            limit * BLOCK_SIZE
+        ));
+        check_resident(&tree, limit);
+        check_proofs(&tree, &full);
+
+        for i in transactions..transactions + 2 * LEAVES_IN_BLOCK as u64 {
+            assert_eq!(
+                test_append(&mut tree, i, false),
+                test_append(&mut full, i, false)
+            );
+        }
+
+        check_proofs(&tree, &full);
+
+        // Resetting the disk brings every block back.
+        reset_tree(&mut tree);
+        {
+            let pages = tree.pages.lock();
+            let blocks = (0..pages.levels()).map(|l| pages.len(l)).sum::<usize>();
+            assert_eq!(pages.resident, blocks);
+        }
+        write_tree(&mut tree);
+        check_disk_tree(&mut tree, true);
+        check_resident(&tree, limit);
+        check_proofs(&tree, &full);
+
+        drop(tree);
+        drop(full);
+        remove_tree_files(path);
+        remove_tree_files(full_path);
+    }
+
     fn validate_id(tree: &AppendOnlyMerkle, id: u64) {
         if !tree.validate_transaction_id(id) {
             panic!("Id {} is not valid.", id.commas());
@@ -3103,11 +3889,13 @@ mod tests {
                     panic!("block.check failed with {}", e);
                 }
 
+                let expected = pnk!(tree.block(1, 0));
+
                 for i in 0..HASHES_IN_BLOCK {
-                    if block.hashes[i] != tree.blocks[1][0].hashes[i] {
+                    if block.hashes[i] != expected.hashes[i] {
                         panic!(
                             "Hash mismatch:  {}, {:?}, {:?}",
-                            i, block.hashes[i], tree.blocks[1][0].hashes[i]
+                            i, block.hashes[i], expected.hashes[i]
                         );
                     }
                 }
@@ -11,29 +11,35 @@
 use {
     ruc::*,
     serde::{Deserialize, Serialize},
+    std::{collections::HashSet, hash::Hash, mem},
 };
 
 /// Define a sliding window
 #[derive(Clone, Default, Debug, Serialize, Deserialize, Eq, PartialEq)]
-pub struct SlidingSet<T> {
+pub struct SlidingSet<T: Eq + Hash> {
     current: usize,
     width: usize,
-    map: Vec<Vec<T>>,
+    map: Vec<HashSet<T>>,
+    // Keys below this index have been dropped by a resizing,
+    // so they can not be checked any more even if the window covers them.
+    #[serde(default)]
+    floor: usize,
 }
 
-impl<T> SlidingSet<T> {
+impl<T: Eq + Hash> SlidingSet<T> {
     /// Create a new sliding window start with 0
     #[inline(always)]
     pub fn new(width: usize) -> Self {
         let mut map = Vec::with_capacity(width as usize);
         for _ in 0..width {
-            map.push(Vec::new());
+            map.push(HashSet::new());
         }
         let current = 0;
         SlidingSet {
             current,
             width,
             map,
+            floor: 0,
         }
     }
 
@@ -45,9 +51,68 @@ impl<T> SlidingSet<T> {
         let current_index = self.current % self.width;
         self.map[current_index].clear();
     }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The lowest index whose keys are still tracked,
+    /// it is raised after the window has been widened.
+    #[inline(always)]
+    pub fn floor(&self) -> usize {
+        self.floor
+    }
+
+    /// The range of indexes(both ends included) in which
+    /// a key can be inserted and checked.
+    #[inline(always)]
+    pub fn range(&self) -> (usize, usize) {
+        let low = (self.current + 1).saturating_sub(self.width);
+        (low.max(self.floor), self.current)
+    }
+
+    /// Change the width of the window, keys in the new window are kept.
+    ///
+    /// The slots that had been cleared before a widening will not be
+    /// checkable again, so the indexes of them are excluded by the `floor`.
+    pub fn set_width(&mut self, width: usize) -> Result<()> {
+        if 0 == width {
+            return Err(eg!("SlidingSet::set_width: zero width"));
+        }
+        if width == self.width {
+            return Ok(());
+        }
+
+        let mut map = Vec::with_capacity(width);
+        for _ in 0..width {
+            map.push(HashSet::new());
+        }
+        let low = (self.current + 1).saturating_sub(width.min(self.width));
+        for index in low..=self.current {
+            map[index % width] = mem::take(&mut self.map[index % self.width]);
+        }
+
+        if width > self.width {
+            self.floor = self
+                .floor
+                .max((self.current + 1).saturating_sub(self.width));
+        }
+        self.width = width;
+        self.map = map;
+
+        Ok(())
+    }
 }
 
-impl<T: Eq + Copy + std::fmt::Debug> SlidingSet<T> {
+impl<T: Eq + Hash + Copy + std::fmt::Debug> SlidingSet<T> {
     /// Check if a key with user-defined-type exists at specified index in current window
     #[inline(always)]
     pub fn has_key_at(&self, index: usize, key: T) -> bool {
@@ -62,15 +127,17 @@ impl<T: Eq + Copy + std::fmt::Debug> SlidingSet<T> {
     /// Insertion should be failed if key has already existed at specified index.
     #[inline(always)]
     pub fn insert(&mut self, key: T, index: usize) -> Result<()> {
-        if index <= self.current && index + self.width >= (self.current + 1) {
-            if self.map[index % self.width].contains(&key) {
+        if index <= self.current
+            && index + self.width >= (self.current + 1)
+            && index >= self.floor
+        {
+            if self.map[index % self.width].insert(key) {
+                Ok(())
+            } else {
                 Err(eg!(format!(
                     "SlidingSet::insert: ({:?}, {}) already in set",
                     key, index
                 )))
-            } else {
-                self.map[index % self.width].push(key);
-                Ok(())
             }
         } else {
             Err(eg!(format!("({:?}, {}) is out of range", key, index)))
@@ -117,4 +184,37 @@ mod tests {
             assert!(ss.has_key_at((i + 1) * width - 1, digests[(i + 1) * width - 1]));
         }
     }
+
+    #[test]
+    fn test_set_width() {
+        let mut ss = SlidingSet::<u64>::new(4);
+        for i in 0..10 {
+            pnk!(ss.insert(i as u64, i));
+            ss.incr_current();
+        }
+        assert_eq!(ss.range(), (7, 10));
+        assert!(ss.insert(6, 6).is_err());
+
+        // narrowing keeps the keys in the new window
+        pnk!(ss.set_width(2));
+        assert_eq!(ss.range(), (9, 10));
+        assert!(ss.has_key_at(9, 9));
+        assert!(!ss.has_key_at(8, 8));
+        assert!(ss.insert(9, 9).is_err());
+
+        // widening does not revive the dropped slots
+        pnk!(ss.set_width(8));
+        assert_eq!(ss.range(), (9, 10));
+        assert!(ss.has_key_at(9, 9));
+        assert!(ss.insert(8, 8).is_err());
+        pnk!(ss.insert(10, 10));
+
+        for i in 11..20 {
+            ss.incr_current();
+            pnk!(ss.insert(i as u64, i));
+        }
+        assert_eq!(ss.range(), (12, 19));
+        assert!(ss.has_key_at(12, 12));
+        assert!(ss.set_width(0).is_err());
+    }
 }
@@ -23,6 +23,7 @@ PRIV_KEY="o9gXFI5ft1VOkzYhvFpgUTWVoskM1CEih0zJcm3-EAQ="
 
 # other
 export BLOCK_INTERVAL="5"
+export FINDORAD_NETWORK_PROFILE="local"
 
 # show envs
 if [ "$1" == "s" ]; then